@@ -147,11 +147,15 @@ class LogParser:
         if search(r'(?:panic|ERROR)', log) is not None:
             raise ParseError('Worker(s) panicked')
 
+        # Erasure-coded batches are identified by the commitment to their encoding.
+        tmp = findall(r'Batch ([^ ]+) is erasure-coded as ([^ ]+)', log)
+        commitments = dict(tmp)
+
         tmp = findall(r'Batch ([^ ]+) contains (\d+) B', log)
-        sizes = {d: int(s) for d, s in tmp}
+        sizes = {commitments.get(d, d): int(s) for d, s in tmp}
 
         tmp = findall(r'Batch ([^ ]+) contains sample tx (\d+)', log)
-        samples = {int(s): d for d, s in tmp}
+        samples = {int(s): commitments.get(d, d) for d, s in tmp}
 
         ip = search(r'booted on (/ip4/\d+.\d+.\d+.\d+)', log).group(1)
 
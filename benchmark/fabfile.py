@@ -36,7 +36,8 @@ def local(ctx, debug=True):
             'certificates_synchronize_timeout': '2_000ms',
             'payload_synchronize_timeout': '2_000ms',
             'payload_availability_timeout': '2_000ms',
-            'handler_certificate_deliver_timeout': '2_000ms'
+            'handler_certificate_deliver_timeout': '2_000ms',
+            'catch_up_threshold': 50
         },
         "consensus_api_grpc": {
             "socket_addr": "/ip4/127.0.0.1/tcp/0/http",
@@ -46,6 +47,57 @@ def local(ctx, debug=True):
         'max_concurrent_requests': 500_000,
         'prometheus_metrics': {
             "socket_addr": "/ip4/127.0.0.1/tcp/0/http"
+        },
+        'leader_election': 'deterministic',
+        'leader_reputation': {
+            'enabled': False,
+            'window_size': 1_000,
+            'bad_nodes_stake_percent': 20
+        },
+        'pruning': {
+            'retention': 'keep_all',
+            'retention_depth': 100_000,
+            'interval': '60_000ms'
+        },
+        'batching': {
+            'max_batch_transactions': 0,
+            'max_transaction_size': 0,
+            'adaptive_delay': False,
+            'min_batch_delay': '10ms'
+        },
+        'deduplication': {
+            'worker_cache_size': 0,
+            'worker_window': '60_000ms',
+            'executor_cache_size': 0
+        },
+        'ingress': {
+            'client_rate': 0,
+            'client_burst': 10_000,
+            'client_queue_size': 1_000,
+            'client_subscriptions': 1_000,
+            'client_weights': {}
+        },
+        'compression': {
+            'codec': 'none',
+            'accepted_codecs': ['lz4', 'zstd'],
+            'min_batch_size': 1_024,
+            'max_decompressed_size': 16_777_216
+        },
+        'dissemination': 'replication',
+        'header_limits': {
+            'max_payload_entries': 10_000,
+            'max_parents': 1_000,
+            'max_header_size': 1_048_576
+        },
+        'peer_reputation': {
+            'max_score': 10,
+            'invalid_penalty': 5,
+            'ban_threshold': -10,
+            'ban_duration': '60_000ms'
+        },
+        'state_commitments': {
+            'interval': 100,
+            'max_pending': 100
         }
     }
     try:
@@ -71,6 +123,7 @@ def demo(ctx, debug=True):
         "batch_size": 500000,
         "block_synchronizer": {
             'range_synchronize_timeout': '30_000ms',
+            "catch_up_threshold": 50,
             "certificates_synchronize_timeout": "2_000ms",
             "handler_certificate_deliver_timeout": "2_000ms",
             "payload_availability_timeout": "2_000ms",
@@ -92,6 +145,57 @@ def demo(ctx, debug=True):
         'prometheus_metrics': {
             # Use a random available local port.
             "socket_addr": "/ip4/127.0.0.1/tcp/0/http"
+        },
+        'leader_election': 'deterministic',
+        'leader_reputation': {
+            'enabled': False,
+            'window_size': 1_000,
+            'bad_nodes_stake_percent': 20
+        },
+        'pruning': {
+            'retention': 'keep_all',
+            'retention_depth': 100_000,
+            'interval': '60_000ms'
+        },
+        'batching': {
+            'max_batch_transactions': 0,
+            'max_transaction_size': 0,
+            'adaptive_delay': False,
+            'min_batch_delay': '10ms'
+        },
+        'deduplication': {
+            'worker_cache_size': 0,
+            'worker_window': '60_000ms',
+            'executor_cache_size': 0
+        },
+        'ingress': {
+            'client_rate': 0,
+            'client_burst': 10_000,
+            'client_queue_size': 1_000,
+            'client_subscriptions': 1_000,
+            'client_weights': {}
+        },
+        'compression': {
+            'codec': 'none',
+            'accepted_codecs': ['lz4', 'zstd'],
+            'min_batch_size': 1_024,
+            'max_decompressed_size': 16_777_216
+        },
+        'dissemination': 'replication',
+        'header_limits': {
+            'max_payload_entries': 10_000,
+            'max_parents': 1_000,
+            'max_header_size': 1_048_576
+        },
+        'peer_reputation': {
+            'max_score': 10,
+            'invalid_penalty': 5,
+            'ban_threshold': -10,
+            'ban_duration': '60_000ms'
+        },
+        'state_commitments': {
+            'interval': 100,
+            'max_pending': 100
         }
     }
     try:
@@ -200,7 +304,8 @@ def remote(ctx, debug=False):
             'certificates_synchronize_timeout': '2_000ms',
             'payload_synchronize_timeout': '2_000ms',
             'payload_availability_timeout': '2_000ms',
-            'handler_certificate_deliver_timeout': '2_000ms'
+            'handler_certificate_deliver_timeout': '2_000ms',
+            'catch_up_threshold': 50
         },
         "consensus_api_grpc": {
             "socket_addr": "/ip4/127.0.0.1/tcp/0/http",
@@ -210,6 +315,57 @@ def remote(ctx, debug=False):
         'max_concurrent_requests': 500_000,
         'prometheus_metrics': {
             "socket_addr": "/ip4/127.0.0.1/tcp/0/http"
+        },
+        'leader_election': 'deterministic',
+        'leader_reputation': {
+            'enabled': False,
+            'window_size': 1_000,
+            'bad_nodes_stake_percent': 20
+        },
+        'pruning': {
+            'retention': 'keep_all',
+            'retention_depth': 100_000,
+            'interval': '60_000ms'
+        },
+        'batching': {
+            'max_batch_transactions': 0,
+            'max_transaction_size': 0,
+            'adaptive_delay': False,
+            'min_batch_delay': '10ms'
+        },
+        'deduplication': {
+            'worker_cache_size': 0,
+            'worker_window': '60_000ms',
+            'executor_cache_size': 0
+        },
+        'ingress': {
+            'client_rate': 0,
+            'client_burst': 10_000,
+            'client_queue_size': 1_000,
+            'client_subscriptions': 1_000,
+            'client_weights': {}
+        },
+        'compression': {
+            'codec': 'none',
+            'accepted_codecs': ['lz4', 'zstd'],
+            'min_batch_size': 1_024,
+            'max_decompressed_size': 16_777_216
+        },
+        'dissemination': 'replication',
+        'header_limits': {
+            'max_payload_entries': 10_000,
+            'max_parents': 1_000,
+            'max_header_size': 1_048_576
+        },
+        'peer_reputation': {
+            'max_score': 10,
+            'invalid_penalty': 5,
+            'ban_threshold': -10,
+            'ban_duration': '60_000ms'
+        },
+        'state_commitments': {
+            'interval': 100,
+            'max_pending': 100
         }
     }
     try:
@@ -48,6 +48,18 @@ pub enum ConfigError {
 
     #[error("Failed to write config file '{file}': {message}")]
     ExportError { file: String, message: String },
+
+    #[error("The stake distribution of the committee admits no threshold for the common coin")]
+    NoCoinThreshold,
+
+    #[error("Node {0} has no coin key pair, but the committee has coin keys")]
+    MissingCoinKey(String),
+
+    #[error("The coin key pair of node {0} is not its share of the committee's coin keys")]
+    WrongCoinKey(String),
+
+    #[error("Invalid pruning parameters: {0}")]
+    InvalidPruning(String),
 }
 
 #[derive(Error, Debug)]
@@ -135,6 +147,342 @@ pub struct Parameters {
     pub max_concurrent_requests: usize,
     /// Properties for the prometheus metrics
     pub prometheus_metrics: PrometheusMetricsParameters,
+    /// How the consensus protocol elects the leader of each round. All the authorities of the
+    /// committee must use the same mode.
+    #[serde(default)]
+    pub leader_election: LeaderElection,
+    /// The parameters of the reputation-based leader schedule.
+    #[serde(default)]
+    pub leader_reputation: LeaderReputationParameters,
+    /// The retention policy of the consensus sequence and the certificates it references.
+    #[serde(default)]
+    pub pruning: PruningParameters,
+    /// The policy of the workers for assembling client transactions into batches.
+    #[serde(default)]
+    pub batching: BatchingParameters,
+    /// The deduplication of the transactions submitted or sequenced more than once.
+    #[serde(default)]
+    pub deduplication: DeduplicationParameters,
+    /// The admission control of the client transactions submitted to the workers.
+    #[serde(default)]
+    pub ingress: IngressParameters,
+    /// The compression of the batches sent between workers.
+    #[serde(default)]
+    pub compression: CompressionParameters,
+    /// How the workers disseminate their batches to the other workers.
+    #[serde(default)]
+    pub dissemination: Dissemination,
+    /// The limits on the headers received from the other primaries.
+    #[serde(default)]
+    pub header_limits: HeaderLimitsParameters,
+    /// How the synchronizers score the peers they fetch data from.
+    #[serde(default)]
+    pub peer_reputation: PeerReputationParameters,
+    /// How often the execution state is committed to and compared with the other authorities.
+    #[serde(default)]
+    pub state_commitments: StateCommitmentParameters,
+}
+
+/// The leader election modes available to the consensus protocols.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LeaderElection {
+    /// Stake-weighted choice seeded by the round number. The leaders of all future rounds are
+    /// known in advance.
+    #[default]
+    Deterministic,
+    /// Stake-weighted choice seeded by a threshold coin, combined from the randomness shares
+    /// carried by the certificates of a round. The leader of a round cannot be predicted before
+    /// honest authorities reveal their shares. Falls back to `Deterministic` if no coin keys were
+    /// dealt to the committee.
+    RandomCoin,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LeaderReputationParameters {
+    /// Whether leaders with a poor reputation are swapped out of the leader schedule. All the
+    /// authorities of the committee must agree on this setting.
+    pub enabled: bool,
+    /// The number of sequenced certificates over which the reputation scores are accumulated
+    /// before the leader schedule is recomputed.
+    pub window_size: u64,
+    /// The share of the total stake (in percent) held by the lowest scoring authorities that are
+    /// deprioritized. It is capped so that 2f+1 stake remains eligible.
+    pub bad_nodes_stake_percent: u64,
+}
+
+impl Default for LeaderReputationParameters {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window_size: 1_000,
+            bad_nodes_stake_percent: 20,
+        }
+    }
+}
+
+/// The parts of the history retained by the node.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RetentionPolicy {
+    /// Never prune the history.
+    #[default]
+    KeepAll,
+    /// Keep the certificates of the last sequence numbers.
+    SequenceNumbers,
+    /// Keep the certificates sequenced since the last committed rounds.
+    Rounds,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PruningParameters {
+    /// What the retention depth is counted in. The certificates that were not yet processed by
+    /// the executor, or that belong to the last `gc_depth` rounds, are never pruned, whatever the
+    /// policy.
+    pub retention: RetentionPolicy,
+    /// The number of sequence numbers or rounds retained, depending on the policy.
+    pub retention_depth: u64,
+    /// The delay between two runs of the pruner.
+    #[serde(with = "duration_format")]
+    pub interval: Duration,
+}
+
+impl Default for PruningParameters {
+    fn default() -> Self {
+        Self {
+            retention: RetentionPolicy::KeepAll,
+            retention_depth: 100_000,
+            interval: Duration::from_secs(60),
+        }
+    }
+}
+
+impl PruningParameters {
+    /// Ensures that a round-based policy retains at least the last `gc_depth` rounds, which
+    /// consensus reloads on restart and the certificate synchronization serves to the peers.
+    pub fn validate(&self, gc_depth: u64) -> Result<(), ConfigError> {
+        if self.retention == RetentionPolicy::Rounds && self.retention_depth < gc_depth {
+            return Err(ConfigError::InvalidPruning(format!(
+                "the retention depth ({}) is below the garbage collection depth ({gc_depth})",
+                self.retention_depth
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BatchingParameters {
+    /// The maximum number of transactions in a batch. The workers seal a batch when it reaches
+    /// this count, even if it is below `batch_size`. No limit if set to 0.
+    pub max_batch_transactions: usize,
+    /// The maximum size of a single transaction. Larger transactions are rejected. No limit if
+    /// set to 0. Denominated in bytes.
+    pub max_transaction_size: usize,
+    /// Whether the batch delay adapts to the observed ingress rate, from `min_batch_delay` when
+    /// the workers receive few transactions to `max_batch_delay` when batches fill up in time.
+    pub adaptive_delay: bool,
+    /// The shortest delay after which the workers seal a batch when the delay is adaptive.
+    #[serde(with = "duration_format")]
+    pub min_batch_delay: Duration,
+}
+
+impl Default for BatchingParameters {
+    fn default() -> Self {
+        Self {
+            max_batch_transactions: 0,
+            max_transaction_size: 0,
+            adaptive_delay: false,
+            min_batch_delay: Duration::from_millis(10),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DeduplicationParameters {
+    /// The number of transaction digests remembered by each worker. The workers drop the
+    /// transactions they already received within `worker_window`. Disabled if set to 0.
+    pub worker_cache_size: usize,
+    /// How long the workers remember the transactions they received.
+    #[serde(with = "duration_format")]
+    pub worker_window: Duration,
+    /// The number of transaction digests remembered by the executor, which skips the
+    /// transactions it already executed. The digests are persisted along with the execution
+    /// indices of their transactions, so all the executors skip the same duplicates, including
+    /// across restarts. Disabled if set to 0.
+    pub executor_cache_size: usize,
+}
+
+impl Default for DeduplicationParameters {
+    fn default() -> Self {
+        Self {
+            worker_cache_size: 0,
+            worker_window: Duration::from_secs(60),
+            executor_cache_size: 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct IngressParameters {
+    /// The number of transactions per second each client may submit to a worker. A client is
+    /// identified by its IP address. The transactions beyond the rate are rejected. Unlimited
+    /// if set to 0.
+    pub client_rate: u64,
+    /// The number of transactions a client may submit in a burst above `client_rate`.
+    pub client_burst: u64,
+    /// The number of transactions of each client queued for the batch maker. A client whose
+    /// queue is full waits for it to drain, without holding back the other clients.
+    pub client_queue_size: usize,
+    /// The number of transactions each client may follow the status of at once, by IP address.
+    /// Unlimited if set to 0.
+    pub client_subscriptions: usize,
+    /// The share of the batch maker given to each client, relative to the other clients with
+    /// pending transactions, by IP address. The clients not listed have a weight of 1.
+    pub client_weights: BTreeMap<String, u64>,
+}
+
+impl Default for IngressParameters {
+    fn default() -> Self {
+        Self {
+            client_rate: 0,
+            client_burst: 10_000,
+            client_queue_size: 1_000,
+            client_subscriptions: 1_000,
+            client_weights: BTreeMap::new(),
+        }
+    }
+}
+
+/// The modes available to the workers for disseminating their batches.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Dissemination {
+    /// Every batch is sent in full to the workers of all the other authorities.
+    #[default]
+    Replication,
+    /// Every batch is erasure-coded into one chunk per authority, and each worker only receives
+    /// the chunk of its authority. The workers reconstruct the batches they need from the
+    /// chunks of f+1 authorities. All the authorities of the committee must agree on this
+    /// setting. Only committees of authorities of equal stake erasure-code their batches: the
+    /// batches of the others are replicated.
+    ErasureCoding,
+}
+
+/// The codecs available to compress the batches sent between workers.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchCodec {
+    /// The batches are sent as is.
+    #[default]
+    None,
+    /// Fast compression, with a modest ratio.
+    Lz4,
+    /// Better compression ratio, for a higher CPU cost.
+    Zstd,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CompressionParameters {
+    /// The codec this worker compresses its batches with. The batches requested by other
+    /// workers are only compressed if the requester accepts the codec, and the batches
+    /// broadcast to them only if they advertised it in their last batch request.
+    pub codec: BatchCodec,
+    /// The codecs this worker advertises in its batch requests. The compressed batches
+    /// received with any other codec are rejected.
+    pub accepted_codecs: Vec<BatchCodec>,
+    /// The serialized size (in bytes) below which batches are sent uncompressed.
+    pub min_batch_size: usize,
+    /// The maximum size (in bytes) of a decompressed batch. The compressed batches inflating
+    /// beyond it are rejected.
+    pub max_decompressed_size: usize,
+}
+
+impl Default for CompressionParameters {
+    fn default() -> Self {
+        Self {
+            codec: BatchCodec::None,
+            accepted_codecs: vec![BatchCodec::Lz4, BatchCodec::Zstd],
+            min_batch_size: 1_024,
+            max_decompressed_size: 16 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct HeaderLimitsParameters {
+    /// The maximum number of batch digests in the payload of a header. Our own headers never
+    /// exceed it: the digests beyond it are left for the next header.
+    pub max_payload_entries: usize,
+    /// The maximum number of parents of a header.
+    pub max_parents: usize,
+    /// The maximum size (in bytes) of a serialized header.
+    pub max_header_size: usize,
+}
+
+impl Default for HeaderLimitsParameters {
+    fn default() -> Self {
+        Self {
+            max_payload_entries: 10_000,
+            max_parents: 1_000,
+            max_header_size: 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PeerReputationParameters {
+    /// The highest score of a peer. Every valid response raises the score of its sender by 1,
+    /// every timeout lowers it by 1.
+    pub max_score: i64,
+    /// The score a peer loses for every invalid response.
+    pub invalid_penalty: i64,
+    /// The score at or below which a peer is banned.
+    pub ban_threshold: i64,
+    /// How long banned peers are left out of the sync requests. Their score is reset once
+    /// the ban expires.
+    #[serde(with = "duration_format")]
+    pub ban_duration: Duration,
+}
+
+impl Default for PeerReputationParameters {
+    fn default() -> Self {
+        Self {
+            max_score: 10,
+            invalid_penalty: 5,
+            ban_threshold: -10,
+            ban_duration: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StateCommitmentParameters {
+    /// The number of sequenced certificates between two commitments to the execution state.
+    /// All the authorities of the committee must use the same interval for their commitments
+    /// to be compared. Zero disables the commitments.
+    pub interval: u64,
+    /// The number of commitments of every other authority kept while waiting for our own. The
+    /// commitments more than `max_pending` intervals away from our execution are dropped.
+    pub max_pending: usize,
+}
+
+impl Default for StateCommitmentParameters {
+    fn default() -> Self {
+        Self {
+            interval: 100,
+            max_pending: 100,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -219,6 +567,11 @@ pub struct BlockSynchronizerParameters {
         default = "BlockSynchronizerParameters::default_handler_certificate_deliver_timeout"
     )]
     pub handler_certificate_deliver_timeout: Duration,
+    /// How many rounds behind the certificates we receive from our peers we may fall before
+    /// catching up by synchronizing whole ranges of rounds, rather than chasing the missing
+    /// parents certificate by certificate.
+    #[serde(default = "BlockSynchronizerParameters::default_catch_up_threshold")]
+    pub catch_up_threshold: u64,
 }
 
 impl BlockSynchronizerParameters {
@@ -237,6 +590,9 @@ impl BlockSynchronizerParameters {
     fn default_handler_certificate_deliver_timeout() -> Duration {
         Duration::from_secs(30)
     }
+    fn default_catch_up_threshold() -> u64 {
+        50
+    }
 }
 
 impl Default for BlockSynchronizerParameters {
@@ -252,6 +608,7 @@ impl Default for BlockSynchronizerParameters {
                 BlockSynchronizerParameters::default_payload_availability_timeout(),
             handler_certificate_deliver_timeout:
                 BlockSynchronizerParameters::default_handler_certificate_deliver_timeout(),
+            catch_up_threshold: BlockSynchronizerParameters::default_catch_up_threshold(),
         }
     }
 }
@@ -270,6 +627,17 @@ impl Default for Parameters {
             consensus_api_grpc: ConsensusAPIGrpcParameters::default(),
             max_concurrent_requests: 500_000,
             prometheus_metrics: PrometheusMetricsParameters::default(),
+            leader_election: LeaderElection::default(),
+            leader_reputation: LeaderReputationParameters::default(),
+            pruning: PruningParameters::default(),
+            batching: BatchingParameters::default(),
+            deduplication: DeduplicationParameters::default(),
+            ingress: IngressParameters::default(),
+            compression: CompressionParameters::default(),
+            dissemination: Dissemination::default(),
+            header_limits: HeaderLimitsParameters::default(),
+            peer_reputation: PeerReputationParameters::default(),
+            state_commitments: StateCommitmentParameters::default(),
         }
     }
 }
@@ -334,6 +702,10 @@ impl Parameters {
                 .handler_certificate_deliver_timeout
                 .as_secs()
         );
+        info!(
+            "Catch up threshold set to {} rounds",
+            self.block_synchronizer.catch_up_threshold
+        );
         info!(
             "Max concurrent requests set to {}",
             self.max_concurrent_requests
@@ -342,6 +714,88 @@ impl Parameters {
             "Prometheus metrics server will run on {}",
             self.prometheus_metrics.socket_addr
         );
+        info!("Leader election set to {:?}", self.leader_election);
+        info!(
+            "Leader reputation set to {} over {} certificates, deprioritizing {}% of the stake",
+            if self.leader_reputation.enabled {
+                "enabled"
+            } else {
+                "disabled"
+            },
+            self.leader_reputation.window_size,
+            self.leader_reputation.bad_nodes_stake_percent
+        );
+        info!(
+            "Pruning set to {:?} with a retention depth of {}, every {} ms",
+            self.pruning.retention,
+            self.pruning.retention_depth,
+            self.pruning.interval.as_millis()
+        );
+        info!(
+            "Max batch transactions set to {}",
+            self.batching.max_batch_transactions
+        );
+        info!(
+            "Max transaction size set to {} B",
+            self.batching.max_transaction_size
+        );
+        info!(
+            "Adaptive batch delay set to {}, down to {} ms",
+            if self.batching.adaptive_delay {
+                "enabled"
+            } else {
+                "disabled"
+            },
+            self.batching.min_batch_delay.as_millis()
+        );
+        info!(
+            "Worker deduplication set to {} transactions over {} ms",
+            self.deduplication.worker_cache_size,
+            self.deduplication.worker_window.as_millis()
+        );
+        info!(
+            "Executor deduplication set to {} transactions",
+            self.deduplication.executor_cache_size
+        );
+        info!(
+            "Client rate limit set to {} tx/s with bursts of {} tx",
+            self.ingress.client_rate, self.ingress.client_burst
+        );
+        info!(
+            "Client queue size set to {} transactions, with {} weighted clients",
+            self.ingress.client_queue_size,
+            self.ingress.client_weights.len()
+        );
+        info!(
+            "Client subscriptions limited to {} transactions",
+            self.ingress.client_subscriptions
+        );
+        info!(
+            "Batch compression set to {:?} above {} B, accepting {:?}",
+            self.compression.codec,
+            self.compression.min_batch_size,
+            self.compression.accepted_codecs
+        );
+        info!(
+            "Max decompressed batch size set to {} B",
+            self.compression.max_decompressed_size
+        );
+        info!("Batch dissemination set to {:?}", self.dissemination);
+        info!(
+            "Header limits set to {} payload entries, {} parents and {} B",
+            self.header_limits.max_payload_entries,
+            self.header_limits.max_parents,
+            self.header_limits.max_header_size
+        );
+        info!(
+            "Peers banned for {} ms at a reputation score of {}",
+            self.peer_reputation.ban_duration.as_millis(),
+            self.peer_reputation.ban_threshold
+        );
+        info!(
+            "State commitments set to every {} certificates, keeping {} pending",
+            self.state_commitments.interval, self.state_commitments.max_pending
+        );
     }
 }
 
@@ -484,12 +938,42 @@ pub struct Authority {
 
 pub type SharedCommittee = Arc<ArcSwap<Committee>>;
 
+/// The keys of the threshold coin of a committee, dealt by `crypto::coin::deal`.
+#[derive(Clone, Serialize, Deserialize, Debug, Eq, PartialEq)]
+pub struct CoinKeys {
+    /// The public key verifying the coin.
+    pub public_key: PublicKey,
+    /// The number of shares revealing the coin.
+    pub threshold: usize,
+    /// The share of every authority.
+    pub shares: BTreeMap<PublicKey, CoinShare>,
+}
+
+/// The share of the threshold coin held by an authority.
+#[derive(Clone, Serialize, Deserialize, Debug, Eq, PartialEq)]
+pub struct CoinShare {
+    /// The index of the share, used to combine it with the others.
+    pub index: u64,
+    /// The public key verifying the randomness shares of the authority.
+    pub public_key: PublicKey,
+}
+
+impl CoinKeys {
+    /// Returns the share of the specified authority.
+    pub fn share(&self, name: &PublicKey) -> Option<&CoinShare> {
+        self.shares.get(name)
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug, Eq, PartialEq)]
 pub struct Committee {
     /// The authorities of epoch.
     pub authorities: BTreeMap<PublicKey, Authority>,
     /// The epoch number of this committee
     pub epoch: Epoch,
+    /// The keys of the threshold coin electing the leaders, if they were dealt.
+    #[serde(default)]
+    pub coin: Option<CoinKeys>,
 }
 
 impl std::fmt::Display for Committee {
@@ -549,11 +1033,71 @@ impl Committee {
         (total_votes + 2) / 3
     }
 
+    /// Returns the number of shares that reveal the common coin: more than any set of authorities
+    /// holding less than `validity_threshold` can produce, so that faulty authorities cannot
+    /// predict the coin, and no more than any set holding `quorum_threshold` can produce, so that
+    /// the coin is revealed by the certificates of every round.
+    pub fn coin_threshold(&self) -> Result<usize, ConfigError> {
+        let mut stakes: Vec<_> = self.authorities.values().map(|x| x.stake).collect();
+        stakes.sort_unstable();
+
+        // The largest number of authorities whose stake is below the validity threshold.
+        let mut total = 0;
+        let faulty = stakes
+            .iter()
+            .take_while(|stake| {
+                total += **stake;
+                total < self.validity_threshold()
+            })
+            .count();
+
+        // The smallest number of authorities whose stake reaches the quorum threshold.
+        let mut total = 0;
+        let quorum = stakes
+            .iter()
+            .rev()
+            .take_while(|stake| {
+                let missing = total < self.quorum_threshold();
+                total += **stake;
+                missing
+            })
+            .count();
+
+        let threshold = faulty + 1;
+        if threshold > quorum {
+            return Err(ConfigError::NoCoinThreshold);
+        }
+        Ok(threshold)
+    }
+
+    /// Checks that the specified public key verifies the share of the common coin of the
+    /// authority, if coin keys were dealt to the committee.
+    pub fn check_coin_key(
+        &self,
+        name: &PublicKey,
+        coin_key: Option<&PublicKey>,
+    ) -> Result<(), ConfigError> {
+        let coin = match &self.coin {
+            Some(coin) => coin,
+            None => return Ok(()),
+        };
+        let coin_key = coin_key.ok_or_else(|| ConfigError::MissingCoinKey(name.encode_base64()))?;
+        match coin.share(name) {
+            Some(share) if &share.public_key == coin_key => Ok(()),
+            _ => Err(ConfigError::WrongCoinKey(name.encode_base64())),
+        }
+    }
+
     /// Returns a leader node as a weighted choice seeded by the provided integer
     pub fn leader(&self, seed: u64) -> PublicKey {
         let mut seed_bytes = [0u8; 32];
         seed_bytes[32 - 8..].copy_from_slice(&seed.to_le_bytes());
-        let mut rng = StdRng::from_seed(seed_bytes);
+        self.leader_from_randomness(seed_bytes)
+    }
+
+    /// Returns a leader node as a weighted choice seeded by the provided randomness
+    pub fn leader_from_randomness(&self, randomness: [u8; 32]) -> PublicKey {
+        let mut rng = StdRng::from_seed(randomness);
         let choices = self
             .authorities
             .iter()
@@ -725,5 +1269,44 @@ mod tests {
         assert!(logs_contain(
             "Prometheus metrics server will run on /ip4/127.0.0.1/tcp"
         ));
+        assert!(logs_contain("Leader election set to Deterministic"));
+        assert!(logs_contain(
+            "Leader reputation set to disabled over 1000 certificates, deprioritizing 20% of the stake"
+        ));
+        assert!(logs_contain(
+            "Pruning set to KeepAll with a retention depth of 100000, every 60000 ms"
+        ));
+        assert!(logs_contain("Max batch transactions set to 0"));
+        assert!(logs_contain("Max transaction size set to 0 B"));
+        assert!(logs_contain(
+            "Adaptive batch delay set to disabled, down to 10 ms"
+        ));
+        assert!(logs_contain(
+            "Worker deduplication set to 0 transactions over 60000 ms"
+        ));
+        assert!(logs_contain("Executor deduplication set to 0 transactions"));
+        assert!(logs_contain(
+            "Client rate limit set to 0 tx/s with bursts of 10000 tx"
+        ));
+        assert!(logs_contain(
+            "Client queue size set to 1000 transactions, with 0 weighted clients"
+        ));
+        assert!(logs_contain(
+            "Batch compression set to None above 1024 B, accepting [Lz4, Zstd]"
+        ));
+        assert!(logs_contain(
+            "Max decompressed batch size set to 16777216 B"
+        ));
+        assert!(logs_contain("Batch dissemination set to Replication"));
+        assert!(logs_contain(
+            "Header limits set to 10000 payload entries, 1000 parents and 1048576 B"
+        ));
+        assert!(logs_contain(
+            "Peers banned for 60000 ms at a reputation score of -10"
+        ));
+        assert!(logs_contain("Catch up threshold set to 50 rounds"));
+        assert!(logs_contain(
+            "State commitments set to every 100 certificates, keeping 100 pending"
+        ));
     }
 }
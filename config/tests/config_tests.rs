@@ -18,8 +18,12 @@
 // 1. Run `cargo insta test --review` under `./config`.
 // 2. Review, accept or reject changes.
 
-use config::{ConsensusAPIGrpcParameters, Import, Parameters, PrometheusMetricsParameters, Stake};
-use crypto::PublicKey;
+use config::{
+    CoinKeys, CoinShare, ConsensusAPIGrpcParameters, Import, Parameters,
+    PrometheusMetricsParameters, PruningParameters, RetentionPolicy, Stake,
+};
+use crypto::{coin, PublicKey};
+use fastcrypto::traits::KeyPair as _;
 use insta::assert_json_snapshot;
 use multiaddr::Multiaddr;
 use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
@@ -52,6 +56,91 @@ fn leader_election_rotates_through_all() {
     assert!(leader_counts_stepping_by_2.values().all(|v| *v >= 20));
 }
 
+#[test]
+fn coin_threshold() {
+    // With equal stakes, the coin is revealed by f+1 shares.
+    let fixture = CommitteeFixture::builder().build();
+    let committee = fixture.committee();
+    assert_eq!(committee.coin_threshold().unwrap(), 2);
+
+    let fixture = CommitteeFixture::builder()
+        .committee_size(7.try_into().unwrap())
+        .build();
+    let committee = fixture.committee();
+    assert_eq!(committee.coin_threshold().unwrap(), 3);
+
+    // With most of the stake held by one authority, a quorum of two authorities holds fewer
+    // shares than the faulty authorities together.
+    let mut committee = committee;
+    committee.authorities.values_mut().next().unwrap().stake = 10;
+    assert!(matches!(
+        committee.coin_threshold(),
+        Err(config::ConfigError::NoCoinThreshold)
+    ));
+}
+
+#[test]
+fn check_coin_key() {
+    let fixture = CommitteeFixture::builder().build();
+    let mut committee = fixture.committee();
+    let names: Vec<_> = committee.authorities.keys().cloned().collect();
+
+    // Any key pair is accepted when no coin keys were dealt.
+    committee.check_coin_key(&names[0], None).unwrap();
+
+    let threshold = committee.coin_threshold().unwrap();
+    let mut rng = StdRng::from_seed([0; 32]);
+    let (public_key, keypairs) = coin::deal(committee.size(), threshold, &mut rng);
+    let shares = (1..)
+        .zip(&names)
+        .zip(&keypairs)
+        .map(|((index, name), keypair)| {
+            let public_key = keypair.public().clone();
+            (name.clone(), CoinShare { index, public_key })
+        })
+        .collect();
+    committee.coin = Some(CoinKeys {
+        public_key,
+        threshold,
+        shares,
+    });
+
+    // Once they were, each authority needs its own share.
+    committee
+        .check_coin_key(&names[0], Some(keypairs[0].public()))
+        .unwrap();
+    assert!(matches!(
+        committee.check_coin_key(&names[0], None),
+        Err(config::ConfigError::MissingCoinKey(_))
+    ));
+    assert!(matches!(
+        committee.check_coin_key(&names[0], Some(keypairs[1].public())),
+        Err(config::ConfigError::WrongCoinKey(_))
+    ));
+}
+
+#[test]
+fn pruning_retains_gc_depth() {
+    let parameters = Parameters::default();
+    let pruning = PruningParameters {
+        retention: RetentionPolicy::Rounds,
+        retention_depth: parameters.gc_depth - 1,
+        ..PruningParameters::default()
+    };
+    assert!(matches!(
+        pruning.validate(parameters.gc_depth),
+        Err(config::ConfigError::InvalidPruning(_))
+    ));
+
+    // The sequence numbers are not counted in rounds: the pruner retains the last `gc_depth`
+    // rounds on top of them.
+    let pruning = PruningParameters {
+        retention: RetentionPolicy::SequenceNumbers,
+        ..pruning
+    };
+    assert!(pruning.validate(parameters.gc_depth).is_ok());
+}
+
 #[test]
 fn update_primary_network_info_test() {
     let fixture = CommitteeFixture::builder().build();
@@ -1,5 +1,6 @@
 // Copyright (c) 2022, Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
+use config::LeaderElection;
 use consensus::{
     bullshark::Bullshark,
     consensus::{ConsensusProtocol, ConsensusState},
@@ -49,11 +50,12 @@ pub fn process_certificates(c: &mut Criterion) {
             .sum();
         consensus_group.throughput(Throughput::Bytes(data_size as u64));
 
-        let mut ordering_engine = Bullshark {
-            committee: committee.clone(),
+        let mut ordering_engine = Bullshark::new(
+            committee.clone(),
             store,
             gc_depth,
-        };
+            LeaderElection::default(),
+        );
         consensus_group.bench_with_input(
             BenchmarkId::new("batched", certificates.len()),
             &certificates,
@@ -3,9 +3,10 @@
 // SPDX-License-Identifier: Apache-2.0
 use crate::{
     consensus::{ConsensusProtocol, ConsensusState, Dag},
+    leader_schedule::LeaderSchedule,
     utils, ConsensusOutput,
 };
-use config::{Committee, Stake};
+use config::{Committee, LeaderElection, LeaderReputationParameters, Stake};
 use fastcrypto::{traits::EncodeDecodeBase64, Hash};
 use std::{collections::HashMap, sync::Arc};
 use tracing::debug;
@@ -22,6 +23,10 @@ pub struct Bullshark {
     pub store: Arc<ConsensusStore>,
     /// The depth of the garbage collector.
     pub gc_depth: Round,
+    /// How the leader of each round is elected.
+    pub leader_election: LeaderElection,
+    /// Swaps the leaders with a poor reputation out of the schedule.
+    pub leader_schedule: LeaderSchedule,
 }
 
 impl ConsensusProtocol for Bullshark {
@@ -57,8 +62,13 @@ impl ConsensusProtocol for Bullshark {
         if leader_round <= state.last_committed_round {
             return Ok(Vec::new());
         }
-        let (leader_digest, leader) = match Self::leader(&self.committee, leader_round, &state.dag)
-        {
+        let (leader_digest, leader) = match Self::leader(
+            &self.committee,
+            self.leader_election,
+            &self.leader_schedule,
+            leader_round,
+            &state.dag,
+        ) {
             Some(x) => x,
             None => return Ok(Vec::new()),
         };
@@ -84,16 +94,24 @@ impl ConsensusProtocol for Bullshark {
         // Get an ordered list of past leaders that are linked to the current leader.
         debug!("Leader {:?} has enough support", leader);
         let mut sequence = Vec::new();
-        for leader in utils::order_leaders(&self.committee, leader, state, Self::leader)
-            .iter()
-            .rev()
-        {
+        let leaders =
+            utils::order_leaders(&self.committee, leader, state, |committee, round, dag| {
+                Self::leader(
+                    committee,
+                    self.leader_election,
+                    &self.leader_schedule,
+                    round,
+                    dag,
+                )
+            });
+        for leader in leaders.iter().rev() {
             // Starting from the oldest leader, flatten the sub-dag referenced by the leader.
             for x in utils::order_dag(self.gc_depth, leader, state) {
                 let digest = x.digest();
 
                 // Update and clean up internal state.
                 state.update(&x, self.gc_depth);
+                self.leader_schedule.commit(&x, consensus_index);
 
                 // Add the certificate to the sequence.
                 sequence.push(ConsensusOutput {
@@ -110,8 +128,15 @@ impl ConsensusProtocol for Bullshark {
                     &state.last_committed,
                     &consensus_index,
                     &digest,
+                    &leader.round(),
                 )?;
             }
+
+            // The remaining leaders were elected with an outdated schedule. They are elected again
+            // with the new schedule when processing the next certificates.
+            if self.leader_schedule.update(consensus_index) {
+                break;
+            }
         }
 
         // Log the latest committed round of every authority (for debug).
@@ -125,6 +150,7 @@ impl ConsensusProtocol for Bullshark {
     }
 
     fn update_committee(&mut self, new_committee: Committee) -> StoreResult<()> {
+        self.leader_schedule.reset(new_committee.clone());
         self.committee = new_committee;
         self.store.clear()
     }
@@ -132,35 +158,64 @@ impl ConsensusProtocol for Bullshark {
 
 impl Bullshark {
     /// Create a new Bullshark consensus instance.
-    pub fn new(committee: Committee, store: Arc<ConsensusStore>, gc_depth: Round) -> Self {
+    pub fn new(
+        committee: Committee,
+        store: Arc<ConsensusStore>,
+        gc_depth: Round,
+        leader_election: LeaderElection,
+    ) -> Self {
         Self {
+            leader_schedule: LeaderSchedule::new(
+                committee.clone(),
+                LeaderReputationParameters::default(),
+            ),
             committee,
             store,
             gc_depth,
+            leader_election,
         }
     }
 
+    /// Use a reputation-based leader schedule.
+    pub fn with_leader_schedule(mut self, leader_schedule: LeaderSchedule) -> Self {
+        self.leader_schedule = leader_schedule;
+        self
+    }
+
     /// Returns the certificate (and the certificate's digest) originated by the leader of the
     /// specified round (if any).
     fn leader<'a>(
         committee: &Committee,
+        leader_election: LeaderElection,
+        leader_schedule: &LeaderSchedule,
         round: Round,
         dag: &'a Dag,
     ) -> Option<&'a (CertificateDigest, Certificate)> {
-        // Note: this function is often called with even rounds only. While we do not aim at random selection
-        // yet (see issue #10), repeated calls to this function should still pick from the whole roster of leaders.
+        // Note: this function is often called with even rounds only. Repeated calls to this function
+        // should still pick from the whole roster of leaders.
 
         cfg_if::cfg_if! {
             if #[cfg(test)] {
-                // consensus tests rely on returning the same leader.
-                let leader = committee.authorities.iter().next().expect("Empty authorities table!").0;
+                // consensus tests rely on returning the same leader, unless they exercise the coin.
+                let leader = match leader_election {
+                    LeaderElection::Deterministic => {
+                        committee.authorities.keys().next().expect("Empty authorities table!").clone()
+                    }
+                    LeaderElection::RandomCoin => {
+                        utils::elect_leader(committee, leader_election, round, round, dag)?
+                    }
+                };
             } else {
-                // Elect the leader in a stake-weighted choice seeded by the round
-                let leader = &committee.leader(round);
+                // The coin of the leader round is revealed by the leader round itself: the leader
+                // cannot be known before the headers of that round are disseminated.
+                let leader = utils::elect_leader(committee, leader_election, round, round, dag)?;
             }
         }
 
+        // Deprioritize the authorities with a poor reputation.
+        let leader = leader_schedule.leader(round, leader);
+
         // Return its certificate and the certificate's digest.
-        dag.get(&round).and_then(|x| x.get(leader))
+        dag.get(&round).and_then(|x| x.get(&leader))
     }
 }
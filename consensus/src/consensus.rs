@@ -182,7 +182,7 @@ pub struct Consensus<ConsensusProtocol> {
     /// if it already sent us its whole history.
     rx_primary: metered_channel::Receiver<Certificate>,
     /// Outputs the sequence of ordered certificates to the primary (for cleanup and feedback).
-    tx_primary: metered_channel::Sender<Certificate>,
+    tx_primary: metered_channel::Sender<ConsensusOutput>,
     /// Outputs the sequence of ordered certificates to the application layer.
     tx_output: metered_channel::Sender<ConsensusOutput>,
 
@@ -208,7 +208,7 @@ where
         cert_store: CertificateStore,
         rx_reconfigure: watch::Receiver<ReconfigureNotification>,
         rx_primary: metered_channel::Receiver<Certificate>,
-        tx_primary: metered_channel::Sender<Certificate>,
+        tx_primary: metered_channel::Sender<ConsensusOutput>,
         tx_output: metered_channel::Sender<ConsensusOutput>,
         protocol: Protocol,
         metrics: Arc<ConsensusMetrics>,
@@ -325,7 +325,7 @@ where
                         }
 
                         self.tx_primary
-                            .send(certificate.clone())
+                            .send(output.clone())
                             .await
                             .expect("Failed to send certificate to primary");
 
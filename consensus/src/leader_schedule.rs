@@ -0,0 +1,194 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use config::{Committee, LeaderReputationParameters, Stake};
+use crypto::PublicKey;
+use fastcrypto::{traits::EncodeDecodeBase64, Hash};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use storage::CertificateStore;
+use tracing::debug;
+use types::{Certificate, CertificateDigest, ConsensusStore, Round, SequenceNumber, StoreResult};
+
+#[cfg(test)]
+#[path = "tests/leader_schedule_tests.rs"]
+pub mod leader_schedule_tests;
+
+/// Deprioritizes the authorities with a poor reputation in the leader schedule. An authority earns
+/// reputation when its certificates are committed and when they are referenced by committed
+/// certificates. The scores only depend on the committed sequence, so that all honest nodes
+/// compute the same schedule.
+pub struct LeaderSchedule {
+    /// The committee information.
+    committee: Committee,
+    /// The reputation parameters.
+    parameters: LeaderReputationParameters,
+    /// The window of sequenced certificates from which the current schedule will be derived.
+    window: u64,
+    /// The reputation scores accumulated in each window that is not yet reflected in the schedule.
+    scores: BTreeMap<u64, HashMap<PublicKey, u64>>,
+    /// The author and window of the certificates committed since the start of the current window.
+    committed: HashMap<CertificateDigest, (PublicKey, u64)>,
+    /// The authorities that may not lead any round.
+    bad_nodes: HashSet<PublicKey>,
+    /// The authorities leading the rounds of the bad nodes, best first.
+    good_nodes: Vec<PublicKey>,
+}
+
+impl LeaderSchedule {
+    /// Create a schedule that does not deprioritize any authority yet.
+    pub fn new(committee: Committee, parameters: LeaderReputationParameters) -> Self {
+        Self {
+            committee,
+            parameters,
+            window: 0,
+            scores: BTreeMap::new(),
+            committed: HashMap::new(),
+            bad_nodes: HashSet::new(),
+            good_nodes: Vec::new(),
+        }
+    }
+
+    /// Rebuild the schedule after a crash by replaying the certificates sequenced over the
+    /// current and the previous windows.
+    pub fn recover(
+        committee: Committee,
+        parameters: LeaderReputationParameters,
+        consensus_store: &ConsensusStore,
+        certificate_store: &CertificateStore,
+    ) -> StoreResult<Self> {
+        let mut schedule = Self::new(committee, parameters);
+        let next_index = consensus_store.read_last_consensus_index()?;
+        if !schedule.parameters.enabled || next_index == 0 {
+            return Ok(schedule);
+        }
+
+        let window_size = schedule.parameters.window_size;
+        let start = (next_index / window_size).saturating_sub(1) * window_size;
+        schedule.window = start / window_size;
+
+        // The certificate sequenced at index `i` is persisted under the key `i + 1`.
+        let digests = consensus_store.read_sequenced_certificates(&(start + 1..=next_index))?;
+        for (digest, consensus_index) in digests.into_iter().zip(start..next_index) {
+            let certificate = match digest {
+                Some(digest) => certificate_store.read(digest)?,
+                None => None,
+            };
+            if let Some(certificate) = certificate {
+                schedule.commit(&certificate, consensus_index);
+            }
+        }
+        schedule.update(next_index);
+        Ok(schedule)
+    }
+
+    /// Forget all reputation scores, typically when the committee changes.
+    pub fn reset(&mut self, committee: Committee) {
+        *self = Self::new(committee, self.parameters.clone());
+    }
+
+    /// Returns the authority leading the specified round in place of the elected leader.
+    pub fn leader(&self, round: Round, elected: PublicKey) -> PublicKey {
+        if !self.bad_nodes.contains(&elected) || self.good_nodes.is_empty() {
+            return elected;
+        }
+        let index = (round / 2) as usize % self.good_nodes.len();
+        self.good_nodes[index].clone()
+    }
+
+    /// Update the reputation scores with a newly sequenced certificate.
+    pub fn commit(&mut self, certificate: &Certificate, consensus_index: SequenceNumber) {
+        if !self.parameters.enabled {
+            return;
+        }
+
+        // Only reward references within the same window, so that the scores of a window do not
+        // depend on when the previous schedule was computed.
+        let window = consensus_index / self.parameters.window_size;
+        let referenced: Vec<_> = certificate
+            .header
+            .parents
+            .iter()
+            .filter_map(|x| self.committed.get(x))
+            .filter(|(_, w)| *w == window)
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        let scores = self.scores.entry(window).or_insert_with(HashMap::new);
+        *scores.entry(certificate.origin()).or_insert(0) += 1;
+        for name in referenced {
+            *scores.entry(name).or_insert(0) += 1;
+        }
+
+        self.committed
+            .insert(certificate.digest(), (certificate.origin(), window));
+    }
+
+    /// Recompute the schedule if the sequence moved past the current window. This should only be
+    /// called once all the certificates of a leader are sequenced. Returns whether the schedule
+    /// changed, in which case the leaders elected with the previous schedule must be discarded.
+    pub fn update(&mut self, next_index: SequenceNumber) -> bool {
+        if !self.parameters.enabled {
+            return false;
+        }
+
+        let window = next_index / self.parameters.window_size;
+        if window <= self.window {
+            return false;
+        }
+
+        let scores = self.scores.remove(&(window - 1)).unwrap_or_default();
+        self.compute(&scores);
+        self.window = window;
+        self.scores.retain(|w, _| *w >= window);
+        self.committed.retain(|_, (_, w)| *w >= window);
+
+        debug!(
+            "Leader schedule updated at index {}, bad nodes: {:?}",
+            next_index,
+            self.bad_nodes
+                .iter()
+                .map(|x| x.encode_base64())
+                .collect::<Vec<_>>()
+        );
+        true
+    }
+
+    /// Derive the bad and good nodes from the reputation scores of a window.
+    fn compute(&mut self, scores: &HashMap<PublicKey, u64>) {
+        let total_stake: Stake = self.committee.authorities.values().map(|x| x.stake).sum();
+        let limit = (total_stake * self.parameters.bad_nodes_stake_percent / 100)
+            .min(self.committee.validity_threshold() - 1);
+
+        // Sort the authorities by score, breaking ties by name so all nodes agree.
+        let mut ranking: Vec<_> = self
+            .committee
+            .authorities
+            .iter()
+            .map(|(name, authority)| {
+                (
+                    scores.get(name).copied().unwrap_or(0),
+                    name,
+                    authority.stake,
+                )
+            })
+            .collect();
+        ranking.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(b.1)));
+        let best = ranking.last().map(|x| x.0).unwrap_or(0);
+
+        self.bad_nodes.clear();
+        let mut bad_stake = 0;
+        for (score, name, stake) in &ranking {
+            if *score == best || bad_stake + stake > limit {
+                break;
+            }
+            bad_stake += stake;
+            self.bad_nodes.insert((*name).clone());
+        }
+
+        self.good_nodes = ranking
+            .iter()
+            .rev()
+            .map(|(_, name, _)| (*name).clone())
+            .filter(|name| !self.bad_nodes.contains(name))
+            .collect();
+    }
+}
@@ -10,6 +10,7 @@
 pub mod bullshark;
 pub mod consensus;
 pub mod dag;
+pub mod leader_schedule;
 pub mod metrics;
 pub mod tusk;
 mod utils;
@@ -4,10 +4,13 @@
 use super::*;
 
 use crate::{metrics::ConsensusMetrics, Consensus};
+use config::{CoinKeys, CoinShare};
 use crypto::PublicKey;
 #[allow(unused_imports)]
 use fastcrypto::traits::KeyPair;
+use fastcrypto::traits::Signer;
 use prometheus::Registry;
+use rand::{rngs::StdRng, SeedableRng};
 #[cfg(test)]
 use std::collections::{BTreeSet, VecDeque};
 use storage::CertificateStore;
@@ -16,21 +19,44 @@ use test_utils::CommitteeFixture;
 #[allow(unused_imports)]
 use tokio::sync::mpsc::channel;
 use tokio::sync::watch;
-use types::{CertificateDigest, ReconfigureNotification};
+use types::{coin_message, CertificateDigest, ReconfigureNotification};
 
 pub fn make_consensus_store(store_path: &std::path::Path) -> Arc<ConsensusStore> {
     const LAST_COMMITTED_CF: &str = "last_committed";
     const SEQUENCE_CF: &str = "sequence";
+    const SEQUENCE_BY_CERTIFICATE_CF: &str = "sequence_by_certificate";
+    const SEQUENCE_BY_LEADER_ROUND_CF: &str = "sequence_by_leader_round";
 
-    let rocksdb = rocks::open_cf(store_path, None, &[LAST_COMMITTED_CF, SEQUENCE_CF])
-        .expect("Failed to create database");
-
-    let (last_committed_map, sequence_map) = reopen!(&rocksdb,
+    let rocksdb = rocks::open_cf(
+        store_path,
+        None,
+        &[
+            LAST_COMMITTED_CF,
+            SEQUENCE_CF,
+            SEQUENCE_BY_CERTIFICATE_CF,
+            SEQUENCE_BY_LEADER_ROUND_CF,
+        ],
+    )
+    .expect("Failed to create database");
+
+    let (
+        last_committed_map,
+        sequence_map,
+        sequence_by_certificate_map,
+        sequence_by_leader_round_map,
+    ) = reopen!(&rocksdb,
         LAST_COMMITTED_CF;<PublicKey, Round>,
-        SEQUENCE_CF;<SequenceNumber, CertificateDigest>
+        SEQUENCE_CF;<SequenceNumber, CertificateDigest>,
+        SEQUENCE_BY_CERTIFICATE_CF;<CertificateDigest, SequenceNumber>,
+        SEQUENCE_BY_LEADER_ROUND_CF;<(Round, SequenceNumber), CertificateDigest>
     );
 
-    Arc::new(ConsensusStore::new(last_committed_map, sequence_map))
+    Arc::new(ConsensusStore::new(
+        last_committed_map,
+        sequence_map,
+        sequence_by_certificate_map,
+        sequence_by_leader_round_map,
+    ))
 }
 
 pub fn make_certificate_store(store_path: &std::path::Path) -> CertificateStore {
@@ -86,7 +112,12 @@ async fn commit_one() {
     let store = make_consensus_store(&test_utils::temp_dir());
     let cert_store = make_certificate_store(&test_utils::temp_dir());
     let gc_depth = 50;
-    let bullshark = Bullshark::new(committee.clone(), store.clone(), gc_depth);
+    let bullshark = Bullshark::new(
+        committee.clone(),
+        store.clone(),
+        gc_depth,
+        LeaderElection::default(),
+    );
     let metrics = Arc::new(ConsensusMetrics::new(&Registry::new()));
     let _consensus_handle = Consensus::spawn(
         committee,
@@ -148,7 +179,12 @@ async fn dead_node() {
     let store = make_consensus_store(&test_utils::temp_dir());
     let cert_store = make_certificate_store(&test_utils::temp_dir());
     let gc_depth = 50;
-    let bullshark = Bullshark::new(committee.clone(), store.clone(), gc_depth);
+    let bullshark = Bullshark::new(
+        committee.clone(),
+        store.clone(),
+        gc_depth,
+        LeaderElection::default(),
+    );
     let metrics = Arc::new(ConsensusMetrics::new(&Registry::new()));
 
     let _consensus_handle = Consensus::spawn(
@@ -261,7 +297,12 @@ async fn not_enough_support() {
     let store = make_consensus_store(&test_utils::temp_dir());
     let cert_store = make_certificate_store(&test_utils::temp_dir());
     let gc_depth = 50;
-    let bullshark = Bullshark::new(committee.clone(), store.clone(), gc_depth);
+    let bullshark = Bullshark::new(
+        committee.clone(),
+        store.clone(),
+        gc_depth,
+        LeaderElection::default(),
+    );
     let metrics = Arc::new(ConsensusMetrics::new(&Registry::new()));
 
     let _consensus_handle = Consensus::spawn(
@@ -344,7 +385,12 @@ async fn missing_leader() {
     let store = make_consensus_store(&test_utils::temp_dir());
     let cert_store = make_certificate_store(&test_utils::temp_dir());
     let gc_depth = 50;
-    let bullshark = Bullshark::new(committee.clone(), store.clone(), gc_depth);
+    let bullshark = Bullshark::new(
+        committee.clone(),
+        store.clone(),
+        gc_depth,
+        LeaderElection::default(),
+    );
     let metrics = Arc::new(ConsensusMetrics::new(&Registry::new()));
 
     let _consensus_handle = Consensus::spawn(
@@ -403,7 +449,12 @@ async fn epoch_change() {
     let store = make_consensus_store(&test_utils::temp_dir());
     let cert_store = make_certificate_store(&test_utils::temp_dir());
     let gc_depth = 50;
-    let bullshark = Bullshark::new(committee.clone(), store.clone(), gc_depth);
+    let bullshark = Bullshark::new(
+        committee.clone(),
+        store.clone(),
+        gc_depth,
+        LeaderElection::default(),
+    );
     let metrics = Arc::new(ConsensusMetrics::new(&Registry::new()));
     let _consensus_handle = Consensus::spawn(
         committee.clone(),
@@ -492,7 +543,12 @@ async fn restart_with_new_committee() {
         let cert_store = make_certificate_store(&test_utils::temp_dir());
         let gc_depth = 50;
         let metrics = Arc::new(ConsensusMetrics::new(&Registry::new()));
-        let bullshark = Bullshark::new(committee.clone(), store.clone(), gc_depth);
+        let bullshark = Bullshark::new(
+            committee.clone(),
+            store.clone(),
+            gc_depth,
+            LeaderElection::default(),
+        );
 
         let handle = Consensus::spawn(
             committee.clone(),
@@ -560,3 +616,282 @@ async fn restart_with_new_committee() {
         handle.await.unwrap();
     }
 }
+
+// Deal the keys of the common coin to the committee. Returns the key pair of the share of every
+// authority, in the order of the committee.
+fn deal_coin(committee: &mut Committee) -> Vec<crypto::KeyPair> {
+    let mut rng = StdRng::from_seed([0; 32]);
+    let threshold = committee.coin_threshold().unwrap();
+    let (public_key, keypairs) = crypto::coin::deal(committee.size(), threshold, &mut rng);
+    let shares = committee
+        .authorities
+        .keys()
+        .zip(&keypairs)
+        .enumerate()
+        .map(|(i, (name, keypair))| {
+            let share = CoinShare {
+                index: i as u64 + 1,
+                public_key: keypair.public().clone(),
+            };
+            (name.clone(), share)
+        })
+        .collect();
+    committee.coin = Some(CoinKeys {
+        public_key,
+        threshold,
+        shares,
+    });
+    keypairs
+}
+
+// Make the certificates carry the shares of the common coin of their author.
+fn reveal_coin_shares(
+    committee: &Committee,
+    keypairs: &[crypto::KeyPair],
+    certificates: &mut VecDeque<Certificate>,
+) {
+    for certificate in certificates.iter_mut() {
+        let position = committee
+            .authorities
+            .keys()
+            .position(|x| *x == certificate.origin())
+            .unwrap();
+        let message = coin_message(certificate.epoch(), certificate.round());
+        certificate.header.randomness = keypairs[position].sign(message.as_ref());
+    }
+}
+
+// Derive the leader elected by the common coin of a round from the shares of some authorities.
+fn coin_leader(
+    committee: &Committee,
+    keypairs: &[crypto::KeyPair],
+    positions: &[usize],
+    round: Round,
+) -> PublicKey {
+    let message = coin_message(committee.epoch(), round);
+    let shares: Vec<_> = positions
+        .iter()
+        .map(|i| (*i as u64 + 1, keypairs[*i].sign(message.as_ref())))
+        .collect();
+    let coin = crypto::coin::combine(&shares).unwrap();
+    committee.leader_from_randomness(fastcrypto::blake2b_256(|hasher| {
+        blake2::digest::Update::update(hasher, coin.as_ref())
+    }))
+}
+
+// Run for 3 dag rounds of signed certificates in ideal conditions, electing the leaders with the
+// common coin. We should commit the leader of round 2 seeded by the coin of round 2, whichever
+// shares it is combined from.
+#[test]
+fn commit_one_with_random_coin() {
+    let fixture = CommitteeFixture::builder().build();
+    let mut committee = fixture.committee();
+    let keys: Vec<_> = fixture.authorities().map(|a| a.keypair().copy()).collect();
+    let coin_keypairs = deal_coin(&mut committee);
+    let genesis = Certificate::genesis(&committee)
+        .iter()
+        .map(|x| x.digest())
+        .collect::<BTreeSet<_>>();
+    let (mut certificates, _) =
+        test_utils::make_optimal_signed_certificates(1..=3, &genesis, &committee, &keys);
+    reveal_coin_shares(&committee, &coin_keypairs, &mut certificates);
+
+    // Derive the leader of round 2 from shares other than the ones consensus combines.
+    let expected = coin_leader(&committee, &coin_keypairs, &[3, 2], 2);
+
+    let metrics = Arc::new(ConsensusMetrics::new(&Registry::new()));
+    let mut state = ConsensusState::new(Certificate::genesis(&committee), metrics);
+    let store = make_consensus_store(&test_utils::temp_dir());
+    let mut bullshark = Bullshark::new(committee, store, 50, LeaderElection::RandomCoin);
+
+    let mut sequence = Vec::new();
+    for certificate in certificates {
+        sequence.extend(
+            bullshark
+                .process_certificate(&mut state, 0, certificate)
+                .unwrap(),
+        );
+    }
+
+    // Ensure the last ordered certificate is the elected leader of round 2.
+    let output = sequence.last().unwrap();
+    assert_eq!(output.certificate.round(), 2);
+    assert_eq!(output.certificate.origin(), expected);
+}
+
+// Run for 3 dag rounds of signed certificates, electing the leaders with the common coin, while
+// an authority other than the leader withholds its certificate of round 2. The remaining shares
+// still reveal the coin, so we should commit the same leader.
+#[test]
+fn commit_one_with_withheld_coin_share() {
+    let fixture = CommitteeFixture::builder().build();
+    let mut committee = fixture.committee();
+    let keys: Vec<_> = fixture.authorities().map(|a| a.keypair().copy()).collect();
+    let coin_keypairs = deal_coin(&mut committee);
+    let genesis = Certificate::genesis(&committee)
+        .iter()
+        .map(|x| x.digest())
+        .collect::<BTreeSet<_>>();
+    let (mut certificates, _) =
+        test_utils::make_optimal_signed_certificates(1..=3, &genesis, &committee, &keys);
+    reveal_coin_shares(&committee, &coin_keypairs, &mut certificates);
+
+    let expected = coin_leader(&committee, &coin_keypairs, &[0, 1], 2);
+    let withheld = committee
+        .authorities
+        .keys()
+        .find(|x| **x != expected)
+        .unwrap()
+        .clone();
+
+    let metrics = Arc::new(ConsensusMetrics::new(&Registry::new()));
+    let mut state = ConsensusState::new(Certificate::genesis(&committee), metrics);
+    let store = make_consensus_store(&test_utils::temp_dir());
+    let mut bullshark = Bullshark::new(committee, store, 50, LeaderElection::RandomCoin);
+
+    let mut sequence = Vec::new();
+    for certificate in certificates
+        .into_iter()
+        .filter(|x| x.round() != 2 || x.origin() != withheld)
+    {
+        sequence.extend(
+            bullshark
+                .process_certificate(&mut state, 0, certificate)
+                .unwrap(),
+        );
+    }
+
+    let output = sequence.last().unwrap();
+    assert_eq!(output.certificate.round(), 2);
+    assert_eq!(output.certificate.origin(), expected);
+}
+
+// Run for 3 dag rounds of signed certificates, electing the leaders with the common coin, while
+// receiving fewer than `threshold` certificates of round 2. The coin of round 2 is not revealed,
+// so we should not commit anything.
+#[test]
+fn no_commit_without_random_coin() {
+    let fixture = CommitteeFixture::builder().build();
+    let mut committee = fixture.committee();
+    let keys: Vec<_> = fixture.authorities().map(|a| a.keypair().copy()).collect();
+    let coin_keypairs = deal_coin(&mut committee);
+    let genesis = Certificate::genesis(&committee)
+        .iter()
+        .map(|x| x.digest())
+        .collect::<BTreeSet<_>>();
+    let (mut certificates, _) =
+        test_utils::make_optimal_signed_certificates(1..=3, &genesis, &committee, &keys);
+    reveal_coin_shares(&committee, &coin_keypairs, &mut certificates);
+
+    let metrics = Arc::new(ConsensusMetrics::new(&Registry::new()));
+    let mut state = ConsensusState::new(Certificate::genesis(&committee), metrics);
+    let store = make_consensus_store(&test_utils::temp_dir());
+    let threshold = committee.coin.as_ref().unwrap().threshold;
+    let revealed: Vec<_> = committee
+        .authorities
+        .keys()
+        .take(threshold - 1)
+        .cloned()
+        .collect();
+    let mut bullshark = Bullshark::new(committee, store, 50, LeaderElection::RandomCoin);
+
+    for certificate in certificates
+        .into_iter()
+        .filter(|x| x.round() != 2 || revealed.contains(&x.origin()))
+    {
+        let sequence = bullshark
+            .process_certificate(&mut state, 0, certificate)
+            .unwrap();
+        assert!(sequence.is_empty());
+    }
+}
+
+// Run for 3 dag rounds of signed certificates, electing the leaders with the common coin, in a
+// committee without coin keys. We should fall back to the deterministic leader of round 2.
+#[test]
+fn commit_one_with_random_coin_without_coin_keys() {
+    let fixture = CommitteeFixture::builder().build();
+    let committee = fixture.committee();
+    let keys: Vec<_> = fixture.authorities().map(|a| a.keypair().copy()).collect();
+    let genesis = Certificate::genesis(&committee)
+        .iter()
+        .map(|x| x.digest())
+        .collect::<BTreeSet<_>>();
+    let (certificates, _) =
+        test_utils::make_optimal_signed_certificates(1..=3, &genesis, &committee, &keys);
+    let expected = committee.leader(2);
+
+    let metrics = Arc::new(ConsensusMetrics::new(&Registry::new()));
+    let mut state = ConsensusState::new(Certificate::genesis(&committee), metrics);
+    let store = make_consensus_store(&test_utils::temp_dir());
+    let mut bullshark = Bullshark::new(committee, store, 50, LeaderElection::RandomCoin);
+
+    let mut sequence = Vec::new();
+    for certificate in certificates {
+        sequence.extend(
+            bullshark
+                .process_certificate(&mut state, 0, certificate)
+                .unwrap(),
+        );
+    }
+
+    let output = sequence.last().unwrap();
+    assert_eq!(output.certificate.round(), 2);
+    assert_eq!(output.certificate.origin(), expected);
+}
+
+// Commit the leader of round 2 and ensure the sequence can be looked up by certificate and by
+// leader round.
+#[test]
+fn commit_one_is_indexed() {
+    let fixture = CommitteeFixture::builder().build();
+    let committee = fixture.committee();
+    let keys: Vec<_> = fixture.authorities().map(|a| a.public_key()).collect();
+    let genesis = Certificate::genesis(&committee)
+        .iter()
+        .map(|x| x.digest())
+        .collect::<BTreeSet<_>>();
+    let (mut certificates, next_parents) =
+        test_utils::make_optimal_certificates(&committee, 1..=2, &genesis, &keys);
+    let (_, certificate) =
+        test_utils::mock_certificate(&committee, keys[0].clone(), 3, next_parents.clone());
+    certificates.push_back(certificate);
+    let (_, certificate) =
+        test_utils::mock_certificate(&committee, keys[1].clone(), 3, next_parents);
+    certificates.push_back(certificate);
+    let uncommitted = certificates.back().unwrap().digest();
+
+    let metrics = Arc::new(ConsensusMetrics::new(&Registry::new()));
+    let mut state = ConsensusState::new(Certificate::genesis(&committee), metrics);
+    let store = make_consensus_store(&test_utils::temp_dir());
+    let mut bullshark = Bullshark::new(committee, store.clone(), 50, LeaderElection::default());
+
+    let mut sequence = Vec::new();
+    for certificate in certificates {
+        sequence.extend(
+            bullshark
+                .process_certificate(&mut state, 0, certificate)
+                .unwrap(),
+        );
+    }
+    assert_eq!(sequence.len(), 5);
+
+    // Every committed certificate maps back to its consensus index.
+    for output in &sequence {
+        assert_eq!(
+            store
+                .read_consensus_index(&output.certificate.digest())
+                .unwrap(),
+            Some(output.consensus_index)
+        );
+    }
+    assert_eq!(store.read_consensus_index(&uncommitted).unwrap(), None);
+
+    // The whole sub-dag is committed by the leader of round 2, in order.
+    let expected: Vec<_> = sequence
+        .iter()
+        .map(|x| (x.consensus_index, x.certificate.digest()))
+        .collect();
+    assert_eq!(store.read_committed_by_leader_round(2).unwrap(), expected);
+    assert!(store.read_committed_by_leader_round(4).unwrap().is_empty());
+}
@@ -0,0 +1,146 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use super::*;
+
+use crate::bullshark::bullshark_tests::make_certificate_store;
+use std::collections::BTreeSet;
+use test_utils::{make_consensus_store, CommitteeFixture};
+
+fn parameters() -> LeaderReputationParameters {
+    LeaderReputationParameters {
+        enabled: true,
+        window_size: 4,
+        bad_nodes_stake_percent: 25,
+    }
+}
+
+fn genesis(committee: &Committee) -> BTreeSet<CertificateDigest> {
+    Certificate::genesis(committee)
+        .iter()
+        .map(|x| x.digest())
+        .collect()
+}
+
+#[test]
+fn silent_authority_is_swapped_out() {
+    let fixture = CommitteeFixture::builder().build();
+    let committee = fixture.committee();
+    let mut keys: Vec<_> = fixture.authorities().map(|a| a.public_key()).collect();
+    keys.sort();
+    let silent = keys.pop().unwrap();
+
+    // Only the first three authorities get their certificates committed.
+    let (certificates, _) =
+        test_utils::make_optimal_certificates(&committee, 1..=2, &genesis(&committee), &keys);
+
+    let mut schedule = LeaderSchedule::new(committee, parameters());
+    for (consensus_index, certificate) in certificates.iter().enumerate() {
+        schedule.commit(certificate, consensus_index as SequenceNumber);
+    }
+
+    // The schedule is recomputed once the sequence moves past the first window.
+    assert!(!schedule.update(3));
+    assert!(schedule.update(6));
+    assert!(!schedule.update(7));
+
+    // The silent authority no longer leads any round, the others keep their rounds.
+    for round in (2..20).step_by(2) {
+        assert_ne!(schedule.leader(round, silent.clone()), silent);
+        for name in &keys {
+            assert_eq!(&schedule.leader(round, name.clone()), name);
+        }
+    }
+}
+
+#[test]
+fn equal_scores_are_not_penalized() {
+    let fixture = CommitteeFixture::builder().build();
+    let committee = fixture.committee();
+    let keys: Vec<_> = fixture.authorities().map(|a| a.public_key()).collect();
+
+    let (certificates, _) =
+        test_utils::make_optimal_certificates(&committee, 1..=1, &genesis(&committee), &keys);
+
+    let mut schedule = LeaderSchedule::new(committee, parameters());
+    for (consensus_index, certificate) in certificates.iter().enumerate() {
+        schedule.commit(certificate, consensus_index as SequenceNumber);
+    }
+    assert!(schedule.update(4));
+
+    for name in &keys {
+        assert_eq!(&schedule.leader(2, name.clone()), name);
+    }
+}
+
+#[test]
+fn disabled_schedule_never_changes() {
+    let fixture = CommitteeFixture::builder().build();
+    let committee = fixture.committee();
+    let mut keys: Vec<_> = fixture.authorities().map(|a| a.public_key()).collect();
+    let silent = keys.pop().unwrap();
+
+    let (certificates, _) =
+        test_utils::make_optimal_certificates(&committee, 1..=2, &genesis(&committee), &keys);
+
+    let mut schedule = LeaderSchedule::new(
+        committee,
+        LeaderReputationParameters {
+            enabled: false,
+            ..parameters()
+        },
+    );
+    for (consensus_index, certificate) in certificates.iter().enumerate() {
+        schedule.commit(certificate, consensus_index as SequenceNumber);
+    }
+
+    assert!(!schedule.update(6));
+    assert_eq!(schedule.leader(2, silent.clone()), silent);
+}
+
+#[test]
+fn recover_matches_live_schedule() {
+    let fixture = CommitteeFixture::builder().build();
+    let committee = fixture.committee();
+    let mut keys: Vec<_> = fixture.authorities().map(|a| a.public_key()).collect();
+    keys.sort();
+    let _ = keys.pop().unwrap();
+
+    let (certificates, _) =
+        test_utils::make_optimal_certificates(&committee, 1..=3, &genesis(&committee), &keys);
+
+    // Sequence the certificates as consensus would, in two leaders' sub-dags.
+    let consensus_store = make_consensus_store(&test_utils::temp_dir());
+    let certificate_store = make_certificate_store(&test_utils::temp_dir());
+    let mut live = LeaderSchedule::new(committee.clone(), parameters());
+    for (consensus_index, certificate) in certificates.iter().enumerate() {
+        let consensus_index = consensus_index as SequenceNumber;
+        certificate_store.write(certificate.clone()).unwrap();
+        consensus_store
+            .write_consensus_state(
+                &HashMap::new(),
+                &(consensus_index + 1),
+                &certificate.digest(),
+                &certificate.round(),
+            )
+            .unwrap();
+        live.commit(certificate, consensus_index);
+
+        if consensus_index + 1 == 5 {
+            assert!(live.update(5));
+        }
+    }
+    assert!(live.update(9));
+
+    // Rebuild the schedule from the stores.
+    let recovered = LeaderSchedule::recover(
+        committee,
+        parameters(),
+        &consensus_store,
+        &certificate_store,
+    )
+    .unwrap();
+
+    assert_eq!(recovered.window, live.window);
+    assert_eq!(recovered.bad_nodes, live.bad_nodes);
+    assert_eq!(recovered.good_nodes, live.good_nodes);
+}
@@ -21,16 +21,39 @@ use types::{CertificateDigest, ReconfigureNotification};
 pub fn make_consensus_store(store_path: &std::path::Path) -> Arc<ConsensusStore> {
     const LAST_COMMITTED_CF: &str = "last_committed";
     const SEQUENCE_CF: &str = "sequence";
+    const SEQUENCE_BY_CERTIFICATE_CF: &str = "sequence_by_certificate";
+    const SEQUENCE_BY_LEADER_ROUND_CF: &str = "sequence_by_leader_round";
 
-    let rocksdb = rocks::open_cf(store_path, None, &[LAST_COMMITTED_CF, SEQUENCE_CF])
-        .expect("Failed to create database");
-
-    let (last_committed_map, sequence_map) = reopen!(&rocksdb,
+    let rocksdb = rocks::open_cf(
+        store_path,
+        None,
+        &[
+            LAST_COMMITTED_CF,
+            SEQUENCE_CF,
+            SEQUENCE_BY_CERTIFICATE_CF,
+            SEQUENCE_BY_LEADER_ROUND_CF,
+        ],
+    )
+    .expect("Failed to create database");
+
+    let (
+        last_committed_map,
+        sequence_map,
+        sequence_by_certificate_map,
+        sequence_by_leader_round_map,
+    ) = reopen!(&rocksdb,
         LAST_COMMITTED_CF;<PublicKey, Round>,
-        SEQUENCE_CF;<SequenceNumber, CertificateDigest>
+        SEQUENCE_CF;<SequenceNumber, CertificateDigest>,
+        SEQUENCE_BY_CERTIFICATE_CF;<CertificateDigest, SequenceNumber>,
+        SEQUENCE_BY_LEADER_ROUND_CF;<(Round, SequenceNumber), CertificateDigest>
     );
 
-    Arc::new(ConsensusStore::new(last_committed_map, sequence_map))
+    Arc::new(ConsensusStore::new(
+        last_committed_map,
+        sequence_map,
+        sequence_by_certificate_map,
+        sequence_by_leader_round_map,
+    ))
 }
 
 pub fn make_certificate_store(store_path: &std::path::Path) -> CertificateStore {
@@ -83,7 +106,12 @@ async fn commit_one() {
     let store = make_consensus_store(&test_utils::temp_dir());
     let cert_store = make_certificate_store(&test_utils::temp_dir());
     let gc_depth = 50;
-    let tusk = Tusk::new(committee.clone(), store.clone(), gc_depth);
+    let tusk = Tusk::new(
+        committee.clone(),
+        store.clone(),
+        gc_depth,
+        LeaderElection::default(),
+    );
     let metrics = Arc::new(ConsensusMetrics::new(&Registry::new()));
 
     let _consensus_handle = Consensus::spawn(
@@ -146,7 +174,12 @@ async fn dead_node() {
     let store = make_consensus_store(&test_utils::temp_dir());
     let cert_store = make_certificate_store(&test_utils::temp_dir());
     let gc_depth = 50;
-    let tusk = Tusk::new(committee.clone(), store.clone(), gc_depth);
+    let tusk = Tusk::new(
+        committee.clone(),
+        store.clone(),
+        gc_depth,
+        LeaderElection::default(),
+    );
     let metrics = Arc::new(ConsensusMetrics::new(&Registry::new()));
 
     let _consensus_handle = Consensus::spawn(
@@ -256,7 +289,12 @@ async fn not_enough_support() {
     let store = make_consensus_store(&test_utils::temp_dir());
     let cert_store = make_certificate_store(&test_utils::temp_dir());
     let gc_depth = 50;
-    let tusk = Tusk::new(committee.clone(), store.clone(), gc_depth);
+    let tusk = Tusk::new(
+        committee.clone(),
+        store.clone(),
+        gc_depth,
+        LeaderElection::default(),
+    );
     let metrics = Arc::new(ConsensusMetrics::new(&Registry::new()));
 
     let _consensus_handle = Consensus::spawn(
@@ -337,7 +375,12 @@ async fn missing_leader() {
     let store = make_consensus_store(&test_utils::temp_dir());
     let cert_store = make_certificate_store(&test_utils::temp_dir());
     let gc_depth = 50;
-    let tusk = Tusk::new(committee.clone(), store.clone(), gc_depth);
+    let tusk = Tusk::new(
+        committee.clone(),
+        store.clone(),
+        gc_depth,
+        LeaderElection::default(),
+    );
     let metrics = Arc::new(ConsensusMetrics::new(&Registry::new()));
     let _consensus_handle = Consensus::spawn(
         committee,
@@ -395,7 +438,12 @@ async fn epoch_change() {
     let store = make_consensus_store(&test_utils::temp_dir());
     let cert_store = make_certificate_store(&test_utils::temp_dir());
     let gc_depth = 50;
-    let tusk = Tusk::new(committee.clone(), store.clone(), gc_depth);
+    let tusk = Tusk::new(
+        committee.clone(),
+        store.clone(),
+        gc_depth,
+        LeaderElection::default(),
+    );
     let metrics = Arc::new(ConsensusMetrics::new(&Registry::new()));
     let _consensus_handle = Consensus::spawn(
         committee.clone(),
@@ -477,7 +525,12 @@ async fn restart_with_new_committee() {
         let cert_store = make_certificate_store(&test_utils::temp_dir());
         let metrics = Arc::new(ConsensusMetrics::new(&Registry::new()));
         let gc_depth = 50;
-        let tusk = Tusk::new(committee.clone(), store.clone(), gc_depth);
+        let tusk = Tusk::new(
+            committee.clone(),
+            store.clone(),
+            gc_depth,
+            LeaderElection::default(),
+        );
 
         let handle = Consensus::spawn(
             committee.clone(),
@@ -5,7 +5,7 @@ use crate::{
     consensus::{ConsensusProtocol, ConsensusState, Dag},
     utils, ConsensusOutput, SequenceNumber,
 };
-use config::{Committee, Stake};
+use config::{Committee, LeaderElection, Stake};
 use fastcrypto::{traits::EncodeDecodeBase64, Hash};
 use std::{collections::HashMap, sync::Arc};
 use tracing::debug;
@@ -22,6 +22,8 @@ pub struct Tusk {
     pub store: Arc<ConsensusStore>,
     /// The depth of the garbage collector.
     pub gc_depth: Round,
+    /// How the leader of each round is elected.
+    pub leader_election: LeaderElection,
 }
 
 impl ConsensusProtocol for Tusk {
@@ -57,8 +59,12 @@ impl ConsensusProtocol for Tusk {
         if leader_round <= state.last_committed_round {
             return Ok(Vec::new());
         }
-        let (leader_digest, leader) = match Self::leader(&self.committee, leader_round, &state.dag)
-        {
+        let (leader_digest, leader) = match Self::leader(
+            &self.committee,
+            self.leader_election,
+            leader_round,
+            &state.dag,
+        ) {
             Some(x) => x,
             None => return Ok(Vec::new()),
         };
@@ -84,7 +90,11 @@ impl ConsensusProtocol for Tusk {
         // Get an ordered list of past leaders that are linked to the current leader.
         debug!("Leader {:?} has enough support", leader);
         let mut sequence = Vec::new();
-        for leader in utils::order_leaders(&self.committee, leader, state, Self::leader)
+        let leader_election = self.leader_election;
+        for leader in
+            utils::order_leaders(&self.committee, leader, state, |committee, round, dag| {
+                Self::leader(committee, leader_election, round, dag)
+            })
             .iter()
             .rev()
         {
@@ -110,6 +120,7 @@ impl ConsensusProtocol for Tusk {
                     &state.last_committed,
                     &consensus_index,
                     &digest,
+                    &leader.round(),
                 )?;
             }
         }
@@ -132,11 +143,17 @@ impl ConsensusProtocol for Tusk {
 
 impl Tusk {
     /// Create a new Tusk consensus instance.
-    pub fn new(committee: Committee, store: Arc<ConsensusStore>, gc_depth: Round) -> Self {
+    pub fn new(
+        committee: Committee,
+        store: Arc<ConsensusStore>,
+        gc_depth: Round,
+        leader_election: LeaderElection,
+    ) -> Self {
         Self {
             committee,
             store,
             gc_depth,
+            leader_election,
         }
     }
 
@@ -144,27 +161,33 @@ impl Tusk {
     /// specified round (if any).
     fn leader<'a>(
         committee: &Committee,
+        leader_election: LeaderElection,
         round: Round,
         dag: &'a Dag,
     ) -> Option<&'a (CertificateDigest, Certificate)> {
-        // TODO: We should elect the leader of round r-2 using the common coin revealed at round r.
-        // At this stage, we are guaranteed to have 2f+1 certificates from round r (which is enough to
-        // compute the coin). We currently just use a stake-weighted choise seeded by the round.
+        // With the common coin, we elect the leader of round r-2 using the coin revealed at round r.
+        // At this stage, we are guaranteed to have 2f+1 certificates from round r.
         //
-        // Note: this function is often called with even rounds only. While we do not aim at random selection
-        // yet (see issue #10), repeated calls to this function should still pick from the whole roster of leaders.
+        // Note: this function is often called with even rounds only. Repeated calls to this function
+        // should still pick from the whole roster of leaders.
         cfg_if::cfg_if! {
             if #[cfg(test)] {
-                // consensus tests rely on returning the same leader.
-                let leader = committee.authorities.iter().next().expect("Empty authorities table!").0;
+                // consensus tests rely on returning the same leader, unless they exercise the coin.
+                let leader = match leader_election {
+                    LeaderElection::Deterministic => {
+                        committee.authorities.keys().next().expect("Empty authorities table!").clone()
+                    }
+                    LeaderElection::RandomCoin => {
+                        utils::elect_leader(committee, leader_election, round, round + 2, dag)?
+                    }
+                };
             } else {
-                // Elect the leader in a stake-weighted choice seeded by the round
-                let leader = &committee.leader(round);
+                let leader = utils::elect_leader(committee, leader_election, round, round + 2, dag)?;
             }
         }
 
         // Return its certificate and the certificate's digest.
-        dag.get(&round).and_then(|x| x.get(leader))
+        dag.get(&round).and_then(|x| x.get(&leader))
     }
 }
 
@@ -203,7 +226,7 @@ mod tests {
 
         let consensus_index = 0;
         let mut state = ConsensusState::new(Certificate::genesis(&committee), metrics);
-        let mut tusk = Tusk::new(committee, store, gc_depth);
+        let mut tusk = Tusk::new(committee, store, gc_depth, LeaderElection::default());
         for certificate in certificates {
             tusk.process_certificate(&mut state, consensus_index, certificate)
                 .unwrap();
@@ -249,7 +272,12 @@ mod tests {
 
         let mut state = ConsensusState::new(Certificate::genesis(&committee), metrics);
         let consensus_index = 0;
-        let mut tusk = Tusk::new((**arc_committee.load()).clone(), store, gc_depth);
+        let mut tusk = Tusk::new(
+            (**arc_committee.load()).clone(),
+            store,
+            gc_depth,
+            LeaderElection::default(),
+        );
 
         for certificate in certificates {
             tusk.process_certificate(&mut state, consensus_index, certificate)
@@ -2,11 +2,60 @@
 // Copyright (c) 2022, Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 use crate::consensus::{ConsensusState, Dag};
-use config::Committee;
+use blake2::digest::Update;
+use config::{Committee, LeaderElection};
+use crypto::PublicKey;
 use std::collections::HashSet;
 use tracing::debug;
 use types::{Certificate, CertificateDigest, Round};
 
+/// Elect the leader of `leader_round`. In `RandomCoin` mode, the election is seeded by the
+/// threshold coin of `coin_round`, combined from the randomness shares of the certificates of that
+/// round, so no leader is elected until we hold `threshold` of them. Any `threshold` shares reveal
+/// the same coin, and every round below our highest one holds a quorum of certificates, hence
+/// enough shares: all the authorities elect the same leaders. If no coin keys were dealt to the
+/// committee, the election falls back to the deterministic schedule.
+pub fn elect_leader(
+    committee: &Committee,
+    leader_election: LeaderElection,
+    leader_round: Round,
+    coin_round: Round,
+    dag: &Dag,
+) -> Option<PublicKey> {
+    match leader_election {
+        LeaderElection::Deterministic => Some(committee.leader(leader_round)),
+        LeaderElection::RandomCoin => {
+            let coin = match &committee.coin {
+                Some(coin) => coin,
+                None => return Some(committee.leader(leader_round)),
+            };
+
+            let mut shares: Vec<_> = dag
+                .get(&coin_round)?
+                .iter()
+                .filter_map(|(name, (_, certificate))| {
+                    let share = coin.share(name)?;
+                    Some((share.index, certificate.header.randomness.clone()))
+                })
+                .collect();
+            if shares.len() < coin.threshold {
+                debug!(
+                    "Coin of round {coin_round} not revealed yet ({}/{} shares)",
+                    shares.len(),
+                    coin.threshold
+                );
+                return None;
+            }
+            shares.sort_by_key(|(index, _)| *index);
+            shares.truncate(coin.threshold);
+
+            let signature = crypto::coin::combine(&shares)?;
+            let randomness = fastcrypto::blake2b_256(|hasher| hasher.update(signature.as_ref()));
+            Some(committee.leader_from_randomness(randomness))
+        }
+    }
+}
+
 /// Order the past leaders that we didn't already commit.
 pub fn order_leaders<'a, LeaderElector>(
     committee: &Committee,
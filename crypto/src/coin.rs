@@ -0,0 +1,202 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+//! A threshold coin built on BLS signatures. A dealer splits a secret key with Shamir's scheme:
+//! the share of every authority is a BLS key, and its signature on a message is its share of the
+//! coin. Any `threshold` valid shares interpolate (in the exponent) to the unique signature of
+//! the secret key, so the coin does not depend on which shares are combined, and no coalition
+//! smaller than `threshold` can predict it.
+use crate::{KeyPair, PrivateKey, PublicKey, Signature};
+use blst::{
+    blst_bendian_from_scalar, blst_fr, blst_fr_add, blst_fr_from_scalar, blst_fr_from_uint64,
+    blst_fr_inverse, blst_fr_mul, blst_fr_sub, blst_p1, blst_p1_add_or_double, blst_p1_affine,
+    blst_p1_compress, blst_p1_from_affine, blst_p1_mult, blst_p1_uncompress, blst_p2,
+    blst_p2_add_or_double, blst_p2_affine, blst_p2_compress, blst_p2_from_affine, blst_p2_mult,
+    blst_p2_uncompress, blst_scalar, blst_scalar_from_bendian, blst_scalar_from_fr, BLST_ERROR,
+};
+use fastcrypto::traits::{KeyPair as _, ToFromBytes};
+use std::collections::BTreeSet;
+
+/// The number of bits of the scalars of BLS12-381.
+const SCALAR_BITS: usize = 255;
+
+/// The length of the compressed points of G1 and G2.
+const G1_LENGTH: usize = 48;
+const G2_LENGTH: usize = 96;
+
+/// Deals the keys of a threshold coin to `shares` authorities, any `threshold` of which reveal
+/// the coin. Returns the public key the coin verifies against, and the key of every share: the
+/// share at position `i` has index `i + 1`.
+pub fn deal<R: rand::CryptoRng + rand::RngCore>(
+    shares: usize,
+    threshold: usize,
+    rng: &mut R,
+) -> (PublicKey, Vec<KeyPair>) {
+    assert!(
+        0 < threshold && threshold <= shares,
+        "The threshold must be between 1 and the number of shares"
+    );
+
+    // The coefficients of a random polynomial of degree `threshold - 1`, whose value at 0 is the
+    // secret key of the coin.
+    let coefficients: Vec<_> = (0..threshold).map(|_| KeyPair::generate(rng)).collect();
+    let public_key = coefficients[0].public().clone();
+    let coefficients: Vec<_> = coefficients
+        .into_iter()
+        .map(|x| fr_from_bendian(x.private().as_ref()))
+        .collect();
+
+    let keys = (1..=shares as u64)
+        .map(|index| {
+            let x = fr_from_u64(index);
+            let mut y = blst_fr::default();
+            for coefficient in coefficients.iter().rev() {
+                let mut product = blst_fr::default();
+                unsafe {
+                    blst_fr_mul(&mut product, &y, &x);
+                    blst_fr_add(&mut y, &product, coefficient);
+                }
+            }
+            let private = PrivateKey::from_bytes(&bendian_from_fr(&y))
+                .expect("Shares of the coin are valid private keys");
+            KeyPair::from(private)
+        })
+        .collect();
+    (public_key, keys)
+}
+
+/// Combines shares of the coin, given with their index, into the signature of the coin's secret
+/// key. The result is only the coin if at least `threshold` valid shares are combined. Returns
+/// `None` if the indices are not distinct and positive, or if a share is malformed.
+pub fn combine(shares: &[(u64, Signature)]) -> Option<Signature> {
+    let indices: Vec<_> = shares.iter().map(|(index, _)| *index).collect();
+    let coefficients = lagrange_coefficients(&indices)?;
+    let bytes = match shares.first()?.1.as_ref().len() {
+        G1_LENGTH => interpolate_g1(shares, &coefficients)?,
+        G2_LENGTH => interpolate_g2(shares, &coefficients)?,
+        _ => return None,
+    };
+    Signature::from_bytes(&bytes).ok()
+}
+
+fn fr_from_u64(x: u64) -> blst_fr {
+    let mut fr = blst_fr::default();
+    unsafe { blst_fr_from_uint64(&mut fr, [x, 0, 0, 0].as_ptr()) };
+    fr
+}
+
+fn fr_from_bendian(bytes: &[u8]) -> blst_fr {
+    let mut scalar = blst_scalar::default();
+    let mut fr = blst_fr::default();
+    unsafe {
+        blst_scalar_from_bendian(&mut scalar, bytes.as_ptr());
+        blst_fr_from_scalar(&mut fr, &scalar);
+    }
+    fr
+}
+
+fn scalar_from_fr(fr: &blst_fr) -> blst_scalar {
+    let mut scalar = blst_scalar::default();
+    unsafe { blst_scalar_from_fr(&mut scalar, fr) };
+    scalar
+}
+
+fn bendian_from_fr(fr: &blst_fr) -> [u8; 32] {
+    let mut bytes = [0; 32];
+    unsafe { blst_bendian_from_scalar(bytes.as_mut_ptr(), &scalar_from_fr(fr)) };
+    bytes
+}
+
+/// The Lagrange coefficients interpolating the value at 0 of a polynomial from its values at the
+/// specified indices.
+fn lagrange_coefficients(indices: &[u64]) -> Option<Vec<blst_scalar>> {
+    let distinct: BTreeSet<_> = indices.iter().collect();
+    if distinct.len() != indices.len() || indices.contains(&0) {
+        return None;
+    }
+
+    let mut coefficients = Vec::with_capacity(indices.len());
+    for i in indices {
+        let xi = fr_from_u64(*i);
+        let mut numerator = fr_from_u64(1);
+        let mut denominator = fr_from_u64(1);
+        for j in indices.iter().filter(|j| *j != i) {
+            let xj = fr_from_u64(*j);
+            let mut difference = blst_fr::default();
+            unsafe {
+                let previous = numerator;
+                blst_fr_mul(&mut numerator, &previous, &xj);
+                blst_fr_sub(&mut difference, &xj, &xi);
+                let previous = denominator;
+                blst_fr_mul(&mut denominator, &previous, &difference);
+            }
+        }
+        let mut coefficient = blst_fr::default();
+        unsafe {
+            let mut inverse = blst_fr::default();
+            blst_fr_inverse(&mut inverse, &denominator);
+            blst_fr_mul(&mut coefficient, &numerator, &inverse);
+        }
+        coefficients.push(scalar_from_fr(&coefficient));
+    }
+    Some(coefficients)
+}
+
+macro_rules! interpolate {
+    ($name:ident, $length:expr, $point:ty, $affine:ty, $uncompress:ident, $from_affine:ident, $mult:ident, $add:ident, $compress:ident) => {
+        /// Sums the shares multiplied by their coefficient, in the group of the signatures.
+        fn $name(shares: &[(u64, Signature)], coefficients: &[blst_scalar]) -> Option<Vec<u8>> {
+            let mut sum: Option<$point> = None;
+            for ((_, share), coefficient) in shares.iter().zip(coefficients) {
+                let bytes = share.as_ref();
+                if bytes.len() != $length {
+                    return None;
+                }
+                let mut affine = <$affine>::default();
+                let mut point = <$point>::default();
+                let mut term = <$point>::default();
+                unsafe {
+                    if $uncompress(&mut affine, bytes.as_ptr()) != BLST_ERROR::BLST_SUCCESS {
+                        return None;
+                    }
+                    $from_affine(&mut point, &affine);
+                    $mult(&mut term, &point, coefficient.b.as_ptr(), SCALAR_BITS);
+                }
+                sum = Some(match sum {
+                    None => term,
+                    Some(previous) => {
+                        let mut next = <$point>::default();
+                        unsafe { $add(&mut next, &previous, &term) };
+                        next
+                    }
+                });
+            }
+
+            let mut bytes = vec![0; $length];
+            unsafe { $compress(bytes.as_mut_ptr(), &sum?) };
+            Some(bytes)
+        }
+    };
+}
+
+interpolate!(
+    interpolate_g1,
+    G1_LENGTH,
+    blst_p1,
+    blst_p1_affine,
+    blst_p1_uncompress,
+    blst_p1_from_affine,
+    blst_p1_mult,
+    blst_p1_add_or_double,
+    blst_p1_compress
+);
+interpolate!(
+    interpolate_g2,
+    G2_LENGTH,
+    blst_p2,
+    blst_p2_affine,
+    blst_p2_uncompress,
+    blst_p2_from_affine,
+    blst_p2_mult,
+    blst_p2_add_or_double,
+    blst_p2_compress
+);
@@ -47,6 +47,12 @@ pub type NetworkKeyPair = ed25519::Ed25519KeyPair;
 
 ////////////////////////////////////////////////////////////////////////
 
+pub mod coin;
+
+#[cfg(test)]
+#[path = "tests/coin_tests.rs"]
+pub mod coin_tests;
+
 #[cfg(all(test, feature = "celo"))]
 #[path = "tests/bls12377_tests.rs"]
 pub mod bls12377_tests;
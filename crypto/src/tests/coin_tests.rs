@@ -0,0 +1,54 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use crate::{coin, KeyPair, Signature};
+use fastcrypto::traits::{KeyPair as _, VerifyingKey};
+use rand::{rngs::StdRng, SeedableRng as _};
+use signature::Signer;
+
+const MESSAGE: &[u8] = b"Hello, world!";
+
+fn sign(keys: &[KeyPair], indices: &[usize]) -> Vec<(u64, Signature)> {
+    indices
+        .iter()
+        .map(|i| (*i as u64 + 1, keys[*i].sign(MESSAGE)))
+        .collect()
+}
+
+#[test]
+fn combine_any_threshold_of_shares() {
+    let mut rng = StdRng::from_seed([0; 32]);
+    let (public_key, keys) = coin::deal(4, 2, &mut rng);
+
+    // Every share is a valid signature of its own key.
+    for key in &keys {
+        assert!(key.public().verify(MESSAGE, &key.sign(MESSAGE)).is_ok());
+    }
+
+    // Any two shares combine to the same signature of the coin.
+    let first = coin::combine(&sign(&keys, &[0, 1])).unwrap();
+    let second = coin::combine(&sign(&keys, &[3, 2])).unwrap();
+    let all = coin::combine(&sign(&keys, &[0, 1, 2, 3])).unwrap();
+    assert_eq!(first, second);
+    assert_eq!(first, all);
+    assert!(public_key.verify(MESSAGE, &first).is_ok());
+}
+
+#[test]
+fn combine_below_threshold() {
+    let mut rng = StdRng::from_seed([0; 32]);
+    let (public_key, keys) = coin::deal(4, 3, &mut rng);
+
+    let combined = coin::combine(&sign(&keys, &[0, 1])).unwrap();
+    assert!(public_key.verify(MESSAGE, &combined).is_err());
+}
+
+#[test]
+fn combine_invalid_indices() {
+    let mut rng = StdRng::from_seed([0; 32]);
+    let (_, keys) = coin::deal(4, 2, &mut rng);
+
+    let signature = keys[0].sign(MESSAGE);
+    assert!(coin::combine(&[(1, signature.clone()), (1, signature.clone())]).is_none());
+    assert!(coin::combine(&[(0, signature.clone()), (1, signature)]).is_none());
+    assert!(coin::combine(&[]).is_none());
+}
@@ -0,0 +1,27 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use serde::de::DeserializeOwned;
+use std::fmt::Display;
+
+/// Decodes the transactions sequenced by consensus into the transactions of the execution state.
+/// Consensus orders opaque bytes and a bad client or authority may sequence random ones, so the
+/// transactions failing to decode are reported as client errors and not executed.
+pub trait TransactionCodec<Transaction>: Clone + Send + Sync + 'static {
+    /// The error returned when a transaction fails to decode.
+    type Error: Display;
+
+    /// Decodes a transaction sequenced by consensus.
+    fn decode(&self, transaction: &[u8]) -> Result<Transaction, Self::Error>;
+}
+
+/// Codec decoding the transactions serialized with bincode.
+#[derive(Debug, Clone, Default)]
+pub struct BincodeCodec;
+
+impl<Transaction: DeserializeOwned> TransactionCodec<Transaction> for BincodeCodec {
+    type Error = bincode::Error;
+
+    fn decode(&self, transaction: &[u8]) -> Result<Transaction, Self::Error> {
+        bincode::deserialize(transaction)
+    }
+}
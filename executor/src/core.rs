@@ -3,20 +3,28 @@
 use crate::{
     bail,
     errors::{SubscriberError, SubscriberResult},
+    metrics::ExecutorMetrics,
+    scheduler::{self, AccessSet},
     state::ExecutionIndices,
-    ExecutionState, ExecutorOutput, SerializedTransaction,
+    ExecutionState, ExecutorOutput, SerializedTransaction, TransactionCodec,
 };
+use config::{Committee, Epoch, WorkerCache};
 use consensus::ConsensusOutput;
 use fastcrypto::Hash;
-use std::{fmt::Debug, sync::Arc};
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt::Debug,
+    sync::Arc,
+};
 use store::{rocks::TypedStoreError, Store};
 use tokio::{
     sync::{mpsc::Sender, watch},
     task::JoinHandle,
 };
-use tracing::debug;
+use tracing::{debug, info, warn};
 use types::{
-    metered_channel, Batch, BatchDigest, CertificateDigest, ReconfigureNotification, SequenceNumber,
+    metered_channel, transaction_digest, Batch, BatchDigest, CertificateDigest,
+    ReconfigureNotification, SequenceNumber, StateDigest, TransactionDigest,
 };
 
 #[cfg(test)]
@@ -27,54 +35,93 @@ pub mod executor_tests;
 /// consensus messages in the right and complete order. All transactions data referenced by the
 /// certificate should already be downloaded in the temporary storage. This module ensures it does
 /// not processes twice the same transaction (despite crash-recovery).
-pub struct Core<State: ExecutionState> {
+pub struct Core<State: ExecutionState, Codec> {
     /// The temporary storage holding all transactions' data (that may be too big to hold in memory).
     store: Store<(CertificateDigest, BatchDigest), Batch>,
     /// The (global) state to perform execution.
     execution_state: Arc<State>,
+    /// Decodes the transactions sequenced by consensus.
+    codec: Codec,
     /// Receive reconfiguration updates.
     rx_reconfigure: watch::Receiver<ReconfigureNotification>,
     /// Receive ordered consensus output to execute.
     rx_subscriber: metered_channel::Receiver<ConsensusOutput>,
     /// Outputs executed transactions.
     tx_output: Sender<ExecutorOutput<State>>,
+    /// Sends the committee and worker cache of the next epoch to the primary.
+    tx_new_epochs: metered_channel::Sender<(Committee, WorkerCache)>,
+    /// The number of certificates between two commitments to the execution state (0 disables them).
+    state_commitment_interval: u64,
+    /// Sends the digests of the execution state to the primary, to compare them with the other
+    /// authorities.
+    tx_state_digests: metered_channel::Sender<(Epoch, SequenceNumber, StateDigest)>,
+    /// The current epoch. The certificates of previous epochs are not executed.
+    epoch: Epoch,
     /// The indices ensuring we do not execute twice the same transaction.
     execution_indices: ExecutionIndices,
+    /// The last executed transactions, to skip their duplicates.
+    executed: ExecutedTransactions,
+    /// The metrics handler.
+    metrics: Arc<ExecutorMetrics>,
 }
 
-impl<State: ExecutionState> Drop for Core<State> {
+impl<State: ExecutionState, Codec> Drop for Core<State, Codec> {
     fn drop(&mut self) {
         self.execution_state.release_consensus_write_lock();
     }
 }
 
-impl<State> Core<State>
+impl<State, Codec> Core<State, Codec>
 where
     State: ExecutionState + Send + Sync + 'static,
     State::Outcome: Send + 'static,
     State::Error: Debug,
+    Codec: TransactionCodec<State::Transaction>,
 {
     /// Spawn a new executor in a dedicated tokio task.
     #[must_use]
     pub fn spawn(
         store: Store<(CertificateDigest, BatchDigest), Batch>,
         execution_state: Arc<State>,
+        codec: Codec,
         rx_reconfigure: watch::Receiver<ReconfigureNotification>,
         rx_subscriber: metered_channel::Receiver<ConsensusOutput>,
         tx_output: Sender<ExecutorOutput<State>>,
+        tx_new_epochs: metered_channel::Sender<(Committee, WorkerCache)>,
+        state_commitment_interval: u64,
+        tx_state_digests: metered_channel::Sender<(Epoch, SequenceNumber, StateDigest)>,
+        executed_store: Store<ExecutionIndices, TransactionDigest>,
+        dedup_cache_size: usize,
+        metrics: Arc<ExecutorMetrics>,
     ) -> JoinHandle<()> {
         tokio::spawn(async move {
+            let epoch = match &*rx_reconfigure.borrow() {
+                ReconfigureNotification::NewEpoch(committee)
+                | ReconfigureNotification::UpdateCommittee(committee) => committee.epoch(),
+                ReconfigureNotification::Shutdown => return,
+            };
             let execution_indices = execution_state
                 .load_execution_indices()
                 .await
                 .expect("Failed to load execution indices from store");
+            let executed =
+                ExecutedTransactions::load(executed_store, dedup_cache_size, &execution_indices)
+                    .await
+                    .expect("Failed to load executed transactions from store");
             Self {
                 store,
                 execution_state,
+                codec,
                 rx_reconfigure,
                 rx_subscriber,
                 tx_output,
+                tx_new_epochs,
+                state_commitment_interval,
+                tx_state_digests,
+                epoch,
                 execution_indices,
+                executed,
+                metrics,
             }
             .run()
             .await
@@ -88,8 +135,26 @@ where
             tokio::select! {
                 // Execute all transactions associated with the consensus output message.
                 Some(message) = self.rx_subscriber.recv() => {
-                    // This function persists the necessary data to enable crash-recovery.
-                    self.execute_certificate(&message).await?;
+                    // Discard the certificates sequenced after the end of their epoch. Every
+                    // executor discards the same ones, so they do not move the execution indices.
+                    if message.certificate.epoch() < self.epoch {
+                        let epoch = message.certificate.epoch();
+                        debug!("Discarding certificate of past epoch {epoch}");
+                    } else {
+                        // This function persists the necessary data to enable crash-recovery.
+                        self.execute_certificate(&message).await?;
+
+                        // Commit to the execution state every `state_commitment_interval`
+                        // certificates.
+                        self.commit_state(&message).await;
+
+                        // Let the execution state decide whether the epoch ends here.
+                        if let Some((committee, worker_cache)) =
+                            self.execution_state.next_epoch(&message).await
+                        {
+                            self.change_epoch(committee, worker_cache).await?;
+                        }
+                    }
 
                     // Cleanup the temporary persistent storage.
                     self.cleanup_store(&message).await.map_err(SubscriberError::from)?;
@@ -99,14 +164,80 @@ where
                 result = self.rx_reconfigure.changed() => {
                     result.expect("Committee channel dropped");
                     let message = self.rx_reconfigure.borrow().clone();
-                    if let ReconfigureNotification::Shutdown = message {
-                        return Ok(());
+                    match message {
+                        ReconfigureNotification::NewEpoch(committee) => {
+                            if committee.epoch() > self.epoch {
+                                self.enter_epoch(committee.epoch()).await?;
+                            }
+                        },
+                        ReconfigureNotification::UpdateCommittee(_) => (),
+                        ReconfigureNotification::Shutdown => return Ok(()),
                     }
                 }
             }
         }
     }
 
+    /// Hands the digest of the execution state to the primary if the certificate closes an
+    /// interval of `state_commitment_interval` certificates. All the authorities commit at the
+    /// same consensus indices, so that their digests can be compared.
+    async fn commit_state(&mut self, message: &ConsensusOutput) {
+        if self.state_commitment_interval == 0
+            || (message.consensus_index + 1) % self.state_commitment_interval != 0
+        {
+            return;
+        }
+        if let Some(state) = self.execution_state.state_digest(message).await {
+            debug!(
+                "Committing to execution state {state} at index {}",
+                message.consensus_index
+            );
+            if self
+                .tx_state_digests
+                .send((message.certificate.epoch(), message.consensus_index, state))
+                .await
+                .is_err()
+            {
+                debug!("No primary listening for state digests");
+            }
+        }
+    }
+
+    /// Ends the current epoch and asks the primary to move the node to the next one.
+    async fn change_epoch(
+        &mut self,
+        committee: Committee,
+        worker_cache: WorkerCache,
+    ) -> SubscriberResult<()> {
+        if committee.epoch() <= self.epoch {
+            warn!(
+                "Execution state returned past epoch {} (current epoch {})",
+                committee.epoch(),
+                self.epoch
+            );
+            return Ok(());
+        }
+        info!("Epoch {} ends at the current certificate", self.epoch);
+        self.enter_epoch(committee.epoch()).await?;
+        if self
+            .tx_new_epochs
+            .send((committee, worker_cache))
+            .await
+            .is_err()
+        {
+            debug!("No primary listening for epoch changes");
+        }
+        Ok(())
+    }
+
+    /// Moves to a new epoch. The consensus sequence of every epoch starts over at index 0, so
+    /// the execution indices and the executed transactions start over as well.
+    async fn enter_epoch(&mut self, epoch: Epoch) -> SubscriberResult<()> {
+        self.epoch = epoch;
+        self.execution_indices = ExecutionIndices::default();
+        self.executed.clear().await
+    }
+
     /// Cleans up the temporary batch store for the batches stored
     /// for the specified certificate. We are storing the batches per
     /// certificate as bathes of same id can be referenced by multiple
@@ -133,30 +264,62 @@ where
             return Ok(());
         }
 
-        // Execute every batch in the certificate.
+        self.execution_state
+            .begin_certificate(message)
+            .await
+            .map_err(SubscriberError::from)?;
+
+        // Prepare every batch in the certificate.
         let certificate_id = message.certificate.digest();
         let total_batches = message.certificate.header.payload.len();
+        let mut batches = Vec::with_capacity(total_batches);
         for (index, digest) in message.certificate.header.payload.keys().enumerate() {
             // Skip batches that we already executed (after crash-recovery).
             if self
                 .execution_indices
                 .check_next_batch_index(index as SequenceNumber)
             {
-                self.execute_batch(message, certificate_id, *digest, total_batches)
-                    .await?;
+                if let Some(batch) = self
+                    .prepare_batch(certificate_id, *digest, total_batches)
+                    .await?
+                {
+                    batches.push(batch);
+                }
             }
         }
-        Ok(())
+
+        // Execute the transactions concurrently if they all declare the state they access, or
+        // batch by batch otherwise.
+        let access_sets: Option<Vec<_>> = batches
+            .iter()
+            .flat_map(|batch| &batch.to_execute)
+            .map(|(_, transaction)| self.execution_state.access_set(transaction))
+            .collect();
+        match access_sets {
+            Some(access_sets) if !access_sets.is_empty() => {
+                self.execute_concurrently(message, batches, &access_sets)
+                    .await?
+            }
+            _ => {
+                for batch in batches {
+                    self.execute_batch(message, batch).await?;
+                }
+            }
+        }
+
+        self.execution_state
+            .end_certificate(message)
+            .await
+            .map_err(SubscriberError::from)
     }
 
-    /// Execute a single batch of transactions.
-    async fn execute_batch(
+    /// Read a batch from the store and prepare its transactions for execution.
+    async fn prepare_batch(
         &mut self,
-        consensus_output: &ConsensusOutput,
         certificate_id: CertificateDigest,
         batch_digest: BatchDigest,
         total_batches: usize,
-    ) -> SubscriberResult<()> {
+    ) -> SubscriberResult<Option<PreparedBatch<State::Transaction>>> {
         // The store should now hold all transaction data referenced by the input certificate.
         let transactions = match self.store.read((certificate_id, batch_digest)).await? {
             Some(x) => x.0,
@@ -168,93 +331,260 @@ where
                 // (as the second execution attempt will always fail).
                 debug!("Duplicate batch {batch_digest}");
                 self.execution_indices.skip_batch(total_batches);
-                return Ok(());
+                return Ok(None);
             }
         };
 
-        // Execute every transaction in the batch.
+        // Prepare every transaction in the batch, keeping their order. The transactions that
+        // fail to decode are output without being executed.
         let total_transactions = transactions.len();
+        let mut batch = PreparedBatch {
+            digest: batch_digest,
+            outputs: Vec::with_capacity(total_transactions),
+            to_execute: Vec::with_capacity(total_transactions),
+        };
         for (index, transaction) in transactions.into_iter().enumerate() {
             // Skip transactions that we already executed (after crash-recovery).
-            if self
+            if !self
                 .execution_indices
                 .check_next_transaction_index(index as SequenceNumber)
             {
-                // Execute the transaction
-                let result = self
-                    .execute_transaction(
-                        consensus_output,
-                        transaction.clone(),
-                        total_transactions,
-                        total_batches,
-                    )
-                    .await;
-
-                let (bail, result) = match result {
-                    outcome @ Ok(..) => (None, outcome),
-
-                    // We may want to log the errors that are the user's fault (i.e., that are neither
-                    // our fault or the fault of consensus) for debug purposes. It is safe to continue
-                    // by ignoring those transactions since all honest subscribers will do the same.
-                    Err(error @ SubscriberError::ClientExecutionError(_)) => {
-                        debug!("{error}");
-                        (None, Err(error))
-                    }
+                continue;
+            }
 
-                    // We must take special care to errors that are our fault, such as storage errors.
-                    // We may be the only authority experiencing it, and thus cannot continue to process
-                    // transactions until the problem is fixed.
-                    Err(error) => (Some(error.clone()), Err(error)),
-                };
+            // Compute the next expected indices. Those will be persisted upon transaction
+            // execution and are only used for crash-recovery.
+            self.execution_indices
+                .next(total_batches, total_transactions);
 
-                // Output the result (eg. to notify the end-user);
-                let output = (result, transaction);
-                if self.tx_output.send(output).await.is_err() {
-                    debug!("No users listening for transaction execution");
-                }
+            // Skip the duplicates of the transactions we recently executed.
+            if !self
+                .executed
+                .insert(&transaction, &self.execution_indices)
+                .await?
+            {
+                debug!("Skipping duplicate transaction");
+                self.metrics.duplicate_transactions.inc();
+                continue;
+            }
 
-                // Bail if a fatal error occurred.
-                if let Some(e) = bail {
-                    bail!(e);
+            // The consensus simply orders bytes, so we first need to decode the transaction.
+            // If the decoding fails it is safe to ignore the transaction since all correct
+            // clients will do the same. Remember that a bad authority or client may input random
+            // bytes to the consensus.
+            match self.codec.decode(&transaction) {
+                Ok(deserialized) => {
+                    batch
+                        .to_execute
+                        .push((self.execution_indices.clone(), deserialized));
+                    batch.outputs.push((transaction, None));
+                }
+                Err(e) => {
+                    let error = SubscriberError::ClientExecutionError(format!(
+                        "Failed to decode transaction: {e}"
+                    ));
+                    batch.outputs.push((transaction, Some(error)));
                 }
             }
         }
+        Ok(Some(batch))
+    }
+
+    /// Execute a single batch of transactions.
+    async fn execute_batch(
+        &mut self,
+        consensus_output: &ConsensusOutput,
+        batch: PreparedBatch<State::Transaction>,
+    ) -> SubscriberResult<()> {
+        // Execute the transactions. Note that the executor will need to choose whether to
+        // discard transactions from previous epochs by itself.
+        let mut outcomes = if batch.to_execute.is_empty() {
+            Vec::new()
+        } else {
+            self.execution_state
+                .handle_batch(consensus_output, batch.to_execute)
+                .await
+        }
+        .into_iter();
+
+        self.output_batch(batch.digest, batch.outputs, &mut outcomes)
+            .await?;
+        if outcomes.next().is_some() {
+            warn!(
+                "Execution state returned more outcomes than transactions in batch {}",
+                batch.digest
+            );
+        }
         Ok(())
     }
 
-    /// Execute a single transaction.
-    async fn execute_transaction(
+    /// Execute the transactions of several batches concurrently, each one once the earlier
+    /// transactions it conflicts with are executed.
+    async fn execute_concurrently(
         &mut self,
         consensus_output: &ConsensusOutput,
-        serialized: SerializedTransaction,
-        total_transactions: usize,
-        total_batches: usize,
-    ) -> SubscriberResult<<State as ExecutionState>::Outcome> {
-        // Compute the next expected indices. Those will be persisted upon transaction execution
-        // and are only used for crash-recovery.
-        self.execution_indices
-            .next(total_batches, total_transactions);
-
-        // The consensus simply orders bytes, so we first need to deserialize the transaction.
-        // If the deserialization fail it is safe to ignore the transaction since all correct
-        // clients will do the same. Remember that a bad authority or client may input random
-        // bytes to the consensus.
-        let transaction: State::Transaction = match bincode::deserialize(&serialized) {
-            Ok(x) => x,
-            Err(e) => bail!(SubscriberError::ClientExecutionError(format!(
-                "Failed to deserialize transaction: {e}"
-            ))),
-        };
+        batches: Vec<PreparedBatch<State::Transaction>>,
+        access_sets: &[AccessSet],
+    ) -> SubscriberResult<()> {
+        let mut transactions = Vec::with_capacity(access_sets.len());
+        let mut outputs = Vec::with_capacity(batches.len());
+        for batch in batches {
+            transactions.extend(batch.to_execute);
+            outputs.push((batch.digest, batch.outputs));
+        }
 
-        // Execute the transaction. Note that the executor will need to choose whether to discard
-        // transactions from previous epochs by itself.
-        self.execution_state
-            .handle_consensus_transaction(
-                consensus_output,
-                self.execution_indices.clone(),
-                transaction,
-            )
-            .await
-            .map_err(SubscriberError::from)
+        let mut outcomes = scheduler::execute_concurrently(
+            self.execution_state.as_ref(),
+            consensus_output,
+            transactions,
+            access_sets,
+        )
+        .await
+        .into_iter();
+
+        for (digest, outputs) in outputs {
+            self.output_batch(digest, outputs, &mut outcomes).await?;
+        }
+        Ok(())
+    }
+
+    /// Output the results of the transactions of a batch, in order, taking the outcomes of the
+    /// executed ones from `outcomes`. Bails after the first error that is our fault.
+    async fn output_batch(
+        &mut self,
+        batch_digest: BatchDigest,
+        outputs: Vec<(SerializedTransaction, Option<SubscriberError>)>,
+        outcomes: &mut impl Iterator<Item = Result<State::Outcome, State::Error>>,
+    ) -> SubscriberResult<()> {
+        for (transaction, error) in outputs {
+            let result = match error {
+                Some(error) => Err(error),
+                None => match outcomes.next() {
+                    Some(outcome) => outcome.map_err(SubscriberError::from),
+                    None => Err(SubscriberError::NodeExecutionError(format!(
+                        "Missing execution outcomes for batch {batch_digest}"
+                    ))),
+                },
+            };
+
+            let (bail, result) = match result {
+                outcome @ Ok(..) => (None, outcome),
+
+                // We may want to log the errors that are the user's fault (i.e., that are neither
+                // our fault or the fault of consensus) for debug purposes. It is safe to continue
+                // by ignoring those transactions since all honest subscribers will do the same.
+                Err(error @ SubscriberError::ClientExecutionError(_)) => {
+                    debug!("{error}");
+                    (None, Err(error))
+                }
+
+                // We must take special care to errors that are our fault, such as storage errors.
+                // We may be the only authority experiencing it, and thus cannot continue to process
+                // transactions until the problem is fixed.
+                Err(error) => (Some(error.clone()), Err(error)),
+            };
+
+            // Output the result (eg. to notify the end-user);
+            let output = (result, transaction);
+            if self.tx_output.send(output).await.is_err() {
+                debug!("No users listening for transaction execution");
+            }
+
+            // Bail if a fatal error occurred.
+            if let Some(e) = bail {
+                bail!(e);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The transactions of a batch, ready for execution.
+struct PreparedBatch<Transaction> {
+    /// The digest of the batch.
+    digest: BatchDigest,
+    /// The transactions to output, in order, along with the error of those that failed to
+    /// decode.
+    outputs: Vec<(SerializedTransaction, Option<SubscriberError>)>,
+    /// The deserialized transactions to execute, along with the indices to persist.
+    to_execute: Vec<(ExecutionIndices, Transaction)>,
+}
+
+/// Remembers the digests of the last executed transactions. The digests are persisted with the
+/// indices of their transactions, so the cache only depends on the consensus sequence and all the
+/// executors skip the same duplicates, including across restarts.
+struct ExecutedTransactions {
+    /// The maximum number of digests remembered. Disabled if 0.
+    capacity: usize,
+    /// Persists the remembered digests, by the execution indices of their transactions.
+    store: Store<ExecutionIndices, TransactionDigest>,
+    digests: HashSet<TransactionDigest>,
+    /// The remembered digests and the indices of their transactions, by order of execution.
+    queue: VecDeque<(ExecutionIndices, TransactionDigest)>,
+}
+
+impl ExecutedTransactions {
+    /// Loads the digests of the last transactions executed up to `execution_indices`. The
+    /// digests recorded after them belong to transactions that were not executed before a crash
+    /// and that will be executed again, so they are dropped.
+    async fn load(
+        store: Store<ExecutionIndices, TransactionDigest>,
+        capacity: usize,
+        execution_indices: &ExecutionIndices,
+    ) -> SubscriberResult<Self> {
+        let mut entries: Vec<_> = store.iter(None).await.into_iter().collect();
+        entries.sort_by(|(x, _), (y, _)| x.cmp(y));
+        let end = entries.partition_point(|(indices, _)| indices <= execution_indices);
+        let start = end.saturating_sub(capacity);
+
+        let stale = entries[..start]
+            .iter()
+            .chain(&entries[end..])
+            .map(|(indices, _)| indices.clone());
+        store.remove_all(stale).await?;
+
+        entries.truncate(end);
+        let queue: VecDeque<_> = entries.drain(start..).collect();
+        Ok(Self {
+            capacity,
+            store,
+            digests: queue.iter().map(|(_, digest)| *digest).collect(),
+            queue,
+        })
+    }
+
+    /// Forgets all the executed transactions, at the start of an epoch.
+    async fn clear(&mut self) -> SubscriberResult<()> {
+        self.digests.clear();
+        let indices: Vec<_> = self.queue.drain(..).map(|(indices, _)| indices).collect();
+        self.store.remove_all(indices).await?;
+        Ok(())
+    }
+
+    /// Records a transaction about to be executed at the specified indices. Returns false if it
+    /// is a duplicate of one of the last executed transactions.
+    async fn insert(
+        &mut self,
+        transaction: &[u8],
+        indices: &ExecutionIndices,
+    ) -> SubscriberResult<bool> {
+        if self.capacity == 0 {
+            return Ok(true);
+        }
+        let digest = transaction_digest(transaction);
+        if !self.digests.insert(digest) {
+            return Ok(false);
+        }
+        self.store.write(indices.clone(), digest).await;
+        self.queue.push_back((indices.clone(), digest));
+        if self.queue.len() > self.capacity {
+            if let Some((oldest_indices, oldest)) = self.queue.pop_front() {
+                self.digests.remove(&oldest);
+                self.store
+                    .remove_all(std::iter::once(oldest_indices))
+                    .await?;
+            }
+        }
+        Ok(true)
     }
 }
@@ -4,7 +4,7 @@ use config::WorkerId;
 use std::fmt::Debug;
 use store::StoreError;
 use thiserror::Error;
-use types::CertificateDigest;
+use types::{BlockRemoverErrorKind, CertificateDigest};
 
 #[macro_export]
 macro_rules! bail {
@@ -73,6 +73,9 @@ pub enum SubscriberError {
 
     #[error("Client transaction invalid: {0}")]
     ClientExecutionError(String),
+
+    #[error("Failed to delete the batches from the workers: {0:?}")]
+    BatchDeletionError(BlockRemoverErrorKind),
 }
 
 impl From<Box<bincode::ErrorKind>> for SubscriberError {
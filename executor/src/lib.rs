@@ -1,7 +1,9 @@
 // Copyright (c) 2022, Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
+mod codec;
 mod core;
 mod errors;
+mod scheduler;
 mod state;
 mod subscriber;
 
@@ -15,16 +17,18 @@ mod execution_state;
 
 mod metrics;
 
+pub use codec::{BincodeCodec, TransactionCodec};
 pub use errors::{ExecutionStateError, SubscriberError, SubscriberResult};
+pub use scheduler::AccessSet;
 pub use state::ExecutionIndices;
 use tracing::info;
 
 use crate::{core::Core, metrics::ExecutorMetrics, subscriber::Subscriber};
 use async_trait::async_trait;
+use config::{Committee, Epoch, WorkerCache};
 use consensus::ConsensusOutput;
 use primary::BlockCommand;
 use prometheus::Registry;
-use serde::de::DeserializeOwned;
 use std::{fmt::Debug, sync::Arc};
 use storage::CertificateStore;
 use store::Store;
@@ -34,7 +38,7 @@ use tokio::{
 };
 use types::{
     metered_channel, Batch, BatchDigest, CertificateDigest, ConsensusStore,
-    ReconfigureNotification, SequenceNumber,
+    ReconfigureNotification, SequenceNumber, StateDigest, TransactionDigest,
 };
 
 /// Convenience type representing a serialized transaction.
@@ -45,18 +49,18 @@ pub type SerializedTransactionDigest = u64;
 
 #[async_trait]
 pub trait ExecutionState {
-    /// The type of the transaction to process.
-    type Transaction: DeserializeOwned + Send + Debug;
+    /// The type of the transaction to process, decoded by the `TransactionCodec` of the executor.
+    type Transaction: Send + Debug;
 
     /// The error type to return in case something went wrong during execution.
-    type Error: ExecutionStateError;
+    type Error: ExecutionStateError + Send;
 
     /// The execution outcome to output.
-    type Outcome;
+    type Outcome: Send;
 
     /// Execute the transaction and atomically persist the consensus index. This function
-    /// returns an execution outcome that will be output by the executor channel. It may
-    /// also return a new committee to reconfigure the system.
+    /// returns an execution outcome that will be output by the executor channel. The system
+    /// is reconfigured through `next_epoch`.
     async fn handle_consensus_transaction(
         &self,
         consensus_output: &ConsensusOutput,
@@ -64,6 +68,74 @@ pub trait ExecutionState {
         transaction: Self::Transaction,
     ) -> Result<Self::Outcome, Self::Error>;
 
+    /// Called before executing the first batch of a certificate. After crash-recovery, it is
+    /// called again for the certificate that was being executed, before its remaining batches.
+    /// Returning an error stops the executor.
+    async fn begin_certificate(
+        &self,
+        _consensus_output: &ConsensusOutput,
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Execute the transactions of a batch, in order. Every transaction comes with the consensus
+    /// index to persist atomically with its effects: an implementation committing its effects
+    /// once per batch or per certificate must persist the index of the last transaction it
+    /// committed. Returns the outcome of every transaction, in order, and may stop early after
+    /// an error due to a fault in the authority. The default implementation hands the
+    /// transactions one by one to `handle_consensus_transaction`.
+    async fn handle_batch(
+        &self,
+        consensus_output: &ConsensusOutput,
+        transactions: Vec<(ExecutionIndices, Self::Transaction)>,
+    ) -> Vec<Result<Self::Outcome, Self::Error>> {
+        let mut outcomes = Vec::with_capacity(transactions.len());
+        for (execution_indices, transaction) in transactions {
+            let outcome = self
+                .handle_consensus_transaction(consensus_output, execution_indices, transaction)
+                .await;
+            let fatal = matches!(&outcome, Err(e) if e.node_error());
+            outcomes.push(outcome);
+            if fatal {
+                break;
+            }
+        }
+        outcomes
+    }
+
+    /// Called once all the batches of a certificate are executed, before `next_epoch`. It is not
+    /// called for the certificates without transactions. Returning an error stops the executor.
+    async fn end_certificate(
+        &self,
+        _consensus_output: &ConsensusOutput,
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// The keys of the state the transaction reads and writes. If every transaction of a
+    /// certificate declares them, the executor runs the transactions of the certificate
+    /// concurrently through `handle_concurrent_transaction` (bypassing `handle_batch`), each one
+    /// once the earlier transactions it conflicts with are executed. By default, transactions
+    /// declare nothing and run sequentially. Must be implemented along with
+    /// `handle_concurrent_transaction`.
+    fn access_set(&self, _transaction: &Self::Transaction) -> Option<AccessSet> {
+        None
+    }
+
+    /// Execute a transaction of a certificate whose transactions run concurrently, without
+    /// persisting anything. The transactions complete out of order, so the execution state must
+    /// make their effects durable at once in `end_certificate`, along with the highest indices it
+    /// received. After a crash, the certificate is executed again from its start. Only called for
+    /// the execution states implementing `access_set`.
+    async fn handle_concurrent_transaction(
+        &self,
+        _consensus_output: &ConsensusOutput,
+        _execution_indices: ExecutionIndices,
+        _transaction: Self::Transaction,
+    ) -> Result<Self::Outcome, Self::Error> {
+        unimplemented!("Execution states declaring access sets must execute them concurrently")
+    }
+
     /// Simple guardrail ensuring there is a single instance using the state
     /// to call `handle_consensus_transaction`. Many instances may read the state,
     /// or use it for other purposes.
@@ -75,6 +147,36 @@ pub trait ExecutionState {
 
     /// Load the last consensus index from storage.
     async fn load_execution_indices(&self) -> Result<ExecutionIndices, Self::Error>;
+
+    /// Called after the execution of every `state_commitment_interval` certificates, before
+    /// `next_epoch`. Returning the digest of the execution state commits to it: the digest is
+    /// signed and compared with the ones of the other authorities, and a divergence from a
+    /// quorum is reported. The digest must only depend on the transactions executed so far. By
+    /// default, the execution state is not committed to.
+    async fn state_digest(&self, _consensus_output: &ConsensusOutput) -> Option<StateDigest> {
+        None
+    }
+
+    /// Called after the execution of every certificate. Returning the committee and the worker
+    /// cache of the next epoch moves the whole node to that epoch in place: this certificate is
+    /// the last one executed in the current epoch, and the certificates of the current epoch
+    /// sequenced after it are discarded. The consensus sequence of every epoch starts over at
+    /// index 0: the execution state must persist the new epoch atomically with the execution
+    /// indices of its origin, `ExecutionIndices::default()` (see `load_epoch`).
+    async fn next_epoch(
+        &self,
+        _consensus_output: &ConsensusOutput,
+    ) -> Option<(Committee, WorkerCache)> {
+        None
+    }
+
+    /// Load the committee and the worker cache of the last epoch returned by `next_epoch`. The
+    /// node starts in that epoch if it is more recent than the one of its committee, so that it
+    /// does not execute the certificates sequenced after the end of the previous epoch again
+    /// after a restart. By default, the node starts in the epoch of its committee.
+    async fn load_epoch(&self) -> Result<Option<(Committee, WorkerCache)>, Self::Error> {
+        Ok(None)
+    }
 }
 
 /// The output of the executor.
@@ -91,12 +193,18 @@ impl Executor {
     pub async fn spawn<State>(
         store: Store<(CertificateDigest, BatchDigest), Batch>,
         execution_state: Arc<State>,
+        codec: impl TransactionCodec<State::Transaction>,
         tx_reconfigure: &watch::Sender<ReconfigureNotification>,
         rx_consensus: metered_channel::Receiver<ConsensusOutput>,
         tx_output: Sender<ExecutorOutput<State>>,
         tx_get_block_commands: metered_channel::Sender<BlockCommand>,
+        tx_new_epochs: metered_channel::Sender<(Committee, WorkerCache)>,
+        state_commitment_interval: u64,
+        tx_state_digests: metered_channel::Sender<(Epoch, SequenceNumber, StateDigest)>,
         registry: &Registry,
         restored_consensus_output: Vec<ConsensusOutput>,
+        executed_store: Store<ExecutionIndices, TransactionDigest>,
+        dedup_cache_size: usize,
     ) -> SubscriberResult<Vec<JoinHandle<()>>>
     where
         State: ExecutionState + Send + Sync + 'static,
@@ -124,17 +232,24 @@ impl Executor {
             tx_reconfigure.subscribe(),
             rx_consensus,
             tx_executor,
-            arc_metrics,
+            arc_metrics.clone(),
             restored_consensus_output,
         );
 
         // Spawn the executor's core.
-        let executor_handle = Core::<State>::spawn(
+        let executor_handle = Core::spawn(
             store,
             execution_state,
+            codec,
             tx_reconfigure.subscribe(),
             /* rx_subscriber */ rx_executor,
             tx_output,
+            tx_new_epochs,
+            state_commitment_interval,
+            tx_state_digests,
+            executed_store,
+            dedup_cache_size,
+            arc_metrics,
         );
 
         // Return the handle.
@@ -163,9 +278,11 @@ where
         .await?
         .next_certificate_index;
 
+    // Both indices restart from 0 in every epoch. The certificate sequenced at index `i` is
+    // persisted under the key `i + 1`.
     if next_cert_index < consensus_next_index {
         let missing = consensus_store
-            .read_sequenced_certificates(&(next_cert_index..=consensus_next_index - 1))?
+            .read_sequenced_certificates(&(next_cert_index + 1..=consensus_next_index))?
             .iter()
             .zip(next_cert_index..consensus_next_index)
             .filter_map(|(c, seq)| c.map(|digest| (digest, seq)))
@@ -1,6 +1,9 @@
 // Copyright (c) 2022, Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
-use prometheus::{default_registry, register_int_gauge_with_registry, IntGauge, Registry};
+use prometheus::{
+    default_registry, register_int_counter_with_registry, register_int_gauge_with_registry,
+    IntCounter, IntGauge, Registry,
+};
 
 #[derive(Clone, Debug)]
 pub struct ExecutorMetrics {
@@ -8,6 +11,8 @@ pub struct ExecutorMetrics {
     pub tx_executor: IntGauge,
     /// Number of elements in the waiting (ready-to-deliver) list of subscriber
     pub waiting_elements_subscriber: IntGauge,
+    /// Number of sequenced transactions skipped as duplicates of already executed ones
+    pub duplicate_transactions: IntCounter,
 }
 
 impl ExecutorMetrics {
@@ -25,6 +30,12 @@ impl ExecutorMetrics {
                 registry
             )
             .unwrap(),
+            duplicate_transactions: register_int_counter_with_registry!(
+                "executor_duplicate_transactions",
+                "Number of sequenced transactions skipped as duplicates of already executed ones",
+                registry
+            )
+            .unwrap(),
         }
     }
 }
@@ -0,0 +1,155 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use crate::{ExecutionIndices, ExecutionState};
+use consensus::ConsensusOutput;
+use futures::{stream::FuturesUnordered, StreamExt};
+use std::collections::{BTreeSet, HashMap};
+
+#[cfg(test)]
+#[path = "tests/scheduler_tests.rs"]
+pub mod scheduler_tests;
+
+/// The keys of the state a transaction reads and writes. Two transactions conflict if one of
+/// them writes a key the other reads or writes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AccessSet {
+    /// The keys the transaction reads.
+    pub reads: Vec<Vec<u8>>,
+    /// The keys the transaction writes.
+    pub writes: Vec<Vec<u8>>,
+}
+
+/// The order in which transactions must run: every transaction waits for the earlier
+/// transactions it conflicts with.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Dependencies {
+    /// For every transaction, the number of earlier transactions it conflicts with.
+    pub pending: Vec<usize>,
+    /// For every transaction, the later transactions conflicting with it.
+    pub dependents: Vec<Vec<usize>>,
+}
+
+impl Dependencies {
+    pub fn new(access_sets: &[AccessSet]) -> Self {
+        let mut pending = Vec::with_capacity(access_sets.len());
+        let mut dependents = vec![Vec::new(); access_sets.len()];
+        let mut last_writes: HashMap<&[u8], usize> = HashMap::new();
+        let mut reads_since_write: HashMap<&[u8], Vec<usize>> = HashMap::new();
+
+        for (index, access_set) in access_sets.iter().enumerate() {
+            let mut conflicts = BTreeSet::new();
+            for key in &access_set.reads {
+                conflicts.extend(last_writes.get(key.as_slice()));
+            }
+            for key in &access_set.writes {
+                conflicts.extend(last_writes.get(key.as_slice()));
+                conflicts.extend(reads_since_write.get(key.as_slice()).into_iter().flatten());
+            }
+            for conflict in &conflicts {
+                dependents[*conflict].push(index);
+            }
+            pending.push(conflicts.len());
+
+            for key in &access_set.reads {
+                reads_since_write
+                    .entry(key.as_slice())
+                    .or_default()
+                    .push(index);
+            }
+            for key in &access_set.writes {
+                last_writes.insert(key.as_slice(), index);
+                reads_since_write.remove(key.as_slice());
+            }
+        }
+        Self {
+            pending,
+            dependents,
+        }
+    }
+}
+
+/// Executes the transactions concurrently, each one as soon as the earlier transactions it
+/// conflicts with are executed, so that their outcomes are the ones of a sequential execution.
+/// Returns the outcomes in order, stopping after the first error due to a fault in the
+/// authority (like `ExecutionState::handle_batch`).
+pub async fn execute_concurrently<State: ExecutionState + Sync>(
+    execution_state: &State,
+    consensus_output: &ConsensusOutput,
+    transactions: Vec<(ExecutionIndices, State::Transaction)>,
+    access_sets: &[AccessSet],
+) -> Vec<Result<State::Outcome, State::Error>> {
+    let Dependencies {
+        mut pending,
+        dependents,
+    } = Dependencies::new(access_sets);
+    let mut transactions: Vec<_> = transactions.into_iter().map(Some).collect();
+    let mut outcomes: Vec<_> = transactions.iter().map(|_| None).collect();
+
+    let mut running = FuturesUnordered::new();
+    for (index, dependencies) in pending.iter().enumerate() {
+        if *dependencies == 0 {
+            if let Some((execution_indices, transaction)) = transactions[index].take() {
+                running.push(execute(
+                    execution_state,
+                    consensus_output,
+                    index,
+                    execution_indices,
+                    transaction,
+                ));
+            }
+        }
+    }
+
+    // Stop scheduling transactions after a fatal error, but let the running ones finish.
+    let mut fatal = false;
+    while let Some((index, outcome)) = running.next().await {
+        fatal |= matches!(&outcome, Err(e) if e.node_error());
+        outcomes[index] = Some(outcome);
+        if fatal {
+            continue;
+        }
+        for dependent in &dependents[index] {
+            pending[*dependent] -= 1;
+            if pending[*dependent] == 0 {
+                if let Some((execution_indices, transaction)) = transactions[*dependent].take() {
+                    running.push(execute(
+                        execution_state,
+                        consensus_output,
+                        *dependent,
+                        execution_indices,
+                        transaction,
+                    ));
+                }
+            }
+        }
+    }
+
+    let mut ordered = Vec::with_capacity(outcomes.len());
+    for outcome in outcomes {
+        match outcome {
+            Some(outcome) => {
+                let fatal = matches!(&outcome, Err(e) if e.node_error());
+                ordered.push(outcome);
+                if fatal {
+                    break;
+                }
+            }
+            None => break,
+        }
+    }
+    ordered
+}
+
+/// Executes a single transaction, remembering its position.
+async fn execute<State: ExecutionState>(
+    execution_state: &State,
+    consensus_output: &ConsensusOutput,
+    index: usize,
+    execution_indices: ExecutionIndices,
+    transaction: State::Transaction,
+) -> (usize, Result<State::Outcome, State::Error>) {
+    let outcome = execution_state
+        .handle_concurrent_transaction(consensus_output, execution_indices, transaction)
+        .await;
+    (index, outcome)
+}
@@ -5,6 +5,7 @@ use crate::{
     SubscriberError::PayloadRetrieveError,
 };
 use backoff::{Error, ExponentialBackoff};
+use config::Epoch;
 use consensus::ConsensusOutput;
 use fastcrypto::Hash;
 use primary::BlockCommand;
@@ -14,7 +15,7 @@ use tokio::{
     sync::{oneshot, watch},
     task::JoinHandle,
 };
-use tracing::error;
+use tracing::{debug, error};
 use types::{
     bounded_future_queue::BoundedFuturesOrdered, metered_channel, Batch, BatchDigest,
     CertificateDigest, ReconfigureNotification,
@@ -45,6 +46,8 @@ pub struct Subscriber {
     get_block_retry_policy: ExponentialBackoff,
     /// The metrics handler
     metrics: Arc<ExecutorMetrics>,
+    /// The current epoch. The payload of the certificates of previous epochs is not downloaded.
+    epoch: Epoch,
 }
 
 impl Subscriber {
@@ -71,6 +74,12 @@ impl Subscriber {
             ..Default::default()
         };
 
+        let epoch = match &*rx_reconfigure.borrow() {
+            ReconfigureNotification::NewEpoch(committee)
+            | ReconfigureNotification::UpdateCommittee(committee) => committee.epoch(),
+            ReconfigureNotification::Shutdown => 0,
+        };
+
         tokio::spawn(async move {
             Self {
                 store,
@@ -80,6 +89,7 @@ impl Subscriber {
                 tx_get_block_commands,
                 get_block_retry_policy,
                 metrics,
+                epoch,
             }
             .run(restored_consensus_output)
             .await
@@ -118,6 +128,14 @@ impl Subscriber {
             tokio::select! {
                 // Receive the ordered sequence of consensus messages from a consensus node.
                 Some(message) = self.rx_consensus.recv(), if waiting.available_permits() > 0 => {
+                    // The executor does not execute the certificates sequenced after the end
+                    // of their epoch.
+                    if message.certificate.epoch() < self.epoch {
+                        let epoch = message.certificate.epoch();
+                        debug!("Dropping certificate of past epoch {epoch}");
+                        continue;
+                    }
+
                     // Fetch the certificate's payload from the workers. This is done via the
                     // block_waiter component. If the batches are not available in the workers then
                     // block_waiter will do its best to sync from the other peers. Once all batches
@@ -139,8 +157,12 @@ impl Subscriber {
                 result = self.rx_reconfigure.changed() => {
                     result.expect("Committee channel dropped");
                     let message = self.rx_reconfigure.borrow().clone();
-                    if let ReconfigureNotification::Shutdown = message {
-                        return Ok(());
+                    match message {
+                        ReconfigureNotification::NewEpoch(committee) => {
+                            self.epoch = self.epoch.max(committee.epoch());
+                        },
+                        ReconfigureNotification::UpdateCommittee(_) => (),
+                        ReconfigureNotification::Shutdown => return Ok(()),
                     }
                 }
             }
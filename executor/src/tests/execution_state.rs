@@ -1,17 +1,26 @@
 // Copyright (c) 2022, Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
-use crate::{ExecutionIndices, ExecutionState, ExecutionStateError};
+use crate::{AccessSet, ExecutionIndices, ExecutionState, ExecutionStateError};
 use async_trait::async_trait;
+use config::{Committee, WorkerCache};
 use consensus::ConsensusOutput;
+use fastcrypto::DIGEST_LEN;
 
 use futures::executor::block_on;
-use std::path::Path;
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
+    },
+};
 use store::{
     reopen,
     rocks::{open_cf, DBMap},
     Store,
 };
 use thiserror::Error;
+use types::StateDigest;
 
 /// A malformed transaction.
 pub const MALFORMED_TRANSACTION: <TestState as ExecutionState>::Transaction = 400;
@@ -19,9 +28,25 @@ pub const MALFORMED_TRANSACTION: <TestState as ExecutionState>::Transaction = 40
 /// A special transaction that makes the executor engine crash.
 pub const KILLER_TRANSACTION: <TestState as ExecutionState>::Transaction = 500;
 
+/// A special transaction that ends the epoch at the end of its certificate.
+pub const EPOCH_CHANGE_TRANSACTION: <TestState as ExecutionState>::Transaction = 600;
+
 /// A dumb execution state for testing.
 pub struct TestState {
     store: Store<u64, ExecutionIndices>,
+    /// The committee and worker cache of the next epoch.
+    next_epoch: Mutex<Option<(Committee, WorkerCache)>>,
+    /// Whether the `EPOCH_CHANGE_TRANSACTION` was executed in the current certificate.
+    epoch_ended: AtomicBool,
+    /// The number of calls to `begin_certificate`.
+    pub begun_certificates: AtomicU64,
+    /// The number of calls to `end_certificate`.
+    pub ended_certificates: AtomicU64,
+    /// Whether transactions declare the state they access, to be executed concurrently.
+    concurrent: bool,
+    /// The highest indices of the current certificate, persisted at its end when executing
+    /// transactions concurrently.
+    certificate_indices: Mutex<Option<ExecutionIndices>>,
 }
 
 impl std::fmt::Debug for TestState {
@@ -48,16 +73,57 @@ impl ExecutionState for TestState {
         execution_indices: ExecutionIndices,
         transaction: Self::Transaction,
     ) -> Result<Self::Outcome, Self::Error> {
-        if transaction == MALFORMED_TRANSACTION {
-            Err(Self::Error::ClientError)
-        } else if transaction == KILLER_TRANSACTION {
-            Err(Self::Error::ServerError)
-        } else {
+        self.execute(transaction)?;
+        self.store
+            .write(Self::INDICES_ADDRESS, execution_indices)
+            .await;
+        Ok(Vec::default())
+    }
+
+    async fn begin_certificate(
+        &self,
+        _consensus_output: &ConsensusOutput,
+    ) -> Result<(), Self::Error> {
+        self.begun_certificates.fetch_add(1, Ordering::SeqCst);
+        // Forget what a crash interrupted.
+        *self.certificate_indices.lock().unwrap() = None;
+        Ok(())
+    }
+
+    async fn end_certificate(
+        &self,
+        _consensus_output: &ConsensusOutput,
+    ) -> Result<(), Self::Error> {
+        self.ended_certificates.fetch_add(1, Ordering::SeqCst);
+        let certificate_indices = self.certificate_indices.lock().unwrap().take();
+        if let Some(execution_indices) = certificate_indices {
             self.store
                 .write(Self::INDICES_ADDRESS, execution_indices)
                 .await;
-            Ok(Vec::default())
         }
+        Ok(())
+    }
+
+    fn access_set(&self, transaction: &Self::Transaction) -> Option<AccessSet> {
+        // Transactions of the same parity conflict.
+        self.concurrent.then(|| AccessSet {
+            reads: Vec::new(),
+            writes: vec![vec![(transaction % 2) as u8]],
+        })
+    }
+
+    async fn handle_concurrent_transaction(
+        &self,
+        _consensus_output: &ConsensusOutput,
+        execution_indices: ExecutionIndices,
+        transaction: Self::Transaction,
+    ) -> Result<Self::Outcome, Self::Error> {
+        self.execute(transaction)?;
+        let mut certificate_indices = self.certificate_indices.lock().unwrap();
+        if certificate_indices.as_ref() < Some(&execution_indices) {
+            *certificate_indices = Some(execution_indices);
+        }
+        Ok(Vec::default())
     }
 
     fn ask_consensus_write_lock(&self) -> bool {
@@ -75,6 +141,31 @@ impl ExecutionState for TestState {
             .unwrap_or_default();
         Ok(indices)
     }
+
+    async fn state_digest(&self, _consensus_output: &ConsensusOutput) -> Option<StateDigest> {
+        // The state is summarized by the execution indices.
+        let indices = self.get_execution_indices().await;
+        let mut digest = [0u8; DIGEST_LEN];
+        digest[..8].copy_from_slice(&indices.next_certificate_index.to_le_bytes());
+        Some(StateDigest::new(digest))
+    }
+
+    async fn next_epoch(
+        &self,
+        _consensus_output: &ConsensusOutput,
+    ) -> Option<(Committee, WorkerCache)> {
+        if !self.epoch_ended.swap(false, Ordering::SeqCst) {
+            return None;
+        }
+        let next_epoch = self.next_epoch.lock().unwrap().take();
+        if next_epoch.is_some() {
+            // The execution indices start over in the new epoch.
+            self.store
+                .write(Self::INDICES_ADDRESS, ExecutionIndices::default())
+                .await;
+        }
+        next_epoch
+    }
 }
 
 impl TestState {
@@ -88,13 +179,45 @@ impl TestState {
         let map = reopen!(&rocksdb, STATE_CF;<u64, ExecutionIndices>);
         Self {
             store: Store::new(map),
+            next_epoch: Mutex::new(None),
+            epoch_ended: AtomicBool::new(false),
+            begun_certificates: AtomicU64::new(0),
+            ended_certificates: AtomicU64::new(0),
+            concurrent: false,
+            certificate_indices: Mutex::new(None),
         }
     }
 
+    /// Set the committee and worker cache of the epoch starting after `EPOCH_CHANGE_TRANSACTION`.
+    pub fn with_next_epoch(self, committee: Committee, worker_cache: WorkerCache) -> Self {
+        *self.next_epoch.lock().unwrap() = Some((committee, worker_cache));
+        self
+    }
+
+    /// Declare the state accessed by transactions, so that they are executed concurrently.
+    pub fn with_access_sets(mut self) -> Self {
+        self.concurrent = true;
+        self
+    }
+
     /// Load the execution indices; ie. the state.
     pub async fn get_execution_indices(&self) -> ExecutionIndices {
         self.load_execution_indices().await.unwrap()
     }
+
+    /// Execute a transaction, without persisting its indices.
+    fn execute(&self, transaction: u64) -> Result<(), TestStateError> {
+        match transaction {
+            MALFORMED_TRANSACTION => Err(TestStateError::ClientError),
+            KILLER_TRANSACTION => Err(TestStateError::ServerError),
+            _ => {
+                if transaction == EPOCH_CHANGE_TRANSACTION {
+                    self.epoch_ended.store(true, Ordering::SeqCst);
+                }
+                Ok(())
+            }
+        }
+    }
 }
 
 #[derive(Debug, Error)]
@@ -2,13 +2,25 @@
 // SPDX-License-Identifier: Apache-2.0
 use super::*;
 use crate::{
-    execution_state::{TestState, KILLER_TRANSACTION, MALFORMED_TRANSACTION},
-    fixtures::{test_batch, test_certificate, test_store, test_u64_certificates},
+    execution_state::{
+        TestState, EPOCH_CHANGE_TRANSACTION, KILLER_TRANSACTION, MALFORMED_TRANSACTION,
+    },
+    fixtures::{
+        test_batch, test_certificate, test_certificate_store, test_executed_store, test_store,
+        test_u64_certificates,
+    },
+    get_restored_consensus_output, BincodeCodec,
+};
+use config::WorkerId;
+use prometheus::Registry;
+use std::{
+    collections::HashMap,
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
 };
-use std::sync::Arc;
 use test_utils::CommitteeFixture;
 use tokio::sync::mpsc::channel;
-use types::Certificate;
+use types::{Certificate, Header};
 
 #[tokio::test]
 async fn execute_transactions() {
@@ -16,6 +28,8 @@ async fn execute_transactions() {
     let committee = fixture.committee();
     let (tx_executor, rx_executor) = test_utils::test_channel!(10);
     let (tx_output, mut rx_output) = channel(10);
+    let (tx_new_epochs, _rx_new_epochs) = test_utils::test_channel!(1);
+    let (tx_state_digests, _rx_state_digests) = test_utils::test_channel!(1);
 
     let message = ReconfigureNotification::NewEpoch(committee.clone());
     let (_tx_reconfigure, rx_reconfigure) = watch::channel(message);
@@ -23,12 +37,19 @@ async fn execute_transactions() {
     // Spawn the executor.
     let store = test_store();
     let execution_state = Arc::new(TestState::default());
-    let _core_handle = Core::<TestState>::spawn(
+    let _core_handle = Core::<TestState, _>::spawn(
         store.clone(),
         execution_state.clone(),
+        BincodeCodec::default(),
         rx_reconfigure,
         /* rx_subscriber */ rx_executor,
         tx_output,
+        tx_new_epochs,
+        /* state_commitment_interval */ 0,
+        tx_state_digests,
+        test_executed_store(),
+        /* dedup_cache_size */ 0,
+        Arc::new(ExecutorMetrics::new(&Registry::new())),
     );
 
     // Feed certificates to the mock sequencer and add the transaction data to storage (as if
@@ -67,6 +88,8 @@ async fn execute_empty_certificate() {
     let committee = fixture.committee();
     let (tx_executor, rx_executor) = test_utils::test_channel!(10);
     let (tx_output, mut rx_output) = channel(10);
+    let (tx_new_epochs, _rx_new_epochs) = test_utils::test_channel!(1);
+    let (tx_state_digests, _rx_state_digests) = test_utils::test_channel!(1);
 
     let message = ReconfigureNotification::NewEpoch(committee.clone());
     let (_tx_reconfigure, rx_reconfigure) = watch::channel(message);
@@ -74,12 +97,19 @@ async fn execute_empty_certificate() {
     // Spawn the executor.
     let store = test_store();
     let execution_state = Arc::new(TestState::default());
-    let _core_handle = Core::<TestState>::spawn(
+    let _core_handle = Core::<TestState, _>::spawn(
         store.clone(),
         execution_state.clone(),
+        BincodeCodec::default(),
         rx_reconfigure,
         /* rx_subscriber */ rx_executor,
         tx_output,
+        tx_new_epochs,
+        /* state_commitment_interval */ 0,
+        tx_state_digests,
+        test_executed_store(),
+        /* dedup_cache_size */ 0,
+        Arc::new(ExecutorMetrics::new(&Registry::new())),
     );
 
     // Feed empty certificates to the executor.
@@ -124,6 +154,8 @@ async fn execute_malformed_transactions() {
     let committee = fixture.committee();
     let (tx_executor, rx_executor) = test_utils::test_channel!(10);
     let (tx_output, mut rx_output) = channel(10);
+    let (tx_new_epochs, _rx_new_epochs) = test_utils::test_channel!(1);
+    let (tx_state_digests, _rx_state_digests) = test_utils::test_channel!(1);
 
     let message = ReconfigureNotification::NewEpoch(committee.clone());
     let (_tx_reconfigure, rx_reconfigure) = watch::channel(message);
@@ -131,12 +163,19 @@ async fn execute_malformed_transactions() {
     // Spawn the executor.
     let store = test_store();
     let execution_state = Arc::new(TestState::default());
-    let _core_handle = Core::<TestState>::spawn(
+    let _core_handle = Core::<TestState, _>::spawn(
         store.clone(),
         execution_state.clone(),
+        BincodeCodec::default(),
         rx_reconfigure,
         /* rx_subscriber */ rx_executor,
         tx_output,
+        tx_new_epochs,
+        /* state_commitment_interval */ 0,
+        tx_state_digests,
+        test_executed_store(),
+        /* dedup_cache_size */ 0,
+        Arc::new(ExecutorMetrics::new(&Registry::new())),
     );
 
     // Feed a malformed transaction to the mock sequencer
@@ -189,6 +228,8 @@ async fn internal_error_execution() {
     let committee = fixture.committee();
     let (tx_executor, rx_executor) = test_utils::test_channel!(10);
     let (tx_output, mut rx_output) = channel(10);
+    let (tx_new_epochs, _rx_new_epochs) = test_utils::test_channel!(1);
+    let (tx_state_digests, _rx_state_digests) = test_utils::test_channel!(1);
 
     let message = ReconfigureNotification::NewEpoch(committee.clone());
     let (_tx_reconfigure, rx_reconfigure) = watch::channel(message);
@@ -196,12 +237,19 @@ async fn internal_error_execution() {
     // Spawn the executor.
     let store = test_store();
     let execution_state = Arc::new(TestState::default());
-    let _core_hanlde = Core::<TestState>::spawn(
+    let _core_hanlde = Core::<TestState, _>::spawn(
         store.clone(),
         execution_state.clone(),
+        BincodeCodec::default(),
         rx_reconfigure,
         /* rx_subscriber */ rx_executor,
         tx_output,
+        tx_new_epochs,
+        /* state_commitment_interval */ 0,
+        tx_state_digests,
+        test_executed_store(),
+        /* dedup_cache_size */ 0,
+        Arc::new(ExecutorMetrics::new(&Registry::new())),
     );
 
     // Feed a 'killer' transaction to the executor. This is a special test transaction that
@@ -255,6 +303,8 @@ async fn crash_recovery() {
     let committee = fixture.committee();
     let (tx_executor, rx_executor) = test_utils::test_channel!(10);
     let (tx_output, mut rx_output) = channel(10);
+    let (tx_new_epochs, _rx_new_epochs) = test_utils::test_channel!(1);
+    let (tx_state_digests, _rx_state_digests) = test_utils::test_channel!(1);
 
     let reconfigure_notification = ReconfigureNotification::NewEpoch(committee.clone());
     let (_tx_reconfigure, rx_reconfigure) = watch::channel(reconfigure_notification.clone());
@@ -262,12 +312,19 @@ async fn crash_recovery() {
     // Spawn the executor.
     let store = test_store();
     let execution_state = Arc::new(TestState::default());
-    let _core_handle = Core::<TestState>::spawn(
+    let _core_handle = Core::<TestState, _>::spawn(
         store.clone(),
         execution_state.clone(),
+        BincodeCodec::default(),
         rx_reconfigure,
         /* rx_subscriber */ rx_executor,
         tx_output,
+        tx_new_epochs,
+        /* state_commitment_interval */ 0,
+        tx_state_digests,
+        test_executed_store(),
+        /* dedup_cache_size */ 0,
+        Arc::new(ExecutorMetrics::new(&Registry::new())),
     );
 
     // Feed two certificates with good transactions to the executor.
@@ -315,14 +372,23 @@ async fn crash_recovery() {
     // Reboot the executor.
     let (tx_executor, rx_executor) = test_utils::test_channel!(10);
     let (tx_output, mut rx_output) = channel(10);
+    let (tx_new_epochs, _rx_new_epochs) = test_utils::test_channel!(1);
+    let (tx_state_digests, _rx_state_digests) = test_utils::test_channel!(1);
     let (_tx_reconfigure, rx_reconfigure) = watch::channel(reconfigure_notification);
 
-    let _core_handle = Core::<TestState>::spawn(
+    let _core_handle = Core::<TestState, _>::spawn(
         store.clone(),
         execution_state.clone(),
+        BincodeCodec::default(),
         rx_reconfigure,
         /* rx_subscriber */ rx_executor,
         tx_output,
+        tx_new_epochs,
+        /* state_commitment_interval */ 0,
+        tx_state_digests,
+        test_executed_store(),
+        /* dedup_cache_size */ 0,
+        Arc::new(ExecutorMetrics::new(&Registry::new())),
     );
 
     // Feed two certificates with good transactions to the executor.
@@ -376,3 +442,780 @@ async fn assert_storage_cleaned_up(
         );
     }
 }
+
+#[tokio::test]
+async fn certificate_hooks() {
+    let fixture = CommitteeFixture::builder().build();
+    let committee = fixture.committee();
+    let (tx_executor, rx_executor) = test_utils::test_channel!(10);
+    let (tx_output, mut rx_output) = channel(10);
+    let (tx_new_epochs, _rx_new_epochs) = test_utils::test_channel!(1);
+    let (tx_state_digests, _rx_state_digests) = test_utils::test_channel!(1);
+
+    let message = ReconfigureNotification::NewEpoch(committee.clone());
+    let (_tx_reconfigure, rx_reconfigure) = watch::channel(message);
+
+    // Spawn the executor.
+    let store = test_store();
+    let execution_state = Arc::new(TestState::default());
+    let _core_handle = Core::<TestState, _>::spawn(
+        store.clone(),
+        execution_state.clone(),
+        BincodeCodec::default(),
+        rx_reconfigure,
+        /* rx_subscriber */ rx_executor,
+        tx_output,
+        tx_new_epochs,
+        /* state_commitment_interval */ 0,
+        tx_state_digests,
+        test_executed_store(),
+        /* dedup_cache_size */ 0,
+        Arc::new(ExecutorMetrics::new(&Registry::new())),
+    );
+
+    // Feed an empty certificate, then two certificates with transactions.
+    let message = ConsensusOutput {
+        certificate: Certificate::default(),
+        consensus_index: SequenceNumber::default(),
+    };
+    tx_executor.send(message).await.unwrap();
+
+    let certificates = test_u64_certificates(
+        &committee, /* certificates */ 2, /* batches_per_certificate */ 2,
+        /* transactions_per_batch */ 2,
+    );
+    for (certificate, batches) in certificates {
+        for (digest, batch) in batches {
+            store.write((certificate.digest(), digest), batch).await;
+        }
+        let message = ConsensusOutput {
+            certificate,
+            consensus_index: SequenceNumber::default(),
+        };
+        tx_executor.send(message).await.unwrap();
+    }
+
+    // Every transaction is executed through the default batch hook.
+    for _ in 0..8 {
+        let (result, _) = rx_output.recv().await.unwrap();
+        assert!(result.is_ok());
+    }
+
+    // Only the certificates with transactions are wrapped by the certificate hooks.
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(execution_state.begun_certificates.load(Ordering::SeqCst), 2);
+    assert_eq!(execution_state.ended_certificates.load(Ordering::SeqCst), 2);
+    let expected = ExecutionIndices {
+        next_certificate_index: 3,
+        next_batch_index: 0,
+        next_transaction_index: 0,
+    };
+    assert_eq!(execution_state.get_execution_indices().await, expected);
+}
+
+#[tokio::test]
+async fn execute_transactions_concurrently() {
+    let fixture = CommitteeFixture::builder().build();
+    let committee = fixture.committee();
+    let (tx_executor, rx_executor) = test_utils::test_channel!(10);
+    let (tx_output, mut rx_output) = channel(10);
+    let (tx_new_epochs, _rx_new_epochs) = test_utils::test_channel!(1);
+    let (tx_state_digests, _rx_state_digests) = test_utils::test_channel!(1);
+
+    let message = ReconfigureNotification::NewEpoch(committee.clone());
+    let (_tx_reconfigure, rx_reconfigure) = watch::channel(message);
+
+    // Spawn the executor with an execution state declaring the state its transactions access.
+    let store = test_store();
+    let execution_state = Arc::new(TestState::default().with_access_sets());
+    let _core_handle = Core::<TestState, _>::spawn(
+        store.clone(),
+        execution_state.clone(),
+        BincodeCodec::default(),
+        rx_reconfigure,
+        /* rx_subscriber */ rx_executor,
+        tx_output,
+        tx_new_epochs,
+        /* state_commitment_interval */ 0,
+        tx_state_digests,
+        test_executed_store(),
+        /* dedup_cache_size */ 0,
+        Arc::new(ExecutorMetrics::new(&Registry::new())),
+    );
+
+    // Feed two certificates mixing conflicting and independent transactions, and a malformed
+    // transaction.
+    let batches = [vec![1u64, 2, 3], vec![MALFORMED_TRANSACTION, 4], vec![5, 7]];
+    let mut expected_outputs = Vec::new();
+    for transactions in [&batches[..2], &batches[2..]] {
+        let certificate_batches: Vec<_> = transactions
+            .iter()
+            .map(|transactions| test_batch(transactions.clone()))
+            .collect();
+        let payload = certificate_batches
+            .iter()
+            .enumerate()
+            .map(|(worker_id, (digest, _))| (*digest, worker_id as WorkerId))
+            .collect();
+        let certificate = test_certificate(&committee, payload);
+        for (digest, batch) in certificate_batches {
+            expected_outputs.extend(batch.0.clone());
+            store.write((certificate.digest(), digest), batch).await;
+        }
+
+        let message = ConsensusOutput {
+            certificate,
+            consensus_index: SequenceNumber::default(),
+        };
+        tx_executor.send(message).await.unwrap();
+    }
+
+    // Ensure the transactions are output in the consensus order.
+    for expected in expected_outputs {
+        let (result, transaction) = rx_output.recv().await.unwrap();
+        assert_eq!(transaction, expected);
+        let malformed = bincode::serialize(&MALFORMED_TRANSACTION).unwrap();
+        assert_eq!(result.is_ok(), transaction != malformed);
+    }
+
+    // Ensure the indices of each certificate are persisted at its end.
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(execution_state.ended_certificates.load(Ordering::SeqCst), 2);
+    let expected = ExecutionIndices {
+        next_certificate_index: 2,
+        next_batch_index: 0,
+        next_transaction_index: 0,
+    };
+    assert_eq!(execution_state.get_execution_indices().await, expected);
+}
+
+#[tokio::test]
+async fn crash_recovery_concurrently() {
+    let fixture = CommitteeFixture::builder().build();
+    let committee = fixture.committee();
+    let (tx_executor, rx_executor) = test_utils::test_channel!(10);
+    let (tx_output, mut rx_output) = channel(10);
+    let (tx_new_epochs, _rx_new_epochs) = test_utils::test_channel!(1);
+    let (tx_state_digests, _rx_state_digests) = test_utils::test_channel!(1);
+
+    let reconfigure_notification = ReconfigureNotification::NewEpoch(committee.clone());
+    let (_tx_reconfigure, rx_reconfigure) = watch::channel(reconfigure_notification.clone());
+
+    // Spawn the executor with an execution state declaring the state its transactions access.
+    let store = test_store();
+    let execution_state = Arc::new(TestState::default().with_access_sets());
+    let _core_handle = Core::<TestState, _>::spawn(
+        store.clone(),
+        execution_state.clone(),
+        BincodeCodec::default(),
+        rx_reconfigure,
+        /* rx_subscriber */ rx_executor,
+        tx_output,
+        tx_new_epochs,
+        /* state_commitment_interval */ 0,
+        tx_state_digests,
+        test_executed_store(),
+        /* dedup_cache_size */ 0,
+        Arc::new(ExecutorMetrics::new(&Registry::new())),
+    );
+
+    // Feed two certificates with good transactions to the executor.
+    let certificates = test_u64_certificates(
+        &committee, /* certificates */ 2, /* batches_per_certificate */ 2,
+        /* transactions_per_batch */ 2,
+    );
+    for (certificate, batches) in certificates {
+        for (digest, batch) in batches {
+            store.write((certificate.digest(), digest), batch).await;
+        }
+        let message = ConsensusOutput {
+            certificate,
+            consensus_index: SequenceNumber::default(),
+        };
+        tx_executor.send(message).await.unwrap();
+    }
+
+    // Feed a certificate whose 'killer' transaction crashes the test executor engine after
+    // executing the transaction preceding it.
+    let (digest, batch) = test_batch(vec![10u64, KILLER_TRANSACTION]);
+    let payload = [(digest, 0)].iter().cloned().collect();
+    let certificate = test_certificate(&committee, payload);
+    store.write((certificate.digest(), digest), batch).await;
+    let message = ConsensusOutput {
+        certificate,
+        consensus_index: SequenceNumber::default(),
+    };
+    tx_executor.send(message).await.unwrap();
+
+    // Ensure nothing of the interrupted certificate was persisted.
+    while rx_output.recv().await.is_some() {}
+    assert_eq!(execution_state.ended_certificates.load(Ordering::SeqCst), 2);
+    let expected = ExecutionIndices {
+        next_certificate_index: 2,
+        next_batch_index: 0,
+        next_transaction_index: 0,
+    };
+    assert_eq!(execution_state.get_execution_indices().await, expected);
+
+    // Reboot the executor.
+    let (tx_executor, rx_executor) = test_utils::test_channel!(10);
+    let (tx_output, mut rx_output) = channel(10);
+    let (tx_new_epochs, _rx_new_epochs) = test_utils::test_channel!(1);
+    let (tx_state_digests, _rx_state_digests) = test_utils::test_channel!(1);
+    let (_tx_reconfigure, rx_reconfigure) = watch::channel(reconfigure_notification);
+
+    let _core_handle = Core::<TestState, _>::spawn(
+        store.clone(),
+        execution_state.clone(),
+        BincodeCodec::default(),
+        rx_reconfigure,
+        /* rx_subscriber */ rx_executor,
+        tx_output,
+        tx_new_epochs,
+        /* state_commitment_interval */ 0,
+        tx_state_digests,
+        test_executed_store(),
+        /* dedup_cache_size */ 0,
+        Arc::new(ExecutorMetrics::new(&Registry::new())),
+    );
+
+    // Feed two certificates with good transactions to the executor.
+    let certificates = test_u64_certificates(
+        &committee, /* certificates */ 2, /* batches_per_certificate */ 2,
+        /* transactions_per_batch */ 2,
+    );
+    for (certificate, batches) in certificates {
+        for (digest, batch) in batches {
+            store.write((certificate.digest(), digest), batch).await;
+        }
+        let message = ConsensusOutput {
+            certificate,
+            consensus_index: SequenceNumber::default(),
+        };
+        tx_executor.send(message).await.unwrap();
+    }
+
+    // Ensure the indices of the certificates executed after the crash are persisted.
+    for _ in 0..8 {
+        rx_output.recv().await.unwrap();
+    }
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(execution_state.ended_certificates.load(Ordering::SeqCst), 4);
+    let expected = ExecutionIndices {
+        next_certificate_index: 4,
+        next_batch_index: 0,
+        next_transaction_index: 0,
+    };
+    assert_eq!(execution_state.get_execution_indices().await, expected);
+}
+
+/// A codec expecting every transaction to be wrapped in a versioned envelope.
+#[derive(Clone)]
+struct EnvelopeCodec;
+
+impl TransactionCodec<u64> for EnvelopeCodec {
+    type Error = String;
+
+    fn decode(&self, transaction: &[u8]) -> Result<u64, Self::Error> {
+        match transaction.split_first() {
+            Some((1, payload)) => bincode::deserialize(payload).map_err(|e| e.to_string()),
+            _ => Err("Unknown envelope version".to_string()),
+        }
+    }
+}
+
+#[tokio::test]
+async fn execute_with_custom_codec() {
+    let fixture = CommitteeFixture::builder().build();
+    let committee = fixture.committee();
+    let (tx_executor, rx_executor) = test_utils::test_channel!(10);
+    let (tx_output, mut rx_output) = channel(10);
+    let (tx_new_epochs, _rx_new_epochs) = test_utils::test_channel!(1);
+    let (tx_state_digests, _rx_state_digests) = test_utils::test_channel!(1);
+
+    let message = ReconfigureNotification::NewEpoch(committee.clone());
+    let (_tx_reconfigure, rx_reconfigure) = watch::channel(message);
+
+    // Spawn the executor.
+    let store = test_store();
+    let execution_state = Arc::new(TestState::default());
+    let _core_handle = Core::<TestState, _>::spawn(
+        store.clone(),
+        execution_state.clone(),
+        EnvelopeCodec,
+        rx_reconfigure,
+        /* rx_subscriber */ rx_executor,
+        tx_output,
+        tx_new_epochs,
+        /* state_commitment_interval */ 0,
+        tx_state_digests,
+        test_executed_store(),
+        /* dedup_cache_size */ 0,
+        Arc::new(ExecutorMetrics::new(&Registry::new())),
+    );
+
+    // Feed a certificate with a transaction in a supported envelope, then one in an unknown
+    // envelope.
+    let envelope = |version: u8, transaction: u64| {
+        let mut bytes = vec![version];
+        bytes.extend(bincode::serialize(&transaction).unwrap());
+        bytes
+    };
+    let batch = Batch(vec![envelope(1, 10), envelope(2, 11)]);
+    let digest = batch.digest();
+    let payload = [(digest, 0)].iter().cloned().collect();
+    let certificate = test_certificate(&committee, payload);
+    store.write((certificate.digest(), digest), batch).await;
+
+    let message = ConsensusOutput {
+        certificate,
+        consensus_index: SequenceNumber::default(),
+    };
+    tx_executor.send(message).await.unwrap();
+
+    // Ensure the first transaction is executed and the second one rejected as a client error.
+    let (result, transaction) = rx_output.recv().await.unwrap();
+    assert!(result.is_ok());
+    assert_eq!(transaction, envelope(1, 10));
+
+    let (result, transaction) = rx_output.recv().await.unwrap();
+    assert!(matches!(
+        result,
+        Err(SubscriberError::ClientExecutionError(_))
+    ));
+    assert_eq!(transaction, envelope(2, 11));
+}
+
+#[tokio::test]
+async fn skip_duplicate_transactions() {
+    let fixture = CommitteeFixture::builder().build();
+    let committee = fixture.committee();
+    let (tx_executor, rx_executor) = test_utils::test_channel!(10);
+    let (tx_output, mut rx_output) = channel(10);
+    let (tx_new_epochs, _rx_new_epochs) = test_utils::test_channel!(1);
+    let (tx_state_digests, _rx_state_digests) = test_utils::test_channel!(1);
+
+    let message = ReconfigureNotification::NewEpoch(committee.clone());
+    let (_tx_reconfigure, rx_reconfigure) = watch::channel(message);
+
+    // Spawn the executor, remembering the last executed transactions.
+    let store = test_store();
+    let execution_state = Arc::new(TestState::default());
+    let metrics = Arc::new(ExecutorMetrics::new(&Registry::new()));
+    let _core_handle = Core::<TestState, _>::spawn(
+        store.clone(),
+        execution_state.clone(),
+        BincodeCodec::default(),
+        rx_reconfigure,
+        /* rx_subscriber */ rx_executor,
+        tx_output,
+        tx_new_epochs,
+        /* state_commitment_interval */ 0,
+        tx_state_digests,
+        test_executed_store(),
+        /* dedup_cache_size */ 10,
+        metrics.clone(),
+    );
+
+    // Feed two certificates carrying duplicate transactions, within a batch and across batches.
+    for transactions in [vec![1u64, 2, 1], vec![2, 3]] {
+        let (digest, batch) = test_batch(transactions);
+        let payload = [(digest, 0)].iter().cloned().collect();
+        let certificate = test_certificate(&committee, payload);
+        store.write((certificate.digest(), digest), batch).await;
+
+        let message = ConsensusOutput {
+            certificate,
+            consensus_index: SequenceNumber::default(),
+        };
+        tx_executor.send(message).await.unwrap();
+    }
+
+    // Ensure every transaction is executed once.
+    for expected in [1u64, 2, 3] {
+        let (result, transaction) = rx_output.recv().await.unwrap();
+        assert!(result.is_ok());
+        assert_eq!(transaction, bincode::serialize(&expected).unwrap());
+    }
+    assert_eq!(metrics.duplicate_transactions.get(), 2);
+
+    // Ensure the execution indices moved past the duplicates.
+    let expected = ExecutionIndices {
+        next_certificate_index: 2,
+        next_batch_index: 0,
+        next_transaction_index: 0,
+    };
+    assert_eq!(execution_state.get_execution_indices().await, expected);
+}
+
+#[tokio::test]
+async fn skip_duplicate_transactions_after_restart() {
+    let fixture = CommitteeFixture::builder().build();
+    let committee = fixture.committee();
+    let message = ReconfigureNotification::NewEpoch(committee.clone());
+    let (_tx_reconfigure, rx_reconfigure) = watch::channel(message);
+
+    let store = test_store();
+    let executed_store = test_executed_store();
+    let execution_state = Arc::new(TestState::default());
+    let metrics = Arc::new(ExecutorMetrics::new(&Registry::new()));
+
+    // Execute a certificate, then restart the executor and execute a second certificate
+    // carrying a duplicate of a transaction executed before the restart.
+    for (consensus_index, transactions, expected) in
+        [(0, vec![1u64, 2], vec![1u64, 2]), (1, vec![2, 3], vec![3])]
+    {
+        let (tx_executor, rx_executor) = test_utils::test_channel!(10);
+        let (tx_output, mut rx_output) = channel(10);
+        let (tx_new_epochs, _rx_new_epochs) = test_utils::test_channel!(1);
+        let (tx_state_digests, _rx_state_digests) = test_utils::test_channel!(1);
+        let core_handle = Core::<TestState, _>::spawn(
+            store.clone(),
+            execution_state.clone(),
+            BincodeCodec::default(),
+            rx_reconfigure.clone(),
+            /* rx_subscriber */ rx_executor,
+            tx_output,
+            tx_new_epochs,
+            /* state_commitment_interval */ 0,
+            tx_state_digests,
+            executed_store.clone(),
+            /* dedup_cache_size */ 10,
+            metrics.clone(),
+        );
+
+        let (digest, batch) = test_batch(transactions);
+        let payload = [(digest, 0)].iter().cloned().collect();
+        let certificate = test_certificate(&committee, payload);
+        store.write((certificate.digest(), digest), batch).await;
+
+        let message = ConsensusOutput {
+            certificate,
+            consensus_index,
+        };
+        tx_executor.send(message).await.unwrap();
+
+        for expected in expected {
+            let (result, transaction) = rx_output.recv().await.unwrap();
+            assert!(result.is_ok());
+            assert_eq!(transaction, bincode::serialize(&expected).unwrap());
+        }
+        core_handle.abort();
+        let _ = core_handle.await;
+    }
+    assert_eq!(metrics.duplicate_transactions.get(), 1);
+}
+
+#[tokio::test]
+async fn load_executed_transactions() {
+    // Record the digests of three transactions, the last one prepared but not executed.
+    let store = test_executed_store();
+    let indices: Vec<_> = (1..=3)
+        .map(|next_certificate_index| ExecutionIndices {
+            next_certificate_index,
+            ..ExecutionIndices::default()
+        })
+        .collect();
+    let transactions: Vec<_> = (1u64..=3)
+        .map(|x| bincode::serialize(&x).unwrap())
+        .collect();
+    let entries = indices
+        .iter()
+        .cloned()
+        .zip(transactions.iter().map(|x| transaction_digest(x)));
+    store.write_all(entries).await.unwrap();
+
+    // Ensure only the last executed transaction is remembered, and the rest is dropped.
+    let mut executed = ExecutedTransactions::load(store.clone(), 1, &indices[1])
+        .await
+        .unwrap();
+    let stored = store.iter(None).await;
+    assert_eq!(stored.len(), 1);
+    assert!(stored.contains_key(&indices[1]));
+
+    assert!(!executed
+        .insert(&transactions[1], &indices[2])
+        .await
+        .unwrap());
+    assert!(executed
+        .insert(&transactions[2], &indices[2])
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn change_epoch() {
+    let fixture = CommitteeFixture::builder().build();
+    let committee = fixture.committee();
+    let (tx_executor, rx_executor) = test_utils::test_channel!(10);
+    let (tx_output, mut rx_output) = channel(10);
+    let (tx_new_epochs, mut rx_new_epochs) = test_utils::test_channel!(1);
+    let (tx_state_digests, _rx_state_digests) = test_utils::test_channel!(1);
+
+    let message = ReconfigureNotification::NewEpoch(committee.clone());
+    let (_tx_reconfigure, rx_reconfigure) = watch::channel(message);
+
+    // Spawn the executor, with an execution state ending epoch 0 after `EPOCH_CHANGE_TRANSACTION`.
+    let mut next_committee = committee.clone();
+    next_committee.epoch = 1;
+    let store = test_store();
+    let execution_state = Arc::new(
+        TestState::default().with_next_epoch(next_committee.clone(), fixture.worker_cache()),
+    );
+    let _core_handle = Core::<TestState, _>::spawn(
+        store.clone(),
+        execution_state.clone(),
+        BincodeCodec::default(),
+        rx_reconfigure,
+        /* rx_subscriber */ rx_executor,
+        tx_output,
+        tx_new_epochs,
+        /* state_commitment_interval */ 0,
+        tx_state_digests,
+        test_executed_store(),
+        /* dedup_cache_size */ 0,
+        Arc::new(ExecutorMetrics::new(&Registry::new())),
+    );
+
+    // Feed a certificate ending epoch 0, another certificate of epoch 0 sequenced after it, and
+    // a certificate of epoch 1.
+    for (epoch, transactions) in [
+        (0, vec![1u64, EPOCH_CHANGE_TRANSACTION]),
+        (0, vec![2]),
+        (1, vec![3]),
+    ] {
+        let (digest, batch) = test_batch(transactions);
+        let header = Header {
+            epoch,
+            payload: [(digest, 0)].iter().cloned().collect(),
+            ..Header::default()
+        };
+        let certificate = Certificate::new_unsigned(&committee, header, Vec::new()).unwrap();
+        store.write((certificate.digest(), digest), batch).await;
+
+        let message = ConsensusOutput {
+            certificate,
+            consensus_index: SequenceNumber::default(),
+        };
+        tx_executor.send(message).await.unwrap();
+    }
+
+    // Ensure the primary is asked to move to epoch 1.
+    let (committee, _worker_cache) = rx_new_epochs.recv().await.unwrap();
+    assert_eq!(committee, next_committee);
+
+    // Ensure the certificate of epoch 0 sequenced after the end of the epoch is not executed.
+    for expected in [1u64, EPOCH_CHANGE_TRANSACTION, 3] {
+        let (result, transaction) = rx_output.recv().await.unwrap();
+        assert!(result.is_ok());
+        assert_eq!(transaction, bincode::serialize(&expected).unwrap());
+    }
+
+    // Ensure the execution indices start over in epoch 1.
+    let expected = ExecutionIndices {
+        next_certificate_index: 1,
+        next_batch_index: 0,
+        next_transaction_index: 0,
+    };
+    assert_eq!(execution_state.get_execution_indices().await, expected);
+}
+
+#[tokio::test]
+async fn crash_recovery_across_epochs() {
+    let fixture = CommitteeFixture::builder().build();
+    let committee = fixture.committee();
+    let (tx_executor, rx_executor) = test_utils::test_channel!(10);
+    let (tx_output, mut rx_output) = channel(10);
+    let (tx_new_epochs, mut rx_new_epochs) = test_utils::test_channel!(1);
+    let (tx_state_digests, _rx_state_digests) = test_utils::test_channel!(1);
+
+    let message = ReconfigureNotification::NewEpoch(committee.clone());
+    let (_tx_reconfigure, rx_reconfigure) = watch::channel(message);
+
+    // Spawn the executor, with an execution state ending epoch 0 after `EPOCH_CHANGE_TRANSACTION`.
+    let mut next_committee = committee.clone();
+    next_committee.epoch = 1;
+    let store = test_store();
+    let execution_state = Arc::new(
+        TestState::default().with_next_epoch(next_committee.clone(), fixture.worker_cache()),
+    );
+    let core_handle = Core::<TestState, _>::spawn(
+        store.clone(),
+        execution_state.clone(),
+        BincodeCodec::default(),
+        rx_reconfigure,
+        /* rx_subscriber */ rx_executor,
+        tx_output,
+        tx_new_epochs,
+        /* state_commitment_interval */ 0,
+        tx_state_digests,
+        test_executed_store(),
+        /* dedup_cache_size */ 0,
+        Arc::new(ExecutorMetrics::new(&Registry::new())),
+    );
+
+    // A certificate ends epoch 0, then the consensus sequences three certificates in epoch 1.
+    let mut certificates = Vec::new();
+    for (epoch, transactions) in [
+        (0, vec![1u64, EPOCH_CHANGE_TRANSACTION]),
+        (1, vec![2]),
+        (1, vec![3]),
+        (1, vec![4]),
+    ] {
+        let (digest, batch) = test_batch(transactions);
+        let header = Header {
+            epoch,
+            payload: [(digest, 0)].iter().cloned().collect(),
+            ..Header::default()
+        };
+        let certificate = Certificate::new_unsigned(&committee, header, Vec::new()).unwrap();
+        store.write((certificate.digest(), digest), batch).await;
+        certificates.push(certificate);
+    }
+    let consensus_store = test_utils::make_consensus_store(&test_utils::temp_dir());
+    let certificate_store = test_certificate_store();
+    for (consensus_index, certificate) in certificates[1..].iter().enumerate() {
+        certificate_store.write(certificate.clone()).unwrap();
+        consensus_store
+            .write_consensus_state(
+                &HashMap::new(),
+                &(consensus_index as SequenceNumber + 1),
+                &certificate.digest(),
+                &certificate.round(),
+            )
+            .unwrap();
+    }
+
+    // The executor crashes after executing the first certificate of epoch 1.
+    for (consensus_index, certificate) in [(0, &certificates[0]), (0, &certificates[1])] {
+        let message = ConsensusOutput {
+            certificate: certificate.clone(),
+            consensus_index,
+        };
+        tx_executor.send(message).await.unwrap();
+    }
+    rx_new_epochs.recv().await.unwrap();
+    for _ in 0..3 {
+        let (result, _transaction) = rx_output.recv().await.unwrap();
+        assert!(result.is_ok());
+    }
+    core_handle.abort();
+    let _ = core_handle.await;
+    let expected = ExecutionIndices {
+        next_certificate_index: 1,
+        next_batch_index: 0,
+        next_transaction_index: 0,
+    };
+    assert_eq!(execution_state.get_execution_indices().await, expected);
+
+    // The certificates of epoch 1 sequenced but not executed before the crash are restored.
+    let restored =
+        get_restored_consensus_output(consensus_store, certificate_store, execution_state.clone())
+            .await
+            .unwrap();
+    let restored: Vec<_> = restored
+        .into_iter()
+        .map(|output| (output.consensus_index, output.certificate))
+        .collect();
+    assert_eq!(
+        restored,
+        vec![(1, certificates[2].clone()), (2, certificates[3].clone())]
+    );
+
+    // Reboot the executor in epoch 1 and execute them.
+    let (tx_executor, rx_executor) = test_utils::test_channel!(10);
+    let (tx_output, mut rx_output) = channel(10);
+    let (tx_new_epochs, _rx_new_epochs) = test_utils::test_channel!(1);
+    let (tx_state_digests, _rx_state_digests) = test_utils::test_channel!(1);
+    let message = ReconfigureNotification::NewEpoch(next_committee);
+    let (_tx_reconfigure, rx_reconfigure) = watch::channel(message);
+    let _core_handle = Core::<TestState, _>::spawn(
+        store.clone(),
+        execution_state.clone(),
+        BincodeCodec::default(),
+        rx_reconfigure,
+        /* rx_subscriber */ rx_executor,
+        tx_output,
+        tx_new_epochs,
+        /* state_commitment_interval */ 0,
+        tx_state_digests,
+        test_executed_store(),
+        /* dedup_cache_size */ 0,
+        Arc::new(ExecutorMetrics::new(&Registry::new())),
+    );
+    for (consensus_index, certificate) in restored {
+        let message = ConsensusOutput {
+            certificate,
+            consensus_index,
+        };
+        tx_executor.send(message).await.unwrap();
+    }
+    for expected in [3u64, 4] {
+        let (result, transaction) = rx_output.recv().await.unwrap();
+        assert!(result.is_ok());
+        assert_eq!(transaction, bincode::serialize(&expected).unwrap());
+    }
+    let expected = ExecutionIndices {
+        next_certificate_index: 3,
+        next_batch_index: 0,
+        next_transaction_index: 0,
+    };
+    assert_eq!(execution_state.get_execution_indices().await, expected);
+}
+
+#[tokio::test]
+async fn commit_state_periodically() {
+    let fixture = CommitteeFixture::builder().build();
+    let committee = fixture.committee();
+    let (tx_executor, rx_executor) = test_utils::test_channel!(10);
+    let (tx_output, _rx_output) = channel(10);
+    let (tx_new_epochs, _rx_new_epochs) = test_utils::test_channel!(1);
+    let (tx_state_digests, mut rx_state_digests) = test_utils::test_channel!(10);
+
+    let message = ReconfigureNotification::NewEpoch(committee.clone());
+    let (_tx_reconfigure, rx_reconfigure) = watch::channel(message);
+
+    // Spawn the executor, committing to the execution state every 2 certificates.
+    let store = test_store();
+    let execution_state = Arc::new(TestState::default());
+    let _core_handle = Core::<TestState, _>::spawn(
+        store.clone(),
+        execution_state.clone(),
+        BincodeCodec::default(),
+        rx_reconfigure,
+        /* rx_subscriber */ rx_executor,
+        tx_output,
+        tx_new_epochs,
+        /* state_commitment_interval */ 2,
+        tx_state_digests,
+        test_executed_store(),
+        /* dedup_cache_size */ 0,
+        Arc::new(ExecutorMetrics::new(&Registry::new())),
+    );
+
+    // Feed 5 certificates to the mock sequencer.
+    let certificates = test_u64_certificates(
+        &committee, /* certificates */ 5, /* batches_per_certificate */ 1,
+        /* transactions_per_batch */ 1,
+    );
+    for (consensus_index, (certificate, batches)) in certificates.into_iter().enumerate() {
+        for (digest, batch) in batches {
+            store.write((certificate.digest(), digest), batch).await;
+        }
+        let message = ConsensusOutput {
+            certificate,
+            consensus_index: consensus_index as SequenceNumber,
+        };
+        tx_executor.send(message).await.unwrap();
+    }
+
+    // Ensure the execution state is committed to after the second and the fourth certificates.
+    for consensus_index in [1, 3] {
+        let (epoch, index, state) = rx_state_digests.recv().await.unwrap();
+        assert_eq!(epoch, committee.epoch());
+        assert_eq!(index, consensus_index);
+        assert_eq!(state.0[..8], (consensus_index + 1).to_le_bytes());
+    }
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(rx_state_digests.try_recv().is_err());
+}
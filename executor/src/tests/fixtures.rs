@@ -1,17 +1,19 @@
 // Copyright (c) 2022, Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::ExecutionIndices;
 use config::{Committee, WorkerId};
 use fastcrypto::Hash;
 use indexmap::IndexMap;
 use rand::{rngs::StdRng, RngCore, SeedableRng};
 use serde::Serialize;
+use storage::CertificateStore;
 use store::{
     reopen,
     rocks::{open_cf, DBMap},
     Store,
 };
-use types::{Batch, BatchDigest, Certificate, CertificateDigest, Header};
+use types::{Batch, BatchDigest, Certificate, CertificateDigest, Header, Round, TransactionDigest};
 
 /// A test batch containing specific transactions.
 pub fn test_batch<T: Serialize>(transactions: Vec<T>) -> (BatchDigest, Batch) {
@@ -51,6 +53,34 @@ pub fn test_store() -> Store<(CertificateDigest, BatchDigest), Batch> {
     Store::new(temp_batch_map)
 }
 
+/// Make a test storage to hold the digests of the executed transactions.
+pub fn test_executed_store() -> Store<ExecutionIndices, TransactionDigest> {
+    let store_path = tempfile::tempdir().unwrap();
+    const EXECUTED_TRANSACTIONS_CF: &str = "executed_transactions";
+    let rocksdb = open_cf(store_path, None, &[EXECUTED_TRANSACTIONS_CF]).unwrap();
+    let executed_map =
+        reopen!(&rocksdb, EXECUTED_TRANSACTIONS_CF;<ExecutionIndices, TransactionDigest>);
+    Store::new(executed_map)
+}
+
+/// Make a test storage to hold certificates.
+pub fn test_certificate_store() -> CertificateStore {
+    let store_path = tempfile::tempdir().unwrap();
+    const CERTIFICATES_CF: &str = "certificates";
+    const CERTIFICATE_ID_BY_ROUND_CF: &str = "certificate_id_by_round";
+    let rocksdb = open_cf(
+        store_path,
+        None,
+        &[CERTIFICATES_CF, CERTIFICATE_ID_BY_ROUND_CF],
+    )
+    .unwrap();
+    let (certificate_map, certificate_id_by_round_map) = reopen!(&rocksdb,
+        CERTIFICATES_CF;<CertificateDigest, Certificate>,
+        CERTIFICATE_ID_BY_ROUND_CF;<(Round, CertificateDigest), u8>
+    );
+    CertificateStore::new(certificate_map, certificate_id_by_round_map)
+}
+
 /// Create a number of test certificates containing transactions of type u64.
 pub fn test_u64_certificates(
     committee: &Committee,
@@ -0,0 +1,44 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use super::*;
+
+fn access_set(reads: &[u8], writes: &[u8]) -> AccessSet {
+    AccessSet {
+        reads: reads.iter().map(|key| vec![*key]).collect(),
+        writes: writes.iter().map(|key| vec![*key]).collect(),
+    }
+}
+
+#[test]
+fn disjoint_transactions_are_independent() {
+    let access_sets = vec![
+        access_set(&[1], &[1]),
+        access_set(&[2], &[2]),
+        access_set(&[3], &[]),
+    ];
+    let dependencies = Dependencies::new(&access_sets);
+    assert_eq!(dependencies.pending, vec![0, 0, 0]);
+    assert_eq!(dependencies.dependents, vec![vec![], vec![], vec![]]);
+}
+
+#[test]
+fn conflicting_transactions_keep_their_order() {
+    let access_sets = vec![
+        // Writes key 1.
+        access_set(&[], &[1]),
+        // Reads key 1: waits for the first write.
+        access_set(&[1], &[]),
+        // Reads key 1 too: only waits for the first write, not for the other read.
+        access_set(&[1], &[2]),
+        // Writes key 1: waits for the first write and both reads.
+        access_set(&[], &[1]),
+        // Reads key 2: waits for the third transaction only.
+        access_set(&[2], &[]),
+    ];
+    let dependencies = Dependencies::new(&access_sets);
+    assert_eq!(dependencies.pending, vec![0, 1, 1, 3, 1]);
+    assert_eq!(
+        dependencies.dependents,
+        vec![vec![1, 2, 3], vec![3], vec![3, 4], vec![], vec![]]
+    );
+}
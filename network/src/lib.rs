@@ -12,12 +12,14 @@
 mod bounded_executor;
 pub mod metrics;
 mod p2p;
+mod reputation;
 mod retry;
 mod traits;
 
 pub use crate::{
     bounded_executor::BoundedExecutor,
     p2p::P2pNetwork,
+    reputation::PeerReputation,
     retry::RetryConfig,
     traits::{Lucky, LuckyNetwork, ReliableNetwork, UnreliableNetwork},
 };
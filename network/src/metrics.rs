@@ -1,6 +1,9 @@
 // Copyright (c) 2022, Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
-use prometheus::{default_registry, register_int_gauge_vec_with_registry, IntGaugeVec, Registry};
+use prometheus::{
+    default_registry, register_int_counter_vec_with_registry, register_int_gauge_vec_with_registry,
+    IntCounterVec, IntGaugeVec, Registry,
+};
 use std::sync::Arc;
 
 pub trait NetworkMetrics {
@@ -71,6 +74,36 @@ impl Default for WorkerNetworkMetrics {
     }
 }
 
+#[derive(Clone, Debug)]
+pub struct ReputationMetrics {
+    /// The reputation score of each peer
+    pub peer_reputation_score: IntGaugeVec,
+    /// Number of times each peer got banned
+    pub peer_bans: IntCounterVec,
+}
+
+impl ReputationMetrics {
+    /// Registers the metrics of the peers of the specified network (eg. primary or worker).
+    pub fn new(registry: &Registry, network: &str) -> Self {
+        Self {
+            peer_reputation_score: register_int_gauge_vec_with_registry!(
+                format!("{network}_peer_reputation_score"),
+                "The reputation score of each peer",
+                &["peer"],
+                registry
+            )
+            .unwrap(),
+            peer_bans: register_int_counter_vec_with_registry!(
+                format!("{network}_peer_bans"),
+                "Number of times each peer got banned",
+                &["peer"],
+                registry
+            )
+            .unwrap(),
+        }
+    }
+}
+
 pub struct Metrics<N: NetworkMetrics> {
     /// The handler to report the metrics.
     metrics_handler: Arc<N>,
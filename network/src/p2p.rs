@@ -5,7 +5,7 @@ use crate::{
     traits::{Lucky, ReliableNetwork, UnreliableNetwork},
     BoundedExecutor, CancelOnDropHandler, RetryConfig, MAX_TASK_CONCURRENCY,
 };
-use anemo::PeerId;
+use anemo::{types::PeerInfo, PeerId};
 use anyhow::Result;
 use async_trait::async_trait;
 use crypto::{traits::KeyPair, NetworkPublicKey};
@@ -57,6 +57,25 @@ impl P2pNetwork {
         // is even needed. For now do nothing.
     }
 
+    /// Adds a peer to connect to, such as a worker registered at runtime. Fails if the address
+    /// is not supported by the network.
+    pub fn add_known_peer(&self, name: NetworkPublicKey, address: &Multiaddr) -> Result<()> {
+        let peer_info = PeerInfo {
+            peer_id: PeerId(name.0.to_bytes()),
+            affinity: anemo::types::PeerAffinity::High,
+            address: vec![crate::multiaddr_to_address(address)?],
+        };
+        self.network.known_peers().insert(peer_info);
+        Ok(())
+    }
+
+    /// Forgets a peer added by `add_known_peer`.
+    pub fn remove_known_peer(&self, name: &NetworkPublicKey) {
+        self.network
+            .known_peers()
+            .remove(&PeerId(name.0.to_bytes()));
+    }
+
     // Creates a new single-use anemo::Network to connect outbound to a single
     // address. This is for tests and should not be used from worker code.
     pub async fn new_for_single_address(
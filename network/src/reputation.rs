@@ -0,0 +1,248 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use crate::metrics::ReputationMetrics;
+use config::PeerReputationParameters;
+use rand::prelude::SliceRandom as _;
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    hash::Hash,
+    sync::{Arc, Mutex},
+};
+use tokio::time::{Duration, Instant};
+use tracing::warn;
+
+/// The weight of the latest sample in the moving average of the latency of a peer.
+const LATENCY_SMOOTHING: f64 = 0.2;
+
+/// What we remember about a peer.
+#[derive(Clone, Debug, Default)]
+struct Standing {
+    /// Raised by the valid responses of the peer, lowered by its timeouts and invalid responses.
+    score: i64,
+    /// The moving average of the latency of the valid responses of the peer.
+    latency: Option<Duration>,
+    /// Until when the peer is left out of our requests, if banned.
+    banned_until: Option<Instant>,
+}
+
+/// Scores the peers we fetch data from by the latency and validity of their responses, and
+/// temporarily bans the peers whose score drops too low. The clones of a `PeerReputation`
+/// share the same scores, so that the components requesting data from the same peers learn
+/// from each other's experience.
+#[derive(Clone)]
+pub struct PeerReputation<K> {
+    /// The scoring and banning configuration.
+    parameters: PeerReputationParameters,
+    /// The standing of the peers we heard from.
+    peers: Arc<Mutex<HashMap<K, Standing>>>,
+    /// Exports the score and the bans of every peer.
+    metrics: ReputationMetrics,
+}
+
+impl<K: Clone + Eq + Hash + Display> PeerReputation<K> {
+    pub fn new(parameters: PeerReputationParameters, metrics: ReputationMetrics) -> Self {
+        Self {
+            parameters,
+            peers: Arc::new(Mutex::new(HashMap::new())),
+            metrics,
+        }
+    }
+
+    /// Records a valid response of the peer, received `latency` after the request.
+    pub fn record_success(&self, peer: &K, latency: Duration) {
+        self.update(peer, 1, Some(latency));
+    }
+
+    /// Records a request the peer did not answer in time.
+    pub fn record_timeout(&self, peer: &K) {
+        self.update(peer, -1, None);
+    }
+
+    /// Records a response of the peer containing data that failed verification.
+    pub fn record_invalid(&self, peer: &K) {
+        self.update(peer, -self.parameters.invalid_penalty, None);
+    }
+
+    /// The current score of the peer.
+    pub fn score(&self, peer: &K) -> i64 {
+        let mut peers = self.peers.lock().unwrap();
+        self.standing(&mut peers, peer).score
+    }
+
+    /// Whether the peer is currently banned.
+    pub fn is_banned(&self, peer: &K) -> bool {
+        let mut peers = self.peers.lock().unwrap();
+        self.standing(&mut peers, peer).banned_until.is_some()
+    }
+
+    /// Orders the peers by decreasing score, then by increasing latency, and leaves out the
+    /// banned ones. The peers of equal standing keep their relative order. If all the peers
+    /// are banned, they are all returned: a bad peer is better than none.
+    pub fn rank(&self, peers: Vec<K>) -> Vec<K> {
+        let mut standings = self.peers.lock().unwrap();
+        let mut ranked: Vec<_> = peers
+            .iter()
+            .map(|peer| (peer.clone(), self.standing(&mut standings, peer).clone()))
+            .filter(|(_, standing)| standing.banned_until.is_none())
+            .collect();
+        if ranked.is_empty() {
+            return peers;
+        }
+        ranked.sort_by(|(_, a), (_, b)| {
+            b.score.cmp(&a.score).then_with(|| {
+                a.latency
+                    .unwrap_or_default()
+                    .cmp(&b.latency.unwrap_or_default())
+            })
+        });
+        ranked.into_iter().map(|(peer, _)| peer).collect()
+    }
+
+    /// Picks up to `nodes` peers among the best ranked, at random among the peers of equal
+    /// standing to spread the load.
+    pub fn select(&self, mut peers: Vec<K>, nodes: usize) -> Vec<K> {
+        peers.shuffle(&mut rand::thread_rng());
+        let mut ranked = self.rank(peers);
+        ranked.truncate(nodes);
+        ranked
+    }
+
+    fn update(&self, peer: &K, delta: i64, latency: Option<Duration>) {
+        let mut peers = self.peers.lock().unwrap();
+        let standing = self.standing(&mut peers, peer);
+        if standing.banned_until.is_some() {
+            return;
+        }
+
+        standing.score = (standing.score + delta).min(self.parameters.max_score);
+        if let Some(latency) = latency {
+            standing.latency = Some(match standing.latency {
+                Some(average) => {
+                    average.mul_f64(1.0 - LATENCY_SMOOTHING) + latency.mul_f64(LATENCY_SMOOTHING)
+                }
+                None => latency,
+            });
+        }
+
+        let label = peer.to_string();
+        if standing.score <= self.parameters.ban_threshold {
+            warn!(
+                "Banning peer {peer} for {} ms",
+                self.parameters.ban_duration.as_millis()
+            );
+            standing.banned_until = Some(Instant::now() + self.parameters.ban_duration);
+            self.metrics.peer_bans.with_label_values(&[&label]).inc();
+        }
+        self.metrics
+            .peer_reputation_score
+            .with_label_values(&[&label])
+            .set(standing.score);
+    }
+
+    /// The standing of the peer, once its ban is lifted if expired.
+    fn standing<'a>(&self, peers: &'a mut HashMap<K, Standing>, peer: &K) -> &'a mut Standing {
+        let standing = peers.entry(peer.clone()).or_default();
+        if matches!(standing.banned_until, Some(until) if until <= Instant::now()) {
+            *standing = Standing::default();
+            self.metrics
+                .peer_reputation_score
+                .with_label_values(&[&peer.to_string()])
+                .set(0);
+        }
+        standing
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{metrics::ReputationMetrics, reputation::PeerReputation};
+    use config::PeerReputationParameters;
+    use prometheus::Registry;
+    use std::collections::HashMap;
+    use tokio::time::Duration;
+
+    fn reputation() -> PeerReputation<String> {
+        PeerReputation::new(
+            PeerReputationParameters::default(),
+            ReputationMetrics::new(&Registry::new(), "test"),
+        )
+    }
+
+    #[test]
+    fn test_rank_by_score_then_latency() {
+        let reputation = reputation();
+        let peers = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+
+        reputation.record_timeout(&peers[0]);
+        reputation.record_success(&peers[1], Duration::from_millis(100));
+        reputation.record_success(&peers[2], Duration::from_millis(10));
+
+        assert_eq!(
+            reputation.rank(peers.clone()),
+            vec![peers[2].clone(), peers[1].clone(), peers[0].clone()]
+        );
+        assert_eq!(reputation.select(peers.clone(), 1), vec![peers[2].clone()]);
+    }
+
+    #[test]
+    fn test_score_is_capped() {
+        let reputation = reputation();
+        let peer = "a".to_string();
+        for _ in 0..100 {
+            reputation.record_success(&peer, Duration::from_millis(10));
+        }
+        assert_eq!(reputation.score(&peer), 10);
+    }
+
+    #[tokio::test]
+    async fn test_ban_and_expire() {
+        let reputation = PeerReputation::new(
+            PeerReputationParameters {
+                ban_duration: Duration::from_millis(100),
+                ..PeerReputationParameters::default()
+            },
+            ReputationMetrics::new(&Registry::new(), "test"),
+        );
+        let (good, bad) = ("good".to_string(), "bad".to_string());
+
+        // Two invalid responses are enough to get banned.
+        reputation.record_invalid(&bad);
+        assert!(!reputation.is_banned(&bad));
+        reputation.record_invalid(&bad);
+        assert!(reputation.is_banned(&bad));
+        assert_eq!(
+            reputation.rank(vec![good.clone(), bad.clone()]),
+            vec![good.clone()]
+        );
+
+        // Unless there is no one else to ask.
+        assert_eq!(reputation.rank(vec![bad.clone()]), vec![bad.clone()]);
+
+        let mut m = HashMap::new();
+        m.insert("peer", "bad");
+        assert_eq!(
+            reputation
+                .metrics
+                .peer_bans
+                .get_metric_with(&m)
+                .unwrap()
+                .get(),
+            1
+        );
+        assert_eq!(
+            reputation
+                .metrics
+                .peer_reputation_score
+                .get_metric_with(&m)
+                .unwrap()
+                .get(),
+            -10
+        );
+
+        // The ban expires with a clean slate.
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!reputation.is_banned(&bad));
+        assert_eq!(reputation.score(&bad), 0);
+    }
+}
@@ -1,7 +1,7 @@
 // Copyright (c) 2022, Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
-use config::{Authority, Committee, Epoch, WorkerIndex, WorkerInfo};
-use crypto::{KeyPair, NetworkKeyPair};
+use config::{Authority, CoinKeys, CoinShare, Committee, Epoch, WorkerIndex, WorkerInfo};
+use crypto::{coin, KeyPair, NetworkKeyPair};
 use fastcrypto::{
     traits::{KeyPair as _, Signer},
     Digest, Hash,
@@ -13,7 +13,7 @@ use serde_reflection::{Registry, Result, Samples, Tracer, TracerConfig};
 use std::{fs::File, io::Write};
 use structopt::{clap::arg_enum, StructOpt};
 use types::{
-    Batch, BatchDigest, Certificate, CertificateDigest, Header, HeaderDigest,
+    coin_message, Batch, BatchDigest, Certificate, CertificateDigest, Header, HeaderDigest,
     ReconfigureNotification, WorkerPrimaryError, WorkerPrimaryMessage,
 };
 
@@ -66,6 +66,28 @@ fn get_registry() -> Result<Registry> {
                 )
             })
             .collect(),
+        coin: None,
+    };
+    let (public_key, shares) = coin::deal(keys.len(), 2, &mut rng);
+    let coin = CoinKeys {
+        public_key,
+        threshold: 2,
+        shares: keys
+            .iter()
+            .zip(shares.iter())
+            .enumerate()
+            .map(|(i, (kp, share))| {
+                let share = CoinShare {
+                    index: i as u64 + 1,
+                    public_key: share.public().clone(),
+                };
+                (kp.public().clone(), share)
+            })
+            .collect(),
+    };
+    let committee = Committee {
+        coin: Some(coin),
+        ..committee
     };
 
     let certificates: Vec<Certificate> = Certificate::genesis(&committee);
@@ -83,6 +105,7 @@ fn get_registry() -> Result<Registry> {
     header = Header {
         id: header_digest,
         signature: kp.sign(Digest::from(header_digest).as_ref()),
+        randomness: kp.sign(coin_message(header.epoch, header.round).as_ref()),
         ..header
     };
     let worker_pk = network_keys[0].public().clone();
@@ -95,8 +118,6 @@ fn get_registry() -> Result<Registry> {
     tracer.trace_value(&mut samples, &header)?;
     tracer.trace_value(&mut samples, &certificate)?;
 
-    // WorkerIndex & WorkerInfo will be present in a protocol message once dynamic
-    // worker integration is complete.
     let worker_index = WorkerIndex(
         vec![(
             0,
@@ -114,19 +135,27 @@ fn get_registry() -> Result<Registry> {
     let cleanup = PrimaryWorkerMessage::Cleanup(1u64);
     let request_batch = PrimaryWorkerMessage::RequestBatch(BatchDigest([0u8; 32]));
     let delete_batch = PrimaryWorkerMessage::DeleteBatches(vec![BatchDigest([0u8; 32])]);
-    let sync = PrimaryWorkerMessage::Synchronize(vec![BatchDigest([0u8; 32])], pk);
+    let sync = PrimaryWorkerMessage::Synchronize(vec![BatchDigest([0u8; 32])], pk.clone());
+    let certified =
+        PrimaryWorkerMessage::Certified(vec![BatchDigest([0u8; 32])], certificate.digest());
+    let sequenced =
+        PrimaryWorkerMessage::Sequenced(vec![BatchDigest([0u8; 32])], certificate.digest(), 1u64);
     let epoch_change =
         PrimaryWorkerMessage::Reconfigure(ReconfigureNotification::NewEpoch(committee.clone()));
     let update_committee =
         PrimaryWorkerMessage::Reconfigure(ReconfigureNotification::NewEpoch(committee));
     let shutdown = PrimaryWorkerMessage::Reconfigure(ReconfigureNotification::Shutdown);
+    let update_worker_index = PrimaryWorkerMessage::UpdateWorkerIndex(pk, worker_index);
     tracer.trace_value(&mut samples, &cleanup)?;
     tracer.trace_value(&mut samples, &request_batch)?;
     tracer.trace_value(&mut samples, &delete_batch)?;
     tracer.trace_value(&mut samples, &sync)?;
+    tracer.trace_value(&mut samples, &certified)?;
+    tracer.trace_value(&mut samples, &sequenced)?;
     tracer.trace_value(&mut samples, &epoch_change)?;
     tracer.trace_value(&mut samples, &update_committee)?;
     tracer.trace_value(&mut samples, &shutdown)?;
+    tracer.trace_value(&mut samples, &update_worker_index)?;
 
     // 2. Trace the main entry point(s) + every enum separately.
     tracer.trace_type::<Batch>(&samples)?;
@@ -1,22 +1,30 @@
 // Copyright (c) 2022, Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
-use config::{Parameters, SharedCommittee, SharedWorkerCache, WorkerId};
+use config::{
+    Committee, Epoch, Parameters, SharedCommittee, SharedWorkerCache, WorkerCache, WorkerId,
+};
 use consensus::{
     bullshark::Bullshark,
     dag::Dag,
+    leader_schedule::LeaderSchedule,
     metrics::{ChannelMetrics, ConsensusMetrics},
     Consensus, ConsensusOutput,
 };
 
 use crypto::{KeyPair, NetworkKeyPair, PublicKey};
 use executor::{
-    get_restored_consensus_output, ExecutionState, Executor, ExecutorOutput, SerializedTransaction,
-    SubscriberResult,
+    get_restored_consensus_output, ExecutionIndices, ExecutionState, Executor, ExecutorOutput,
+    SerializedTransaction, SubscriberResult, TransactionCodec,
 };
+use eyre::WrapErr;
 use fastcrypto::traits::{KeyPair as _, VerifyingKey};
 use itertools::Itertools;
-use primary::{BlockCommand, NetworkModel, PayloadToken, Primary, PrimaryChannelMetrics};
+use primary::{
+    BlockCommand, BlockRemoverCommand, NetworkModel, PayloadToken, Primary, PrimaryChannelMetrics,
+    SequencerGrpc,
+};
 use prometheus::{IntGauge, Registry};
+use rocksdb::{DBWithThreadMode, MultiThreaded, Options};
 use std::{fmt::Debug, sync::Arc};
 use storage::{CertificateStore, CertificateToken};
 use store::{
@@ -30,13 +38,17 @@ use tokio::{
 };
 use tracing::{debug, info};
 use types::{
-    metered_channel, Batch, BatchDigest, Certificate, CertificateDigest, ConsensusStore, Header,
-    HeaderDigest, ReconfigureNotification, Round, RoundVoteDigestPair, SequenceNumber,
+    metered_channel, Batch, BatchChunk, BatchDigest, Certificate, CertificateDigest,
+    ConsensusStore, Equivocation, EquivocationDigest, Header, HeaderDigest,
+    ReconfigureNotification, Round, RoundVoteDigestPair, SequenceNumber, StateDigest,
+    StateDivergence, StoreResult, TransactionDigest,
 };
-use worker::{metrics::initialise_metrics, Worker};
+use worker::{metrics::initialise_metrics, TransactionValidator, Worker};
 
 pub mod execution_state;
 pub mod metrics;
+pub mod pruner;
+pub mod replay;
 pub mod restarter;
 
 /// All the data stores of the node.
@@ -48,6 +60,10 @@ pub struct NodeStorage {
     pub batch_store: Store<BatchDigest, Batch>,
     pub consensus_store: Arc<ConsensusStore>,
     pub temp_batch_store: Store<(CertificateDigest, BatchDigest), Batch>,
+    pub equivocation_store: Store<EquivocationDigest, Equivocation>,
+    pub chunk_store: Store<BatchDigest, BatchChunk>,
+    pub divergence_store: Store<(Epoch, SequenceNumber), StateDivergence>,
+    pub executed_store: Store<ExecutionIndices, TransactionDigest>,
 }
 
 impl NodeStorage {
@@ -60,27 +76,58 @@ impl NodeStorage {
     const BATCHES_CF: &'static str = "batches";
     const LAST_COMMITTED_CF: &'static str = "last_committed";
     const SEQUENCE_CF: &'static str = "sequence";
+    const SEQUENCE_BY_CERTIFICATE_CF: &'static str = "sequence_by_certificate";
+    const SEQUENCE_BY_LEADER_ROUND_CF: &'static str = "sequence_by_leader_round";
     const TEMP_BATCH_CF: &'static str = "temp_batches";
+    const EQUIVOCATIONS_CF: &'static str = "equivocations";
+    const CHUNKS_CF: &'static str = "chunks";
+    const STATE_DIVERGENCES_CF: &'static str = "state_divergences";
+    const EXECUTED_TRANSACTIONS_CF: &'static str = "executed_transactions";
+
+    /// The column families of all the storage of the node.
+    const COLUMN_FAMILIES: &'static [&'static str] = &[
+        Self::VOTES_CF,
+        Self::HEADERS_CF,
+        Self::CERTIFICATES_CF,
+        Self::CERTIFICATE_ID_BY_ROUND_CF,
+        Self::PAYLOAD_CF,
+        Self::BATCHES_CF,
+        Self::LAST_COMMITTED_CF,
+        Self::SEQUENCE_CF,
+        Self::SEQUENCE_BY_CERTIFICATE_CF,
+        Self::SEQUENCE_BY_LEADER_ROUND_CF,
+        Self::TEMP_BATCH_CF,
+        Self::EQUIVOCATIONS_CF,
+        Self::CHUNKS_CF,
+        Self::STATE_DIVERGENCES_CF,
+        Self::EXECUTED_TRANSACTIONS_CF,
+    ];
 
     /// Open or reopen all the storage of the node.
     pub fn reopen<Path: AsRef<std::path::Path>>(store_path: Path) -> Self {
-        let rocksdb = open_cf(
+        let rocksdb =
+            open_cf(store_path, None, Self::COLUMN_FAMILIES).expect("Cannot open database");
+        Self::from_rocksdb(&rocksdb)
+    }
+
+    /// Open the existing storage of a node for reading only. The storage may be opened this way
+    /// while the node runs, and nothing is ever written to it.
+    pub fn open_read_only<Path: AsRef<std::path::Path>>(store_path: Path) -> eyre::Result<Self> {
+        let store_path = store_path.as_ref();
+        if !store_path.exists() {
+            eyre::bail!("No data store at {}", store_path.display());
+        }
+        let rocksdb = DBWithThreadMode::<MultiThreaded>::open_cf_for_read_only(
+            &Options::default(),
             store_path,
-            None,
-            &[
-                Self::VOTES_CF,
-                Self::HEADERS_CF,
-                Self::CERTIFICATES_CF,
-                Self::CERTIFICATE_ID_BY_ROUND_CF,
-                Self::PAYLOAD_CF,
-                Self::BATCHES_CF,
-                Self::LAST_COMMITTED_CF,
-                Self::SEQUENCE_CF,
-                Self::TEMP_BATCH_CF,
-            ],
+            Self::COLUMN_FAMILIES,
+            /* error_if_log_file_exist */ false,
         )
-        .expect("Cannot open database");
+        .wrap_err_with(|| format!("Cannot open database at {}", store_path.display()))?;
+        Ok(Self::from_rocksdb(&Arc::new(rocksdb)))
+    }
 
+    fn from_rocksdb(rocksdb: &Arc<DBWithThreadMode<MultiThreaded>>) -> Self {
         let (
             votes_map,
             header_map,
@@ -90,8 +137,14 @@ impl NodeStorage {
             batch_map,
             last_committed_map,
             sequence_map,
+            sequence_by_certificate_map,
+            sequence_by_leader_round_map,
             temp_batch_map,
-        ) = reopen!(&rocksdb,
+            equivocations_map,
+            chunks_map,
+            divergences_map,
+            executed_map,
+        ) = reopen!(rocksdb,
             Self::VOTES_CF;<PublicKey, RoundVoteDigestPair>,
             Self::HEADERS_CF;<HeaderDigest, Header>,
             Self::CERTIFICATES_CF;<CertificateDigest, Certificate>,
@@ -100,7 +153,13 @@ impl NodeStorage {
             Self::BATCHES_CF;<BatchDigest, Batch>,
             Self::LAST_COMMITTED_CF;<PublicKey, Round>,
             Self::SEQUENCE_CF;<SequenceNumber, CertificateDigest>,
-            Self::TEMP_BATCH_CF;<(CertificateDigest, BatchDigest), Batch>
+            Self::SEQUENCE_BY_CERTIFICATE_CF;<CertificateDigest, SequenceNumber>,
+            Self::SEQUENCE_BY_LEADER_ROUND_CF;<(Round, SequenceNumber), CertificateDigest>,
+            Self::TEMP_BATCH_CF;<(CertificateDigest, BatchDigest), Batch>,
+            Self::EQUIVOCATIONS_CF;<EquivocationDigest, Equivocation>,
+            Self::CHUNKS_CF;<BatchDigest, BatchChunk>,
+            Self::STATE_DIVERGENCES_CF;<(Epoch, SequenceNumber), StateDivergence>,
+            Self::EXECUTED_TRANSACTIONS_CF;<ExecutionIndices, TransactionDigest>
         );
 
         let vote_digest_store = Store::new(votes_map);
@@ -108,8 +167,17 @@ impl NodeStorage {
         let certificate_store = CertificateStore::new(certificate_map, certificate_id_by_round_map);
         let payload_store = Store::new(payload_map);
         let batch_store = Store::new(batch_map);
-        let consensus_store = Arc::new(ConsensusStore::new(last_committed_map, sequence_map));
+        let consensus_store = Arc::new(ConsensusStore::new(
+            last_committed_map,
+            sequence_map,
+            sequence_by_certificate_map,
+            sequence_by_leader_round_map,
+        ));
         let temp_batch_store = Store::new(temp_batch_map);
+        let equivocation_store = Store::new(equivocations_map);
+        let chunk_store = Store::new(chunks_map);
+        let divergence_store = Store::new(divergences_map);
+        let executed_store = Store::new(executed_map);
 
         Self {
             vote_digest_store,
@@ -119,6 +187,10 @@ impl NodeStorage {
             batch_store,
             consensus_store,
             temp_batch_store,
+            equivocation_store,
+            chunk_store,
+            divergence_store,
+            executed_store,
         }
     }
 }
@@ -134,6 +206,8 @@ impl Node {
     pub async fn spawn_primary<State>(
         // The private-public key pair of this authority.
         keypair: KeyPair,
+        // The key pair of this authority's share of the common coin, if coin keys were dealt.
+        coin_keypair: Option<KeyPair>,
         // The private-public network key pair of this authority.
         network_keypair: NetworkKeyPair,
         // The committee information.
@@ -152,6 +226,8 @@ impl Node {
         internal_consensus: bool,
         // The state used by the client to execute transactions.
         execution_state: Arc<State>,
+        // Decodes the transactions sequenced by consensus for the execution state.
+        codec: impl TransactionCodec<State::Transaction>,
         // A channel to output transactions execution confirmations.
         tx_confirmation: Sender<ExecutorOutput<State>>,
         // A prometheus exporter Registry to use for the metrics
@@ -162,6 +238,23 @@ impl Node {
         State::Outcome: Send + 'static,
         State::Error: Debug,
     {
+        // Resume in the epoch the execution state moved to before a restart.
+        if let Some((new_committee, new_worker_cache)) = execution_state.load_epoch().await? {
+            if new_committee.epoch() > committee.load().epoch() {
+                info!("Resuming in epoch {}", new_committee.epoch());
+                committee.swap(Arc::new(new_committee));
+                worker_cache.swap(Arc::new(new_worker_cache));
+            }
+        }
+
+        // The consensus sequence starts over in every epoch, once the consensus hears of the
+        // epoch. If the node stopped before, the sequence of the previous epoch is dropped.
+        if Self::last_sequenced_epoch(store)?
+            .map_or(false, |epoch| epoch < committee.load().epoch())
+        {
+            store.consensus_store.clear()?;
+        }
+
         let initial_committee = ReconfigureNotification::NewEpoch((**committee.load()).clone());
         let (tx_reconfigure, _rx_reconfigure) = watch::channel(initial_committee);
 
@@ -191,6 +284,30 @@ impl Node {
         let (tx_get_block_commands, rx_get_block_commands) =
             metered_channel::channel(Self::CHANNEL_CAPACITY, &tx_get_block_commands_counter);
 
+        let tx_block_removal_commands_counter = IntGauge::new(
+            PrimaryChannelMetrics::NAME_BLOCK_REMOVAL_COMMANDS,
+            PrimaryChannelMetrics::DESC_BLOCK_REMOVAL_COMMANDS,
+        )
+        .unwrap();
+        let (tx_block_removal_commands, rx_block_removal_commands) =
+            metered_channel::channel(Self::CHANNEL_CAPACITY, &tx_block_removal_commands_counter);
+
+        let new_epochs_counter = IntGauge::new(
+            PrimaryChannelMetrics::NAME_NEW_EPOCHS,
+            PrimaryChannelMetrics::DESC_NEW_EPOCHS,
+        )
+        .unwrap();
+        let (tx_new_epochs, rx_new_epochs) =
+            metered_channel::channel(Self::CHANNEL_CAPACITY, &new_epochs_counter);
+
+        let state_digests_counter = IntGauge::new(
+            PrimaryChannelMetrics::NAME_STATE_DIGESTS,
+            PrimaryChannelMetrics::DESC_STATE_DIGESTS,
+        )
+        .unwrap();
+        let (tx_state_digests, rx_state_digests) =
+            metered_channel::channel(Self::CHANNEL_CAPACITY, &state_digests_counter);
+
         // Compute the public key of this authority.
         let name = keypair.public().clone();
         let mut handles = Vec::new();
@@ -209,11 +326,15 @@ impl Node {
                 store,
                 parameters.clone(),
                 execution_state,
+                codec,
                 &tx_reconfigure,
                 rx_new_certificates,
                 tx_consensus.clone(),
                 tx_confirmation,
                 tx_get_block_commands.clone(),
+                tx_block_removal_commands.clone(),
+                tx_new_epochs,
+                tx_state_digests,
                 registry,
             )
             .await?;
@@ -240,6 +361,7 @@ impl Node {
         let primary_handles = Primary::spawn(
             name.clone(),
             keypair,
+            coin_keypair,
             network_keypair,
             committee.clone(),
             worker_cache.clone(),
@@ -248,14 +370,20 @@ impl Node {
             store.certificate_store.clone(),
             store.payload_store.clone(),
             store.vote_digest_store.clone(),
+            store.equivocation_store.clone(),
+            store.divergence_store.clone(),
             tx_new_certificates,
             /* rx_consensus */ rx_consensus,
             tx_get_block_commands,
             rx_get_block_commands,
+            tx_block_removal_commands,
+            rx_block_removal_commands,
             /* dag */ dag,
             network_model,
             tx_reconfigure,
             tx_consensus,
+            rx_new_epochs,
+            rx_state_digests,
             registry,
         );
         handles.extend(primary_handles);
@@ -280,20 +408,42 @@ impl Node {
         Ok(handles)
     }
 
+    /// The epoch of the last certificate of the consensus sequence, if any.
+    fn last_sequenced_epoch(store: &NodeStorage) -> StoreResult<Option<Epoch>> {
+        // The certificate sequenced at index `i` is persisted under the key `i + 1`.
+        let next_index = store.consensus_store.read_last_consensus_index()?;
+        let digest = store
+            .consensus_store
+            .read_sequenced_certificates(&(next_index..=next_index))?
+            .pop()
+            .flatten();
+        match digest {
+            Some(digest) => Ok(store
+                .certificate_store
+                .read(digest)?
+                .map(|certificate| certificate.epoch())),
+            None => Ok(None),
+        }
+    }
+
     /// Spawn the consensus core and the client executing transactions.
     async fn spawn_consensus<State>(
         committee: SharedCommittee,
         store: &NodeStorage,
         parameters: Parameters,
         execution_state: Arc<State>,
+        codec: impl TransactionCodec<State::Transaction>,
         tx_reconfigure: &watch::Sender<ReconfigureNotification>,
         rx_new_certificates: metered_channel::Receiver<Certificate>,
-        tx_feedback: metered_channel::Sender<Certificate>,
+        tx_feedback: metered_channel::Sender<ConsensusOutput>,
         tx_confirmation: Sender<(
             SubscriberResult<<State as ExecutionState>::Outcome>,
             SerializedTransaction,
         )>,
         tx_get_block_commands: metered_channel::Sender<BlockCommand>,
+        tx_block_removal_commands: metered_channel::Sender<BlockRemoverCommand>,
+        tx_new_epochs: metered_channel::Sender<(Committee, WorkerCache)>,
+        tx_state_digests: metered_channel::Sender<(Epoch, SequenceNumber, StateDigest)>,
         registry: &Registry,
     ) -> SubscriberResult<Vec<JoinHandle<()>>>
     where
@@ -331,11 +481,19 @@ impl Node {
             .inc_by(len_restored);
 
         // Spawn the consensus core who only sequences transactions.
+        let leader_schedule = LeaderSchedule::recover(
+            (**committee.load()).clone(),
+            parameters.leader_reputation.clone(),
+            &store.consensus_store,
+            &store.certificate_store,
+        )?;
         let ordering_engine = Bullshark::new(
             (**committee.load()).clone(),
             store.consensus_store.clone(),
             parameters.gc_depth,
-        );
+            parameters.leader_election,
+        )
+        .with_leader_schedule(leader_schedule);
         let consensus_handles = Consensus::spawn(
             (**committee.load()).clone(),
             store.consensus_store.clone(),
@@ -349,23 +507,56 @@ impl Node {
             parameters.gc_depth,
         );
 
+        // Serve the consensus output to the out-of-process execution engines. The consensus API
+        // is only served with an external consensus, so its address is free.
+        let sequencer_handle = SequencerGrpc::spawn(
+            parameters.consensus_api_grpc.socket_addr.clone(),
+            store.consensus_store.clone(),
+            store.certificate_store.clone(),
+            store.temp_batch_store.clone(),
+            tx_get_block_commands.clone(),
+            parameters.consensus_api_grpc.get_collections_timeout,
+            parameters.gc_depth,
+            store.equivocation_store.clone(),
+            store.divergence_store.clone(),
+        );
+
+        // Prune the history that the executor already processed.
+        let pruner_handle = pruner::Pruner::new(
+            parameters.pruning.clone(),
+            parameters.gc_depth,
+            store,
+            execution_state.clone(),
+            tx_block_removal_commands,
+            tx_reconfigure.subscribe(),
+        )
+        .spawn();
+
         // Spawn the client executing the transactions. It can also synchronize with the
         // subscriber handler if it missed some transactions.
         let executor_handles = Executor::spawn(
             store.temp_batch_store.clone(),
             execution_state,
+            codec,
             tx_reconfigure,
             /* rx_consensus */ rx_sequence,
             /* tx_output */ tx_confirmation,
             tx_get_block_commands,
+            tx_new_epochs,
+            parameters.state_commitments.interval,
+            tx_state_digests,
             registry,
             restored_consensus_output,
+            store.executed_store.clone(),
+            parameters.deduplication.executor_cache_size,
         )
         .await?;
 
         Ok(executor_handles
             .into_iter()
             .chain(std::iter::once(consensus_handles))
+            .chain(std::iter::once(sequencer_handle))
+            .chain(std::iter::once(pruner_handle))
             .collect())
     }
 
@@ -383,6 +574,8 @@ impl Node {
         store: &NodeStorage,
         // The configuration parameters.
         parameters: Parameters,
+        // The validator applied to the transactions submitted by clients.
+        validator: impl TransactionValidator,
         // The prometheus metrics Registry
         registry: &Registry,
     ) -> Vec<JoinHandle<()>> {
@@ -398,7 +591,9 @@ impl Node {
                 committee.clone(),
                 worker_cache.clone(),
                 parameters.clone(),
+                validator.clone(),
                 store.batch_store.clone(),
+                store.chunk_store.clone(),
                 metrics.clone(),
             );
             handles.extend(worker_handles);
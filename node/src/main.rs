@@ -10,19 +10,22 @@
 
 use arc_swap::ArcSwap;
 use clap::{crate_name, crate_version, App, AppSettings, ArgMatches, SubCommand};
-use config::{Committee, Import, Parameters, WorkerCache, WorkerId};
-use crypto::{KeyPair, NetworkKeyPair};
-use executor::{SerializedTransaction, SubscriberResult};
+use config::{CoinKeys, CoinShare, Committee, Import, Parameters, WorkerCache, WorkerId};
+use crypto::{coin, KeyPair, NetworkKeyPair};
+use executor::{BincodeCodec, SerializedTransaction, SubscriberResult};
 use eyre::Context;
 use fastcrypto::{generate_production_keypair, traits::KeyPair as _};
 use futures::future::join_all;
 use node::{
     execution_state::SimpleExecutionState,
     metrics::{primary_metrics_registry, start_prometheus_server, worker_metrics_registry},
+    replay::Replayer,
     Node, NodeStorage,
 };
+use primary::Snapshot;
 use prometheus::Registry;
-use std::sync::Arc;
+use rand::rngs::OsRng;
+use std::{collections::BTreeMap, fs, path::Path, sync::Arc};
 use telemetry_subscribers::TelemetryGuards;
 use tokio::sync::mpsc::{channel, Receiver};
 use tracing::info;
@@ -30,6 +33,8 @@ use tracing::info;
 use tracing::subscriber::set_global_default;
 #[cfg(feature = "benchmark")]
 use tracing_subscriber::filter::{EnvFilter, LevelFilter};
+use types::SequenceNumber;
+use worker::TrivialTransactionValidator;
 
 #[cfg(feature = "dhat-heap")]
 #[global_allocator]
@@ -51,12 +56,19 @@ async fn main() -> Result<(), eyre::Report> {
                 .about("Print a fresh network key pair (ed25519) to file")
                 .args_from_usage("--filename=<FILE> 'The file where to print the new network key pair'"),
         )
+        .subcommand(
+            SubCommand::with_name("generate_coin_keys")
+                .about("Deal the keys of the common coin to the authorities of a committee")
+                .args_from_usage("--committee=<FILE> 'The file containing committee information'")
+                .args_from_usage("--output=<PATH> 'The directory where to print the committee with its coin keys, and the key pair of every share'"),
+        )
         .subcommand(
             SubCommand::with_name("run")
                 .about("Run a node")
                 .args_from_usage("--primary-keys=<FILE> 'The file containing the node's primary keys'")
                 .args_from_usage("--primary-network-keys=<FILE> 'The file containing the node's primary network keys'")
                 .args_from_usage("--worker-keys=<FILE> 'The file containing the node's worker keys'")
+                .args_from_usage("--coin-keys=[FILE] 'The file containing the node's share of the common coin'")
                 .args_from_usage("--committee=<FILE> 'The file containing committee information'")
                 .args_from_usage("--workers=<FILE> 'The file containing worker information'")
                 .args_from_usage("--parameters=[FILE] 'The file containing the node parameters'")
@@ -72,6 +84,33 @@ async fn main() -> Result<(), eyre::Report> {
                 )
                 .setting(AppSettings::SubcommandRequiredElseHelp),
         )
+        .subcommand(
+            SubCommand::with_name("snapshot")
+                .about("Export or import a snapshot of the consensus state of a stopped node")
+                .args_from_usage("--store=<PATH> 'The path of the data store'")
+                .args_from_usage("--filename=<FILE> 'The snapshot file'")
+                .subcommand(
+                    SubCommand::with_name("export")
+                        .about("Write a snapshot of the store to file")
+                        .args_from_usage("--parameters=[FILE] 'The file containing the node parameters'"),
+                )
+                .subcommand(
+                    SubCommand::with_name("import")
+                        .about("Load a snapshot from file into a fresh store")
+                        .args_from_usage("--committee=<FILE> 'The file containing committee information'")
+                        .args_from_usage("--workers=<FILE> 'The file containing worker information'"),
+                )
+                .setting(AppSettings::SubcommandRequiredElseHelp),
+        )
+        .subcommand(
+            SubCommand::with_name("replay")
+                .about("Replay a range of the consensus sequence of a node through the execution state, without writing to its stores")
+                .args_from_usage("--store=<PATH> 'The path of the data store'")
+                .args_from_usage("--worker-stores=[PATH]... 'The paths of the data stores of the workers holding the batches'")
+                .args_from_usage("--parameters=[FILE] 'The file containing the node parameters'")
+                .args_from_usage("--from=<INT> 'The first consensus index to replay'")
+                .args_from_usage("--to=<INT> 'The last consensus index to replay'"),
+        )
         .setting(AppSettings::SubcommandRequiredElseHelp)
         .get_matches();
 
@@ -105,6 +144,10 @@ async fn main() -> Result<(), eyre::Report> {
             config::Export::export(&network_kp, sub_matches.value_of("filename").unwrap())
                 .context("Failed to generate network key pair")?
         }
+        ("generate_coin_keys", Some(sub_matches)) => {
+            let _guard = setup_telemetry(tracing_level, network_tracing_level, None);
+            generate_coin_keys(sub_matches)?
+        }
         ("run", Some(sub_matches)) => {
             let primary_key_file = sub_matches.value_of("primary-keys").unwrap();
             let primary_keypair = KeyPair::import(primary_key_file)
@@ -115,6 +158,11 @@ async fn main() -> Result<(), eyre::Report> {
             let worker_key_file = sub_matches.value_of("worker-keys").unwrap();
             let worker_keypair = NetworkKeyPair::import(worker_key_file)
                 .context("Failed to load the node's worker keypair")?;
+            let coin_keypair = sub_matches
+                .value_of("coin-keys")
+                .map(KeyPair::import)
+                .transpose()
+                .context("Failed to load the node's coin keypair")?;
             let registry = match sub_matches.subcommand() {
                 ("primary", _) => primary_metrics_registry(primary_keypair.public().clone()),
                 ("worker", Some(worker_matches)) => {
@@ -141,12 +189,21 @@ async fn main() -> Result<(), eyre::Report> {
             run(
                 sub_matches,
                 primary_keypair,
+                coin_keypair,
                 primary_network_keypair,
                 worker_keypair,
                 registry,
             )
             .await?
         }
+        ("snapshot", Some(sub_matches)) => {
+            let _guard = setup_telemetry(tracing_level, network_tracing_level, None);
+            snapshot(sub_matches)?
+        }
+        ("replay", Some(sub_matches)) => {
+            let _guard = setup_telemetry(tracing_level, network_tracing_level, None);
+            replay(sub_matches).await?
+        }
         _ => unreachable!(),
     }
     Ok(())
@@ -199,10 +256,197 @@ fn setup_benchmark_telemetry(
     Ok(())
 }
 
+// Exports a snapshot of the store to file, or imports one into a fresh store.
+fn snapshot(matches: &ArgMatches<'_>) -> Result<(), eyre::Report> {
+    let store_path = matches.value_of("store").unwrap();
+    let filename = matches.value_of("filename").unwrap();
+    let store = NodeStorage::reopen(store_path);
+
+    match matches.subcommand() {
+        ("export", Some(sub_matches)) => {
+            let parameters = match sub_matches.value_of("parameters") {
+                Some(filename) => {
+                    Parameters::import(filename).context("Failed to load the node's parameters")?
+                }
+                None => Parameters::default(),
+            };
+            let snapshot = Snapshot::export(
+                &store.consensus_store,
+                &store.certificate_store,
+                parameters.gc_depth,
+            )
+            .context("Failed to read the snapshot from the store")?;
+            let data = bincode::serialize(&snapshot).context("Failed to serialize the snapshot")?;
+            fs::write(filename, data).context("Failed to write the snapshot")?;
+            info!(
+                "Exported {} certificates to {filename}",
+                snapshot.certificates.len()
+            );
+        }
+        ("import", Some(sub_matches)) => {
+            let committee = Committee::import(sub_matches.value_of("committee").unwrap())
+                .context("Failed to load the committee information")?;
+            let worker_cache = Arc::new(ArcSwap::from_pointee(
+                WorkerCache::import(sub_matches.value_of("workers").unwrap())
+                    .context("Failed to load the worker information")?,
+            ));
+            let data = fs::read(filename).context("Failed to read the snapshot")?;
+            let snapshot: Snapshot =
+                bincode::deserialize(&data).context("Failed to deserialize the snapshot")?;
+            snapshot
+                .verify(&committee, worker_cache)
+                .context("Invalid snapshot")?;
+            snapshot
+                .import(&store.consensus_store, &store.certificate_store)
+                .context("Failed to write the snapshot into the store")?;
+            info!(
+                "Imported {} certificates into {store_path}",
+                snapshot.certificates.len()
+            );
+        }
+        _ => unreachable!(),
+    }
+    Ok(())
+}
+
+// Replays a range of the consensus sequence through the execution state, printing the outcome
+// and the execution time of every transaction. The stores are only read.
+async fn replay(matches: &ArgMatches<'_>) -> Result<(), eyre::Report> {
+    let from = matches
+        .value_of("from")
+        .unwrap()
+        .parse::<SequenceNumber>()
+        .context("The first consensus index must be a positive integer")?;
+    let to = matches
+        .value_of("to")
+        .unwrap()
+        .parse::<SequenceNumber>()
+        .context("The last consensus index must be a positive integer")?;
+    if from > to {
+        eyre::bail!("The first consensus index must not exceed the last one");
+    }
+    let parameters = match matches.value_of("parameters") {
+        Some(filename) => {
+            Parameters::import(filename).context("Failed to load the node's parameters")?
+        }
+        None => Parameters::default(),
+    };
+
+    let store = NodeStorage::open_read_only(matches.value_of("store").unwrap())
+        .context("Failed to open the node's store")?;
+    let worker_stores = matches
+        .values_of("worker-stores")
+        .into_iter()
+        .flatten()
+        .map(NodeStorage::open_read_only)
+        .collect::<Result<Vec<_>, _>>()
+        .context("Failed to open the workers' stores")?;
+    let mut replayer = Replayer::new(
+        &store,
+        &worker_stores,
+        Arc::new(SimpleExecutionState::default()),
+        BincodeCodec::default(),
+        parameters.state_commitments.interval,
+    );
+
+    for consensus_index in from..=to {
+        let certificate = match replayer
+            .replay(consensus_index)
+            .await
+            .with_context(|| format!("Failed to replay consensus index {consensus_index}"))?
+        {
+            Some(certificate) => certificate,
+            None => {
+                println!("{consensus_index}: nothing sequenced (or pruned)");
+                continue;
+            }
+        };
+        if certificate.discarded {
+            println!(
+                "{consensus_index}: certificate {} discarded (past epoch)",
+                certificate.digest
+            );
+            continue;
+        }
+
+        println!(
+            "{consensus_index}: certificate {}, {} transactions in {:?}",
+            certificate.digest,
+            certificate.transactions.len(),
+            certificate.elapsed
+        );
+        for transaction in &certificate.transactions {
+            let indices = &transaction.execution_indices;
+            let outcome = match &transaction.result {
+                Ok(outcome) => format!("{outcome:?}"),
+                Err(e) => format!("error: {e}"),
+            };
+            println!(
+                "  batch {} ({}, {}, {}): {outcome} in {:?}",
+                transaction.batch,
+                indices.next_certificate_index,
+                indices.next_batch_index,
+                indices.next_transaction_index,
+                transaction.elapsed
+            );
+        }
+        for digest in &certificate.missing_batches {
+            println!("  batch {digest}: missing from the stores");
+        }
+        if let Some(state) = certificate.state {
+            println!("  execution state {state}");
+        }
+        if let Some(epoch) = certificate.next_epoch {
+            println!("  epoch {epoch} starts after this certificate");
+        }
+    }
+    Ok(())
+}
+
+// Deals the keys of the common coin to the authorities of a committee.
+fn generate_coin_keys(matches: &ArgMatches<'_>) -> Result<(), eyre::Report> {
+    let committee_file = matches.value_of("committee").unwrap();
+    let output = Path::new(matches.value_of("output").unwrap());
+    let mut committee =
+        Committee::import(committee_file).context("Failed to load the committee information")?;
+
+    let threshold = committee
+        .coin_threshold()
+        .context("Failed to deal the coin keys")?;
+    let (public_key, keypairs) = coin::deal(committee.size(), threshold, &mut OsRng);
+
+    fs::create_dir_all(output).context("Failed to create the output directory")?;
+    let mut shares = BTreeMap::new();
+    for ((index, name), keypair) in (1..).zip(committee.authorities.keys()).zip(keypairs) {
+        let filename = output.join(format!("coin-keys-{index}.json"));
+        config::Export::export(&keypair, &filename.to_string_lossy())
+            .context("Failed to write the coin key pair")?;
+        println!("{name}: {}", filename.display());
+
+        let share = CoinShare {
+            index,
+            public_key: keypair.public().clone(),
+        };
+        shares.insert(name.clone(), share);
+    }
+    committee.coin = Some(CoinKeys {
+        public_key,
+        threshold,
+        shares,
+    });
+
+    let filename = output.join("committee.json");
+    config::Export::export(&committee, &filename.to_string_lossy())
+        .context("Failed to write the committee")?;
+    println!("committee: {}", filename.display());
+    Ok(())
+}
+
 // Runs either a worker or a primary.
 async fn run(
     matches: &ArgMatches<'_>,
     primary_keypair: KeyPair,
+    coin_keypair: Option<KeyPair>,
     primary_network_keypair: NetworkKeyPair,
     worker_keypair: NetworkKeyPair,
     registry: Registry,
@@ -227,6 +471,10 @@ async fn run(
         }
         None => Parameters::default(),
     };
+    parameters
+        .pruning
+        .validate(parameters.gc_depth)
+        .context("Invalid node parameters")?;
 
     // Make the data store.
     let store = NodeStorage::reopen(store_path);
@@ -239,8 +487,16 @@ async fn run(
     let node_handles = match matches.subcommand() {
         // Spawn the primary and consensus core.
         ("primary", Some(sub_matches)) => {
+            committee
+                .load()
+                .check_coin_key(
+                    primary_keypair.public(),
+                    coin_keypair.as_ref().map(|x| x.public()),
+                )
+                .context("Invalid coin key pair")?;
             Node::spawn_primary(
                 primary_keypair,
+                coin_keypair,
                 primary_network_keypair,
                 committee,
                 worker_cache,
@@ -248,6 +504,7 @@ async fn run(
                 parameters.clone(),
                 /* consensus */ !sub_matches.is_present("consensus-disabled"),
                 /* execution_state */ Arc::new(SimpleExecutionState::default()),
+                BincodeCodec::default(),
                 tx_transaction_confirmation,
                 &registry,
             )
@@ -270,6 +527,7 @@ async fn run(
                 worker_cache,
                 &store,
                 parameters.clone(),
+                TrivialTransactionValidator::default(),
                 &registry,
             )
         }
@@ -0,0 +1,289 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use crate::NodeStorage;
+use config::{PruningParameters, RetentionPolicy, WorkerId};
+use executor::{ExecutionState, SubscriberError, SubscriberResult};
+use fastcrypto::Hash;
+use primary::{BlockRemoverCommand, PayloadToken};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    sync::Arc,
+};
+use storage::CertificateStore;
+use store::Store;
+use tokio::{
+    sync::{oneshot, watch},
+    task::JoinHandle,
+    time::interval,
+};
+use tracing::{debug, error};
+use types::{
+    metered_channel::Sender, Batch, BatchDigest, Certificate, CertificateDigest, ConsensusStore,
+    Header, HeaderDigest, ReconfigureNotification, Round, SequenceNumber,
+};
+
+/// Periodically prunes the oldest part of the consensus sequence according to the retention
+/// policy, along with the certificates, headers, payload tokens and batches it references. The
+/// batches are deleted by the workers storing them, through the `BlockRemover` of the primary.
+/// Nothing that the executor did not yet process is ever pruned, nor the certificates of the last
+/// `gc_depth` rounds, which consensus reloads on restart.
+pub struct Pruner<State> {
+    /// The retention policy.
+    parameters: PruningParameters,
+    /// The depth of the garbage collection, below which no round is retained.
+    gc_depth: Round,
+    /// The persistent storage of the sequence.
+    consensus_store: Arc<ConsensusStore>,
+    /// The certificates referenced by the sequence.
+    certificate_store: CertificateStore,
+    /// The headers of these certificates.
+    header_store: Store<HeaderDigest, Header>,
+    /// The payload tokens of these headers.
+    payload_store: Store<(BatchDigest, WorkerId), PayloadToken>,
+    /// The batches downloaded by the executor.
+    temp_batch_store: Store<(CertificateDigest, BatchDigest), Batch>,
+    /// The execution state, to find out what the executor already processed.
+    execution_state: Arc<State>,
+    /// Ask the workers to delete the batches referenced by these headers.
+    tx_block_removal_commands: Sender<BlockRemoverCommand>,
+    /// Receive reconfiguration updates.
+    rx_reconfigure: watch::Receiver<ReconfigureNotification>,
+}
+
+impl<State> Pruner<State>
+where
+    State: ExecutionState + Send + Sync + 'static,
+    State::Error: Debug,
+{
+    /// The maximum number of certificates pruned in a single run, to bound the size of the
+    /// write batches.
+    const MAX_PRUNED_PER_RUN: SequenceNumber = 1_000;
+
+    pub fn new(
+        parameters: PruningParameters,
+        gc_depth: Round,
+        store: &NodeStorage,
+        execution_state: Arc<State>,
+        tx_block_removal_commands: Sender<BlockRemoverCommand>,
+        rx_reconfigure: watch::Receiver<ReconfigureNotification>,
+    ) -> Self {
+        Self {
+            parameters,
+            gc_depth,
+            consensus_store: store.consensus_store.clone(),
+            certificate_store: store.certificate_store.clone(),
+            header_store: store.header_store.clone(),
+            payload_store: store.payload_store.clone(),
+            temp_batch_store: store.temp_batch_store.clone(),
+            execution_state,
+            tx_block_removal_commands,
+            rx_reconfigure,
+        }
+    }
+
+    /// Spawn a new pruner in a new tokio task.
+    #[must_use]
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            self.run().await;
+        })
+    }
+
+    /// Main loop pruning the history at every interval.
+    async fn run(mut self) {
+        let mut timer = interval(self.parameters.interval);
+        loop {
+            tokio::select! {
+                _ = timer.tick() => {
+                    match self.prune().await {
+                        Ok(0) => (),
+                        Ok(pruned) => debug!("Pruned {pruned} certificates from the history"),
+                        Err(e) => error!("Failed to prune the history: {e}"),
+                    }
+                },
+
+                // Check whether the committee changed.
+                result = self.rx_reconfigure.changed() => {
+                    result.expect("Committee channel dropped");
+                    let message = self.rx_reconfigure.borrow().clone();
+                    if let ReconfigureNotification::Shutdown = message {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Prune the history up to the point allowed by the retention policy and the progress of
+    /// the executor. Returns the number of certificates pruned.
+    pub async fn prune(&self) -> SubscriberResult<SequenceNumber> {
+        if self.parameters.retention == RetentionPolicy::KeepAll {
+            return Ok(0);
+        }
+
+        let start = match self.consensus_store.read_first_consensus_index()? {
+            Some(x) => x,
+            None => return Ok(0),
+        };
+        let next_index = self.consensus_store.read_last_consensus_index()?;
+        let executed = self
+            .execution_state
+            .load_execution_indices()
+            .await?
+            .next_certificate_index;
+
+        // The executor re-processes the last certificate it executed after a crash, and the last
+        // sequenced certificate determines the next consensus index.
+        let mut end = executed
+            .saturating_sub(1)
+            .min(next_index.saturating_sub(1))
+            .min(start + Self::MAX_PRUNED_PER_RUN);
+        if self.parameters.retention == RetentionPolicy::SequenceNumbers {
+            end = end.min(next_index.saturating_sub(self.parameters.retention_depth));
+        }
+        if end <= start {
+            return Ok(0);
+        }
+
+        // The certificate sequenced at index `i` is persisted under the key `i + 1`.
+        let mut certificates = Vec::new();
+        for digest in self
+            .consensus_store
+            .read_sequenced_certificates(&(start + 1..=end))?
+        {
+            certificates.push(match digest {
+                Some(digest) => self.certificate_store.read(digest)?,
+                None => None,
+            });
+        }
+
+        // Only prune the certificates sequenced before the retained rounds, which always include
+        // the last `gc_depth` rounds. The sequence is not strictly ordered by round, so stop at
+        // the first retained certificate.
+        let mut retained_depth = self.gc_depth;
+        if self.parameters.retention == RetentionPolicy::Rounds {
+            retained_depth = retained_depth.max(self.parameters.retention_depth);
+        }
+        let last_committed_round = self
+            .consensus_store
+            .read_last_committed()
+            .into_values()
+            .max()
+            .unwrap_or_default();
+        let retained_round = last_committed_round.saturating_sub(retained_depth);
+        if let Some(position) = certificates
+            .iter()
+            .position(|x| matches!(x, Some(x) if x.round() >= retained_round))
+        {
+            end = start + position as SequenceNumber;
+            certificates.truncate(position);
+        }
+        if end <= start {
+            return Ok(0);
+        }
+
+        // Delete the data referenced by the sequence first, so that a crash leaves the sequence
+        // pointing at what remains to be pruned.
+        let certificates: Vec<_> = certificates.into_iter().flatten().collect();
+        self.delete(&certificates).await?;
+        self.consensus_store.prune(end)?;
+        Ok(end - start)
+    }
+
+    /// Delete the certificates along with their headers, payload tokens and batches.
+    async fn delete(&self, certificates: &[Certificate]) -> SubscriberResult<()> {
+        let payload: Vec<_> = certificates
+            .iter()
+            .flat_map(|certificate| {
+                certificate
+                    .header
+                    .payload
+                    .iter()
+                    .map(|(digest, worker_id)| (certificate.digest(), *digest, *worker_id))
+            })
+            .collect();
+
+        // The batches of a header which was not committed are proposed again in a later header of
+        // the same author, so keep those that the retained certificates still reference.
+        let retained = self.retained_payload(certificates)?;
+        let batches: HashSet<_> = payload
+            .iter()
+            .map(|(_, digest, worker_id)| (*digest, *worker_id))
+            .filter(|batch| !retained.contains(batch))
+            .collect();
+
+        self.temp_batch_store
+            .remove_all(payload.iter().map(|(id, digest, _)| (*id, *digest)))
+            .await?;
+        self.delete_batches(&batches).await?;
+        self.payload_store.remove_all(batches).await?;
+        self.header_store
+            .remove_all(certificates.iter().map(|x| x.header.id))
+            .await?;
+        self.certificate_store
+            .delete_all(certificates.iter().map(|x| x.digest()))?;
+        Ok(())
+    }
+
+    /// The payload of the certificates which are not pruned, in the rounds where the batches of
+    /// the pruned certificates may have been proposed again.
+    fn retained_payload(
+        &self,
+        certificates: &[Certificate],
+    ) -> SubscriberResult<HashSet<(BatchDigest, WorkerId)>> {
+        let rounds = certificates.iter().map(|x| x.round());
+        let (from, to) = match (rounds.clone().min(), rounds.max()) {
+            (Some(from), Some(to)) => (from, to),
+            _ => return Ok(HashSet::new()),
+        };
+
+        // A header is only given up once its round falls `gc_depth` rounds below the last
+        // committed round, so its batches are proposed again about `gc_depth` rounds later.
+        let max_rounds = to - from + 2 * self.gc_depth + 1;
+        let pruned: HashSet<_> = certificates.iter().map(|x| x.digest()).collect();
+        let digests = self
+            .certificate_store
+            .digests_by_round(from, max_rounds)?
+            .into_values()
+            .flatten()
+            .filter(|digest| !pruned.contains(digest));
+        Ok(self
+            .certificate_store
+            .read_all(digests)?
+            .into_iter()
+            .flatten()
+            .flat_map(|certificate| certificate.header.payload)
+            .collect())
+    }
+
+    /// Ask the workers storing the batches to delete them.
+    async fn delete_batches(
+        &self,
+        batches: &HashSet<(BatchDigest, WorkerId)>,
+    ) -> SubscriberResult<()> {
+        if batches.is_empty() {
+            return Ok(());
+        }
+        let mut batches_by_worker = HashMap::<WorkerId, Vec<BatchDigest>>::new();
+        for (digest, worker_id) in batches {
+            batches_by_worker
+                .entry(*worker_id)
+                .or_default()
+                .push(*digest);
+        }
+
+        let (sender, receiver) = oneshot::channel();
+        self.tx_block_removal_commands
+            .send(BlockRemoverCommand::DeleteBatches {
+                batches: batches_by_worker,
+                sender,
+            })
+            .await
+            .map_err(|_| SubscriberError::ClosedChannel("tx_block_removal_commands".to_owned()))?;
+        receiver
+            .await
+            .map_err(|_| SubscriberError::ClosedChannel("block removal result".to_owned()))?
+            .map_err(SubscriberError::BatchDeletionError)
+    }
+}
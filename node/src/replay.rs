@@ -0,0 +1,267 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use crate::NodeStorage;
+use config::Epoch;
+use consensus::ConsensusOutput;
+use executor::{
+    ExecutionIndices, ExecutionState, SerializedTransaction, SubscriberError, SubscriberResult,
+    TransactionCodec,
+};
+use fastcrypto::Hash;
+use std::{
+    fmt::Debug,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use storage::CertificateStore;
+use store::Store;
+use tracing::debug;
+use types::{
+    Batch, BatchDigest, Certificate, CertificateDigest, ConsensusStore, SequenceNumber, StateDigest,
+};
+
+/// A transaction replayed from the consensus sequence.
+#[derive(Debug)]
+pub struct ReplayedTransaction<Outcome> {
+    /// The digest of the batch holding the transaction.
+    pub batch: BatchDigest,
+    /// The execution indices handed to the execution state along with the transaction.
+    pub execution_indices: ExecutionIndices,
+    /// The transaction, as sequenced by consensus.
+    pub transaction: SerializedTransaction,
+    /// The outcome of the execution, or the error of a transaction that failed to decode.
+    pub result: SubscriberResult<Outcome>,
+    /// The time spent executing the transaction.
+    pub elapsed: Duration,
+}
+
+/// A certificate replayed from the consensus sequence.
+#[derive(Debug)]
+pub struct ReplayedCertificate<Outcome> {
+    /// The consensus index of the certificate.
+    pub consensus_index: SequenceNumber,
+    /// The digest of the certificate.
+    pub digest: CertificateDigest,
+    /// Whether the certificate was discarded for being sequenced after the end of its epoch.
+    pub discarded: bool,
+    /// The replayed transactions, in the order of execution.
+    pub transactions: Vec<ReplayedTransaction<Outcome>>,
+    /// The batches of the certificate that are not in the stores, and thus were not replayed.
+    pub missing_batches: Vec<BatchDigest>,
+    /// The digest of the execution state, if the certificate closes an interval of
+    /// `state_commitment_interval` certificates.
+    pub state: Option<StateDigest>,
+    /// The next epoch, if the execution state ended the epoch at this certificate.
+    pub next_epoch: Option<Epoch>,
+    /// The time spent replaying the certificate.
+    pub elapsed: Duration,
+}
+
+/// Replays a range of the consensus sequence of a node through an execution state, offline. The
+/// certificates and their batches are only read from the stores, and handed to the
+/// execution state the way the executor does: the same transactions, decoded by the same codec,
+/// along with the same execution indices. Transactions are executed one by one through
+/// `handle_consensus_transaction` to time each of them, so a custom `handle_batch` or the
+/// concurrent execution of the executor are not exercised. The executor's cache of recently
+/// executed transactions is not reproduced either, so duplicates are replayed.
+pub struct Replayer<State, Codec> {
+    /// The persistent storage of the sequence.
+    consensus_store: Arc<ConsensusStore>,
+    /// The certificates referenced by the sequence.
+    certificate_store: CertificateStore,
+    /// The batches downloaded by the executor and not yet cleaned up.
+    temp_batch_store: Store<(CertificateDigest, BatchDigest), Batch>,
+    /// The stores of the workers holding the batches, searched in order.
+    batch_stores: Vec<Store<BatchDigest, Batch>>,
+    /// The execution state replaying the transactions.
+    execution_state: Arc<State>,
+    /// Decodes the transactions for the execution state.
+    codec: Codec,
+    /// The number of certificates between two digests of the execution state. Disabled if 0.
+    state_commitment_interval: u64,
+    /// The current epoch, set by the first replayed certificate and moved by `next_epoch`.
+    epoch: Option<Epoch>,
+}
+
+impl<State, Codec> Replayer<State, Codec>
+where
+    State: ExecutionState + Send + Sync + 'static,
+    State::Error: Debug,
+    Codec: TransactionCodec<State::Transaction>,
+{
+    pub fn new(
+        store: &NodeStorage,
+        worker_stores: &[NodeStorage],
+        execution_state: Arc<State>,
+        codec: Codec,
+        state_commitment_interval: u64,
+    ) -> Self {
+        Self {
+            consensus_store: store.consensus_store.clone(),
+            certificate_store: store.certificate_store.clone(),
+            temp_batch_store: store.temp_batch_store.clone(),
+            batch_stores: std::iter::once(store)
+                .chain(worker_stores)
+                .map(|x| x.batch_store.clone())
+                .collect(),
+            execution_state,
+            codec,
+            state_commitment_interval,
+            epoch: None,
+        }
+    }
+
+    /// Replay the certificate sequenced at the specified consensus index. Returns `None` if
+    /// nothing is sequenced at that index, or if it was pruned.
+    pub async fn replay(
+        &mut self,
+        consensus_index: SequenceNumber,
+    ) -> SubscriberResult<Option<ReplayedCertificate<State::Outcome>>> {
+        // The certificate sequenced at index `i` is persisted under the key `i + 1`.
+        let digest = match self
+            .consensus_store
+            .read_sequenced_certificates(&(consensus_index + 1..=consensus_index + 1))?
+            .pop()
+            .flatten()
+        {
+            Some(digest) => digest,
+            None => return Ok(None),
+        };
+        let certificate = self.certificate_store.read(digest)?.ok_or_else(|| {
+            SubscriberError::NodeExecutionError(format!("Missing certificate {digest}"))
+        })?;
+        let message = ConsensusOutput {
+            certificate,
+            consensus_index,
+        };
+
+        let now = Instant::now();
+        let mut replayed = ReplayedCertificate {
+            consensus_index,
+            digest,
+            discarded: false,
+            transactions: Vec::new(),
+            missing_batches: Vec::new(),
+            state: None,
+            next_epoch: None,
+            elapsed: Duration::default(),
+        };
+
+        // Discard the certificates sequenced after the end of their epoch, as the executor does.
+        let epoch = *self
+            .epoch
+            .get_or_insert_with(|| message.certificate.epoch());
+        if message.certificate.epoch() < epoch {
+            let epoch = message.certificate.epoch();
+            debug!("Discarding certificate of past epoch {epoch}");
+            replayed.discarded = true;
+            return Ok(Some(replayed));
+        }
+
+        self.execute_certificate(&message, &mut replayed).await?;
+
+        if self.state_commitment_interval != 0
+            && (consensus_index + 1) % self.state_commitment_interval == 0
+        {
+            replayed.state = self.execution_state.state_digest(&message).await;
+        }
+        if let Some((committee, _)) = self.execution_state.next_epoch(&message).await {
+            if committee.epoch() > epoch {
+                self.epoch = Some(committee.epoch());
+                replayed.next_epoch = Some(committee.epoch());
+            }
+        }
+
+        replayed.elapsed = now.elapsed();
+        Ok(Some(replayed))
+    }
+
+    /// Execute the transactions of a certificate, computing the execution indices the same way
+    /// as the executor.
+    async fn execute_certificate(
+        &self,
+        message: &ConsensusOutput,
+        replayed: &mut ReplayedCertificate<State::Outcome>,
+    ) -> SubscriberResult<()> {
+        let payload = &message.certificate.header.payload;
+        if payload.is_empty() {
+            return Ok(());
+        }
+
+        self.execution_state
+            .begin_certificate(message)
+            .await
+            .map_err(SubscriberError::from)?;
+
+        let mut execution_indices = ExecutionIndices {
+            next_certificate_index: message.consensus_index,
+            ..ExecutionIndices::default()
+        };
+        let total_batches = payload.len();
+        for digest in payload.keys() {
+            let transactions = match self.read_batch(&message.certificate, *digest).await? {
+                Some(batch) => batch.0,
+                None => {
+                    replayed.missing_batches.push(*digest);
+                    execution_indices.skip_batch(total_batches);
+                    continue;
+                }
+            };
+
+            let total_transactions = transactions.len();
+            for transaction in transactions {
+                execution_indices.next(total_batches, total_transactions);
+
+                let now = Instant::now();
+                let result = match self.codec.decode(&transaction) {
+                    Ok(deserialized) => self
+                        .execution_state
+                        .handle_consensus_transaction(
+                            message,
+                            execution_indices.clone(),
+                            deserialized,
+                        )
+                        .await
+                        .map_err(SubscriberError::from),
+                    Err(e) => Err(SubscriberError::ClientExecutionError(format!(
+                        "Failed to decode transaction: {e}"
+                    ))),
+                };
+                replayed.transactions.push(ReplayedTransaction {
+                    batch: *digest,
+                    execution_indices: execution_indices.clone(),
+                    transaction,
+                    result,
+                    elapsed: now.elapsed(),
+                });
+            }
+        }
+
+        self.execution_state
+            .end_certificate(message)
+            .await
+            .map_err(SubscriberError::from)
+    }
+
+    /// Read a batch of the certificate, from the executor's temporary store or, once it cleaned
+    /// it up, from the stores of the workers.
+    async fn read_batch(
+        &self,
+        certificate: &Certificate,
+        digest: BatchDigest,
+    ) -> SubscriberResult<Option<Batch>> {
+        if let Some(batch) = self
+            .temp_batch_store
+            .read((certificate.digest(), digest))
+            .await?
+        {
+            return Ok(Some(batch));
+        }
+        for store in &self.batch_stores {
+            if let Some(batch) = store.read(digest).await? {
+                return Ok(Some(batch));
+            }
+        }
+        Ok(None)
+    }
+}
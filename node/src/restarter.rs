@@ -4,7 +4,7 @@ use crate::{Node, NodeStorage};
 use arc_swap::ArcSwap;
 use config::{Committee, Parameters, SharedWorkerCache, WorkerCache, WorkerId};
 use crypto::{KeyPair, NetworkKeyPair};
-use executor::{ExecutionState, ExecutorOutput};
+use executor::{ExecutionState, ExecutorOutput, TransactionCodec};
 use fastcrypto::traits::KeyPair as _;
 use futures::future::join_all;
 use network::{P2pNetwork, ReliableNetwork};
@@ -12,6 +12,7 @@ use prometheus::Registry;
 use std::{fmt::Debug, path::PathBuf, sync::Arc};
 use tokio::sync::mpsc::{Receiver, Sender};
 use types::{PrimaryWorkerMessage, ReconfigureNotification, WorkerPrimaryMessage};
+use worker::TrivialTransactionValidator;
 
 // Module to start a node (primary, workers and default consensus), keep it running, and restarting it
 /// every time the committee changes.
@@ -26,6 +27,7 @@ impl NodeRestarter {
         worker_cache: SharedWorkerCache,
         storage_base_path: PathBuf,
         execution_state: Arc<State>,
+        codec: impl TransactionCodec<State::Transaction>,
         parameters: Parameters,
         mut rx_reconfigure: Receiver<(
             KeyPair,
@@ -74,6 +76,7 @@ impl NodeRestarter {
             // Restart the relevant components.
             let primary_handles = Node::spawn_primary(
                 primary_keypair,
+                /* coin_keypair */ None,
                 primary_network_keypair,
                 Arc::new(ArcSwap::new(Arc::new(committee.clone()))),
                 worker_cache.clone(),
@@ -81,6 +84,7 @@ impl NodeRestarter {
                 parameters.clone(),
                 /* consensus */ true,
                 execution_state.clone(),
+                codec.clone(),
                 tx_output.clone(),
                 registry,
             )
@@ -94,6 +98,7 @@ impl NodeRestarter {
                 worker_cache.clone(),
                 &store,
                 parameters.clone(),
+                TrivialTransactionValidator::default(),
                 registry,
             );
 
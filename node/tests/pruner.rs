@@ -0,0 +1,325 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use async_trait::async_trait;
+use config::{PruningParameters, RetentionPolicy};
+use consensus::ConsensusOutput;
+use executor::{ExecutionIndices, ExecutionState};
+use fastcrypto::Hash;
+use node::{execution_state::SimpleExecutionError, pruner::Pruner, NodeStorage};
+use primary::BlockRemoverCommand;
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+use test_utils::{temp_dir, CommitteeFixture};
+use tokio::sync::watch;
+use types::{
+    metered_channel, BatchDigest, Certificate, ReconfigureNotification, Round, SequenceNumber,
+};
+
+/// An execution state that processed the sequence up to the specified certificate.
+struct ExecutedUpTo(SequenceNumber);
+
+#[async_trait]
+impl ExecutionState for ExecutedUpTo {
+    type Transaction = String;
+    type Error = SimpleExecutionError;
+    type Outcome = Vec<u8>;
+
+    async fn handle_consensus_transaction(
+        &self,
+        _consensus_output: &ConsensusOutput,
+        _execution_indices: ExecutionIndices,
+        _transaction: Self::Transaction,
+    ) -> Result<Self::Outcome, Self::Error> {
+        Ok(Vec::default())
+    }
+
+    fn ask_consensus_write_lock(&self) -> bool {
+        true
+    }
+
+    fn release_consensus_write_lock(&self) {}
+
+    async fn load_execution_indices(&self) -> Result<ExecutionIndices, Self::Error> {
+        Ok(ExecutionIndices {
+            next_certificate_index: self.0,
+            ..ExecutionIndices::default()
+        })
+    }
+}
+
+/// Sequence the certificates of rounds 1 to 3, each round committed by its own leader.
+async fn sequenced_store() -> (NodeStorage, Vec<Certificate>) {
+    let fixture = CommitteeFixture::builder().build();
+    let committee = fixture.committee();
+    let keys: Vec<_> = fixture.authorities().map(|a| a.public_key()).collect();
+    let genesis: BTreeSet<_> = Certificate::genesis(&committee)
+        .iter()
+        .map(|x| x.digest())
+        .collect();
+    let (certificates, _) =
+        test_utils::make_optimal_certificates(&committee, 1..=3, &genesis, &keys);
+
+    let store = NodeStorage::reopen(temp_dir());
+    let mut last_committed = HashMap::new();
+    for (consensus_index, certificate) in certificates.iter().enumerate() {
+        store
+            .header_store
+            .write(certificate.header.id, certificate.header.clone())
+            .await;
+        store.certificate_store.write(certificate.clone()).unwrap();
+        store
+            .payload_store
+            .write_all(certificate.header.payload.iter().map(|x| ((*x.0, *x.1), 0)))
+            .await
+            .unwrap();
+        last_committed.insert(certificate.origin(), certificate.round());
+        store
+            .consensus_store
+            .write_consensus_state(
+                &last_committed,
+                &(consensus_index as SequenceNumber + 1),
+                &certificate.digest(),
+                &certificate.round(),
+            )
+            .unwrap();
+    }
+    (store, certificates.into_iter().collect())
+}
+
+/// Answer the requests of the pruner to delete batches, and record the deleted batches.
+fn block_remover(
+    mut rx_commands: metered_channel::Receiver<BlockRemoverCommand>,
+) -> Arc<Mutex<HashSet<BatchDigest>>> {
+    let deleted = Arc::new(Mutex::new(HashSet::new()));
+    let deleted_batches = deleted.clone();
+    tokio::spawn(async move {
+        while let Some(command) = rx_commands.recv().await {
+            if let BlockRemoverCommand::DeleteBatches { batches, sender } = command {
+                deleted_batches
+                    .lock()
+                    .unwrap()
+                    .extend(batches.into_values().flatten());
+                let _ = sender.send(Ok(()));
+            }
+        }
+    });
+    deleted
+}
+
+fn pruner(
+    store: &NodeStorage,
+    retention: RetentionPolicy,
+    retention_depth: u64,
+    gc_depth: Round,
+    executed: SequenceNumber,
+) -> (Pruner<ExecutedUpTo>, Arc<Mutex<HashSet<BatchDigest>>>) {
+    let parameters = PruningParameters {
+        retention,
+        retention_depth,
+        ..PruningParameters::default()
+    };
+    let (_tx_reconfigure, rx_reconfigure) = watch::channel(ReconfigureNotification::Shutdown);
+    let (tx_block_removal_commands, rx_block_removal_commands) =
+        test_utils::test_block_removal_commands!(1);
+    let pruner = Pruner::new(
+        parameters,
+        gc_depth,
+        store,
+        Arc::new(ExecutedUpTo(executed)),
+        tx_block_removal_commands,
+        rx_reconfigure,
+    );
+    (pruner, block_remover(rx_block_removal_commands))
+}
+
+#[tokio::test]
+async fn prune_sequence_numbers_up_to_executed() {
+    let (store, certificates) = sequenced_store().await;
+
+    // The executor processed the first 6 certificates, and the policy retains the last 2.
+    let (pruner, deleted) = pruner(&store, RetentionPolicy::SequenceNumbers, 2, 0, 6);
+    assert_eq!(pruner.prune().await.unwrap(), 5);
+
+    // The certificates up to the last executed one are pruned along with their headers, payload
+    // tokens and batches.
+    for certificate in &certificates[..5] {
+        let digest = certificate.digest();
+        assert!(store.certificate_store.read(digest).unwrap().is_none());
+        assert!(store
+            .header_store
+            .read(certificate.header.id)
+            .await
+            .unwrap()
+            .is_none());
+        for (batch, worker_id) in &certificate.header.payload {
+            assert!(store
+                .payload_store
+                .read((*batch, *worker_id))
+                .await
+                .unwrap()
+                .is_none());
+            assert!(deleted.lock().unwrap().contains(batch));
+        }
+        assert_eq!(
+            store.consensus_store.read_consensus_index(&digest).unwrap(),
+            None
+        );
+    }
+    for (consensus_index, certificate) in certificates.iter().enumerate().skip(5) {
+        let digest = certificate.digest();
+        assert!(store.certificate_store.read(digest).unwrap().is_some());
+        for batch in certificate.header.payload.keys() {
+            assert!(!deleted.lock().unwrap().contains(batch));
+        }
+        assert_eq!(
+            store.consensus_store.read_consensus_index(&digest).unwrap(),
+            Some(consensus_index as SequenceNumber)
+        );
+    }
+    assert_eq!(
+        store.consensus_store.read_first_consensus_index().unwrap(),
+        Some(5)
+    );
+    assert_eq!(
+        store.consensus_store.read_last_consensus_index().unwrap(),
+        certificates.len() as SequenceNumber
+    );
+
+    // Nothing more is pruned until the executor makes progress.
+    assert_eq!(pruner.prune().await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn prune_rounds() {
+    let (store, certificates) = sequenced_store().await;
+
+    // Everything is executed, and the policy retains the last committed round.
+    let (pruner, _) = pruner(
+        &store,
+        RetentionPolicy::Rounds,
+        1,
+        0,
+        certificates.len() as u64,
+    );
+    assert_eq!(pruner.prune().await.unwrap(), 4);
+
+    // Only the certificates of the first round are pruned.
+    for certificate in &certificates {
+        assert_eq!(
+            store
+                .certificate_store
+                .read(certificate.digest())
+                .unwrap()
+                .is_none(),
+            certificate.round() == 1
+        );
+    }
+    assert!(store
+        .consensus_store
+        .read_committed_by_leader_round(1)
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        store
+            .consensus_store
+            .read_committed_by_leader_round(2)
+            .unwrap()
+            .len(),
+        4
+    );
+}
+
+#[tokio::test]
+async fn retain_gc_depth() {
+    let (store, certificates) = sequenced_store().await;
+
+    // Everything is executed, and the policy retains no sequence number, but the certificates
+    // of the last `gc_depth` rounds are still needed by consensus.
+    let (pruner, _) = pruner(
+        &store,
+        RetentionPolicy::SequenceNumbers,
+        0,
+        1,
+        certificates.len() as u64,
+    );
+    assert_eq!(pruner.prune().await.unwrap(), 4);
+    for certificate in &certificates {
+        assert_eq!(
+            store
+                .certificate_store
+                .read(certificate.digest())
+                .unwrap()
+                .is_none(),
+            certificate.round() == 1
+        );
+    }
+}
+
+#[tokio::test]
+async fn keep_all_never_prunes() {
+    let (store, certificates) = sequenced_store().await;
+
+    let (pruner, _) = pruner(
+        &store,
+        RetentionPolicy::KeepAll,
+        0,
+        0,
+        certificates.len() as u64,
+    );
+    assert_eq!(pruner.prune().await.unwrap(), 0);
+    assert_eq!(
+        store.consensus_store.read_first_consensus_index().unwrap(),
+        Some(0)
+    );
+}
+
+#[tokio::test]
+async fn retain_reproposed_batches() {
+    let (store, certificates) = sequenced_store().await;
+
+    // A certificate of round 3 which was not sequenced proposed again a batch of round 1.
+    let reproposed = certificates
+        .iter()
+        .find(|x| x.round() == 1)
+        .unwrap()
+        .header
+        .payload
+        .clone();
+    let mut certificate = certificates
+        .iter()
+        .find(|x| x.round() == 3)
+        .unwrap()
+        .clone();
+    certificate.header.payload = reproposed.clone();
+    certificate.header.id = certificate.header.digest();
+    store.certificate_store.write(certificate).unwrap();
+
+    // Everything is executed, and the policy retains the last committed round.
+    let (pruner, deleted) = pruner(
+        &store,
+        RetentionPolicy::Rounds,
+        1,
+        1,
+        certificates.len() as u64,
+    );
+    assert_eq!(pruner.prune().await.unwrap(), 4);
+
+    // The batch proposed again is neither deleted from the workers nor forgotten.
+    for certificate in certificates.iter().filter(|x| x.round() == 1) {
+        for (batch, worker_id) in &certificate.header.payload {
+            let retained = reproposed.contains_key(batch);
+            assert_eq!(deleted.lock().unwrap().contains(batch), !retained);
+            assert_eq!(
+                store
+                    .payload_store
+                    .read((*batch, *worker_id))
+                    .await
+                    .unwrap()
+                    .is_some(),
+                retained
+            );
+        }
+    }
+}
@@ -5,7 +5,7 @@ use bytes::Bytes;
 use config::{Committee, Parameters, SharedWorkerCache, WorkerCache, WorkerId};
 use consensus::ConsensusOutput;
 use crypto::{KeyPair, NetworkKeyPair, PublicKey};
-use executor::{ExecutionIndices, ExecutionState, ExecutionStateError};
+use executor::{BincodeCodec, ExecutionIndices, ExecutionState, ExecutionStateError};
 use fastcrypto::traits::KeyPair as _;
 use futures::future::join_all;
 use network::{P2pNetwork, ReliableNetwork};
@@ -14,7 +14,10 @@ use primary::PrimaryWorkerMessage;
 use prometheus::Registry;
 use std::{
     fmt::Debug,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
 };
 use test_utils::{random_network, CommitteeFixture};
 use tokio::{
@@ -22,6 +25,7 @@ use tokio::{
     time::{interval, sleep, Duration, MissedTickBehavior},
 };
 use types::{ReconfigureNotification, TransactionProto, TransactionsClient, WorkerPrimaryMessage};
+use worker::TrivialTransactionValidator;
 
 /// A simple/dumb execution engine.
 struct SimpleExecutionState {
@@ -119,6 +123,83 @@ impl ExecutionState for SimpleExecutionState {
     }
 }
 
+/// A simple/dumb execution engine moving the node to the next epoch in place.
+struct EpochExecutionState {
+    worker_cache: WorkerCache,
+    committee: Mutex<Committee>,
+    /// Whether the current certificate ends the epoch.
+    epoch_ended: AtomicBool,
+}
+
+impl EpochExecutionState {
+    pub fn new(worker_cache: WorkerCache, committee: Committee) -> Self {
+        Self {
+            worker_cache,
+            committee: Mutex::new(committee),
+            epoch_ended: AtomicBool::new(false),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ExecutionState for EpochExecutionState {
+    type Transaction = u64;
+    type Error = SimpleExecutionError;
+    type Outcome = u64;
+
+    async fn handle_consensus_transaction(
+        &self,
+        _consensus_output: &ConsensusOutput,
+        execution_indices: ExecutionIndices,
+        transaction: Self::Transaction,
+    ) -> Result<Self::Outcome, Self::Error> {
+        // End the epoch every few certificates.
+        let epoch = self.committee.lock().unwrap().epoch();
+        if transaction >= epoch && execution_indices.next_certificate_index % 3 == 0 {
+            self.epoch_ended.store(true, Ordering::SeqCst);
+        }
+        Ok(epoch)
+    }
+
+    fn ask_consensus_write_lock(&self) -> bool {
+        true
+    }
+
+    fn release_consensus_write_lock(&self) {}
+
+    async fn load_execution_indices(&self) -> Result<ExecutionIndices, Self::Error> {
+        Ok(ExecutionIndices::default())
+    }
+
+    async fn next_epoch(
+        &self,
+        _consensus_output: &ConsensusOutput,
+    ) -> Option<(Committee, WorkerCache)> {
+        if !self.epoch_ended.swap(false, Ordering::SeqCst) {
+            return None;
+        }
+        let mut committee = self.committee.lock().unwrap();
+        committee.epoch += 1;
+        let worker_cache = WorkerCache {
+            epoch: committee.epoch,
+            ..self.worker_cache.clone()
+        };
+        Some((committee.clone(), worker_cache))
+    }
+
+    async fn load_epoch(&self) -> Result<Option<(Committee, WorkerCache)>, Self::Error> {
+        let committee = self.committee.lock().unwrap().clone();
+        if committee.epoch() == 0 {
+            return Ok(None);
+        }
+        let worker_cache = WorkerCache {
+            epoch: committee.epoch,
+            ..self.worker_cache.clone()
+        };
+        Ok(Some((committee, worker_cache)))
+    }
+}
+
 /// A simple/dumb execution error.
 #[derive(Debug, thiserror::Error)]
 pub enum SimpleExecutionError {
@@ -232,6 +313,7 @@ async fn restart() {
                 worker_cache,
                 /* base_store_path */ test_utils::temp_dir(),
                 execution_state,
+                BincodeCodec::default(),
                 parameters,
                 rx_node_reconfigure,
                 tx_output,
@@ -370,6 +452,7 @@ async fn epoch_change() {
 
         let _primary_handles = Node::spawn_primary(
             a.keypair().copy(),
+            /* coin_keypair */ None,
             a.network_keypair().copy(),
             Arc::new(ArcSwap::new(Arc::new(committee.clone()))),
             worker_cache.clone(),
@@ -377,6 +460,7 @@ async fn epoch_change() {
             parameters.clone(),
             /* consensus */ true,
             execution_state.clone(),
+            BincodeCodec::default(),
             tx_output,
             &Registry::new(),
         )
@@ -390,6 +474,7 @@ async fn epoch_change() {
             worker_cache.clone(),
             &store,
             parameters.clone(),
+            TrivialTransactionValidator::default(),
             &Registry::new(),
         );
 
@@ -436,3 +521,137 @@ async fn epoch_change() {
     }
     join_all(handles).await;
 }
+
+#[tokio::test]
+async fn in_place_epoch_change() {
+    let fixture = CommitteeFixture::builder().randomize_ports(true).build();
+    let committee = fixture.committee();
+    let parameters = Parameters {
+        batch_size: 200,
+        header_size: 1,
+        ..Parameters::default()
+    };
+
+    // Spawn the nodes. The execution state moves them to the next epoch without restarting them.
+    let mut rx_nodes = Vec::new();
+    for a in fixture.authorities() {
+        let (tx_output, rx_output) = channel(10);
+        let name = a.public_key();
+        let store = NodeStorage::reopen(test_utils::temp_dir());
+        let shared_committee = Arc::new(ArcSwap::from_pointee(committee.clone()));
+        let worker_cache = fixture.shared_worker_cache();
+
+        let execution_state = Arc::new(EpochExecutionState::new(
+            fixture.worker_cache(),
+            committee.clone(),
+        ));
+
+        let _primary_handles = Node::spawn_primary(
+            a.keypair().copy(),
+            /* coin_keypair */ None,
+            a.network_keypair().copy(),
+            shared_committee.clone(),
+            worker_cache.clone(),
+            &store,
+            parameters.clone(),
+            /* consensus */ true,
+            execution_state,
+            BincodeCodec::default(),
+            tx_output,
+            &Registry::new(),
+        )
+        .await
+        .unwrap();
+
+        let _worker_handles = Node::spawn_workers(
+            name,
+            /* worker ids_and_keypairs */ vec![(0, a.worker(0).keypair().copy())],
+            shared_committee,
+            worker_cache,
+            &store,
+            parameters.clone(),
+            TrivialTransactionValidator::default(),
+            &Registry::new(),
+        );
+
+        rx_nodes.push(rx_output);
+    }
+
+    // Give a chance to the nodes to start.
+    tokio::task::yield_now().await;
+
+    // Spawn some clients.
+    let mut tx_clients = Vec::new();
+    for a in fixture.authorities() {
+        let (tx_client_reconfigure, rx_client_reconfigure) = channel(10);
+        tx_clients.push(tx_client_reconfigure);
+
+        let name = a.public_key();
+        let worker_cache = fixture.shared_worker_cache();
+        tokio::spawn(async move { run_client(name, worker_cache, rx_client_reconfigure).await });
+    }
+
+    // Listen to the outputs.
+    let mut handles = Vec::new();
+    for (tx, mut rx) in tx_clients.into_iter().zip(rx_nodes.into_iter()) {
+        handles.push(tokio::spawn(async move {
+            let mut current_epoch = 0u64;
+            while let Some(output) = rx.recv().await {
+                let (outcome, _tx) = output;
+                match outcome {
+                    Ok(epoch) => {
+                        if epoch == 5 {
+                            return;
+                        }
+                        if epoch > current_epoch {
+                            current_epoch = epoch;
+                            tx.send(current_epoch).await.unwrap();
+                        }
+                    }
+                    Err(e) => panic!("{e}"),
+                }
+            }
+        }));
+    }
+    join_all(handles).await;
+}
+
+#[tokio::test]
+async fn resume_epoch_after_restart() {
+    let fixture = CommitteeFixture::builder().randomize_ports(true).build();
+    let committee = fixture.committee();
+    let authority = fixture.authorities().next().unwrap();
+    let (tx_output, _rx_output) = channel(10);
+    let store = NodeStorage::reopen(test_utils::temp_dir());
+    let shared_committee = Arc::new(ArcSwap::from_pointee(committee.clone()));
+    let worker_cache = fixture.shared_worker_cache();
+
+    // The execution state moved to epoch 2 before the node restarted with its initial committee.
+    let mut last_committee = committee.clone();
+    last_committee.epoch = 2;
+    let execution_state = Arc::new(EpochExecutionState::new(
+        fixture.worker_cache(),
+        last_committee,
+    ));
+
+    let _primary_handles = Node::spawn_primary(
+        authority.keypair().copy(),
+        /* coin_keypair */ None,
+        authority.network_keypair().copy(),
+        shared_committee.clone(),
+        worker_cache.clone(),
+        &store,
+        Parameters::default(),
+        /* consensus */ true,
+        execution_state,
+        BincodeCodec::default(),
+        tx_output,
+        &Registry::new(),
+    )
+    .await
+    .unwrap();
+
+    // Ensure the node resumed in the epoch of the execution state.
+    assert_eq!(shared_committee.load().epoch(), 2);
+    assert_eq!(worker_cache.load().epoch, 2);
+}
@@ -0,0 +1,206 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use async_trait::async_trait;
+use consensus::ConsensusOutput;
+use executor::{BincodeCodec, ExecutionIndices, ExecutionState, SubscriberError};
+use fastcrypto::{Hash, DIGEST_LEN};
+use node::{execution_state::SimpleExecutionError, replay::Replayer, NodeStorage};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use test_utils::{temp_dir, CommitteeFixture};
+use types::{Batch, SequenceNumber, StateDigest};
+
+/// An execution state recording the transactions it executes.
+#[derive(Default)]
+struct RecordingState(Mutex<Vec<String>>);
+
+#[async_trait]
+impl ExecutionState for RecordingState {
+    type Transaction = String;
+    type Error = SimpleExecutionError;
+    type Outcome = String;
+
+    async fn handle_consensus_transaction(
+        &self,
+        _consensus_output: &ConsensusOutput,
+        _execution_indices: ExecutionIndices,
+        transaction: Self::Transaction,
+    ) -> Result<Self::Outcome, Self::Error> {
+        self.0.lock().unwrap().push(transaction.clone());
+        Ok(transaction.to_uppercase())
+    }
+
+    fn ask_consensus_write_lock(&self) -> bool {
+        true
+    }
+
+    fn release_consensus_write_lock(&self) {}
+
+    async fn load_execution_indices(&self) -> Result<ExecutionIndices, Self::Error> {
+        Ok(ExecutionIndices::default())
+    }
+
+    async fn state_digest(&self, _consensus_output: &ConsensusOutput) -> Option<StateDigest> {
+        let mut digest = [0; DIGEST_LEN];
+        digest[0] = self.0.lock().unwrap().len() as u8;
+        Some(StateDigest::new(digest))
+    }
+}
+
+fn batch(transactions: &[&str]) -> Batch {
+    Batch(
+        transactions
+            .iter()
+            .map(|x| bincode::serialize(x).unwrap())
+            .collect(),
+    )
+}
+
+fn indices(certificate: u64, batch: u64, transaction: u64) -> ExecutionIndices {
+    ExecutionIndices {
+        next_certificate_index: certificate,
+        next_batch_index: batch,
+        next_transaction_index: transaction,
+    }
+}
+
+#[tokio::test]
+async fn replay_sequence() {
+    let fixture = CommitteeFixture::builder().build();
+    let committee = fixture.committee();
+    let mut authorities = fixture.authorities();
+
+    // The first certificate references a batch still in the temporary store of the executor,
+    // and a batch only held by a worker, with a transaction that fails to decode.
+    let executed = batch(&["a", "b"]);
+    let mut undecodable = batch(&["c"]);
+    undecodable.0.push(vec![0xff]);
+    let author = authorities.next().unwrap();
+    let header = author
+        .header_builder(&committee)
+        .with_payload_batch(executed.clone(), 0)
+        .with_payload_batch(undecodable.clone(), 0)
+        .build(author.keypair())
+        .unwrap();
+    let first = fixture.certificate(&header);
+
+    // The second certificate references a batch missing from all the stores.
+    let missing = batch(&["lost"]);
+    let stored = batch(&["d"]);
+    let author = authorities.next().unwrap();
+    let header = author
+        .header_builder(&committee)
+        .with_payload_batch(missing.clone(), 0)
+        .with_payload_batch(stored.clone(), 0)
+        .build(author.keypair())
+        .unwrap();
+    let second = fixture.certificate(&header);
+
+    let store = NodeStorage::reopen(temp_dir());
+    let worker_store = NodeStorage::reopen(temp_dir());
+    store
+        .temp_batch_store
+        .write((first.digest(), executed.digest()), executed.clone())
+        .await;
+    worker_store
+        .batch_store
+        .write(undecodable.digest(), undecodable.clone())
+        .await;
+    store
+        .batch_store
+        .write(stored.digest(), stored.clone())
+        .await;
+    let mut last_committed = HashMap::new();
+    for (consensus_index, certificate) in [&first, &second].into_iter().enumerate() {
+        store.certificate_store.write(certificate.clone()).unwrap();
+        last_committed.insert(certificate.origin(), certificate.round());
+        store
+            .consensus_store
+            .write_consensus_state(
+                &last_committed,
+                &(consensus_index as SequenceNumber + 1),
+                &certificate.digest(),
+                &certificate.round(),
+            )
+            .unwrap();
+    }
+
+    let execution_state = Arc::new(RecordingState::default());
+    let mut replayer = Replayer::new(
+        &store,
+        &[worker_store],
+        execution_state.clone(),
+        BincodeCodec::default(),
+        /* state_commitment_interval */ 2,
+    );
+
+    // The transactions are replayed with the execution indices computed by the executor.
+    let replayed = replayer.replay(0).await.unwrap().unwrap();
+    assert_eq!(replayed.digest, first.digest());
+    assert!(!replayed.discarded);
+    assert!(replayed.missing_batches.is_empty());
+    assert!(replayed.state.is_none());
+    let expected = [
+        (executed.digest(), indices(0, 0, 1), Some("A")),
+        (executed.digest(), indices(0, 1, 0), Some("B")),
+        (undecodable.digest(), indices(0, 1, 1), Some("C")),
+        (undecodable.digest(), indices(1, 0, 0), None),
+    ];
+    assert_eq!(replayed.transactions.len(), expected.len());
+    for (transaction, (batch, indices, outcome)) in replayed.transactions.iter().zip(expected) {
+        assert_eq!(transaction.batch, batch);
+        assert_eq!(transaction.execution_indices, indices);
+        match (&transaction.result, outcome) {
+            (Ok(result), Some(outcome)) => assert_eq!(result, outcome),
+            (Err(SubscriberError::ClientExecutionError(_)), None) => (),
+            (result, _) => panic!("Unexpected outcome {result:?}"),
+        }
+    }
+
+    // The missing batches are skipped, and the execution state is committed to at the end of
+    // the interval.
+    let replayed = replayer.replay(1).await.unwrap().unwrap();
+    assert_eq!(replayed.digest, second.digest());
+    assert_eq!(replayed.missing_batches, vec![missing.digest()]);
+    assert_eq!(replayed.transactions.len(), 1);
+    assert_eq!(replayed.transactions[0].execution_indices, indices(2, 0, 0));
+    let mut digest = [0; DIGEST_LEN];
+    digest[0] = 4;
+    assert_eq!(replayed.state, Some(StateDigest::new(digest)));
+
+    // Nothing is sequenced past the end of the sequence.
+    assert!(replayer.replay(2).await.unwrap().is_none());
+    assert_eq!(*execution_state.0.lock().unwrap(), vec!["a", "b", "c", "d"]);
+
+    // The stores are left untouched.
+    assert!(store
+        .temp_batch_store
+        .read((first.digest(), executed.digest()))
+        .await
+        .unwrap()
+        .is_some());
+}
+
+#[tokio::test]
+async fn open_stores_read_only() {
+    // A missing store is not created.
+    let path = temp_dir();
+    let missing = path.join("missing");
+    assert!(NodeStorage::open_read_only(&missing).is_err());
+    assert!(!missing.exists());
+
+    // The store of a running node may be read.
+    let store = NodeStorage::reopen(&path);
+    let stored = batch(&["a"]);
+    store
+        .batch_store
+        .write(stored.digest(), stored.clone())
+        .await;
+    let read_only = NodeStorage::open_read_only(&path).unwrap();
+    assert_eq!(
+        read_only.batch_store.read(stored.digest()).await.unwrap(),
+        Some(stored)
+    );
+}
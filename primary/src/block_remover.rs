@@ -43,6 +43,12 @@ pub enum BlockRemoverCommand {
         // the channel to communicate the results
         sender: mpsc::Sender<BlockRemoverResult<RemoveBlocksResponse>>,
     },
+    DeleteBatches {
+        // the batches to delete, by the id of the worker storing them
+        batches: HashMap<WorkerId, Vec<BatchDigest>>,
+        // the channel to communicate the result
+        sender: oneshot::Sender<Result<(), BlockRemoverErrorKind>>,
+    },
 }
 
 #[derive(Clone, Debug)]
@@ -53,6 +59,12 @@ pub struct RemoveBlocksResponse {
 
 pub type DeleteBatchResult = Result<DeleteBatchMessage, DeleteBatchMessage>;
 
+type PendingBatchDeletion = (
+    Vec<RequestKey>,
+    Result<(), BlockRemoverErrorKind>,
+    oneshot::Sender<Result<(), BlockRemoverErrorKind>>,
+);
+
 #[derive(Clone, Default, Debug)]
 pub struct DeleteBatchMessage {
     pub ids: Vec<BatchDigest>,
@@ -225,6 +237,10 @@ pub struct BlockRemover {
 
     map_tx_worker_removal_results: HashMap<RequestKey, oneshot::Sender<DeleteBatchResult>>,
 
+    /// The requests to delete batches from the workers which are waiting for the
+    /// responses of the workers.
+    pending_batch_deletions: FuturesUnordered<BoxFuture<'static, PendingBatchDeletion>>,
+
     // TODO: Change to a oneshot channel instead of an mpsc channel
     /// Receives all the responses to the requests to delete a batch.
     rx_delete_batches: Receiver<DeleteBatchResult>,
@@ -264,6 +280,7 @@ impl BlockRemover {
                 pending_removal_requests: HashMap::new(),
                 map_tx_removal_results: HashMap::new(),
                 map_tx_worker_removal_results: HashMap::new(),
+                pending_batch_deletions: FuturesUnordered::new(),
                 rx_delete_batches,
                 tx_removed_certificates: removed_certificates,
             }
@@ -292,6 +309,13 @@ impl BlockRemover {
                 Some(result) = waiting.next() => {
                     self.handle_remove_waiting_result(result).await;
                 },
+                Some((keys, result, sender)) = self.pending_batch_deletions.next() => {
+                    // Forget the requests to the workers, so late responses are ignored.
+                    for key in keys {
+                        self.map_tx_worker_removal_results.remove(&key);
+                    }
+                    let _ = sender.send(result);
+                },
                 result = self.rx_reconfigure.changed() => {
                     result.expect("Committee channel dropped");
                     let message = self.rx_reconfigure.borrow().clone();
@@ -445,9 +469,9 @@ impl BlockRemover {
         let key = Self::construct_batches_request_key(&ids);
 
         if let Some(sender) = self.map_tx_worker_removal_results.remove(&key) {
-            sender
-                .send(batch_result)
-                .expect("couldn't send delete batch result to channel");
+            if sender.send(batch_result).is_err() {
+                debug!("delete request for key {:?} is no longer pending", key);
+            }
         } else {
             error!("no pending delete request has been found for key {:?}", key);
         }
@@ -529,6 +553,18 @@ impl BlockRemover {
                     }
                 }
             }
+            BlockRemoverCommand::DeleteBatches { batches, sender } => {
+                let receivers = self.send_delete_batches_to_workers(batches).await;
+                let keys: Vec<_> = receivers.iter().map(|(key, _)| key.clone()).collect();
+                let fut = async move {
+                    let waiting = receivers
+                        .into_iter()
+                        .map(|(key, rx)| Self::wait_for_delete_response(key, rx));
+                    let result = try_join_all(waiting).await.map(|_| ());
+                    (keys, result, sender)
+                };
+                self.pending_batch_deletions.push(fut.boxed());
+            }
         }
 
         None
@@ -541,18 +577,25 @@ impl BlockRemover {
         // For each certificate, batch the requests by worker
         // and send the requests
         let batches_by_worker = utils::map_certificate_batches_by_worker(certificates.as_slice());
+        self.send_delete_batches_to_workers(batches_by_worker).await
+    }
 
+    async fn send_delete_batches_to_workers(
+        &mut self,
+        batches_by_worker: HashMap<WorkerId, Vec<BatchDigest>>,
+    ) -> Vec<(RequestKey, oneshot::Receiver<DeleteBatchResult>)> {
         let mut receivers: Vec<(RequestKey, oneshot::Receiver<DeleteBatchResult>)> = Vec::new();
 
         // now send the requests
         for (worker_id, batch_ids) in batches_by_worker {
             // send the batches to each worker id
-            let worker_name = self
-                .worker_cache
-                .load()
-                .worker(&self.name, &worker_id)
-                .expect("Worker id not found")
-                .name;
+            let worker_name = match self.worker_cache.load().worker(&self.name, &worker_id) {
+                Ok(worker) => worker.name,
+                Err(e) => {
+                    warn!("Cannot delete the batches of worker {worker_id}: {e}");
+                    continue;
+                }
+            };
 
             let message = PrimaryWorkerMessage::DeleteBatches(batch_ids.clone());
 
@@ -17,7 +17,7 @@ use futures::{
     stream::FuturesUnordered,
     FutureExt, StreamExt,
 };
-use network::{P2pNetwork, UnreliableNetwork};
+use network::{P2pNetwork, PeerReputation, UnreliableNetwork};
 use rand::{rngs::SmallRng, SeedableRng};
 use std::{
     collections::{BTreeMap, BTreeSet, HashMap, HashSet},
@@ -32,12 +32,12 @@ use tokio::{
         watch,
     },
     task::JoinHandle,
-    time::{sleep, timeout},
+    time::{sleep, timeout, Instant},
 };
 use tracing::{debug, error, info, instrument, trace, warn};
 use types::{
     metered_channel, BatchDigest, Certificate, CertificateDigest, PrimaryWorkerMessage,
-    ReconfigureNotification, Round,
+    ReconfigureNotification, Round, StoreResult,
 };
 
 use self::responses::{AvailabilityResponse, CertificateDigestsResponse};
@@ -114,6 +114,12 @@ enum State {
     RangeSynchronized {
         certificate_ids: CertificateIDsByRounds,
     },
+    CatchUpRangeSynchronized {
+        certificate_ids: CertificateIDsByRounds,
+    },
+    CatchUpCertificatesSynchronized {
+        results: Vec<BlockSynchronizeResult<BlockHeader>>,
+    },
     HeadersSynchronized {
         request_id: RequestID,
         certificates: HashMap<CertificateDigest, BlockSynchronizeResult<BlockHeader>>,
@@ -176,6 +182,21 @@ struct SyncRangeState {
     result_sender: Option<Sender<CertificateIDsByRounds>>,
 }
 
+// Tracks the progress of a catch-up. The catch-up pipelines the range requests with the
+// fetching of the certificates of the ranges already received.
+struct CatchUpState {
+    // The round to catch up to.
+    target: Round,
+    // The first round of the next range to request.
+    next_range_start: Round,
+    // Whether we wait for the digests of a range.
+    range_pending: bool,
+    // Whether we wait for the certificates of a range.
+    fetching: bool,
+    // The digests of a range received while fetching the certificates of the previous one.
+    queued: Option<CertificateIDsByRounds>,
+}
+
 pub struct BlockSynchronizer {
     /// The public key of this primary.
     name: PublicKey,
@@ -195,12 +216,21 @@ pub struct BlockSynchronizer {
     /// Receive answers to requested assets (certificates, payloads) through this channel
     rx_availability_responses: metered_channel::Receiver<AvailabilityResponse>,
 
+    /// Receive the highest round of the certificates we wait the ancestors of
+    rx_observed_round: watch::Receiver<Round>,
+
+    /// Send the certificates we caught up with to the core, for them to reach the consensus
+    tx_core: metered_channel::Sender<PrimaryMessage>,
+
     /// Pending block requests either for header or payload type
     pending_requests: HashMap<PendingIdentifier, Vec<ResultSender>>,
 
     /// Status of the inflight range sync request if there is one running.
     sync_range_state: SyncRangeState,
 
+    /// Status of the catch-up if there is one running.
+    catch_up_state: Option<CatchUpState>,
+
     /// Requests managers
     map_certificate_responses_senders: HashMap<RequestID, Sender<CertificatesResponse>>,
 
@@ -217,6 +247,9 @@ pub struct BlockSynchronizer {
     /// The persistent storage for payload markers from workers
     payload_store: Store<(BatchDigest, WorkerId), PayloadToken>,
 
+    /// Scores the peers by their responses, to leave the bad ones out of our requests
+    reputation: PeerReputation<PublicKey>,
+
     /// The maximum number of rounds to be included in each range request.
     /// Set to be the same as the default GC threshold.
     range_request_max_rounds: u64,
@@ -224,6 +257,10 @@ pub struct BlockSynchronizer {
     /// Timeout when synchronizing a range of certificate digests
     range_synchronize_timeout: Duration,
 
+    /// How many rounds behind the certificates we wait the ancestors of we may fall before
+    /// catching up in bulk.
+    catch_up_threshold: u64,
+
     /// Timeout when synchronizing the certificates
     certificates_synchronize_timeout: Duration,
 
@@ -243,9 +280,12 @@ impl BlockSynchronizer {
         rx_reconfigure: watch::Receiver<ReconfigureNotification>,
         rx_commands: metered_channel::Receiver<Command>,
         rx_availability_responses: metered_channel::Receiver<AvailabilityResponse>,
+        rx_observed_round: watch::Receiver<Round>,
+        tx_core: metered_channel::Sender<PrimaryMessage>,
         network: P2pNetwork,
         payload_store: Store<(BatchDigest, WorkerId), PayloadToken>,
         certificate_store: CertificateStore,
+        reputation: PeerReputation<PublicKey>,
         parameters: Parameters,
     ) -> JoinHandle<()> {
         tokio::spawn(async move {
@@ -257,15 +297,20 @@ impl BlockSynchronizer {
                 rx_reconfigure,
                 rx_commands,
                 rx_availability_responses,
+                rx_observed_round,
+                tx_core,
                 pending_requests: HashMap::new(),
                 sync_range_state: SyncRangeState::default(),
+                catch_up_state: None,
                 map_certificate_responses_senders: HashMap::new(),
                 map_payload_availability_responses_senders: HashMap::new(),
                 network,
                 payload_store,
                 certificate_store,
+                reputation,
                 range_request_max_rounds: parameters.gc_depth,
                 range_synchronize_timeout: parameters.block_synchronizer.range_synchronize_timeout,
+                catch_up_threshold: parameters.block_synchronizer.catch_up_threshold,
                 certificates_synchronize_timeout: parameters
                     .block_synchronizer
                     .certificates_synchronize_timeout,
@@ -326,6 +371,11 @@ impl BlockSynchronizer {
                         AvailabilityResponse::Payload(payload_availability_response) => self.handle_payload_availability_response(payload_availability_response).await,
                     }
                 },
+                Ok(()) = self.rx_observed_round.changed() => {
+                    for fut in self.handle_observed_round().await {
+                        waiting.push(fut);
+                    }
+                },
                 Some(state) = waiting.next() => {
                     match state {
                         State::RangeSynchronized { certificate_ids } => {
@@ -336,6 +386,16 @@ impl BlockSynchronizer {
                             // Range sync is done. Clear the state.
                             self.sync_range_state = SyncRangeState::default();
                         },
+                        State::CatchUpRangeSynchronized { certificate_ids } => {
+                            for fut in self.handle_catch_up_range(certificate_ids).await {
+                                waiting.push(fut);
+                            }
+                        },
+                        State::CatchUpCertificatesSynchronized { results } => {
+                            for fut in self.handle_catch_up_certificates(results).await {
+                                waiting.push(fut);
+                            }
+                        },
                         State::HeadersSynchronized { request_id, certificates } => {
                             debug!("Result for the block headers synchronize request id {request_id}");
 
@@ -377,6 +437,8 @@ impl BlockSynchronizer {
                         ReconfigureNotification::NewEpoch(new_committee)=> {
                             self.network.cleanup(self.committee.network_diff(&new_committee));
                             self.committee = new_committee;
+                            // The rounds we were catching up to belong to the previous epoch.
+                            self.catch_up_state = None;
                         }
                         ReconfigureNotification::UpdateCommittee(new_committee)=> {
                             self.network.cleanup(self.committee.network_diff(&new_committee));
@@ -402,6 +464,23 @@ impl BlockSynchronizer {
             return None;
         }
 
+        // NOTE: Assuming locally missing certificates in existing rounds are negligible issues,
+        // range sync starts from the next round where there is no certificate stored locally.
+        // We can switch to a more fine grained per-certificate-author range sync if necessary.
+        // Start sync from round 0, if there is no certificate in store.
+        let last_round = self.certificate_store.last_round_number();
+        let range_start = if let Some(r) = last_round { r + 1 } else { 0 };
+        Some(self.synchronize_range(range_start, respond_to).await)
+    }
+
+    /// Broadcasts a range sync request for the digests of the certificates from `range_start`,
+    /// and returns the future waiting for the responses. The caller must ensure that no other
+    /// range sync is running.
+    async fn synchronize_range<'a>(
+        &mut self,
+        range_start: Round,
+        respond_to: Sender<CertificateIDsByRounds>,
+    ) -> BoxFuture<'a, State> {
         // Initialize range sync state.
         let (sender, receiver) = channel(self.committee.size());
         assert!(self.sync_range_state.responded_peers.is_empty());
@@ -410,28 +489,237 @@ impl BlockSynchronizer {
         self.sync_range_state.item_sender = Some(sender);
 
         // Broadcast range sync request.
-        let last_round = self.certificate_store.last_round_number();
-        // NOTE: Assuming locally missing certificates in existing rounds are negligible issues,
-        // range sync starts from the next round where there is no certificate stored locally.
-        // We can switch to a more fine grained per-certificate-author range sync if necessary.
-        // Start sync from round 0, if there is no certificate in store.
-        let range_start = if let Some(r) = last_round { r + 1 } else { 0 };
         let message = PrimaryMessage::CertificatesRangeRequest {
             range_start,
             max_rounds: self.range_request_max_rounds,
             requestor: self.name.clone(),
         };
-        self.broadcast_batch_request(message.clone()).await;
+        let primaries = self.broadcast_batch_request(message.clone()).await;
 
         // Responses are not await here to avoid blocking the BlockSynchronizer loop.
-        Some(
-            Self::wait_for_range_sync_responses(
-                receiver,
-                self.committee.clone(),
-                self.range_synchronize_timeout,
-            )
-            .boxed(),
+        Self::wait_for_range_sync_responses(
+            receiver,
+            self.committee.clone(),
+            self.range_synchronize_timeout,
+            primaries,
+            self.reputation.clone(),
         )
+        .boxed()
+    }
+
+    /// Starts catching up when the certificates we wait the ancestors of are more than
+    /// `catch_up_threshold` rounds ahead of the last round we hold. Rather than chasing the
+    /// missing parents certificate by certificate, the catch-up synchronizes whole ranges of
+    /// rounds: it requests the digests of a range while fetching the certificates of the
+    /// previous one.
+    #[instrument(level = "trace", skip_all)]
+    async fn handle_observed_round<'a>(&mut self) -> Vec<BoxFuture<'a, State>> {
+        let target = *self.rx_observed_round.borrow();
+        if self.catch_up_state.is_some() {
+            return vec![];
+        }
+
+        let last_round = self.certificate_store.last_round_number();
+        if target.saturating_sub(last_round.unwrap_or_default()) <= self.catch_up_threshold {
+            return vec![];
+        }
+
+        let next_range_start = if let Some(r) = last_round { r + 1 } else { 0 };
+        info!("Catching up from round {next_range_start} to round {target}");
+        self.catch_up_state = Some(CatchUpState {
+            target,
+            next_range_start,
+            range_pending: false,
+            fetching: false,
+            queued: None,
+        });
+        self.advance_catch_up().await
+    }
+
+    /// Queues the digests of a range for their certificates to be fetched, and moves on to
+    /// the next range. An empty range means our peers have nothing more to give us.
+    async fn handle_catch_up_range<'a>(
+        &mut self,
+        certificate_ids: CertificateIDsByRounds,
+    ) -> Vec<BoxFuture<'a, State>> {
+        let state = match self.catch_up_state.as_mut() {
+            Some(state) => state,
+            None => return vec![],
+        };
+        state.range_pending = false;
+        let last_round = certificate_ids.keys().next_back().copied();
+        match last_round {
+            Some(last_round) => {
+                state.next_range_start = last_round + 1;
+                state.queued = Some(certificate_ids);
+            }
+            None => state.next_range_start = state.target + 1,
+        }
+        self.advance_catch_up().await
+    }
+
+    /// Stores the certificates fetched for a range in bulk, and hands them over to the core in
+    /// causal order so that they reach the consensus. The certificates have been verified as
+    /// they were received. Only the certificates whose ancestry is complete are stored: the
+    /// certificates after a hole in the range are stored by the core once it has their parents.
+    async fn handle_catch_up_certificates<'a>(
+        &mut self,
+        results: Vec<BlockSynchronizeResult<BlockHeader>>,
+    ) -> Vec<BoxFuture<'a, State>> {
+        let state = match self.catch_up_state.as_mut() {
+            Some(state) => state,
+            None => return vec![],
+        };
+        state.fetching = false;
+
+        let missing = results.iter().filter(|result| result.is_err()).count();
+        if missing > 0 {
+            // The core will chase the missing certificates one by one.
+            warn!("Failed to fetch {missing} certificates while catching up");
+        }
+        let mut certificates: Vec<_> = results
+            .into_iter()
+            .filter_map(|result| result.ok())
+            .filter(|block_header| !block_header.fetched_from_storage)
+            .map(|block_header| block_header.certificate)
+            .collect();
+        certificates.sort_by_key(|certificate| certificate.round());
+
+        let complete = match self.complete_ancestry(&certificates) {
+            Ok(complete) => complete,
+            Err(e) => {
+                error!("Failed to read the parents of the certificates we caught up with: {e}");
+                self.catch_up_state = None;
+                return vec![];
+            }
+        };
+        let incomplete = certificates.len() - complete.len();
+        if let Err(e) = self.certificate_store.write_all(complete) {
+            error!("Failed to store the certificates we caught up with: {e}");
+            self.catch_up_state = None;
+            return vec![];
+        }
+        debug!(
+            "Caught up with {} certificates, {incomplete} of which miss ancestors",
+            certificates.len()
+        );
+
+        for certificate in certificates {
+            if self
+                .tx_core
+                .send(PrimaryMessage::Certificate(certificate))
+                .await
+                .is_err()
+            {
+                error!("Failed to send caught up certificate to core");
+            }
+        }
+
+        self.advance_catch_up().await
+    }
+
+    /// Returns the certificates, sorted by round, whose parents are all either in the store or
+    /// among the returned certificates.
+    fn complete_ancestry(&self, certificates: &[Certificate]) -> StoreResult<Vec<Certificate>> {
+        let fetched: HashSet<_> = certificates.iter().map(|x| x.digest()).collect();
+        let mut known: HashSet<_> = Certificate::genesis(&self.committee)
+            .iter()
+            .map(|x| x.digest())
+            .collect();
+        let outside: Vec<_> = certificates
+            .iter()
+            .flat_map(|x| x.header.parents.iter().copied())
+            .filter(|digest| !fetched.contains(digest) && !known.contains(digest))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let stored = self.certificate_store.read_all(outside.clone())?;
+        known.extend(
+            outside
+                .into_iter()
+                .zip(stored)
+                .filter_map(|(digest, certificate)| certificate.map(|_| digest)),
+        );
+
+        Ok(certificates
+            .iter()
+            .filter(|certificate| {
+                let complete = certificate.header.parents.iter().all(|x| known.contains(x));
+                if complete {
+                    known.insert(certificate.digest());
+                }
+                complete
+            })
+            .cloned()
+            .collect())
+    }
+
+    /// Fetches the certificates of the queued range if we are not fetching already, and
+    /// requests the next range if none is pending or queued. The catch-up is over once there
+    /// is nothing left to request nor to fetch.
+    async fn advance_catch_up<'a>(&mut self) -> Vec<BoxFuture<'a, State>> {
+        let mut futures = Vec::new();
+        let state = match self.catch_up_state.as_mut() {
+            Some(state) => state,
+            None => return futures,
+        };
+
+        if !state.fetching {
+            if let Some(certificate_ids) = state.queued.take() {
+                state.fetching = true;
+                let block_ids: Vec<_> = certificate_ids.into_values().flatten().collect();
+                let (sender, mut receiver) = channel(block_ids.len().max(1));
+                if let Some(fut) = self
+                    .handle_synchronize_block_headers_command(block_ids, sender)
+                    .await
+                {
+                    futures.push(fut);
+                }
+                // The results are all in once the requests of all the block ids are resolved.
+                futures.push(
+                    async move {
+                        let mut results = Vec::new();
+                        while let Some(result) = receiver.recv().await {
+                            results.push(result);
+                        }
+                        State::CatchUpCertificatesSynchronized { results }
+                    }
+                    .boxed(),
+                );
+            }
+        }
+
+        let state = self.catch_up_state.as_mut().unwrap();
+        // Another range sync may be running, in which case we request the range once the
+        // certificates we fetch are in.
+        if !state.range_pending
+            && state.queued.is_none()
+            && state.next_range_start <= state.target
+            && !self.sync_range_state.running
+        {
+            state.range_pending = true;
+            let range_start = state.next_range_start;
+            let (sender, mut receiver) = channel(1);
+            futures.push(self.synchronize_range(range_start, sender).await);
+            futures.push(
+                async move {
+                    let certificate_ids = receiver.recv().await.unwrap_or_default();
+                    State::CatchUpRangeSynchronized { certificate_ids }
+                }
+                .boxed(),
+            );
+        }
+
+        // Unless we caught up, the next observed round resumes the catch-up.
+        let state = self.catch_up_state.as_ref().unwrap();
+        if !state.range_pending && !state.fetching && state.queued.is_none() {
+            info!(
+                "Catch-up ended at round {}",
+                state.next_range_start.saturating_sub(1)
+            );
+            self.catch_up_state = None;
+        }
+        futures
     }
 
     #[instrument(level = "trace", skip_all)]
@@ -468,11 +756,13 @@ impl BlockSynchronizer {
                 certificate_ids.len(),
                 self.range_request_max_rounds,
             );
+            self.reputation.record_invalid(&from);
             return;
         }
         for cert_ids in certificate_ids.values() {
             if cert_ids.len() > self.committee.size() {
                 trace!("dropping range sync request from {} with one round containing too digests: {} > {}", from, cert_ids.len(), self.committee.size());
+                self.reputation.record_invalid(&from);
                 return;
             }
         }
@@ -492,17 +782,23 @@ impl BlockSynchronizer {
         mut receiver: Receiver<(CertificateIDsByRounds, PublicKey)>,
         committee: Committee,
         range_synchronize_timeout: Duration,
+        primaries_sent_requests_to: Vec<PublicKey>,
+        reputation: PeerReputation<PublicKey>,
     ) -> State {
-        let total_expected_responses = committee.size() - 1;
+        let total_expected_responses = primaries_sent_requests_to.len();
         let mut num_of_responses: usize = 0;
+        let mut responded = Vec::new();
         let mut received_certificates_ids = BTreeMap::<(Round, CertificateDigest), Stake>::new();
 
+        let start = Instant::now();
         let timer = sleep(range_synchronize_timeout);
         tokio::pin!(timer);
 
         loop {
             tokio::select! {
                 Some((certificate_ids, from)) = receiver.recv() => {
+                    reputation.record_success(&from, start.elapsed());
+                    responded.push(from.clone());
                     let stake = committee.stake(&from);
                     for (round, cert_ids) in &certificate_ids {
                         for cert_id in cert_ids {
@@ -517,6 +813,7 @@ impl BlockSynchronizer {
                     }
                 },
                 () = &mut timer => {
+                    Self::record_timeouts(&reputation, &primaries_sent_requests_to, &responded);
                     break;
                 }
             }
@@ -631,6 +928,7 @@ impl BlockSynchronizer {
                 certificates_to_sync,
                 primaries,
                 receiver,
+                self.reputation.clone(),
             )
             .boxed(),
         )
@@ -699,6 +997,7 @@ impl BlockSynchronizer {
                 to_sync,
                 primaries,
                 receiver,
+                self.reputation.clone(),
             )
             .boxed(),
         )
@@ -819,18 +1118,23 @@ impl BlockSynchronizer {
         missing_payload_certs
     }
 
-    // Broadcasts a message to all the other primary nodes.
+    // Broadcasts a message to all the other primary nodes, except the banned ones.
     // It returns back the primary names to which we have sent the requests.
     #[instrument(level = "trace", skip_all)]
     async fn broadcast_batch_request(&mut self, message: PrimaryMessage) -> Vec<PublicKey> {
-        // Naively now just broadcast the request to all the primaries
-
-        let (keys, network_keys): (Vec<_>, Vec<_>) = self
-            .committee
-            .others_primaries(&self.name)
-            .into_iter()
-            .map(|(name, _address, network_key)| (name, network_key))
-            .unzip();
+        let primaries = self.committee.others_primaries(&self.name);
+        let keys = self
+            .reputation
+            .rank(primaries.iter().map(|(name, _, _)| name.clone()).collect());
+        let network_keys: Vec<_> = keys
+            .iter()
+            .filter_map(|name| {
+                primaries
+                    .iter()
+                    .find(|(x, _, _)| x == name)
+                    .map(|(_, _, network_key)| network_key.clone())
+            })
+            .collect();
 
         self.network
             .unreliable_broadcast(network_keys.clone(), &message)
@@ -854,24 +1158,26 @@ impl BlockSynchronizer {
         // those are uniquely distributed across the peers.
         peers.rebalance_values();
 
+        let mut futures = Vec::new();
         for peer in peers.peers().values() {
             self.send_synchronize_payload_requests(peer.clone().name, peer.assigned_values())
-                .await
-        }
+                .await;
 
-        peers
-            .unique_values()
-            .into_iter()
-            .map(|certificate| {
-                Self::wait_for_block_payload(
-                    self.payload_synchronize_timeout,
-                    request_id,
-                    self.payload_store.clone(),
-                    certificate,
-                )
-                .boxed()
-            })
-            .collect()
+            for certificate in peer.assigned_values() {
+                futures.push(
+                    Self::wait_for_block_payload(
+                        self.payload_synchronize_timeout,
+                        request_id,
+                        self.payload_store.clone(),
+                        certificate,
+                        peer.name.clone(),
+                        self.reputation.clone(),
+                    )
+                    .boxed(),
+                );
+            }
+        }
+        futures
     }
 
     /// This method sends the necessary requests to the worker nodes to
@@ -915,7 +1221,10 @@ impl BlockSynchronizer {
         request_id: RequestID,
         payload_store: Store<(BatchDigest, WorkerId), PayloadToken>,
         certificate: Certificate,
+        peer: PublicKey,
+        reputation: PeerReputation<PublicKey>,
     ) -> State {
+        let start = Instant::now();
         let futures = certificate
             .header
             .payload
@@ -931,6 +1240,7 @@ impl BlockSynchronizer {
                 .into_iter()
                 .any(|r| r.map_or_else(|_| true, |f| f.is_none()))
         {
+            reputation.record_timeout(&peer);
             return State::PayloadSynchronized {
                 request_id,
                 result: Err(SyncError::Timeout {
@@ -939,6 +1249,7 @@ impl BlockSynchronizer {
             };
         }
 
+        reputation.record_success(&peer, start.elapsed());
         State::PayloadSynchronized {
             request_id,
             result: Ok(BlockHeader {
@@ -994,11 +1305,14 @@ impl BlockSynchronizer {
         block_ids: Vec<CertificateDigest>,
         primaries_sent_requests_to: Vec<PublicKey>,
         mut receiver: Receiver<CertificatesResponse>,
+        reputation: PeerReputation<PublicKey>,
     ) -> State {
         let total_expected_certificates = block_ids.len();
         let mut num_of_responses: u32 = 0;
         let num_of_requests_sent: u32 = primaries_sent_requests_to.len() as u32;
+        let mut responded = Vec::new();
 
+        let start = Instant::now();
         let timer = sleep(fetch_certificates_timeout);
         tokio::pin!(timer);
 
@@ -1023,6 +1337,12 @@ impl BlockSynchronizer {
                         continue;
                     }
 
+                    // skip, we already got an invalid answer from this peer
+                    if responded.contains(&response.from) {
+                        continue;
+                    }
+                    responded.push(response.from.clone());
+
                     num_of_responses += 1;
 
                     match response.validate_certificates(&committee, worker_cache.clone()) {
@@ -1032,8 +1352,10 @@ impl BlockSynchronizer {
                             // we reject the payload - it shouldn't happen.
                             if certificates.iter().any(|c|!block_ids.contains(&c.digest())) {
                                 warn!("Will not process certificates, found at least one that we haven't asked for");
+                                reputation.record_invalid(&response.from);
                                 continue;
                             }
+                            reputation.record_success(&response.from, start.elapsed());
 
                             // add them as a new peer
                             peers.add_peer(response.from.clone(), certificates);
@@ -1053,10 +1375,12 @@ impl BlockSynchronizer {
                         },
                         Err(err) => {
                             warn!("Got invalid certificates from peer: {:?}", err);
+                            reputation.record_invalid(&response.from);
                         }
                     }
                 },
                 () = &mut timer => {
+                    Self::record_timeouts(&reputation, &primaries_sent_requests_to, &responded);
                     let result = Self::resolve_block_synchronize_result(&peers, block_ids, true);
 
                     return State::HeadersSynchronized {
@@ -1074,6 +1398,7 @@ impl BlockSynchronizer {
         certificates: Vec<Certificate>,
         primaries_sent_requests_to: Vec<PublicKey>,
         mut receiver: Receiver<PayloadAvailabilityResponse>,
+        reputation: PeerReputation<PublicKey>,
     ) -> State {
         let total_expected_block_ids = certificates.len();
         let mut num_of_responses: u32 = 0;
@@ -1087,6 +1412,7 @@ impl BlockSynchronizer {
             .map(|(id, _)| id.to_owned())
             .collect();
 
+        let start = Instant::now();
         let timer = sleep(fetch_certificates_timeout);
         tokio::pin!(timer);
 
@@ -1109,6 +1435,7 @@ impl BlockSynchronizer {
                     }
 
                     num_of_responses += 1;
+                    reputation.record_success(&response.from, start.elapsed());
 
                     // Ensure we got responses for the certificates we asked for.
                     // Even if we have found one certificate that doesn't match
@@ -1143,6 +1470,8 @@ impl BlockSynchronizer {
                     }
                 },
                 () = &mut timer => {
+                    let responded: Vec<_> = peers.peers().keys().cloned().collect();
+                    Self::record_timeouts(&reputation, &primaries_sent_requests_to, &responded);
                     let result = Self::resolve_block_synchronize_result(&peers, block_ids, true);
 
                     return State::PayloadAvailabilityReceived {
@@ -1202,6 +1531,19 @@ impl BlockSynchronizer {
         result
     }
 
+    // Lowers the score of the peers that did not respond to our request in time.
+    fn record_timeouts(
+        reputation: &PeerReputation<PublicKey>,
+        primaries_sent_requests_to: &[PublicKey],
+        responded: &[PublicKey],
+    ) {
+        for name in primaries_sent_requests_to {
+            if !responded.contains(name) {
+                reputation.record_timeout(name);
+            }
+        }
+    }
+
     fn reached_response_ratio(num_of_responses: u32, num_of_expected_responses: u32) -> bool {
         let ratio: f32 =
             ((num_of_responses as f32 / num_of_expected_responses as f32) * 100.0).round();
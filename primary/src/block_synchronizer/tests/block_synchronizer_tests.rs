@@ -7,7 +7,7 @@ use crate::{
         },
         BlockSynchronizer, CertificatesResponse, Command, PendingIdentifier, RequestID, SyncError,
     },
-    common::{create_db_stores, worker_listener},
+    common::{create_db_stores, create_test_peer_reputation, worker_listener},
     primary::PrimaryMessage,
     PrimaryWorkerMessage,
 };
@@ -17,7 +17,7 @@ use fastcrypto::Hash;
 use futures::{future::try_join_all, stream::FuturesUnordered};
 use network::P2pNetwork;
 use std::{
-    collections::{BTreeMap, HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     time::Duration,
 };
 use test_utils::{fixture_batch_with_transactions, CommitteeFixture, PrimaryToPrimaryMockServer};
@@ -83,9 +83,12 @@ async fn test_successful_range_synchronization() {
         rx_reconfigure,
         rx_commands,
         rx_availability_responses,
+        watch::channel(0).1,
+        test_utils::test_channel!(10).0,
         P2pNetwork::new(network.clone()),
         payload_store.clone(),
         certificate_store.clone(),
+        create_test_peer_reputation(),
         Parameters::default(),
     );
 
@@ -301,9 +304,12 @@ async fn test_successful_headers_synchronization() {
         rx_reconfigure,
         rx_commands,
         rx_availability_responses,
+        watch::channel(0).1,
+        test_utils::test_channel!(10).0,
         P2pNetwork::new(network.clone()),
         payload_store.clone(),
         certificate_store.clone(),
+        create_test_peer_reputation(),
         Parameters::default(),
     );
 
@@ -483,9 +489,12 @@ async fn test_successful_payload_synchronization() {
         rx_reconfigure,
         rx_commands,
         rx_availability_responses,
+        watch::channel(0).1,
+        test_utils::test_channel!(10).0,
         P2pNetwork::new(network.clone()),
         payload_store.clone(),
         certificate_store.clone(),
+        create_test_peer_reputation(),
         Parameters::default(),
     );
 
@@ -690,15 +699,20 @@ async fn test_multiple_overlapping_requests() {
         rx_reconfigure,
         rx_commands,
         rx_availability_responses,
+        rx_observed_round: watch::channel(0).1,
+        tx_core: test_utils::test_channel!(10).0,
         pending_requests: HashMap::new(),
         sync_range_state: Default::default(),
+        catch_up_state: None,
         map_certificate_responses_senders: HashMap::new(),
         map_payload_availability_responses_senders: HashMap::new(),
         network: P2pNetwork::new(network),
         payload_store,
         certificate_store,
+        reputation: create_test_peer_reputation(),
         range_request_max_rounds: 50,
         range_synchronize_timeout: Duration::from_secs(10),
+        catch_up_threshold: 50,
         certificates_synchronize_timeout: Duration::from_secs(1),
         payload_synchronize_timeout: Duration::from_secs(1),
         payload_availability_timeout: Duration::from_secs(1),
@@ -826,9 +840,12 @@ async fn test_timeout_while_waiting_for_certificates() {
         rx_reconfigure,
         rx_commands,
         rx_availability_responses,
+        watch::channel(0).1,
+        test_utils::test_channel!(10).0,
         P2pNetwork::new(network),
         payload_store.clone(),
         certificate_store.clone(),
+        create_test_peer_reputation(),
         params.clone(),
     );
 
@@ -914,15 +931,20 @@ async fn test_reply_with_certificates_already_in_storage() {
         rx_reconfigure,
         rx_commands,
         rx_availability_responses,
+        rx_observed_round: watch::channel(0).1,
+        tx_core: test_utils::test_channel!(10).0,
         pending_requests: Default::default(),
         sync_range_state: Default::default(),
+        catch_up_state: None,
         map_certificate_responses_senders: Default::default(),
         map_payload_availability_responses_senders: Default::default(),
         network: P2pNetwork::new(network),
         certificate_store: certificate_store.clone(),
         payload_store,
+        reputation: create_test_peer_reputation(),
         range_request_max_rounds: Default::default(),
         range_synchronize_timeout: Default::default(),
+        catch_up_threshold: Default::default(),
         certificates_synchronize_timeout: Default::default(),
         payload_synchronize_timeout: Default::default(),
         payload_availability_timeout: Default::default(),
@@ -1019,15 +1041,20 @@ async fn test_reply_with_payload_already_in_storage() {
         rx_reconfigure,
         rx_commands,
         rx_availability_responses,
+        rx_observed_round: watch::channel(0).1,
+        tx_core: test_utils::test_channel!(10).0,
         pending_requests: Default::default(),
         sync_range_state: Default::default(),
+        catch_up_state: None,
         map_certificate_responses_senders: Default::default(),
         map_payload_availability_responses_senders: Default::default(),
         network: P2pNetwork::new(network),
         certificate_store: certificate_store.clone(),
         payload_store: payload_store.clone(),
+        reputation: create_test_peer_reputation(),
         range_request_max_rounds: Default::default(),
         range_synchronize_timeout: Default::default(),
+        catch_up_threshold: Default::default(),
         certificates_synchronize_timeout: Default::default(),
         payload_synchronize_timeout: Default::default(),
         payload_availability_timeout: Default::default(),
@@ -1130,15 +1157,20 @@ async fn test_reply_with_payload_already_in_storage_for_own_certificates() {
         rx_reconfigure,
         rx_commands,
         rx_availability_responses,
+        rx_observed_round: watch::channel(0).1,
+        tx_core: test_utils::test_channel!(10).0,
         pending_requests: Default::default(),
         sync_range_state: Default::default(),
+        catch_up_state: None,
         map_certificate_responses_senders: Default::default(),
         map_payload_availability_responses_senders: Default::default(),
         network: P2pNetwork::new(network),
         certificate_store: certificate_store.clone(),
         payload_store: payload_store.clone(),
+        reputation: create_test_peer_reputation(),
         range_request_max_rounds: Default::default(),
         range_synchronize_timeout: Default::default(),
+        catch_up_threshold: Default::default(),
         certificates_synchronize_timeout: Default::default(),
         payload_synchronize_timeout: Default::default(),
         payload_availability_timeout: Default::default(),
@@ -1199,6 +1231,402 @@ async fn test_reply_with_payload_already_in_storage_for_own_certificates() {
 }
 
 #[must_use]
+#[tokio::test]
+async fn test_catch_up_when_far_behind() {
+    telemetry_subscribers::init_for_testing();
+    // GIVEN
+    let (_, certificate_store, payload_store) = create_db_stores();
+
+    let fixture = CommitteeFixture::builder().randomize_ports(true).build();
+    let committee = fixture.committee();
+    let worker_cache = fixture.shared_worker_cache();
+    let primary = fixture.authorities().nth(1).unwrap();
+    let name = primary.public_key();
+    let network_key = primary.network_keypair().copy().private().0.to_bytes();
+
+    let (_tx_reconfigure, rx_reconfigure) =
+        watch::channel(ReconfigureNotification::NewEpoch(committee.clone()));
+    let (_tx_commands, rx_commands) = test_utils::test_channel!(10);
+    let (tx_availability_responses, rx_availability_responses) = test_utils::test_channel!(10);
+    let (tx_observed_round, rx_observed_round) = watch::channel(0);
+    let (tx_core, mut rx_core) = test_utils::test_channel!(100);
+
+    // AND the certificates of rounds 1 ~ 10, of which we only hold rounds 1 ~ 2.
+    let mut certificates = HashMap::new();
+    let mut certificate_ids = BTreeMap::<Round, Vec<CertificateDigest>>::new();
+    let mut parents: BTreeSet<_> = Certificate::genesis(&committee)
+        .iter()
+        .map(|c| c.digest())
+        .collect();
+    for prior_round in 0..10 {
+        let (round, headers) = fixture.headers_round(prior_round, &parents);
+        parents.clear();
+        for header in headers {
+            let certificate = fixture.certificate(&header);
+            parents.insert(certificate.digest());
+            if round <= 2 {
+                certificate_store.write(certificate).unwrap();
+            } else {
+                certificate_ids
+                    .entry(round)
+                    .or_default()
+                    .push(certificate.digest());
+                certificates.insert(certificate.digest(), certificate);
+            }
+        }
+    }
+
+    let own_address = network::multiaddr_to_address(&committee.primary(&name).unwrap()).unwrap();
+    let network = anemo::Network::bind(own_address)
+        .server_name("narwhal")
+        .private_key(network_key)
+        .start(anemo::Router::new())
+        .unwrap();
+
+    for (_pubkey, address, network_pubkey) in committee.others_primaries(&name) {
+        let peer_id = PeerId(network_pubkey.0.to_bytes());
+        let address = network::multiaddr_to_address(&address).unwrap();
+        let peer_info = PeerInfo {
+            peer_id,
+            affinity: anemo::types::PeerAffinity::High,
+            address: vec![address],
+        };
+        network.known_peers().insert(peer_info);
+    }
+
+    // AND create the synchronizer, catching up when more than 5 rounds behind
+    let params = Parameters {
+        block_synchronizer: BlockSynchronizerParameters {
+            catch_up_threshold: 5,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let _synchronizer_handle = BlockSynchronizer::spawn(
+        name.clone(),
+        committee.clone(),
+        worker_cache.clone(),
+        rx_reconfigure,
+        rx_commands,
+        rx_availability_responses,
+        rx_observed_round,
+        tx_core,
+        P2pNetwork::new(network.clone()),
+        payload_store.clone(),
+        certificate_store.clone(),
+        create_test_peer_reputation(),
+        params,
+    );
+
+    // AND the other primaries
+    let mut peers: Vec<_> = fixture
+        .authorities()
+        .filter(|a| a.public_key() != name)
+        .map(|a| {
+            let address = committee.primary(&a.public_key()).unwrap();
+            let (receiver, network) =
+                PrimaryToPrimaryMockServer::spawn(a.network_keypair().copy(), address);
+            (a.public_key(), receiver, network)
+        })
+        .collect();
+
+    // Wait for connectivity
+    let (mut events, mut connected) = network.subscribe();
+    while connected.len() != committee.size() - 1 {
+        let event = events.recv().await.unwrap();
+        match event {
+            anemo::types::PeerEvent::NewPeer(peer_id) => connected.push(peer_id),
+            anemo::types::PeerEvent::LostPeer(_, _) => {
+                panic!("we shouldn't see any lost peer events")
+            }
+        }
+    }
+
+    // WHEN we wait for the ancestors of a certificate of round 10
+    tx_observed_round.send(10).unwrap();
+
+    // THEN the primaries are asked for the range of rounds we miss
+    for (peer, receiver, _) in peers.iter_mut() {
+        match timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .unwrap()
+            .unwrap()
+        {
+            PrimaryMessage::CertificatesRangeRequest {
+                range_start,
+                max_rounds,
+                ..
+            } => {
+                assert_eq!(range_start, 3, "Start of requested range is incorrect");
+                assert_eq!(max_rounds, 50, "Max rounds is incorrect");
+            }
+            message => panic!("Unexpected request {message:?}"),
+        }
+        tx_availability_responses
+            .send(AvailabilityResponse::CertificateDigest(
+                CertificateDigestsResponse {
+                    certificate_ids: certificate_ids.clone(),
+                    from: peer.clone(),
+                },
+            ))
+            .await
+            .unwrap();
+    }
+
+    // AND for the certificates of the range
+    for (peer, receiver, _) in peers.iter_mut() {
+        match timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .unwrap()
+            .unwrap()
+        {
+            PrimaryMessage::CertificatesBatchRequest {
+                certificate_ids, ..
+            } => {
+                assert_eq!(certificate_ids.len(), certificates.len());
+                tx_availability_responses
+                    .send(AvailabilityResponse::Certificate(CertificatesResponse {
+                        certificates: certificate_ids
+                            .into_iter()
+                            .map(|id| (id, certificates.get(&id).cloned()))
+                            .collect(),
+                        from: peer.clone(),
+                    }))
+                    .await
+                    .unwrap();
+            }
+            message => panic!("Unexpected request {message:?}"),
+        }
+    }
+
+    // AND the certificates are handed over to the core in causal order
+    let mut rounds = Vec::new();
+    for _ in 0..certificates.len() {
+        match timeout(Duration::from_secs(5), rx_core.recv())
+            .await
+            .unwrap()
+            .unwrap()
+        {
+            PrimaryMessage::Certificate(certificate) => rounds.push(certificate.round()),
+            message => panic!("Unexpected message {message:?}"),
+        }
+    }
+    assert!(rounds.windows(2).all(|w| w[0] <= w[1]));
+
+    // AND they are all stored
+    let stored = certificate_store
+        .read_all(certificates.keys().copied())
+        .unwrap();
+    assert!(stored.iter().all(Option::is_some));
+    assert_eq!(certificate_store.last_round_number(), Some(10));
+}
+
+#[must_use]
+#[tokio::test]
+async fn test_catch_up_with_missing_certificate() {
+    telemetry_subscribers::init_for_testing();
+    // GIVEN
+    let (_, certificate_store, payload_store) = create_db_stores();
+
+    let fixture = CommitteeFixture::builder().randomize_ports(true).build();
+    let committee = fixture.committee();
+    let worker_cache = fixture.shared_worker_cache();
+    let primary = fixture.authorities().nth(1).unwrap();
+    let name = primary.public_key();
+    let network_key = primary.network_keypair().copy().private().0.to_bytes();
+
+    let (_tx_reconfigure, rx_reconfigure) =
+        watch::channel(ReconfigureNotification::NewEpoch(committee.clone()));
+    let (_tx_commands, rx_commands) = test_utils::test_channel!(10);
+    let (tx_availability_responses, rx_availability_responses) = test_utils::test_channel!(10);
+    let (tx_observed_round, rx_observed_round) = watch::channel(0);
+    let (tx_core, mut rx_core) = test_utils::test_channel!(100);
+
+    // AND the certificates of rounds 1 ~ 10, of which we only hold rounds 1 ~ 2.
+    let mut certificates = HashMap::new();
+    let mut certificate_ids = BTreeMap::<Round, Vec<CertificateDigest>>::new();
+    let mut parents: BTreeSet<_> = Certificate::genesis(&committee)
+        .iter()
+        .map(|c| c.digest())
+        .collect();
+    for prior_round in 0..10 {
+        let (round, headers) = fixture.headers_round(prior_round, &parents);
+        parents.clear();
+        for header in headers {
+            let certificate = fixture.certificate(&header);
+            parents.insert(certificate.digest());
+            if round <= 2 {
+                certificate_store.write(certificate).unwrap();
+            } else {
+                certificate_ids
+                    .entry(round)
+                    .or_default()
+                    .push(certificate.digest());
+                certificates.insert(certificate.digest(), certificate);
+            }
+        }
+    }
+
+    // AND a certificate of round 5 that none of the other primaries can give us.
+    let missing = certificate_ids[&5][0];
+
+    let own_address = network::multiaddr_to_address(&committee.primary(&name).unwrap()).unwrap();
+    let network = anemo::Network::bind(own_address)
+        .server_name("narwhal")
+        .private_key(network_key)
+        .start(anemo::Router::new())
+        .unwrap();
+
+    for (_pubkey, address, network_pubkey) in committee.others_primaries(&name) {
+        let peer_id = PeerId(network_pubkey.0.to_bytes());
+        let address = network::multiaddr_to_address(&address).unwrap();
+        let peer_info = PeerInfo {
+            peer_id,
+            affinity: anemo::types::PeerAffinity::High,
+            address: vec![address],
+        };
+        network.known_peers().insert(peer_info);
+    }
+
+    // AND create the synchronizer, catching up when more than 5 rounds behind
+    let params = Parameters {
+        block_synchronizer: BlockSynchronizerParameters {
+            catch_up_threshold: 5,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let _synchronizer_handle = BlockSynchronizer::spawn(
+        name.clone(),
+        committee.clone(),
+        worker_cache.clone(),
+        rx_reconfigure,
+        rx_commands,
+        rx_availability_responses,
+        rx_observed_round,
+        tx_core,
+        P2pNetwork::new(network.clone()),
+        payload_store.clone(),
+        certificate_store.clone(),
+        create_test_peer_reputation(),
+        params,
+    );
+
+    // AND the other primaries
+    let mut peers: Vec<_> = fixture
+        .authorities()
+        .filter(|a| a.public_key() != name)
+        .map(|a| {
+            let address = committee.primary(&a.public_key()).unwrap();
+            let (receiver, network) =
+                PrimaryToPrimaryMockServer::spawn(a.network_keypair().copy(), address);
+            (a.public_key(), receiver, network)
+        })
+        .collect();
+
+    // Wait for connectivity
+    let (mut events, mut connected) = network.subscribe();
+    while connected.len() != committee.size() - 1 {
+        let event = events.recv().await.unwrap();
+        match event {
+            anemo::types::PeerEvent::NewPeer(peer_id) => connected.push(peer_id),
+            anemo::types::PeerEvent::LostPeer(_, _) => {
+                panic!("we shouldn't see any lost peer events")
+            }
+        }
+    }
+
+    // WHEN we wait for the ancestors of a certificate of round 10
+    tx_observed_round.send(10).unwrap();
+
+    // THEN the primaries are asked for the range of rounds we miss
+    for (peer, receiver, _) in peers.iter_mut() {
+        match timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .unwrap()
+            .unwrap()
+        {
+            PrimaryMessage::CertificatesRangeRequest {
+                range_start,
+                max_rounds,
+                ..
+            } => {
+                assert_eq!(range_start, 3, "Start of requested range is incorrect");
+                assert_eq!(max_rounds, 50, "Max rounds is incorrect");
+            }
+            message => panic!("Unexpected request {message:?}"),
+        }
+        tx_availability_responses
+            .send(AvailabilityResponse::CertificateDigest(
+                CertificateDigestsResponse {
+                    certificate_ids: certificate_ids.clone(),
+                    from: peer.clone(),
+                },
+            ))
+            .await
+            .unwrap();
+    }
+
+    // AND for the certificates of the range
+    for (peer, receiver, _) in peers.iter_mut() {
+        match timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .unwrap()
+            .unwrap()
+        {
+            PrimaryMessage::CertificatesBatchRequest {
+                certificate_ids, ..
+            } => {
+                assert_eq!(certificate_ids.len(), certificates.len());
+                tx_availability_responses
+                    .send(AvailabilityResponse::Certificate(CertificatesResponse {
+                        certificates: certificate_ids
+                            .into_iter()
+                            .map(|id| {
+                                let certificate = certificates.get(&id).filter(|_| id != missing);
+                                (id, certificate.cloned())
+                            })
+                            .collect(),
+                        from: peer.clone(),
+                    }))
+                    .await
+                    .unwrap();
+            }
+            message => panic!("Unexpected request {message:?}"),
+        }
+    }
+
+    // AND the certificates we got are handed over to the core in causal order
+    let mut rounds = Vec::new();
+    for _ in 0..certificates.len() - 1 {
+        match timeout(Duration::from_secs(5), rx_core.recv())
+            .await
+            .unwrap()
+            .unwrap()
+        {
+            PrimaryMessage::Certificate(certificate) => {
+                assert_ne!(certificate.digest(), missing);
+                rounds.push(certificate.round());
+            }
+            message => panic!("Unexpected message {message:?}"),
+        }
+    }
+    assert!(rounds.windows(2).all(|w| w[0] <= w[1]));
+
+    // AND only the certificates whose ancestry is complete are stored: the certificates
+    // after the missing one are left to the core.
+    for (digest, certificate) in &certificates {
+        let stored = certificate_store.read(*digest).unwrap();
+        assert_eq!(
+            stored.is_some(),
+            certificate.round() <= 5 && *digest != missing,
+            "Unexpected storage of certificate of round {}",
+            certificate.round()
+        );
+    }
+    assert_eq!(certificate_store.last_round_number(), Some(5));
+}
+
 fn primary_listener(
     num_of_expected_responses: i32,
     network_keypair: NetworkKeyPair,
@@ -47,6 +47,9 @@ pub struct CertificateWaiter {
     rx_synchronizer: Receiver<Certificate>,
     /// Loops back to the core certificates for which we got all parents.
     tx_core: Sender<Certificate>,
+    /// Publishes the highest round of the certificates we wait the ancestors of, so that the
+    /// `BlockSynchronizer` catches up in bulk when we fall far behind.
+    tx_observed_round: watch::Sender<Round>,
     /// List of digests (certificates) that are waiting to be processed. Their processing will
     /// resume when we get all their dependencies. The map holds a cancellation `Sender`
     /// which we can use to give up on a certificate.
@@ -72,6 +75,7 @@ impl CertificateWaiter {
         rx_reconfigure: watch::Receiver<ReconfigureNotification>,
         rx_synchronizer: Receiver<Certificate>,
         tx_core: Sender<Certificate>,
+        tx_observed_round: watch::Sender<Round>,
         metrics: Arc<PrimaryMetrics>,
     ) -> JoinHandle<()> {
         tokio::spawn(async move {
@@ -83,6 +87,7 @@ impl CertificateWaiter {
                 rx_reconfigure,
                 rx_synchronizer,
                 tx_core,
+                tx_observed_round,
                 pending: DashMap::new(),
                 metrics,
             }
@@ -134,6 +139,15 @@ impl CertificateWaiter {
                         continue;
                     }
 
+                    let round = certificate.round();
+                    self.tx_observed_round.send_if_modified(|observed| {
+                        let higher = round > *observed;
+                        if higher {
+                            *observed = round;
+                        }
+                        higher
+                    });
+
                     // Add the certificate to the waiter pool. The waiter will return it to us when
                     // all its parents are in the store.
                     let wait_for = certificate.header.parents.iter().cloned().collect();
@@ -162,6 +176,8 @@ impl CertificateWaiter {
                         ReconfigureNotification::NewEpoch(committee) => {
                             self.committee = committee;
                             self.pending.clear();
+                            // Rounds start over with the new epoch.
+                            self.tx_observed_round.send_replace(0);
                         },
                         ReconfigureNotification::UpdateCommittee(committee) => {
                             self.committee = committee;
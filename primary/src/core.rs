@@ -4,16 +4,16 @@
 use crate::{
     aggregators::{CertificatesAggregator, VotesAggregator},
     metrics::PrimaryMetrics,
-    primary::PrimaryMessage,
+    primary::{PrimaryMessage, PrimaryWorkerMessage},
     synchronizer::Synchronizer,
 };
 use async_recursion::async_recursion;
-use config::{Committee, Epoch, SharedWorkerCache};
+use config::{Committee, Epoch, HeaderLimitsParameters, SharedWorkerCache, WorkerId};
 use crypto::{PublicKey, Signature};
 use fastcrypto::{Hash as _, SignatureService};
-use network::{CancelOnDropHandler, P2pNetwork, ReliableNetwork};
+use network::{CancelOnDropHandler, P2pNetwork, ReliableNetwork, UnreliableNetwork};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{hash_map::Entry, HashMap, HashSet},
     sync::Arc,
     time::Instant,
 };
@@ -26,7 +26,8 @@ use types::{
     ensure,
     error::{DagError, DagResult},
     metered_channel::{Receiver, Sender},
-    Certificate, Header, HeaderDigest, ReconfigureNotification, Round, RoundVoteDigestPair, Vote,
+    BatchDigest, Certificate, Equivocation, EquivocationDigest, Header, HeaderDigest,
+    ReconfigureNotification, Round, RoundVoteDigestPair, Vote,
 };
 
 #[cfg(test)]
@@ -52,6 +53,8 @@ pub struct Core {
     rx_consensus_round_updates: watch::Receiver<u64>,
     /// The depth of the garbage collector.
     gc_depth: Round,
+    /// The maximum payload entries, parents and size of the headers we accept.
+    header_limits: HeaderLimitsParameters,
 
     /// Watch channel to reconfigure the committee.
     rx_reconfigure: watch::Receiver<ReconfigureNotification>,
@@ -76,6 +79,14 @@ pub struct Core {
     current_header: Header,
     /// The store to persist the last voted round per authority, used to ensure idempotence.
     vote_digest_store: Store<PublicKey, RoundVoteDigestPair>,
+    /// The store to persist the proofs of equivocation we detect.
+    equivocation_store: Store<EquivocationDigest, Equivocation>,
+    /// The first valid header we received from each authority at each round, to detect
+    /// conflicting headers.
+    seen_headers: HashMap<Round, HashMap<PublicKey, Header>>,
+    /// The first valid vote we received from each authority on our current round, to detect
+    /// conflicting votes.
+    seen_votes: HashMap<PublicKey, Vote>,
     /// Aggregates votes into a certificate.
     votes_aggregator: VotesAggregator,
     /// Aggregates certificates to use as parents for new headers.
@@ -98,10 +109,12 @@ impl Core {
         header_store: Store<HeaderDigest, Header>,
         certificate_store: CertificateStore,
         vote_digest_store: Store<PublicKey, RoundVoteDigestPair>,
+        equivocation_store: Store<EquivocationDigest, Equivocation>,
         synchronizer: Synchronizer,
         signature_service: SignatureService<Signature>,
         rx_consensus_round_updates: watch::Receiver<u64>,
         gc_depth: Round,
+        header_limits: HeaderLimitsParameters,
         rx_committee: watch::Receiver<ReconfigureNotification>,
         rx_primaries: Receiver<PrimaryMessage>,
         rx_header_waiter: Receiver<Header>,
@@ -123,6 +136,7 @@ impl Core {
                 signature_service,
                 rx_consensus_round_updates,
                 gc_depth,
+                header_limits,
                 rx_reconfigure: rx_committee,
                 rx_primaries,
                 rx_header_waiter,
@@ -134,6 +148,9 @@ impl Core {
                 processing: HashMap::with_capacity(2 * gc_depth as usize),
                 current_header: Header::default(),
                 vote_digest_store,
+                equivocation_store,
+                seen_headers: HashMap::with_capacity(2 * gc_depth as usize),
+                seen_votes: HashMap::new(),
                 votes_aggregator: VotesAggregator::new(),
                 certificates_aggregators: HashMap::with_capacity(2 * gc_depth as usize),
                 network: primary_network,
@@ -158,6 +175,7 @@ impl Core {
         // Reset the votes aggregator.
         self.current_header = header.clone();
         self.votes_aggregator = VotesAggregator::new();
+        self.seen_votes.clear();
 
         // Broadcast the new header in a reliable manner.
         let peers = self
@@ -385,6 +403,26 @@ impl Core {
                 .with_label_values(&[&certificate.epoch().to_string()])
                 .inc();
 
+            // Let our workers know their batches are certified (so they can notify their clients).
+            let mut batches: HashMap<WorkerId, Vec<BatchDigest>> = HashMap::new();
+            for (digest, worker_id) in &certificate.header.payload {
+                batches.entry(*worker_id).or_default().push(*digest);
+            }
+            for (worker_id, digests) in batches {
+                // The worker may have been removed since it stored the batches.
+                let worker_name = match self.worker_cache.load().worker(&self.name, &worker_id) {
+                    Ok(worker) => worker.name,
+                    Err(e) => {
+                        warn!(
+                            "Cannot notify worker {worker_id} that its batches are certified: {e}"
+                        );
+                        continue;
+                    }
+                };
+                let message = PrimaryWorkerMessage::Certified(digests, certificate.digest());
+                self.network.unreliable_send(worker_name, &message).await;
+            }
+
             // Process the new certificate.
             match self.process_certificate(certificate).await {
                 Ok(()) => (),
@@ -510,9 +548,21 @@ impl Core {
             DagError::TooOld(header.id.into(), header.round, self.gc_round)
         );
 
+        // Reject oversized headers before doing any expensive work on them.
+        if let Err(limit) = header.check_limits(&self.header_limits) {
+            self.metrics
+                .headers_exceeding_limits
+                .with_label_values(&[&self.committee.epoch().to_string(), limit.as_str()])
+                .inc();
+            return Err(DagError::HeaderLimitExceeded(header.id, limit));
+        }
+
         // Verify the header's signature.
         header.verify(&self.committee, self.worker_cache.clone())?;
 
+        // Keep a proof if its author already sent us a different header for this round.
+        self.record_header(header).await?;
+
         // TODO [issue #672]: Prevent bad nodes from sending junk headers with high round numbers.
 
         Ok(())
@@ -534,16 +584,24 @@ impl Core {
             DagError::VoteTooOld(vote.digest().into(), vote.round, self.current_header.round)
         );
 
-        // Ensure we receive a vote on the expected header.
+        // Ensure we receive a vote on a header of our current round.
         ensure!(
-            vote.id == self.current_header.id
-                && vote.origin == self.current_header.author
-                && vote.round == self.current_header.round,
+            vote.origin == self.current_header.author && vote.round == self.current_header.round,
             DagError::UnexpectedVote(vote.id)
         );
 
         // Verify the vote.
-        vote.verify(&self.committee).map_err(DagError::from)
+        vote.verify(&self.committee)?;
+
+        // Keep a proof if its author already voted for a different header of ours at this round.
+        self.record_vote(vote).await?;
+
+        // Ensure we receive a vote on the expected header.
+        ensure!(
+            vote.id == self.current_header.id,
+            DagError::UnexpectedVote(vote.id)
+        );
+        Ok(())
     }
 
     async fn sanitize_certificate(&mut self, certificate: &Certificate) -> DagResult<()> {
@@ -567,9 +625,78 @@ impl Core {
         );
 
         // Verify the certificate (and the embedded header).
-        certificate
-            .verify(&self.committee, self.worker_cache.clone())
-            .map_err(DagError::from)
+        certificate.verify(&self.committee, self.worker_cache.clone())?;
+
+        // Keep a proof if the author of the embedded header already sent us a different header.
+        self.record_header(&certificate.header).await
+    }
+
+    /// Remembers the first valid header of each authority at each round, and records an
+    /// equivocation if the authority sent a different one.
+    async fn record_header(&mut self, header: &Header) -> DagResult<()> {
+        let first = match self
+            .seen_headers
+            .entry(header.round)
+            .or_insert_with(HashMap::new)
+            .entry(header.author.clone())
+        {
+            Entry::Occupied(entry) if entry.get().id != header.id => entry.get().clone(),
+            Entry::Occupied(_) => return Ok(()),
+            Entry::Vacant(entry) => {
+                entry.insert(header.clone());
+                return Ok(());
+            }
+        };
+        self.record_equivocation(Equivocation::Headers(first, header.clone()))
+            .await
+    }
+
+    /// Remembers the first valid vote of each authority on our current round, and records an
+    /// equivocation if the authority voted for a different header.
+    async fn record_vote(&mut self, vote: &Vote) -> DagResult<()> {
+        let first = match self.seen_votes.entry(vote.author.clone()) {
+            Entry::Occupied(entry) if entry.get().id != vote.id => entry.get().clone(),
+            Entry::Occupied(_) => return Ok(()),
+            Entry::Vacant(entry) => {
+                entry.insert(vote.clone());
+                return Ok(());
+            }
+        };
+        self.record_equivocation(Equivocation::Votes(first, vote.clone()))
+            .await
+    }
+
+    /// Persists a proof of equivocation, unless we already recorded one for the same author, round
+    /// and kind of message.
+    async fn record_equivocation(&mut self, equivocation: Equivocation) -> DagResult<()> {
+        let digest = equivocation.digest();
+        if self
+            .equivocation_store
+            .read(digest)
+            .await
+            .map_err(StoreError)?
+            .is_some()
+        {
+            return Ok(());
+        }
+
+        warn!(
+            "Authority {} equivocated at round {}: {:?}",
+            equivocation.offender(),
+            equivocation.round(),
+            equivocation
+        );
+        let kind = match equivocation {
+            Equivocation::Headers(..) => "headers",
+            Equivocation::Votes(..) => "votes",
+        };
+        self.metrics
+            .equivocations_detected
+            .with_label_values(&[&equivocation.epoch().to_string(), kind])
+            .inc();
+
+        self.equivocation_store.write(digest, equivocation).await;
+        Ok(())
     }
 
     /// If a new committee is available, update our internal state.
@@ -600,6 +727,8 @@ impl Core {
             error!("Error in change epoch when clearing vote store {}", e);
         }
         self.processing.clear();
+        self.seen_headers.clear();
+        self.seen_votes.clear();
         self.certificates_aggregators.clear();
         self.cancel_handlers.clear();
 
@@ -681,6 +810,7 @@ impl Core {
 
                         let gc_round = round - self.gc_depth;
                         self.processing.retain(|k, _| k > &gc_round);
+                        self.seen_headers.retain(|k, _| k > &gc_round);
                         self.certificates_aggregators.retain(|k, _| k > &gc_round);
                         self.cancel_handlers.retain(|k, _| k > &gc_round);
                         self.gc_round = gc_round;
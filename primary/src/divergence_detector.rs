@@ -0,0 +1,307 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use crate::metrics::PrimaryMetrics;
+use config::{Epoch, SharedCommittee, Stake};
+use crypto::{PublicKey, Signature};
+use fastcrypto::SignatureService;
+use network::{CancelOnDropHandler, P2pNetwork, ReliableNetwork};
+use std::{collections::BTreeMap, sync::Arc};
+use store::Store;
+use tokio::{sync::watch, task::JoinHandle};
+use tracing::{debug, error, info, warn};
+use types::{
+    error::DagResult, metered_channel::Receiver, PrimaryMessage, ReconfigureNotification,
+    SequenceNumber, StateCommitment, StateDigest, StateDivergence,
+};
+
+#[cfg(test)]
+#[path = "tests/divergence_detector_tests.rs"]
+mod divergence_detector_tests;
+
+/// What we know of the execution state at a consensus index.
+#[derive(Default)]
+struct Checkpoint {
+    /// Our digest of the execution state, once we executed the certificate of the index.
+    ours: Option<StateDigest>,
+    /// The first commitment of every other authority.
+    commitments: BTreeMap<PublicKey, StateCommitment>,
+    /// The pending messages propagating our commitment.
+    pending: Vec<CancelOnDropHandler<anyhow::Result<anemo::Response<()>>>>,
+    /// Whether our divergence at this index was already reported.
+    reported: bool,
+}
+
+/// Compares our execution state with the one of the other authorities. It signs and
+/// broadcasts the digests of the execution state committed to by the executor, and collects the
+/// commitments broadcast by the other primaries. When a quorum of the committee commits to
+/// another digest than ours at the same consensus index, our execution diverged from the rest
+/// of the committee: the divergence is logged, counted by the `state_divergences` metric and
+/// persisted, so that the operator can query it over gRPC.
+///
+/// The commitments are kept for the consensus indices within `max_pending` commitment intervals
+/// of our execution only, at most `max_pending` of them per authority ahead of our execution,
+/// and dropped at the end of every epoch.
+pub struct DivergenceDetector {
+    /// The public key of this authority.
+    name: PublicKey,
+    /// The committee information.
+    committee: SharedCommittee,
+    /// Service to sign our commitments.
+    signature_service: SignatureService<Signature>,
+    /// Watch channel to reconfigure the committee.
+    rx_reconfigure: watch::Receiver<ReconfigureNotification>,
+    /// Receives the digests of our execution state from the executor.
+    rx_state_digests: Receiver<(Epoch, SequenceNumber, StateDigest)>,
+    /// Receives the commitments of the other primaries.
+    rx_commitments: Receiver<StateCommitment>,
+    /// A network sender to broadcast our commitments.
+    network: P2pNetwork,
+    /// The store of the divergences detected.
+    divergence_store: Store<(Epoch, SequenceNumber), StateDivergence>,
+    /// The number of sequenced certificates between two commitments.
+    interval: u64,
+    /// The number of commitment intervals around our execution for which commitments are kept.
+    max_pending: usize,
+    /// The highest consensus index we committed to.
+    executed: SequenceNumber,
+    /// What we know of the execution state at the last consensus indices.
+    checkpoints: BTreeMap<SequenceNumber, Checkpoint>,
+    /// The metrics handler
+    metrics: Arc<PrimaryMetrics>,
+}
+
+impl DivergenceDetector {
+    #[must_use]
+    pub fn spawn(
+        name: PublicKey,
+        committee: SharedCommittee,
+        signature_service: SignatureService<Signature>,
+        rx_reconfigure: watch::Receiver<ReconfigureNotification>,
+        rx_state_digests: Receiver<(Epoch, SequenceNumber, StateDigest)>,
+        rx_commitments: Receiver<StateCommitment>,
+        network: P2pNetwork,
+        divergence_store: Store<(Epoch, SequenceNumber), StateDivergence>,
+        interval: u64,
+        max_pending: usize,
+        metrics: Arc<PrimaryMetrics>,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            Self {
+                name,
+                committee,
+                signature_service,
+                rx_reconfigure,
+                rx_state_digests,
+                rx_commitments,
+                network,
+                divergence_store,
+                interval,
+                max_pending,
+                executed: 0,
+                checkpoints: BTreeMap::new(),
+                metrics,
+            }
+            .run()
+            .await;
+        })
+    }
+
+    /// Signs and broadcasts the digest of our execution state, then compares it with the
+    /// commitments of the other authorities.
+    async fn handle_state_digest(
+        &mut self,
+        epoch: Epoch,
+        consensus_index: SequenceNumber,
+        state: StateDigest,
+    ) {
+        // The commitments of a past epoch are rejected by the other authorities.
+        if epoch != self.committee.load().epoch() {
+            debug!("Dropping state digest of epoch {epoch} at index {consensus_index}");
+            return;
+        }
+
+        let commitment = StateCommitment::new(
+            self.name.clone(),
+            epoch,
+            consensus_index,
+            state,
+            &mut self.signature_service,
+        )
+        .await;
+        debug!("Broadcasting {commitment:?}");
+
+        let addresses = self
+            .committee
+            .load()
+            .others_primaries(&self.name)
+            .into_iter()
+            .map(|(_, _, network_key)| network_key)
+            .collect();
+        let message = PrimaryMessage::StateCommitment(commitment);
+        let handlers = self.network.broadcast(addresses, &message).await;
+
+        self.executed = self.executed.max(consensus_index);
+        let checkpoint = self.checkpoints.entry(consensus_index).or_default();
+        checkpoint.ours = Some(state);
+        checkpoint.pending = handlers;
+
+        self.metrics
+            .state_commitments
+            .with_label_values(&[&epoch.to_string(), "own"])
+            .inc();
+        self.check(consensus_index).await;
+        self.trim();
+    }
+
+    async fn handle_commitment(&mut self, commitment: StateCommitment) -> DagResult<()> {
+        commitment.verify(&self.committee.load())?;
+
+        // Ignore our own commitments, and keep the first commitment of every authority.
+        if commitment.author == self.name {
+            return Ok(());
+        }
+        let consensus_index = commitment.consensus_index;
+        let epoch = commitment.epoch;
+        if !self.is_pending(&commitment) {
+            debug!(
+                "Ignoring {commitment:?}: our execution is at index {}",
+                self.executed
+            );
+            return Ok(());
+        }
+        let checkpoint = self.checkpoints.entry(consensus_index).or_default();
+        if checkpoint.commitments.contains_key(&commitment.author) {
+            debug!("Ignoring duplicate {commitment:?}");
+            return Ok(());
+        }
+        checkpoint
+            .commitments
+            .insert(commitment.author.clone(), commitment);
+
+        self.metrics
+            .state_commitments
+            .with_label_values(&[&epoch.to_string(), "other"])
+            .inc();
+        self.check(consensus_index).await;
+        self.trim();
+        Ok(())
+    }
+
+    /// Reports our divergence at the consensus index, if a quorum of the committee committed
+    /// to another digest than ours.
+    async fn check(&mut self, consensus_index: SequenceNumber) {
+        let divergence = match self.find_divergence(consensus_index) {
+            Some(divergence) => divergence,
+            None => return,
+        };
+
+        error!(
+            "Execution state diverged at consensus index {consensus_index}: our digest is {} but a quorum committed to {}",
+            divergence.ours, divergence.quorum[0].state
+        );
+        self.metrics
+            .state_divergences
+            .with_label_values(&[&divergence.epoch.to_string()])
+            .inc();
+        self.divergence_store
+            .write((divergence.epoch, consensus_index), divergence)
+            .await;
+    }
+
+    fn find_divergence(&mut self, consensus_index: SequenceNumber) -> Option<StateDivergence> {
+        let committee = self.committee.load();
+        let checkpoint = self.checkpoints.get_mut(&consensus_index)?;
+        let ours = match checkpoint.ours {
+            Some(ours) if !checkpoint.reported => ours,
+            _ => return None,
+        };
+
+        let mut stakes: BTreeMap<StateDigest, Stake> = BTreeMap::new();
+        for commitment in checkpoint.commitments.values() {
+            *stakes.entry(commitment.state).or_default() += committee.stake(&commitment.author);
+        }
+        let (state, _) = stakes
+            .into_iter()
+            .find(|(state, stake)| *state != ours && *stake >= committee.quorum_threshold())?;
+
+        checkpoint.reported = true;
+        Some(StateDivergence {
+            epoch: committee.epoch(),
+            consensus_index,
+            ours,
+            quorum: checkpoint
+                .commitments
+                .values()
+                .filter(|commitment| commitment.state == state)
+                .cloned()
+                .collect(),
+        })
+    }
+
+    /// The number of consensus indices around our execution for which commitments are kept.
+    fn window(&self) -> SequenceNumber {
+        self.interval
+            .saturating_mul(self.max_pending as SequenceNumber)
+    }
+
+    /// Whether to keep a commitment: its consensus index must be within the window around our
+    /// execution, and its author must not be more than `max_pending` commitments ahead of us.
+    fn is_pending(&self, commitment: &StateCommitment) -> bool {
+        let consensus_index = commitment.consensus_index;
+        if consensus_index.saturating_add(self.window()) < self.executed {
+            return false;
+        }
+        if consensus_index <= self.executed {
+            return true;
+        }
+        if consensus_index - self.executed > self.window() {
+            return false;
+        }
+        let ahead = self
+            .checkpoints
+            .range(self.executed + 1..)
+            .filter(|(_, checkpoint)| checkpoint.commitments.contains_key(&commitment.author))
+            .count();
+        ahead < self.max_pending
+    }
+
+    /// Forgets the consensus indices behind the window around our execution.
+    fn trim(&mut self) {
+        let oldest = self.executed.saturating_sub(self.window());
+        self.checkpoints = self.checkpoints.split_off(&oldest);
+    }
+
+    async fn run(&mut self) {
+        info!(
+            "DivergenceDetector on node {} has started successfully.",
+            self.name
+        );
+        loop {
+            tokio::select! {
+                Some((epoch, consensus_index, state)) = self.rx_state_digests.recv() => {
+                    self.handle_state_digest(epoch, consensus_index, state).await;
+                },
+
+                Some(commitment) = self.rx_commitments.recv() => {
+                    if let Err(e) = self.handle_commitment(commitment).await {
+                        warn!("Invalid state commitment: {e}");
+                    }
+                },
+
+                result = self.rx_reconfigure.changed() => {
+                    result.expect("Committee channel dropped");
+                    let message = self.rx_reconfigure.borrow().clone();
+                    match message {
+                        ReconfigureNotification::NewEpoch(_) => {
+                            // The commitments of the previous epoch can no longer be compared.
+                            self.checkpoints.clear();
+                            self.executed = 0;
+                        },
+                        ReconfigureNotification::UpdateCommittee(_) => (),
+                        ReconfigureNotification::Shutdown => return
+                    }
+                }
+            }
+        }
+    }
+}
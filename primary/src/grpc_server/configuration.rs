@@ -1,31 +1,67 @@
 // Copyright (c) 2022, Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use config::{SharedCommittee, Stake};
-use crypto::PublicKey;
+use crate::WorkerMembershipCommand;
+use config::{SharedCommittee, Stake, WorkerInfo};
+use crypto::{NetworkPublicKey, PublicKey};
 use fastcrypto::traits::ToFromBytes;
 use multiaddr::Multiaddr;
 use std::collections::BTreeMap;
+use tokio::sync::oneshot;
 use tonic::{Request, Response, Status};
 use types::{
-    Configuration, Empty, GetPrimaryAddressResponse, MultiAddrProto, NewEpochRequest,
-    NewNetworkInfoRequest, PublicKeyProto,
+    error::DagResult, metered_channel::Sender, AddWorkerRequest, Configuration, Empty,
+    GetPrimaryAddressResponse, MultiAddrProto, NewEpochRequest, NewNetworkInfoRequest,
+    PublicKeyProto, RemoveWorkerRequest,
 };
 
 pub struct NarwhalConfiguration {
     primary_address: Multiaddr,
     /// The committee
     committee: SharedCommittee,
+    /// Channel to register and deregister our workers.
+    tx_worker_membership_commands: Sender<WorkerMembershipCommand>,
 }
 
 impl NarwhalConfiguration {
-    pub fn new(primary_address: Multiaddr, committee: SharedCommittee) -> Self {
+    pub fn new(
+        primary_address: Multiaddr,
+        committee: SharedCommittee,
+        tx_worker_membership_commands: Sender<WorkerMembershipCommand>,
+    ) -> Self {
         Self {
             primary_address,
             committee,
+            tx_worker_membership_commands,
         }
     }
 
+    /// Parses the provided address, or returns a Status message naming the missing field.
+    fn get_address(request: Option<&MultiAddrProto>, field: &str) -> Result<Multiaddr, Status> {
+        request
+            .ok_or_else(|| Status::invalid_argument(format!("Missing {field}")))?
+            .address
+            .parse()
+            .map_err(|err| Status::invalid_argument(format!("Could not serialize: {:?}", err)))
+    }
+
+    /// Sends a command to the `WorkerMembership` and waits for its result.
+    async fn send_worker_membership_command(
+        &self,
+        command: WorkerMembershipCommand,
+        receiver: oneshot::Receiver<DagResult<()>>,
+    ) -> Result<Response<Empty>, Status> {
+        self.tx_worker_membership_commands
+            .send(command)
+            .await
+            .map_err(|_| Status::internal("Worker membership channel closed"))?;
+        receiver
+            .await
+            .map_err(|_| Status::internal("Worker membership channel closed"))?
+            .map_err(|err| Status::invalid_argument(err.to_string()))?;
+        Ok(Response::new(Empty {}))
+    }
+
     /// Extracts and verifies the public key provided from the RoundsRequest.
     /// The method will return a result where the OK() will hold the
     /// parsed public key. The Err() will hold a Status message with the
@@ -134,4 +170,46 @@ impl Configuration for NarwhalConfiguration {
             }),
         }))
     }
+
+    async fn add_worker(
+        &self,
+        request: Request<AddWorkerRequest>,
+    ) -> Result<Response<Empty>, Status> {
+        let add_worker_request = request.into_inner();
+        let name = NetworkPublicKey::from_bytes(add_worker_request.network_key.as_ref())
+            .map_err(|_| Status::invalid_argument("Invalid network key: couldn't parse"))?;
+        let info = WorkerInfo {
+            name,
+            transactions: Self::get_address(
+                add_worker_request.transactions.as_ref(),
+                "transactions address",
+            )?,
+            worker_address: Self::get_address(
+                add_worker_request.worker_address.as_ref(),
+                "worker address",
+            )?,
+        };
+        network::multiaddr_to_address(&info.worker_address)
+            .map_err(|err| Status::invalid_argument(format!("Invalid worker address: {err}")))?;
+
+        let (sender, receiver) = oneshot::channel();
+        let command = WorkerMembershipCommand::AddWorker {
+            id: add_worker_request.worker_id,
+            info,
+            sender,
+        };
+        self.send_worker_membership_command(command, receiver).await
+    }
+
+    async fn remove_worker(
+        &self,
+        request: Request<RemoveWorkerRequest>,
+    ) -> Result<Response<Empty>, Status> {
+        let (sender, receiver) = oneshot::channel();
+        let command = WorkerMembershipCommand::RemoveWorker {
+            id: request.into_inner().worker_id,
+            sender,
+        };
+        self.send_worker_membership_command(command, receiver).await
+    }
 }
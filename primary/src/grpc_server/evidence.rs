@@ -0,0 +1,106 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use bytes::Bytes;
+use config::Epoch;
+use store::Store;
+use tonic::{Request, Response, Status};
+use types::{
+    Equivocation, EquivocationDigest, EquivocationProof, Evidence, GetEquivocationsRequest,
+    GetEquivocationsResponse, GetStateDivergencesRequest, GetStateDivergencesResponse,
+    PublicKeyProto, SequenceNumber, StateDivergence, StateDivergenceReport,
+};
+
+/// Exports the proofs of equivocation collected by the `Core`, so that the application can
+/// punish the offenders. Every proof can be checked with `Equivocation::verify`. It also
+/// exports the divergences of our execution state detected by the `DivergenceDetector`.
+pub struct NarwhalEvidence {
+    /// The store of the equivocations detected by the `Core`.
+    equivocation_store: Store<EquivocationDigest, Equivocation>,
+    /// The store of the divergences detected by the `DivergenceDetector`.
+    divergence_store: Store<(Epoch, SequenceNumber), StateDivergence>,
+}
+
+impl NarwhalEvidence {
+    pub fn new(
+        equivocation_store: Store<EquivocationDigest, Equivocation>,
+        divergence_store: Store<(Epoch, SequenceNumber), StateDivergence>,
+    ) -> Self {
+        Self {
+            equivocation_store,
+            divergence_store,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl Evidence for NarwhalEvidence {
+    async fn get_equivocations(
+        &self,
+        request: Request<GetEquivocationsRequest>,
+    ) -> Result<Response<GetEquivocationsResponse>, Status> {
+        let from_epoch = request.into_inner().from_epoch;
+
+        let mut equivocations: Vec<_> = self
+            .equivocation_store
+            .iter(None)
+            .await
+            .into_values()
+            .filter(|x| x.epoch() >= from_epoch)
+            .collect();
+        equivocations.sort_by_key(|x| (x.epoch(), x.round()));
+
+        let equivocations = equivocations
+            .into_iter()
+            .map(|equivocation| {
+                let proof = bincode::serialize(&equivocation)
+                    .map_err(|e| Status::internal(format!("Failed to serialize proof: {e}")))?;
+                Ok(EquivocationProof {
+                    offender: Some(PublicKeyProto::from(equivocation.offender().clone())),
+                    epoch: equivocation.epoch(),
+                    round: equivocation.round(),
+                    proof: Bytes::from(proof),
+                })
+            })
+            .collect::<Result<_, Status>>()?;
+
+        Ok(Response::new(GetEquivocationsResponse { equivocations }))
+    }
+
+    async fn get_state_divergences(
+        &self,
+        request: Request<GetStateDivergencesRequest>,
+    ) -> Result<Response<GetStateDivergencesResponse>, Status> {
+        let from_epoch = request.into_inner().from_epoch;
+
+        let mut divergences: Vec<_> = self
+            .divergence_store
+            .iter(None)
+            .await
+            .into_values()
+            .filter(|x| x.epoch >= from_epoch)
+            .collect();
+        divergences.sort_by_key(|x| (x.epoch, x.consensus_index));
+
+        let divergences = divergences
+            .into_iter()
+            .map(|divergence| {
+                let proof = bincode::serialize(&divergence.quorum)
+                    .map_err(|e| Status::internal(format!("Failed to serialize proof: {e}")))?;
+                let quorum = divergence
+                    .quorum
+                    .first()
+                    .map(|commitment| commitment.state.0.to_vec())
+                    .unwrap_or_default();
+                Ok(StateDivergenceReport {
+                    epoch: divergence.epoch,
+                    consensus_index: divergence.consensus_index,
+                    ours: Bytes::from(divergence.ours.0.to_vec()),
+                    quorum: Bytes::from(quorum),
+                    proof: Bytes::from(proof),
+                })
+            })
+            .collect::<Result<_, Status>>()?;
+
+        Ok(Response::new(GetStateDivergencesResponse { divergences }))
+    }
+}
@@ -5,7 +5,7 @@ use self::{configuration::NarwhalConfiguration, validator::NarwhalValidator};
 use crate::{
     block_synchronizer::handler::Handler,
     grpc_server::{metrics::EndpointMetrics, proposer::NarwhalProposer},
-    BlockCommand, BlockRemoverCommand,
+    BlockCommand, BlockRemoverCommand, WorkerMembershipCommand,
 };
 use config::SharedCommittee;
 use consensus::dag::Dag;
@@ -18,16 +18,21 @@ use tracing::{error, info};
 use types::{metered_channel::Sender, ConfigurationServer, ProposerServer, ValidatorServer};
 
 mod configuration;
+mod evidence;
 pub mod metrics;
 mod proposer;
+mod sequencer;
 mod validator;
 
+pub use sequencer::SequencerGrpc;
+
 pub struct ConsensusAPIGrpc<SynchronizerHandler: Handler + Send + Sync + 'static> {
     name: PublicKey,
     // Multiaddr of gRPC server
     socket_address: Multiaddr,
     tx_get_block_commands: Sender<BlockCommand>,
     tx_block_removal_commands: Sender<BlockRemoverCommand>,
+    tx_worker_membership_commands: Sender<WorkerMembershipCommand>,
     get_collections_timeout: Duration,
     remove_collections_timeout: Duration,
     block_synchronizer_handler: Arc<SynchronizerHandler>,
@@ -43,6 +48,7 @@ impl<SynchronizerHandler: Handler + Send + Sync + 'static> ConsensusAPIGrpc<Sync
         socket_address: Multiaddr,
         tx_get_block_commands: Sender<BlockCommand>,
         tx_block_removal_commands: Sender<BlockRemoverCommand>,
+        tx_worker_membership_commands: Sender<WorkerMembershipCommand>,
         get_collections_timeout: Duration,
         remove_collections_timeout: Duration,
         block_synchronizer_handler: Arc<SynchronizerHandler>,
@@ -56,6 +62,7 @@ impl<SynchronizerHandler: Handler + Send + Sync + 'static> ConsensusAPIGrpc<Sync
                 socket_address,
                 tx_get_block_commands,
                 tx_block_removal_commands,
+                tx_worker_membership_commands,
                 get_collections_timeout,
                 remove_collections_timeout,
                 block_synchronizer_handler,
@@ -86,8 +93,8 @@ impl<SynchronizerHandler: Handler + Send + Sync + 'static> ConsensusAPIGrpc<Sync
                 .primary(&self.name)
                 .expect("Our public key is not in the committee"),
             Arc::clone(&self.committee),
+            self.tx_worker_membership_commands.to_owned(),
         );
-
         let config = mysten_network::config::Config::default();
         let server = config
             .server_builder_with_metrics(self.endpoints_metrics.clone())
@@ -0,0 +1,340 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use super::evidence::NarwhalEvidence;
+use crate::{BlockCommand, Snapshot};
+use config::Epoch;
+use fastcrypto::Hash;
+use futures::Stream;
+use multiaddr::Multiaddr;
+use std::{pin::Pin, sync::Arc, time::Duration};
+use storage::CertificateStore;
+use store::Store;
+use tokio::{
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+    time::timeout,
+};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+use tracing::{debug, error, info};
+use types::{
+    metered_channel::Sender, Batch, BatchDigest, BatchProto, Certificate, CertificateDigest,
+    CertificateDigestProto, CommittedCertificatesRequest, CommittedCertificatesResponse,
+    ConsensusIndex, ConsensusOutputProto, ConsensusStore, Empty, Equivocation, EquivocationDigest,
+    EvidenceServer, Round, SequenceNumber, SequencedCertificate, Sequencer, SequencerServer,
+    SnapshotResponse, StateDivergence, SubscribeConsensusOutputRequest,
+};
+
+/// Serves the consensus output to the out-of-process execution engines, and lookups into the
+/// history of the sequence. It reads the sequence persisted by consensus, so it can only run
+/// alongside the internal consensus. It also exports the equivocations and the divergences of the
+/// execution state detected by the primary.
+pub struct SequencerGrpc {
+    // Multiaddr of gRPC server
+    socket_address: Multiaddr,
+    consensus_store: Arc<ConsensusStore>,
+    certificate_store: CertificateStore,
+    temp_batch_store: Store<(CertificateDigest, BatchDigest), Batch>,
+    tx_get_block_commands: Sender<BlockCommand>,
+    get_collections_timeout: Duration,
+    gc_depth: Round,
+    equivocation_store: Store<EquivocationDigest, Equivocation>,
+    divergence_store: Store<(Epoch, SequenceNumber), StateDivergence>,
+}
+
+impl SequencerGrpc {
+    #[must_use]
+    pub fn spawn(
+        socket_address: Multiaddr,
+        consensus_store: Arc<ConsensusStore>,
+        certificate_store: CertificateStore,
+        temp_batch_store: Store<(CertificateDigest, BatchDigest), Batch>,
+        tx_get_block_commands: Sender<BlockCommand>,
+        get_collections_timeout: Duration,
+        gc_depth: Round,
+        equivocation_store: Store<EquivocationDigest, Equivocation>,
+        divergence_store: Store<(Epoch, SequenceNumber), StateDivergence>,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let _ = Self {
+                socket_address,
+                consensus_store,
+                certificate_store,
+                temp_batch_store,
+                tx_get_block_commands,
+                get_collections_timeout,
+                gc_depth,
+                equivocation_store,
+                divergence_store,
+            }
+            .run()
+            .await
+            .map_err(|e| error!("{:?}", e));
+        })
+    }
+
+    async fn run(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let narwhal_sequencer = NarwhalSequencer::new(
+            self.consensus_store.clone(),
+            self.certificate_store.clone(),
+            self.temp_batch_store.clone(),
+            self.tx_get_block_commands.clone(),
+            self.get_collections_timeout,
+            self.gc_depth,
+        );
+        let narwhal_evidence = NarwhalEvidence::new(
+            self.equivocation_store.clone(),
+            self.divergence_store.clone(),
+        );
+
+        let config = mysten_network::config::Config::default();
+        let server = config
+            .server_builder()
+            .add_service(SequencerServer::new(narwhal_sequencer))
+            .add_service(EvidenceServer::new(narwhal_evidence))
+            .bind(&self.socket_address)
+            .await?;
+        let local_addr = server.local_addr();
+        info!("Sequencer gRPC Server listening on {local_addr}");
+
+        server.serve().await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct NarwhalSequencer {
+    consensus_store: Arc<ConsensusStore>,
+    certificate_store: CertificateStore,
+    temp_batch_store: Store<(CertificateDigest, BatchDigest), Batch>,
+    tx_get_block_commands: Sender<BlockCommand>,
+    get_collections_timeout: Duration,
+    gc_depth: Round,
+}
+
+impl NarwhalSequencer {
+    /// The number of outputs buffered for every subscriber.
+    const SUBSCRIBER_BUFFER_SIZE: usize = 100;
+
+    pub fn new(
+        consensus_store: Arc<ConsensusStore>,
+        certificate_store: CertificateStore,
+        temp_batch_store: Store<(CertificateDigest, BatchDigest), Batch>,
+        tx_get_block_commands: Sender<BlockCommand>,
+        get_collections_timeout: Duration,
+        gc_depth: Round,
+    ) -> Self {
+        Self {
+            consensus_store,
+            certificate_store,
+            temp_batch_store,
+            tx_get_block_commands,
+            get_collections_timeout,
+            gc_depth,
+        }
+    }
+
+    /// Stream the consensus output starting at the specified consensus index until the
+    /// subscriber goes away.
+    async fn follow(
+        self,
+        mut next_index: SequenceNumber,
+        include_batches: bool,
+        tx_output: mpsc::Sender<Result<ConsensusOutputProto, Status>>,
+    ) {
+        // Subscribe before reading the store, so no update can be missed in between.
+        let mut rx_sequence_updates = self.consensus_store.subscribe();
+        loop {
+            let last_index = match self.consensus_store.read_last_consensus_index() {
+                Ok(x) => x,
+                Err(e) => {
+                    let _ = tx_output
+                        .send(Err(Status::internal(format!("Storage failure: {e}"))))
+                        .await;
+                    return;
+                }
+            };
+
+            while next_index < last_index {
+                let output = self.output(next_index, include_batches).await;
+                let failed = output.is_err();
+                if tx_output.send(output).await.is_err() || failed {
+                    debug!("Consensus output subscriber went away");
+                    return;
+                }
+                next_index += 1;
+            }
+
+            tokio::select! {
+                result = rx_sequence_updates.changed() => {
+                    if result.is_err() {
+                        return;
+                    }
+                },
+                () = tx_output.closed() => {
+                    debug!("Consensus output subscriber went away");
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Load the certificate sequenced at the specified consensus index.
+    async fn output(
+        &self,
+        consensus_index: SequenceNumber,
+        include_batches: bool,
+    ) -> Result<ConsensusOutputProto, Status> {
+        // The certificate sequenced at index `i` is persisted under the key `i + 1`.
+        let digest = self
+            .consensus_store
+            .read_sequenced_certificates(&(consensus_index + 1..=consensus_index + 1))
+            .map_err(|e| Status::internal(format!("Storage failure: {e}")))?
+            .pop()
+            .flatten()
+            .ok_or_else(|| {
+                Status::not_found(format!("No certificate sequenced at {consensus_index}"))
+            })?;
+        let certificate = self
+            .certificate_store
+            .read(digest)
+            .map_err(|e| Status::internal(format!("Storage failure: {e}")))?
+            .ok_or_else(|| Status::not_found(format!("Certificate {digest} not found")))?;
+
+        let batches = if include_batches {
+            self.batches(&certificate).await?
+        } else {
+            Vec::new()
+        };
+
+        Ok(ConsensusOutputProto {
+            certificate: bincode::serialize(&certificate)
+                .map_err(|e| Status::internal(format!("Serialization failure: {e}")))?
+                .into(),
+            certificate_id: Some(digest.into()),
+            consensus_index,
+            batches,
+        })
+    }
+
+    /// Resolve the batches referenced by a certificate, either from the executor's temporary
+    /// store or, once it cleaned them up, from the workers.
+    async fn batches(&self, certificate: &Certificate) -> Result<Vec<BatchProto>, Status> {
+        let certificate_id = certificate.digest();
+        let mut batches = Vec::with_capacity(certificate.header.payload.len());
+        for digest in certificate.header.payload.keys() {
+            match self
+                .temp_batch_store
+                .read((certificate_id, *digest))
+                .await
+                .map_err(|e| Status::internal(format!("Storage failure: {e}")))?
+            {
+                Some(batch) => batches.push(BatchProto {
+                    id: Some((*digest).into()),
+                    transactions: batch.0.into_iter().map(Into::into).collect(),
+                }),
+                None => return self.fetch_batches(certificate_id).await,
+            }
+        }
+        Ok(batches)
+    }
+
+    /// Fetch the batches of a certificate from the workers.
+    async fn fetch_batches(
+        &self,
+        certificate_id: CertificateDigest,
+    ) -> Result<Vec<BatchProto>, Status> {
+        let (sender, receiver) = oneshot::channel();
+        self.tx_get_block_commands
+            .send(BlockCommand::GetBlock {
+                id: certificate_id,
+                sender,
+            })
+            .await
+            .map_err(|err| Status::internal(format!("Send Error: {err:?}")))?;
+        let block = timeout(self.get_collections_timeout, receiver)
+            .await
+            .map_err(|_err| Status::internal("Timeout, no result has been received in time"))?
+            .map_err(|_err| Status::internal("Fetch Error, no result has been received"))?
+            .map_err(|err| Status::internal(format!("Couldn't fetch batches: {err:?}")))?;
+        Ok(block.batches.into_iter().map(Into::into).collect())
+    }
+}
+
+#[tonic::async_trait]
+impl Sequencer for NarwhalSequencer {
+    type SubscribeConsensusOutputStream =
+        Pin<Box<dyn Stream<Item = Result<ConsensusOutputProto, Status>> + Send>>;
+
+    async fn subscribe_consensus_output(
+        &self,
+        request: Request<SubscribeConsensusOutputRequest>,
+    ) -> Result<Response<Self::SubscribeConsensusOutputStream>, Status> {
+        let SubscribeConsensusOutputRequest {
+            start_index,
+            include_batches,
+        } = request.into_inner();
+
+        let (tx_output, rx_output) = mpsc::channel(Self::SUBSCRIBER_BUFFER_SIZE);
+        tokio::spawn(self.clone().follow(start_index, include_batches, tx_output));
+
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx_output))))
+    }
+
+    async fn get_consensus_index(
+        &self,
+        request: Request<CertificateDigestProto>,
+    ) -> Result<Response<ConsensusIndex>, Status> {
+        let certificate_id: CertificateDigest = request
+            .into_inner()
+            .try_into()
+            .map_err(|err| Status::invalid_argument(format!("Could not serialize: {:?}", err)))?;
+
+        let consensus_index = self
+            .consensus_store
+            .read_consensus_index(&certificate_id)
+            .map_err(|e| Status::internal(format!("Storage failure: {e}")))?
+            .ok_or_else(|| {
+                Status::not_found(format!("Certificate {certificate_id} was not sequenced"))
+            })?;
+        Ok(Response::new(ConsensusIndex { consensus_index }))
+    }
+
+    async fn get_committed_certificates(
+        &self,
+        request: Request<CommittedCertificatesRequest>,
+    ) -> Result<Response<CommittedCertificatesResponse>, Status> {
+        let leader_round = request.into_inner().leader_round;
+        let certificates = self
+            .consensus_store
+            .read_committed_by_leader_round(leader_round)
+            .map_err(|e| Status::internal(format!("Storage failure: {e}")))?
+            .into_iter()
+            .map(|(consensus_index, digest)| SequencedCertificate {
+                certificate_id: Some(digest.into()),
+                consensus_index,
+            })
+            .collect();
+        Ok(Response::new(CommittedCertificatesResponse {
+            certificates,
+        }))
+    }
+
+    async fn export_snapshot(
+        &self,
+        _request: Request<Empty>,
+    ) -> Result<Response<SnapshotResponse>, Status> {
+        let snapshot = Snapshot::export(
+            &self.consensus_store,
+            &self.certificate_store,
+            self.gc_depth,
+        )
+        .map_err(|e| Status::internal(format!("Storage failure: {e}")))?;
+        let snapshot = bincode::serialize(&snapshot)
+            .map_err(|e| Status::internal(format!("Serialization failure: {e}")))?;
+        Ok(Response::new(SnapshotResponse {
+            snapshot: snapshot.into(),
+        }))
+    }
+}
@@ -8,7 +8,7 @@ use crate::{
 use config::{Committee, SharedWorkerCache, WorkerId};
 use crypto::PublicKey;
 use futures::future::{try_join_all, BoxFuture};
-use network::{LuckyNetwork, P2pNetwork, UnreliableNetwork};
+use network::{P2pNetwork, PeerReputation, UnreliableNetwork};
 use serde::{de::DeserializeOwned, Serialize};
 use std::{
     collections::HashMap,
@@ -66,6 +66,8 @@ pub struct HeaderWaiter {
     sync_retry_delay: Duration,
     /// Determine with how many nodes to sync when re-trying to send sync-request.
     sync_retry_nodes: usize,
+    /// Picks the nodes to sync with when re-trying, leaving out the banned ones.
+    reputation: PeerReputation<PublicKey>,
 
     /// Watch channel to reconfigure the committee.
     rx_reconfigure: watch::Receiver<ReconfigureNotification>,
@@ -108,6 +110,7 @@ impl HeaderWaiter {
         gc_depth: Round,
         sync_retry_delay: Duration,
         sync_retry_nodes: usize,
+        reputation: PeerReputation<PublicKey>,
         rx_reconfigure: watch::Receiver<ReconfigureNotification>,
         rx_synchronizer: Receiver<WaiterMessage>,
         tx_core: Sender<Header>,
@@ -125,6 +128,7 @@ impl HeaderWaiter {
                 gc_depth,
                 sync_retry_delay,
                 sync_retry_nodes,
+                reputation,
                 rx_reconfigure,
                 rx_synchronizer,
                 tx_core,
@@ -310,13 +314,18 @@ impl HeaderWaiter {
                     }
 
                     if !retry.is_empty() {
-                        let network_keys = self.committee
-                            .others_primaries(&self.name)
+                        let primaries = self.committee.others_primaries(&self.name);
+                        let names = self.reputation.select(
+                            primaries.iter().map(|(name, _, _)| name.clone()).collect(),
+                            self.sync_retry_nodes
+                        );
+                        let network_keys = primaries
                             .into_iter()
+                            .filter(|(name, _, _)| names.contains(name))
                             .map(|(_, _, network_key)| network_key)
                             .collect();
                         let message = PrimaryMessage::CertificatesRequest(retry, self.name.clone());
-                        self.network.lucky_broadcast(network_keys, &message, self.sync_retry_nodes).await;
+                        self.network.unreliable_broadcast(network_keys, &message).await;
                     }
                     // Reschedule the timer.
                     timer.as_mut().reset(Instant::now() + Duration::from_millis(TIMER_RESOLUTION));
@@ -11,13 +11,18 @@ use thiserror::Error;
 use tokio::{sync::watch, task::JoinHandle};
 use tracing::{error, info, instrument};
 use types::{
-    metered_channel::Receiver, BatchDigest, Certificate, CertificateDigest, ReconfigureNotification,
+    metered_channel::Receiver, BatchDigest, Certificate, CertificateDigest,
+    ReconfigureNotification, Round,
 };
 
 #[cfg(test)]
 #[path = "tests/helper_tests.rs"]
 mod helper_tests;
 
+/// The most rounds of certificate digests we send back in reply to a range request, whatever
+/// the requestor asks for.
+const MAX_RANGE_ROUNDS: u64 = 1_000;
+
 #[derive(Debug, Error)]
 enum HelperError {
     #[error("Storage failure: {0}")]
@@ -107,6 +112,17 @@ impl Helper {
                             .process_payload_availability(certificate_ids, requestor)
                             .await;
                     }
+                    // A request for the digests of the certificates we hold from a round
+                    // onwards, from a primary catching up with the others.
+                    PrimaryMessage::CertificatesRangeRequest {
+                        range_start,
+                        max_rounds,
+                        requestor,
+                    } => {
+                        let _ = self
+                            .process_certificates_range(range_start, max_rounds, requestor)
+                            .await;
+                    }
                     _ => {
                         panic!("Received unexpected message!");
                     }
@@ -201,6 +217,36 @@ impl Helper {
         Ok(())
     }
 
+    /// Processes a range request by sending back the digests of the certificates we hold for
+    /// at most `max_rounds` rounds from `range_start`, grouped by round.
+    #[instrument(level = "debug", skip_all, fields(origin = ?origin, range_start, max_rounds), err)]
+    async fn process_certificates_range(
+        &mut self,
+        range_start: Round,
+        max_rounds: u64,
+        origin: PublicKey,
+    ) -> Result<(), HelperError> {
+        if max_rounds == 0 {
+            return Err(HelperError::InvalidRequest(
+                "no rounds requested - ignore request".to_string(),
+            ));
+        }
+
+        let certificate_ids = self
+            .certificate_store
+            .digests_by_round(range_start, max_rounds.min(MAX_RANGE_ROUNDS))?;
+
+        let message = PrimaryMessage::CertificatesRangeResponse {
+            certificate_ids,
+            from: self.name.clone(),
+        };
+        self.primary_network
+            .unreliable_send(self.committee.network_key(&origin).unwrap(), &message)
+            .await;
+
+        Ok(())
+    }
+
     #[instrument(level="debug", skip_all, fields(origin = ?origin, num_certificate_ids = digests.len(), mode = batch_mode), err)]
     async fn process_certificates(
         &mut self,
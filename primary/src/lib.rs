@@ -14,15 +14,18 @@ pub mod block_synchronizer;
 mod block_waiter;
 mod certificate_waiter;
 mod core;
+mod divergence_detector;
 mod grpc_server;
 mod header_waiter;
 mod helper;
 mod payload_receiver;
 mod primary;
 mod proposer;
+mod snapshot;
 mod state_handler;
 mod synchronizer;
 mod utils;
+mod worker_membership;
 
 #[cfg(test)]
 #[path = "tests/common.rs"]
@@ -41,7 +44,9 @@ pub use crate::{
         BlockHeader,
     },
     block_waiter::{BlockCommand, BlockWaiter, GetBlockResponse},
-    grpc_server::metrics::EndpointMetrics,
+    grpc_server::{metrics::EndpointMetrics, SequencerGrpc},
     metrics::PrimaryChannelMetrics,
     primary::{NetworkModel, PayloadToken, Primary, PrimaryWorkerMessage, CHANNEL_CAPACITY},
+    snapshot::Snapshot,
+    worker_membership::WorkerMembershipCommand,
 };
@@ -2,7 +2,10 @@
 // SPDX-License-Identifier: Apache-2.0
 use crate::EndpointMetrics;
 use mysten_network::metrics::MetricsCallbackProvider;
-use network::{metrics, metrics::PrimaryNetworkMetrics};
+use network::{
+    metrics,
+    metrics::{PrimaryNetworkMetrics, ReputationMetrics},
+};
 use prometheus::{
     core::{AtomicI64, GenericGauge},
     default_registry, register_histogram_vec_with_registry, register_int_counter_vec_with_registry,
@@ -19,6 +22,7 @@ pub(crate) struct Metrics {
     pub(crate) primary_channel_metrics: Option<PrimaryChannelMetrics>,
     pub(crate) node_metrics: Option<PrimaryMetrics>,
     pub(crate) network_metrics: Option<PrimaryNetworkMetrics>,
+    pub(crate) reputation_metrics: Option<ReputationMetrics>,
 }
 
 /// Initialises the metrics
@@ -38,12 +42,16 @@ pub(crate) fn initialise_metrics(metrics_registry: &Registry) -> Metrics {
     // Network metrics for the primary to primary comms
     let network_metrics = metrics::PrimaryNetworkMetrics::new(metrics_registry);
 
+    // The reputation of the other primaries
+    let reputation_metrics = ReputationMetrics::new(metrics_registry, "primary");
+
     Metrics {
         node_metrics: Some(node_metrics),
         endpoint_metrics: Some(endpoint_metrics),
         primary_channel_metrics: Some(primary_channel_metrics),
         primary_endpoint_metrics: Some(primary_endpoint_metrics),
         network_metrics: Some(network_metrics),
+        reputation_metrics: Some(reputation_metrics),
     }
 }
 
@@ -85,12 +93,26 @@ pub struct PrimaryChannelMetrics {
     pub tx_availability_responses: IntGauge,
     /// occupancy of the channel from the `primary::WorkerReceiverHandler` to the `primary::StateHandler`
     pub tx_state_handler: IntGauge,
+    /// occupancy of the channel from the `primary::BlockRemover` to the `primary::StateHandler`
+    pub tx_removed_certificates: IntGauge,
+    /// occupancy of the channel from the `primary::StateHandler` to the `primary::Proposer`
+    pub tx_committed_own_headers: IntGauge,
     /// occupancy of the channel from the reconfigure notification to most components.
     pub tx_reconfigure: IntGauge,
     /// occupancy of the channel from the `Consensus` to the `primary::Core`
     pub tx_committed_certificates: IntGauge,
     /// occupancy of the channel from the `primary::Core` to the `Consensus`
     pub tx_new_certificates: IntGauge,
+    /// occupancy of the channel from the `executor::Core` to the `primary::StateHandler`
+    pub tx_new_epochs: IntGauge,
+    /// occupancy of the channel from the `primary::ConsensusAPIGrpc` to the `primary::WorkerMembership`
+    pub tx_worker_membership_commands: IntGauge,
+    /// occupancy of the channel from the `primary::PrimaryReceiverHandler` to the `primary::WorkerMembership`
+    pub tx_worker_index_updates: IntGauge,
+    /// occupancy of the channel from the `executor::Core` to the `primary::DivergenceDetector`
+    pub tx_state_digests: IntGauge,
+    /// occupancy of the channel from the `primary::PrimaryReceiverHandler` to the `primary::DivergenceDetector`
+    pub tx_state_commitments: IntGauge,
 }
 
 impl PrimaryChannelMetrics {
@@ -109,6 +131,21 @@ impl PrimaryChannelMetrics {
     pub const NAME_GET_BLOCK_COMMANDS: &'static str = "tx_get_block_commands";
     pub const DESC_GET_BLOCK_COMMANDS: &'static str =
         "occupancy of the channel from the `primary::ConsensusAPIGrpc` & `executor::Subscriber` to the `primary::BlockWaiter`";
+    // The consistent use of this constant in the below, as well as in `node::spawn_primary` is
+    // load-bearing, see `replace_registered_new_epochs_metric`.
+    pub const NAME_NEW_EPOCHS: &'static str = "tx_new_epochs";
+    pub const DESC_NEW_EPOCHS: &'static str =
+        "occupancy of the channel from the `executor::Core` to the `primary::StateHandler`";
+    // The consistent use of this constant in the below, as well as in `node::spawn_primary` is
+    // load-bearing, see `replace_registered_state_digests_metric`.
+    pub const NAME_STATE_DIGESTS: &'static str = "tx_state_digests";
+    pub const DESC_STATE_DIGESTS: &'static str =
+        "occupancy of the channel from the `executor::Core` to the `primary::DivergenceDetector`";
+    // The consistent use of this constant in the below, as well as in `node::spawn_primary` is
+    // load-bearing, see `replace_registered_block_removal_commands_metric`.
+    pub const NAME_BLOCK_REMOVAL_COMMANDS: &'static str = "tx_block_removal_commands";
+    pub const DESC_BLOCK_REMOVAL_COMMANDS: &'static str =
+        "occupancy of the channel from the `primary::ConsensusAPIGrpc` & `node::Pruner` to the `primary::BlockRemover`";
 
     pub fn new(registry: &Registry) -> Self {
         Self {
@@ -173,8 +210,8 @@ impl PrimaryChannelMetrics {
                 registry
             ).unwrap(),
             tx_block_removal_commands: register_int_gauge_with_registry!(
-                "tx_block_removal_commands",
-                "occupancy of the channel from the `primary::ConsensusAPIGrpc` to the `primary::BlockRemover`",
+                Self::NAME_BLOCK_REMOVAL_COMMANDS,
+                Self::DESC_BLOCK_REMOVAL_COMMANDS,
                 registry
             ).unwrap(),
             tx_batch_removal: register_int_gauge_with_registry!(
@@ -197,6 +234,16 @@ impl PrimaryChannelMetrics {
                 "occupancy of the channel from the `primary::WorkerReceiverHandler` to the `primary::StateHandler`",
                 registry
             ).unwrap(),
+            tx_removed_certificates: register_int_gauge_with_registry!(
+                "tx_removed_certificates",
+                "occupancy of the channel from the `primary::BlockRemover` to the `primary::StateHandler`",
+                registry
+            ).unwrap(),
+            tx_committed_own_headers: register_int_gauge_with_registry!(
+                "tx_committed_own_headers",
+                "occupancy of the channel from the `primary::StateHandler` to the `primary::Proposer`",
+                registry
+            ).unwrap(),
             tx_reconfigure: register_int_gauge_with_registry!(
                 "tx_reconfigure",
                 "occupancy of the channel from the reconfigure notification to most components.",
@@ -212,6 +259,31 @@ impl PrimaryChannelMetrics {
                 Self::DESC_NEW_CERTS,
                 registry
             ).unwrap(),
+            tx_new_epochs: register_int_gauge_with_registry!(
+                Self::NAME_NEW_EPOCHS,
+                Self::DESC_NEW_EPOCHS,
+                registry
+            ).unwrap(),
+            tx_worker_membership_commands: register_int_gauge_with_registry!(
+                "tx_worker_membership_commands",
+                "occupancy of the channel from the `primary::ConsensusAPIGrpc` to the `primary::WorkerMembership`",
+                registry
+            ).unwrap(),
+            tx_worker_index_updates: register_int_gauge_with_registry!(
+                "tx_worker_index_updates",
+                "occupancy of the channel from the `primary::PrimaryReceiverHandler` to the `primary::WorkerMembership`",
+                registry
+            ).unwrap(),
+            tx_state_digests: register_int_gauge_with_registry!(
+                Self::NAME_STATE_DIGESTS,
+                Self::DESC_STATE_DIGESTS,
+                registry
+            ).unwrap(),
+            tx_state_commitments: register_int_gauge_with_registry!(
+                "tx_state_commitments",
+                "occupancy of the channel from the `primary::PrimaryReceiverHandler` to the `primary::DivergenceDetector`",
+                registry
+            ).unwrap(),
         }
     }
 
@@ -259,6 +331,36 @@ impl PrimaryChannelMetrics {
         registry.register(collector).unwrap();
         self.tx_get_block_commands = tx_get_block_commands_counter;
     }
+
+    pub fn replace_registered_new_epochs_metric(
+        &mut self,
+        registry: &Registry,
+        collector: Box<GenericGauge<AtomicI64>>,
+    ) {
+        let new_epochs_counter =
+            IntGauge::new(Self::NAME_NEW_EPOCHS, Self::DESC_NEW_EPOCHS).unwrap();
+        // TODO: Sanity-check by hashing the descs against one another
+        registry
+            .unregister(Box::new(new_epochs_counter.clone()))
+            .unwrap();
+        registry.register(collector).unwrap();
+        self.tx_new_epochs = new_epochs_counter;
+    }
+
+    pub fn replace_registered_state_digests_metric(
+        &mut self,
+        registry: &Registry,
+        collector: Box<GenericGauge<AtomicI64>>,
+    ) {
+        let state_digests_counter =
+            IntGauge::new(Self::NAME_STATE_DIGESTS, Self::DESC_STATE_DIGESTS).unwrap();
+        // TODO: Sanity-check by hashing the descs against one another
+        registry
+            .unregister(Box::new(state_digests_counter.clone()))
+            .unwrap();
+        registry.register(collector).unwrap();
+        self.tx_state_digests = state_digests_counter;
+    }
 }
 
 #[derive(Clone)]
@@ -297,6 +399,18 @@ pub struct PrimaryMetrics {
     pub waiting_elements_certificate_waiter: IntGaugeVec,
     /// Number of votes that were requested but not sent due to previously having voted differently
     pub votes_dropped_equivocation_protection: IntCounterVec,
+    /// Number of batch digests of our garbage collected headers proposed again
+    pub reproposed_batches: IntCounterVec,
+    /// Number of worker index updates applied (ours + others)
+    pub worker_index_updates: IntCounterVec,
+    /// Number of equivocations detected (conflicting headers or votes)
+    pub equivocations_detected: IntCounterVec,
+    /// Number of headers rejected for exceeding a limit, by limit
+    pub headers_exceeding_limits: IntCounterVec,
+    /// Number of state commitments handled (ours + others)
+    pub state_commitments: IntCounterVec,
+    /// Number of times our execution state disagreed with a quorum of the committee
+    pub state_divergences: IntCounterVec,
 }
 
 impl PrimaryMetrics {
@@ -421,6 +535,48 @@ impl PrimaryMetrics {
                 registry
             )
             .unwrap(),
+            reproposed_batches: register_int_counter_vec_with_registry!(
+                "reproposed_batches",
+                "Number of batch digests of our garbage collected headers proposed again",
+                &["epoch"],
+                registry
+            )
+            .unwrap(),
+            worker_index_updates: register_int_counter_vec_with_registry!(
+                "worker_index_updates",
+                "Number of worker index updates applied (ours + others)",
+                &["epoch", "source"],
+                registry
+            )
+            .unwrap(),
+            equivocations_detected: register_int_counter_vec_with_registry!(
+                "equivocations_detected",
+                "Number of equivocations detected (conflicting headers or votes)",
+                &["epoch", "kind"],
+                registry
+            )
+            .unwrap(),
+            headers_exceeding_limits: register_int_counter_vec_with_registry!(
+                "headers_exceeding_limits",
+                "Number of headers rejected for exceeding a limit, by limit",
+                &["epoch", "limit"],
+                registry
+            )
+            .unwrap(),
+            state_commitments: register_int_counter_vec_with_registry!(
+                "state_commitments",
+                "Number of state commitments handled (ours + others)",
+                &["epoch", "source"],
+                registry
+            )
+            .unwrap(),
+            state_divergences: register_int_counter_vec_with_registry!(
+                "state_divergences",
+                "Number of times our execution state disagreed with a quorum of the committee",
+                &["epoch"],
+                registry
+            )
+            .unwrap(),
         }
     }
 }
@@ -482,4 +638,22 @@ impl Default for PrimaryEndpointMetrics {
     fn default() -> Self {
         Self::new(default_registry())
     }
+
+    pub fn replace_registered_block_removal_commands_metric(
+        &mut self,
+        registry: &Registry,
+        collector: Box<GenericGauge<AtomicI64>>,
+    ) {
+        let block_removal_commands_counter = IntGauge::new(
+            Self::NAME_BLOCK_REMOVAL_COMMANDS,
+            Self::DESC_BLOCK_REMOVAL_COMMANDS,
+        )
+        .unwrap();
+        // TODO: Sanity-check by hashing the descs against one another
+        registry
+            .unregister(Box::new(block_removal_commands_counter.clone()))
+            .unwrap();
+        registry.register(collector).unwrap();
+        self.tx_block_removal_commands = block_removal_commands_counter;
+    }
 }
@@ -11,6 +11,7 @@ use crate::{
     block_waiter::{BatchMessageError, BatchResult, BlockWaiter},
     certificate_waiter::CertificateWaiter,
     core::Core,
+    divergence_detector::DivergenceDetector,
     grpc_server::ConsensusAPIGrpc,
     header_waiter::HeaderWaiter,
     helper::Helper,
@@ -19,23 +20,27 @@ use crate::{
     proposer::Proposer,
     state_handler::StateHandler,
     synchronizer::Synchronizer,
-    BlockCommand, BlockRemover, CertificatesResponse, DeleteBatchMessage,
+    worker_membership::WorkerMembership,
+    BlockCommand, BlockRemover, BlockRemoverCommand, CertificatesResponse, DeleteBatchMessage,
     PayloadAvailabilityResponse,
 };
 
 use anemo::{types::PeerInfo, PeerId};
 use async_trait::async_trait;
-use config::{Parameters, SharedCommittee, SharedWorkerCache, WorkerId, WorkerInfo};
-use consensus::dag::Dag;
+use config::{
+    Committee, Epoch, HeaderLimitsParameters, Parameters, SharedCommittee, SharedWorkerCache,
+    WorkerCache, WorkerId,
+};
+use consensus::{dag::Dag, ConsensusOutput};
 use crypto::{KeyPair, NetworkKeyPair, PublicKey};
 use fastcrypto::{
     traits::{EncodeDecodeBase64, KeyPair as _},
     SignatureService,
 };
 use multiaddr::Protocol;
-use network::P2pNetwork;
+use network::{P2pNetwork, PeerReputation};
 use prometheus::Registry;
-use std::{collections::BTreeMap, net::Ipv4Addr, sync::Arc};
+use std::{net::Ipv4Addr, sync::Arc};
 use storage::CertificateStore;
 use store::Store;
 use tokio::{sync::watch, task::JoinHandle};
@@ -43,9 +48,11 @@ use tracing::info;
 use types::{
     error::DagError,
     metered_channel::{channel, Receiver, Sender},
-    BatchDigest, BatchMessage, Certificate, Header, HeaderDigest, PrimaryToPrimary,
-    PrimaryToPrimaryServer, ReconfigureNotification, RoundVoteDigestPair, WorkerInfoResponse,
-    WorkerPrimaryError, WorkerPrimaryMessage, WorkerToPrimary, WorkerToPrimaryServer,
+    BatchDigest, BatchMessage, Certificate, Equivocation, EquivocationDigest, Header, HeaderDigest,
+    PrimaryToPrimary, PrimaryToPrimaryServer, ReconfigureNotification, RoundVoteDigestPair,
+    SequenceNumber, StateCommitment, StateDigest, StateDivergence, WorkerIndexUpdate,
+    WorkerInfoResponse, WorkerPrimaryError, WorkerPrimaryMessage, WorkerToPrimary,
+    WorkerToPrimaryServer,
 };
 pub use types::{PrimaryMessage, PrimaryWorkerMessage};
 
@@ -71,6 +78,7 @@ impl Primary {
     pub fn spawn(
         name: PublicKey,
         signer: KeyPair,
+        coin_signer: Option<KeyPair>,
         network_signer: NetworkKeyPair,
         committee: SharedCommittee,
         worker_cache: SharedWorkerCache,
@@ -79,14 +87,20 @@ impl Primary {
         certificate_store: CertificateStore,
         payload_store: Store<(BatchDigest, WorkerId), PayloadToken>,
         vote_digest_store: Store<PublicKey, RoundVoteDigestPair>,
+        equivocation_store: Store<EquivocationDigest, Equivocation>,
+        divergence_store: Store<(Epoch, SequenceNumber), StateDivergence>,
         tx_consensus: Sender<Certificate>,
-        rx_consensus: Receiver<Certificate>,
+        rx_consensus: Receiver<ConsensusOutput>,
         tx_get_block_commands: Sender<BlockCommand>,
         rx_get_block_commands: Receiver<BlockCommand>,
+        tx_block_removal_commands: Sender<BlockRemoverCommand>,
+        rx_block_removal_commands: Receiver<BlockRemoverCommand>,
         dag: Option<Arc<Dag>>,
         network_model: NetworkModel,
         tx_reconfigure: watch::Sender<ReconfigureNotification>,
-        tx_committed_certificates: Sender<Certificate>,
+        tx_committed_certificates: Sender<ConsensusOutput>,
+        rx_new_epochs: Receiver<(Committee, WorkerCache)>,
+        rx_state_digests: Receiver<(Epoch, SequenceNumber, StateDigest)>,
         registry: &Registry,
     ) -> Vec<JoinHandle<()>> {
         // Write the parameters to the logs.
@@ -101,6 +115,12 @@ impl Primary {
         let node_metrics = Arc::new(metrics.node_metrics.unwrap());
         let _network_metrics = Arc::new(metrics.network_metrics.unwrap());
 
+        // Scores the other primaries we fetch certificates and payloads from.
+        let peer_reputation = PeerReputation::new(
+            parameters.peer_reputation.clone(),
+            metrics.reputation_metrics.unwrap(),
+        );
+
         let (tx_others_digests, rx_others_digests) =
             channel(CHANNEL_CAPACITY, &primary_channel_metrics.tx_others_digests);
         let (tx_our_digests, rx_our_digests) =
@@ -133,10 +153,6 @@ impl Primary {
         );
         let (tx_batches, rx_batches) =
             channel(CHANNEL_CAPACITY, &primary_channel_metrics.tx_batches);
-        let (tx_block_removal_commands, rx_block_removal_commands) = channel(
-            CHANNEL_CAPACITY,
-            &primary_channel_metrics.tx_block_removal_commands,
-        );
         let (tx_batch_removal, rx_batch_removal) =
             channel(CHANNEL_CAPACITY, &primary_channel_metrics.tx_batch_removal);
         let (tx_block_synchronizer_commands, rx_block_synchronizer_commands) = channel(
@@ -149,6 +165,26 @@ impl Primary {
         );
         let (tx_state_handler, rx_state_handler) =
             channel(CHANNEL_CAPACITY, &primary_channel_metrics.tx_state_handler);
+        let (tx_removed_certificates, rx_removed_certificates) = channel(
+            CHANNEL_CAPACITY,
+            &primary_channel_metrics.tx_removed_certificates,
+        );
+        let (tx_committed_own_headers, rx_committed_own_headers) = channel(
+            CHANNEL_CAPACITY,
+            &primary_channel_metrics.tx_committed_own_headers,
+        );
+        let (tx_worker_membership_commands, rx_worker_membership_commands) = channel(
+            CHANNEL_CAPACITY,
+            &primary_channel_metrics.tx_worker_membership_commands,
+        );
+        let (tx_worker_index_updates, rx_worker_index_updates) = channel(
+            CHANNEL_CAPACITY,
+            &primary_channel_metrics.tx_worker_index_updates,
+        );
+        let (tx_state_commitments, rx_state_commitments) = channel(
+            CHANNEL_CAPACITY,
+            &primary_channel_metrics.tx_state_commitments,
+        );
 
         // we need to hack the gauge from this consensus channel into the primary registry
         // This avoids a cyclic dependency in the initialization of consensus and primary
@@ -169,15 +205,21 @@ impl Primary {
             Box::new(tx_get_block_commands_gauge),
         );
 
-        let (tx_consensus_round_updates, rx_consensus_round_updates) = watch::channel(0u64);
+        let block_removal_commands_gauge = tx_block_removal_commands.gauge().clone();
+        primary_channel_metrics.replace_registered_block_removal_commands_metric(
+            registry,
+            Box::new(block_removal_commands_gauge),
+        );
 
-        let our_workers = worker_cache
-            .load()
-            .workers
-            .get(&name)
-            .expect("Our public key is not in the worker cache")
-            .0
-            .clone();
+        let new_epochs_gauge = rx_new_epochs.gauge().clone();
+        primary_channel_metrics
+            .replace_registered_new_epochs_metric(registry, Box::new(new_epochs_gauge));
+
+        let state_digests_gauge = rx_state_digests.gauge().clone();
+        primary_channel_metrics
+            .replace_registered_state_digests_metric(registry, Box::new(state_digests_gauge));
+
+        let (tx_consensus_round_updates, rx_consensus_round_updates) = watch::channel(0u64);
 
         // Spawn the network receiver listening to messages from the other primaries.
         let address = committee
@@ -191,6 +233,11 @@ impl Primary {
             tx_primary_messages: tx_primary_messages.clone(),
             tx_helper_requests,
             tx_availability_responses,
+            tx_worker_index_updates,
+            tx_state_commitments,
+            committee: committee.clone(),
+            header_limits: parameters.header_limits.clone(),
+            metrics: node_metrics.clone(),
         });
         let worker_service = WorkerToPrimaryServer::new(WorkerReceiverHandler {
             tx_our_digests,
@@ -198,7 +245,8 @@ impl Primary {
             tx_batches,
             tx_batch_removal,
             tx_state_handler,
-            our_workers,
+            name: name.clone(),
+            worker_cache: worker_cache.clone(),
             metrics: node_metrics.clone(),
         });
 
@@ -252,7 +300,15 @@ impl Primary {
             dag.clone(),
         );
 
-        // The `SignatureService` is used to require signatures on specific digests.
+        // The `SignatureService` is used to require signatures on specific digests. Our shares of
+        // the common coin are signed with our coin key, or with our own key if no coin keys were
+        // dealt to the committee.
+        committee
+            .load()
+            .check_coin_key(&name, coin_signer.as_ref().map(|x| x.public()))
+            .expect("Our coin key pair is not our share of the committee's coin keys");
+        let coin_signature_service =
+            SignatureService::new(coin_signer.unwrap_or_else(|| signer.copy()));
         let signature_service = SignatureService::new(signer);
 
         // TODO (Laura): if we are restarting and not advancing, for the headers in the header
@@ -266,10 +322,12 @@ impl Primary {
             header_store.clone(),
             certificate_store.clone(),
             vote_digest_store,
+            equivocation_store,
             synchronizer,
             signature_service.clone(),
             tx_consensus_round_updates.subscribe(),
             parameters.gc_depth,
+            parameters.header_limits.clone(),
             tx_reconfigure.subscribe(),
             /* rx_primaries */ rx_primary_messages,
             /* rx_header_waiter */ rx_headers_loopback,
@@ -289,7 +347,7 @@ impl Primary {
 
         let block_synchronizer_handler = Arc::new(BlockSynchronizerHandler::new(
             tx_block_synchronizer_commands,
-            tx_primary_messages,
+            tx_primary_messages.clone(),
             certificate_store.clone(),
             parameters
                 .block_synchronizer
@@ -327,11 +385,14 @@ impl Primary {
             tx_reconfigure.subscribe(),
             rx_block_removal_commands,
             rx_batch_removal,
-            tx_committed_certificates,
+            tx_removed_certificates,
         );
 
         // Responsible for finding missing blocks (certificates) and fetching
-        // them from the primary peers by synchronizing also their batches.
+        // them from the primary peers by synchronizing also their batches. It
+        // also catches up in bulk when the `CertificateWaiter` waits for the
+        // ancestors of certificates too far ahead of us.
+        let (tx_observed_round, rx_observed_round) = watch::channel(0);
         let block_synchronizer_network = P2pNetwork::new(network.clone());
         let block_synchronizer_handle = BlockSynchronizer::spawn(
             name.clone(),
@@ -340,9 +401,12 @@ impl Primary {
             tx_reconfigure.subscribe(),
             rx_block_synchronizer_commands,
             rx_availability_responses,
+            rx_observed_round,
+            /* tx_core */ tx_primary_messages,
             block_synchronizer_network,
             payload_store.clone(),
             certificate_store.clone(),
+            peer_reputation.clone(),
             parameters.clone(),
         );
 
@@ -360,6 +424,7 @@ impl Primary {
             parameters.gc_depth,
             parameters.sync_retry_delay,
             parameters.sync_retry_nodes,
+            peer_reputation,
             tx_reconfigure.subscribe(),
             /* rx_synchronizer */ rx_sync_headers,
             /* tx_core */ tx_headers_loopback,
@@ -377,6 +442,7 @@ impl Primary {
             tx_reconfigure.subscribe(),
             /* rx_synchronizer */ rx_sync_certificates,
             /* tx_core */ tx_certificates_loopback,
+            tx_observed_round,
             node_metrics.clone(),
         );
 
@@ -385,14 +451,48 @@ impl Primary {
         let proposer_handle = Proposer::spawn(
             name.clone(),
             (**committee.load()).clone(),
-            signature_service,
+            signature_service.clone(),
+            coin_signature_service,
             parameters.header_size,
+            parameters.header_limits.max_payload_entries,
             parameters.max_header_delay,
+            parameters.gc_depth,
             network_model,
             tx_reconfigure.subscribe(),
             /* rx_core */ rx_parents,
             /* rx_workers */ rx_our_digests,
             /* tx_core */ tx_headers,
+            rx_committed_own_headers,
+            node_metrics.clone(),
+        );
+
+        // The `WorkerMembership` registers and deregisters our workers within the epoch, and keeps
+        // the workers of the other authorities up to date.
+        let worker_membership_handle = WorkerMembership::spawn(
+            name.clone(),
+            committee.clone(),
+            worker_cache.clone(),
+            signature_service.clone(),
+            tx_reconfigure.subscribe(),
+            rx_worker_membership_commands,
+            rx_worker_index_updates,
+            P2pNetwork::new(network.clone()),
+            node_metrics.clone(),
+        );
+
+        // The `DivergenceDetector` commits to our execution state and compares it with the
+        // execution state of the other authorities.
+        let divergence_detector_handle = DivergenceDetector::spawn(
+            name.clone(),
+            committee.clone(),
+            signature_service,
+            tx_reconfigure.subscribe(),
+            rx_state_digests,
+            rx_state_commitments,
+            P2pNetwork::new(network.clone()),
+            divergence_store,
+            parameters.state_commitments.interval,
+            parameters.state_commitments.max_pending,
             node_metrics,
         );
 
@@ -415,8 +515,11 @@ impl Primary {
             committee.clone(),
             worker_cache,
             rx_consensus,
+            rx_removed_certificates,
             tx_consensus_round_updates,
+            tx_committed_own_headers,
             rx_state_handler,
+            rx_new_epochs,
             tx_reconfigure,
             P2pNetwork::new(network),
         );
@@ -428,6 +531,7 @@ impl Primary {
                 parameters.consensus_api_grpc.socket_addr,
                 tx_get_block_commands,
                 tx_block_removal_commands,
+                tx_worker_membership_commands,
                 parameters.consensus_api_grpc.get_collections_timeout,
                 parameters.consensus_api_grpc.remove_collections_timeout,
                 block_synchronizer_handler,
@@ -460,6 +564,8 @@ impl Primary {
             proposer_handle,
             helper_handle,
             state_handler_handle,
+            worker_membership_handle,
+            divergence_detector_handle,
         ];
 
         if let Some(h) = consensus_api_handle {
@@ -476,6 +582,12 @@ struct PrimaryReceiverHandler {
     tx_primary_messages: Sender<PrimaryMessage>,
     tx_helper_requests: Sender<PrimaryMessage>,
     tx_availability_responses: Sender<AvailabilityResponse>,
+    tx_worker_index_updates: Sender<WorkerIndexUpdate>,
+    tx_state_commitments: Sender<StateCommitment>,
+    committee: SharedCommittee,
+    /// The maximum payload entries, parents and size of the headers we accept.
+    header_limits: HeaderLimitsParameters,
+    metrics: Arc<PrimaryMetrics>,
 }
 
 #[async_trait]
@@ -492,6 +604,11 @@ impl PrimaryToPrimary for PrimaryReceiverHandler {
                 .send(message)
                 .await
                 .map_err(|_| DagError::ShuttingDown),
+            PrimaryMessage::CertificatesRangeRequest { .. } => self
+                .tx_helper_requests
+                .send(message)
+                .await
+                .map_err(|_| DagError::ShuttingDown),
             PrimaryMessage::CertificatesRangeResponse {
                 certificate_ids,
                 from,
@@ -534,6 +651,34 @@ impl PrimaryToPrimary for PrimaryReceiverHandler {
                 }))
                 .await
                 .map_err(|_| DagError::ShuttingDown),
+            PrimaryMessage::WorkerIndexUpdate(update) => self
+                .tx_worker_index_updates
+                .send(update)
+                .await
+                .map_err(|_| DagError::ShuttingDown),
+            PrimaryMessage::StateCommitment(commitment) => self
+                .tx_state_commitments
+                .send(commitment)
+                .await
+                .map_err(|_| DagError::ShuttingDown),
+            // Drop oversized headers before they reach the core.
+            PrimaryMessage::Header(header) => match header.check_limits(&self.header_limits) {
+                Ok(()) => self
+                    .tx_primary_messages
+                    .send(PrimaryMessage::Header(header))
+                    .await
+                    .map_err(|_| DagError::ShuttingDown),
+                Err(limit) => {
+                    self.metrics
+                        .headers_exceeding_limits
+                        .with_label_values(&[
+                            &self.committee.load().epoch().to_string(),
+                            limit.as_str(),
+                        ])
+                        .inc();
+                    Err(DagError::HeaderLimitExceeded(header.id, limit))
+                }
+            },
             _ => self
                 .tx_primary_messages
                 .send(message)
@@ -553,7 +698,8 @@ struct WorkerReceiverHandler {
     tx_batches: Sender<BatchResult>,
     tx_batch_removal: Sender<DeleteBatchResult>,
     tx_state_handler: Sender<ReconfigureNotification>,
-    our_workers: BTreeMap<WorkerId, WorkerInfo>,
+    name: PublicKey,
+    worker_cache: SharedWorkerCache,
     metrics: Arc<PrimaryMetrics>,
 }
 
@@ -625,8 +771,13 @@ impl WorkerToPrimary for WorkerReceiverHandler {
         &self,
         _request: anemo::Request<()>,
     ) -> Result<anemo::Response<WorkerInfoResponse>, anemo::rpc::Status> {
-        Ok(anemo::Response::new(WorkerInfoResponse {
-            workers: self.our_workers.clone(),
-        }))
+        let workers = self
+            .worker_cache
+            .load()
+            .workers
+            .get(&self.name)
+            .map(|index| index.0.clone())
+            .unwrap_or_default();
+        Ok(anemo::Response::new(WorkerInfoResponse { workers }))
     }
 }
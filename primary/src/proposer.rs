@@ -5,7 +5,7 @@ use crate::{metrics::PrimaryMetrics, NetworkModel};
 use config::{Committee, Epoch, WorkerId};
 use crypto::{PublicKey, Signature};
 use fastcrypto::{Digest, Hash as _, SignatureService};
-use std::{cmp::Ordering, sync::Arc};
+use std::{cmp::Ordering, collections::BTreeMap, sync::Arc};
 use tokio::{
     sync::watch,
     task::JoinHandle,
@@ -30,10 +30,16 @@ pub struct Proposer {
     committee: Committee,
     /// Service to sign headers.
     signature_service: SignatureService<Signature>,
+    /// Service to sign our shares of the common coin.
+    coin_signature_service: SignatureService<Signature>,
     /// The size of the headers' payload.
     header_size: usize,
+    /// The maximum number of batches' digests in a header.
+    max_payload_entries: usize,
     /// The maximum delay to wait for batches' digests.
     max_header_delay: Duration,
+    /// The depth of the garbage collection.
+    gc_depth: Round,
     /// The network model in which the node operates.
     network_model: NetworkModel,
 
@@ -45,6 +51,8 @@ pub struct Proposer {
     rx_workers: Receiver<(BatchDigest, WorkerId)>,
     /// Sends newly created headers to the `Core`.
     tx_core: Sender<Header>,
+    /// Receives the last committed round along with the rounds of our headers that got committed.
+    rx_committed_own_headers: Receiver<(Round, Vec<Round>)>,
 
     /// The current round of the dag.
     round: Round,
@@ -56,6 +64,8 @@ pub struct Proposer {
     digests: Vec<(BatchDigest, WorkerId)>,
     /// Keeps track of the size (in bytes) of batches' digests that we received so far.
     payload_size: usize,
+    /// The payload of our headers that are not committed yet, by round.
+    proposed_headers: BTreeMap<Round, Vec<(BatchDigest, WorkerId)>>,
     /// Metrics handler
     metrics: Arc<PrimaryMetrics>,
 }
@@ -67,13 +77,17 @@ impl Proposer {
         name: PublicKey,
         committee: Committee,
         signature_service: SignatureService<Signature>,
+        coin_signature_service: SignatureService<Signature>,
         header_size: usize,
+        max_payload_entries: usize,
         max_header_delay: Duration,
+        gc_depth: Round,
         network_model: NetworkModel,
         rx_reconfigure: watch::Receiver<ReconfigureNotification>,
         rx_core: Receiver<(Vec<Certificate>, Round, Epoch)>,
         rx_workers: Receiver<(BatchDigest, WorkerId)>,
         tx_core: Sender<Header>,
+        rx_committed_own_headers: Receiver<(Round, Vec<Round>)>,
         metrics: Arc<PrimaryMetrics>,
     ) -> JoinHandle<()> {
         let genesis = Certificate::genesis(&committee);
@@ -82,18 +96,23 @@ impl Proposer {
                 name,
                 committee,
                 signature_service,
+                coin_signature_service,
                 header_size,
+                max_payload_entries,
                 max_header_delay,
+                gc_depth,
                 network_model,
                 rx_reconfigure,
                 rx_core,
                 rx_workers,
                 tx_core,
+                rx_committed_own_headers,
                 round: 0,
                 last_parents: genesis,
                 last_leader: None,
                 digests: Vec::with_capacity(2 * header_size),
                 payload_size: 0,
+                proposed_headers: BTreeMap::new(),
                 metrics,
             }
             .run()
@@ -102,14 +121,29 @@ impl Proposer {
     }
 
     async fn make_header(&mut self) -> DagResult<()> {
+        // Leave the digests that do not fit in this header for the next one.
+        let entries = self.digests.len().min(self.max_payload_entries);
+        let payload: Vec<_> = self.digests.drain(..entries).collect();
+        self.payload_size = self
+            .digests
+            .iter()
+            .map(|(digest, _)| Digest::from(*digest).size())
+            .sum();
+
+        // Remember the payload until the header is committed.
+        if !payload.is_empty() {
+            self.proposed_headers.insert(self.round, payload.clone());
+        }
+
         // Make a new header.
         let header = Header::new(
             self.name.clone(),
             self.round,
             self.committee.epoch(),
-            self.digests.drain(..).collect(),
+            payload.into_iter().collect(),
             self.last_parents.drain(..).map(|x| x.digest()).collect(),
             &mut self.signature_service,
+            &mut self.coin_signature_service,
         )
         .await;
         debug!("Created {header:?}");
@@ -129,12 +163,48 @@ impl Proposer {
 
     /// Update the committee and cleanup internal state.
     fn change_epoch(&mut self, committee: Committee) {
+        // The headers of the previous epoch can no longer be committed.
+        let proposed_headers = std::mem::take(&mut self.proposed_headers);
+        self.repropose(proposed_headers);
+
         self.committee = committee;
 
         self.round = 0;
         self.last_parents = Certificate::genesis(&self.committee);
     }
 
+    /// Forget our committed headers, and propose again the payload of the ones that were garbage
+    /// collected by consensus before being committed.
+    fn process_committed_own_headers(&mut self, committed_round: Round, committed: Vec<Round>) {
+        for round in committed {
+            self.proposed_headers.remove(&round);
+        }
+
+        // Consensus garbage collects the certificates below this round.
+        let gc_round = committed_round.saturating_sub(self.gc_depth);
+        let retained = self.proposed_headers.split_off(&gc_round);
+        let expired = std::mem::replace(&mut self.proposed_headers, retained);
+        self.repropose(expired);
+    }
+
+    /// Feed the payload of uncommitted headers back into the digests waiting for the next header.
+    fn repropose(&mut self, headers: BTreeMap<Round, Vec<(BatchDigest, WorkerId)>>) {
+        for (round, digests) in headers {
+            debug!(
+                "Proposing again {} batches of our uncommitted header of round {round}",
+                digests.len()
+            );
+            self.metrics
+                .reproposed_batches
+                .with_label_values(&[&self.committee.epoch.to_string()])
+                .inc_by(digests.len() as u64);
+            for (digest, worker_id) in digests {
+                self.payload_size += Digest::from(digest).size();
+                self.digests.push((digest, worker_id));
+            }
+        }
+    }
+
     // Main loop listening to incoming messages.
     /// Update the last leader certificate. This is only relevant in partial synchrony.
     fn update_leader(&mut self) -> bool {
@@ -244,7 +314,6 @@ impl Proposer {
                     Err(e) => panic!("Unexpected error: {e}"),
                     Ok(()) => (),
                 }
-                self.payload_size = 0;
 
                 // Reschedule the timer.
                 let deadline = Instant::now() + self.max_header_delay;
@@ -311,6 +380,11 @@ impl Proposer {
                     self.digests.push((digest, worker_id));
                 }
 
+                // Receive the progress of consensus on our headers.
+                Some((committed_round, committed)) = self.rx_committed_own_headers.recv() => {
+                    self.process_committed_own_headers(committed_round, committed);
+                }
+
                 // Check whether the timer expired.
                 () = &mut timer, if !timer_expired => {
                     // Nothing to do.
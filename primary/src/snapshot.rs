@@ -0,0 +1,99 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use config::{Committee, SharedWorkerCache};
+use crypto::PublicKey;
+use fastcrypto::Hash;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use storage::CertificateStore;
+use types::{
+    ensure,
+    error::{DagError, DagResult},
+    Certificate, CertificateDigest, ConsensusStore, Round, SequenceNumber,
+};
+
+#[cfg(test)]
+#[path = "tests/snapshot_tests.rs"]
+pub mod snapshot_tests;
+
+/// A portable snapshot of the consensus state of a node. A fresh node importing it starts
+/// consensus from the last committed rounds instead of syncing the whole history.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// The last committed round of each authority.
+    pub last_committed: BTreeMap<PublicKey, Round>,
+    /// The last sequenced certificate, its consensus index, and the round of the leader that
+    /// committed it.
+    pub last_sequenced: Option<(SequenceNumber, CertificateDigest, Round)>,
+    /// The certificates from the garbage collection round onwards, sorted by round.
+    pub certificates: Vec<Certificate>,
+}
+
+impl Snapshot {
+    /// Take a snapshot of the consensus state, with the certificates within `gc_depth` of the
+    /// last committed round.
+    pub fn export(
+        consensus_store: &ConsensusStore,
+        certificate_store: &CertificateStore,
+        gc_depth: Round,
+    ) -> DagResult<Self> {
+        // Read the consensus state first: the certificates are always stored before being
+        // sequenced, so they are all available afterwards.
+        let last_committed: BTreeMap<_, _> =
+            consensus_store.read_last_committed().into_iter().collect();
+        let last_sequenced = consensus_store.read_last_sequenced()?;
+
+        let last_committed_round = last_committed.values().max().copied().unwrap_or_default();
+        let gc_round = last_committed_round.saturating_sub(gc_depth);
+        let mut certificates = certificate_store.after_round(gc_round)?;
+
+        // The last sequenced certificate may be older than the garbage collection round.
+        if let Some((_, digest, _)) = &last_sequenced {
+            if !certificates.iter().any(|x| x.digest() == *digest) {
+                if let Some(certificate) = certificate_store.read(*digest)? {
+                    certificates.insert(0, certificate);
+                }
+            }
+        }
+
+        Ok(Self {
+            last_committed,
+            last_sequenced,
+            certificates,
+        })
+    }
+
+    /// Verify the certificates of the snapshot. The consensus state itself is not signed: the
+    /// snapshot must come from a trusted source.
+    pub fn verify(&self, committee: &Committee, worker_cache: SharedWorkerCache) -> DagResult<()> {
+        for certificate in &self.certificates {
+            certificate.verify(committee, worker_cache.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Write the snapshot into fresh stores.
+    pub fn import(
+        &self,
+        consensus_store: &ConsensusStore,
+        certificate_store: &CertificateStore,
+    ) -> DagResult<()> {
+        ensure!(
+            consensus_store.read_last_consensus_index()? == 0
+                && certificate_store.last_round()?.is_empty(),
+            DagError::NonEmptyStore
+        );
+
+        certificate_store.write_all(self.certificates.iter().cloned())?;
+        if let Some((consensus_index, digest, leader_round)) = &self.last_sequenced {
+            // The certificate sequenced at index `i` is persisted under the key `i + 1`.
+            consensus_store.write_consensus_state(
+                &self.last_committed.clone().into_iter().collect(),
+                &(consensus_index + 1),
+                digest,
+                leader_round,
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -2,14 +2,22 @@
 // Copyright (c) 2022, Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 use crate::primary::PrimaryWorkerMessage;
-use config::{SharedCommittee, SharedWorkerCache, WorkerCache, WorkerIndex};
+use config::{Committee, SharedCommittee, SharedWorkerCache, WorkerCache, WorkerId, WorkerIndex};
+use consensus::ConsensusOutput;
 use crypto::PublicKey;
-use network::{P2pNetwork, UnreliableNetwork};
-use std::{collections::BTreeMap, sync::Arc};
+use fastcrypto::Hash;
+use network::{CancelOnDropHandler, P2pNetwork, ReliableNetwork, UnreliableNetwork};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
 use tap::TapOptional;
 use tokio::{sync::watch, task::JoinHandle};
 use tracing::{info, warn};
-use types::{metered_channel::Receiver, Certificate, ReconfigureNotification, Round};
+use types::{
+    metered_channel::{Receiver, Sender},
+    BatchDigest, Certificate, ReconfigureNotification, Round,
+};
 
 /// Receives the highest round reached by consensus and update it for all tasks.
 pub struct StateHandler {
@@ -20,17 +28,29 @@ pub struct StateHandler {
     /// The worker information cache.
     worker_cache: SharedWorkerCache,
     /// Receives the ordered certificates from consensus.
-    rx_consensus: Receiver<Certificate>,
+    rx_consensus: Receiver<ConsensusOutput>,
+    /// Receives the certificates removed by the `BlockRemover` (when using external consensus).
+    rx_removed_certificates: Receiver<Certificate>,
     /// Signals a new consensus round
     tx_consensus_round_updates: watch::Sender<u64>,
+    /// Notifies the `Proposer` of the last committed round and of our committed headers.
+    tx_committed_own_headers: Sender<(Round, Vec<Round>)>,
     /// Receives notifications to reconfigure the system.
     rx_reconfigure: Receiver<ReconfigureNotification>,
+    /// Receives the committee and worker cache of the next epoch, decided by the executor.
+    rx_new_epochs: Receiver<(Committee, WorkerCache)>,
     /// Channel to signal committee changes.
     tx_reconfigure: watch::Sender<ReconfigureNotification>,
     /// The latest round committed by consensus.
     last_committed_round: Round,
+    /// The rounds of our committed headers that the `Proposer` was not notified of yet.
+    committed_own_headers: Vec<Round>,
+    /// Whether the `Proposer` should be notified of the progress of consensus on our headers.
+    notify_proposer: bool,
     /// A network sender to notify our workers of cleanup events.
     network: P2pNetwork,
+    /// The pending messages reconfiguring our workers for the last epoch change.
+    worker_reconfigurations: Vec<CancelOnDropHandler<anyhow::Result<anemo::Response<()>>>>,
 }
 
 impl StateHandler {
@@ -39,9 +59,12 @@ impl StateHandler {
         name: PublicKey,
         committee: SharedCommittee,
         worker_cache: SharedWorkerCache,
-        rx_consensus: Receiver<Certificate>,
+        rx_consensus: Receiver<ConsensusOutput>,
+        rx_removed_certificates: Receiver<Certificate>,
         tx_consensus_round_updates: watch::Sender<u64>,
+        tx_committed_own_headers: Sender<(Round, Vec<Round>)>,
         rx_reconfigure: Receiver<ReconfigureNotification>,
+        rx_new_epochs: Receiver<(Committee, WorkerCache)>,
         tx_reconfigure: watch::Sender<ReconfigureNotification>,
         network: P2pNetwork,
     ) -> JoinHandle<()> {
@@ -51,11 +74,17 @@ impl StateHandler {
                 committee,
                 worker_cache,
                 rx_consensus,
+                rx_removed_certificates,
                 tx_consensus_round_updates,
+                tx_committed_own_headers,
                 rx_reconfigure,
+                rx_new_epochs,
                 tx_reconfigure,
                 last_committed_round: 0,
+                committed_own_headers: Vec::new(),
+                notify_proposer: false,
                 network,
+                worker_reconfigurations: Vec::new(),
             }
             .run()
             .await;
@@ -63,10 +92,10 @@ impl StateHandler {
     }
 
     async fn handle_sequenced(&mut self, certificate: Certificate) {
-        // TODO [issue #9]: Re-include batch digests that have not been sequenced into our next block.
-
         let round = certificate.round();
-        if round > self.last_committed_round {
+        let own = certificate.origin() == self.name;
+        let advanced = round > self.last_committed_round;
+        if advanced {
             self.last_committed_round = round;
 
             // Trigger cleanup on the primary.
@@ -84,6 +113,88 @@ impl StateHandler {
             let message = PrimaryWorkerMessage::Cleanup(round);
             self.network.unreliable_broadcast(addresses, &message).await;
         }
+
+        // Let the proposer know which of our headers got committed, so it can propose again the
+        // batches of the ones that were garbage collected instead. The notifications are sent
+        // from the main loop, coalesced while the proposer is busy.
+        if own {
+            self.committed_own_headers.push(round);
+        }
+        self.notify_proposer |= own || advanced;
+    }
+
+    async fn notify_sequenced(&mut self, output: &ConsensusOutput) {
+        // Only our own certificates hold batches of our workers.
+        let certificate = &output.certificate;
+        if certificate.origin() != self.name {
+            return;
+        }
+
+        // Let our workers know their batches are sequenced (so they can notify their clients).
+        let mut batches: HashMap<WorkerId, Vec<BatchDigest>> = HashMap::new();
+        for (digest, worker_id) in &certificate.header.payload {
+            batches.entry(*worker_id).or_default().push(*digest);
+        }
+        for (worker_id, digests) in batches {
+            // The worker may have been removed since it stored the batches.
+            let worker_name = match self.worker_cache.load().worker(&self.name, &worker_id) {
+                Ok(worker) => worker.name,
+                Err(e) => {
+                    warn!("Cannot notify worker {worker_id} that its batches are sequenced: {e}");
+                    continue;
+                }
+            };
+            let message = PrimaryWorkerMessage::Sequenced(
+                digests,
+                certificate.digest(),
+                output.consensus_index,
+            );
+            self.network.unreliable_send(worker_name, &message).await;
+        }
+    }
+
+    /// Moves the primary, our workers and the consensus to the committee and the worker cache of
+    /// a new epoch, without restarting any of them.
+    async fn change_epoch(&mut self, committee: Committee, worker_cache: WorkerCache) {
+        if committee.epoch() <= self.committee.load().epoch() {
+            warn!("Ignoring the change to past epoch {}", committee.epoch());
+            return;
+        }
+
+        // Cleanup the network.
+        self.network
+            .cleanup(self.worker_cache.load().network_diff(committee.keys()));
+
+        // Update the worker cache and the committee.
+        self.worker_cache.swap(Arc::new(worker_cache));
+        self.committee.swap(Arc::new(committee.clone()));
+
+        // Trigger cleanup on the primary. Our committed headers of the past epoch are forgotten
+        // by the proposer.
+        self.last_committed_round = 0;
+        self.committed_own_headers.clear();
+        self.notify_proposer = false;
+        let _ = self.tx_consensus_round_updates.send(0); // ignore error when receivers dropped.
+
+        // Reconfigure our workers (if we are still part of the committee). The messages of a
+        // previous epoch change are superseded.
+        let addresses = self
+            .worker_cache
+            .load()
+            .our_workers(&self.name)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|x| x.name)
+            .collect();
+        let message =
+            PrimaryWorkerMessage::Reconfigure(ReconfigureNotification::NewEpoch(committee.clone()));
+        self.worker_reconfigurations = self.network.broadcast(addresses, &message).await;
+
+        // Notify all other tasks.
+        info!("Moving to epoch {}", committee.epoch());
+        self.tx_reconfigure
+            .send(ReconfigureNotification::NewEpoch(committee))
+            .expect("Reconfigure channel dropped");
     }
 
     async fn run(&mut self) {
@@ -93,10 +204,27 @@ impl StateHandler {
         );
         loop {
             tokio::select! {
-                Some(certificate) = self.rx_consensus.recv() => {
+                Some(output) = self.rx_consensus.recv() => {
+                    self.notify_sequenced(&output).await;
+                    self.handle_sequenced(output.certificate).await;
+                },
+
+                Some(certificate) = self.rx_removed_certificates.recv() => {
                     self.handle_sequenced(certificate).await;
                 },
 
+                Some((committee, worker_cache)) = self.rx_new_epochs.recv() => {
+                    self.change_epoch(committee, worker_cache).await;
+                },
+
+                // Notify the proposer without blocking on it: it may itself wait on the core,
+                // which waits on consensus, which waits on us.
+                Ok(permit) = self.tx_committed_own_headers.reserve(), if self.notify_proposer => {
+                    let committed = std::mem::take(&mut self.committed_own_headers);
+                    permit.send((self.last_committed_round, committed));
+                    self.notify_proposer = false;
+                },
+
                 Some(message) = self.rx_reconfigure.recv() => {
                     let shutdown = match &message {
                         ReconfigureNotification::NewEpoch(committee) => {
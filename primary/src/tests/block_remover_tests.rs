@@ -25,7 +25,7 @@ use test_utils::{
     fixture_batch_with_transactions, test_network, CommitteeFixture, PrimaryToWorkerMockServer,
 };
 use tokio::{
-    sync::{mpsc, watch},
+    sync::{mpsc, oneshot, watch},
     task::JoinHandle,
     time::{sleep, timeout},
 };
@@ -210,6 +210,102 @@ async fn test_successful_blocks_delete() {
     assert_eq!(total_deleted, block_ids.len());
 }
 
+#[tokio::test]
+async fn test_successful_batches_delete() {
+    // GIVEN
+    let (header_store, certificate_store, payload_store) = create_db_stores();
+    let (_tx_consensus, rx_consensus) = test_utils::test_channel!(1);
+    let (tx_removed_certificates, _rx_removed_certificates) = test_utils::test_channel!(10);
+    let (tx_commands, rx_commands) = test_utils::test_channel!(10);
+    let (tx_delete_batches, rx_delete_batches) = test_utils::test_channel!(10);
+
+    // AND the necessary keys
+    let fixture = CommitteeFixture::builder().randomize_ports(true).build();
+    let committee = fixture.committee();
+    let worker_cache = fixture.shared_worker_cache();
+    let primary = fixture.authorities().nth(1).unwrap();
+    let name = primary.public_key();
+    let (_tx_reconfigure, rx_reconfigure) =
+        watch::channel(ReconfigureNotification::NewEpoch(committee.clone()));
+    let consensus_metrics = Arc::new(ConsensusMetrics::new(&Registry::new()));
+    let dag = Arc::new(Dag::new(&committee, rx_consensus, consensus_metrics).1);
+
+    let network = test_network(primary.network_keypair(), primary.address());
+    let _remover_handler = BlockRemover::spawn(
+        name.clone(),
+        committee.clone(),
+        worker_cache.clone(),
+        certificate_store,
+        header_store,
+        payload_store,
+        Some(dag),
+        P2pNetwork::new(network.clone()),
+        rx_reconfigure,
+        rx_commands,
+        rx_delete_batches,
+        tx_removed_certificates,
+    );
+
+    // AND batches stored by 2 workers (0 and 1)
+    let mut worker_batches: HashMap<WorkerId, Vec<BatchDigest>> = HashMap::new();
+    for worker_id in 0..2 {
+        for _ in 0..5 {
+            worker_batches
+                .entry(worker_id)
+                .or_insert_with(Vec::new)
+                .push(fixture_batch_with_transactions(10).digest());
+        }
+    }
+
+    // AND bootstrap the workers
+    let handlers = FuturesUnordered::new();
+    for (worker_id, batch_digests) in worker_batches.clone() {
+        let worker = primary.worker(worker_id);
+        let network_key = worker.keypair();
+        let address = &worker.info().worker_address;
+
+        let handle = worker_listener(
+            network_key,
+            address.to_owned(),
+            batch_digests,
+            tx_delete_batches.clone(),
+        );
+        handlers.push(handle);
+
+        let address = network::multiaddr_to_address(address).unwrap();
+        let peer_id = PeerId(worker.keypair().public().0.to_bytes());
+        network
+            .connect_with_peer_id(address, peer_id)
+            .await
+            .unwrap();
+    }
+
+    // WHEN we ask to delete the batches
+    let (tx_result, rx_result) = oneshot::channel();
+    tx_commands
+        .send(BlockRemoverCommand::DeleteBatches {
+            batches: worker_batches,
+            sender: tx_result,
+        })
+        .await
+        .unwrap();
+
+    // THEN the workers receive the delete requests
+    if timeout(Duration::from_millis(4_000), try_join_all(handlers))
+        .await
+        .is_err()
+    {
+        panic!("workers haven't received expected delete batch requests")
+    }
+
+    // AND we get back a successful result
+    let result = timeout(Duration::from_millis(5_000), rx_result)
+        .await
+        .expect("Timeout, no result has been received in time")
+        .unwrap();
+    assert!(result.is_ok(), "Unexpected error: {:?}", result);
+}
+
 #[tokio::test]
 async fn test_timeout() {
     // GIVEN
@@ -390,6 +486,7 @@ async fn test_unlocking_pending_requests() {
         pending_removal_requests: HashMap::new(),
         map_tx_removal_results: HashMap::new(),
         map_tx_worker_removal_results: HashMap::new(),
+        pending_batch_deletions: FuturesUnordered::new(),
         rx_delete_batches,
         tx_removed_certificates,
     };
@@ -1,6 +1,8 @@
 // Copyright (c) 2022, Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
-use crate::common::create_test_vote_store;
+use crate::common::{
+    create_test_equivocation_store, create_test_peer_reputation, create_test_vote_store,
+};
 use crate::{
     certificate_waiter::{CertificateWaiter, GC_RESOLUTION},
     common::create_db_stores,
@@ -9,6 +11,7 @@ use crate::{
     metrics::PrimaryMetrics,
     synchronizer::Synchronizer,
 };
+use config::HeaderLimitsParameters;
 use fastcrypto::{traits::KeyPair, Hash, SignatureService};
 use network::P2pNetwork;
 use prometheus::Registry;
@@ -84,6 +87,7 @@ async fn process_certificate_missing_parents_in_reverse() {
         gc_depth,
         /* sync_retry_delay */ Duration::from_secs(5),
         /* sync_retry_nodes */ 3,
+        create_test_peer_reputation(),
         rx_reconfigure.clone(),
         rx_sync_headers,
         tx_headers_loopback,
@@ -100,6 +104,7 @@ async fn process_certificate_missing_parents_in_reverse() {
         rx_reconfigure.clone(),
         rx_sync_certificates,
         tx_certificates_loopback,
+        watch::channel(0).0,
         metrics.clone(),
     );
 
@@ -111,10 +116,12 @@ async fn process_certificate_missing_parents_in_reverse() {
         header_store.clone(),
         certificates_store.clone(),
         create_test_vote_store(),
+        create_test_equivocation_store(),
         synchronizer,
         signature_service,
         rx_consensus_round_updates,
         /* gc_depth */ gc_depth,
+        HeaderLimitsParameters::default(),
         rx_reconfigure,
         /* rx_primaries */ rx_primary_messages,
         /* rx_header_waiter */ rx_headers_loopback,
@@ -238,6 +245,7 @@ async fn process_certificate_check_gc_fires() {
         gc_depth,
         /* sync_retry_delay */ Duration::from_secs(5),
         /* sync_retry_nodes */ 3,
+        create_test_peer_reputation(),
         rx_reconfigure.clone(),
         rx_sync_headers,
         tx_headers_loopback,
@@ -254,6 +262,7 @@ async fn process_certificate_check_gc_fires() {
         rx_reconfigure.clone(),
         rx_sync_certificates,
         tx_certificates_loopback,
+        watch::channel(0).0,
         metrics.clone(),
     );
 
@@ -265,10 +274,12 @@ async fn process_certificate_check_gc_fires() {
         header_store.clone(),
         certificates_store.clone(),
         create_test_vote_store(),
+        create_test_equivocation_store(),
         synchronizer,
         signature_service,
         rx_consensus_round_updates,
         /* gc_depth */ gc_depth,
+        HeaderLimitsParameters::default(),
         rx_reconfigure,
         /* rx_primaries */ rx_primary_messages,
         /* rx_header_waiter */ rx_headers_loopback,
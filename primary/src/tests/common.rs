@@ -2,18 +2,21 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::PayloadToken;
-use config::WorkerId;
+use config::{Epoch, PeerReputationParameters, WorkerId};
 use crypto::NetworkKeyPair;
+use network::{metrics::ReputationMetrics, PeerReputation};
+use prometheus::Registry;
 use std::time::Duration;
 use storage::CertificateStore;
 use store::{reopen, rocks, rocks::DBMap, Store};
 use test_utils::{
-    temp_dir, PrimaryToWorkerMockServer, CERTIFICATES_CF, CERTIFICATE_ID_BY_ROUND_CF, HEADERS_CF,
-    PAYLOAD_CF, VOTES_CF,
+    temp_dir, PrimaryToWorkerMockServer, CERTIFICATES_CF, CERTIFICATE_ID_BY_ROUND_CF,
+    EQUIVOCATIONS_CF, HEADERS_CF, PAYLOAD_CF, STATE_DIVERGENCES_CF, VOTES_CF,
 };
 use types::{
-    BatchDigest, Certificate, CertificateDigest, Header, HeaderDigest, PrimaryWorkerMessage, Round,
-    RoundVoteDigestPair,
+    BatchDigest, Certificate, CertificateDigest, Equivocation, EquivocationDigest, Header,
+    HeaderDigest, PrimaryWorkerMessage, Round, RoundVoteDigestPair, SequenceNumber,
+    StateDivergence,
 };
 
 use crypto::PublicKey;
@@ -50,6 +53,13 @@ pub fn create_db_stores() -> (
     )
 }
 
+pub fn create_test_peer_reputation() -> PeerReputation<PublicKey> {
+    PeerReputation::new(
+        PeerReputationParameters::default(),
+        ReputationMetrics::new(&Registry::new(), "primary"),
+    )
+}
+
 pub fn create_test_vote_store() -> Store<PublicKey, RoundVoteDigestPair> {
     // Create a new test store.
     let rocksdb = rocks::open_cf(temp_dir(), None, &[VOTES_CF]).expect("Failed creating database");
@@ -57,6 +67,23 @@ pub fn create_test_vote_store() -> Store<PublicKey, RoundVoteDigestPair> {
     Store::new(votes_map)
 }
 
+pub fn create_test_equivocation_store() -> Store<EquivocationDigest, Equivocation> {
+    // Create a new test store.
+    let rocksdb =
+        rocks::open_cf(temp_dir(), None, &[EQUIVOCATIONS_CF]).expect("Failed creating database");
+    let equivocations_map = reopen!(&rocksdb, EQUIVOCATIONS_CF;<EquivocationDigest, Equivocation>);
+    Store::new(equivocations_map)
+}
+
+pub fn create_test_divergence_store() -> Store<(Epoch, SequenceNumber), StateDivergence> {
+    // Create a new test store.
+    let rocksdb = rocks::open_cf(temp_dir(), None, &[STATE_DIVERGENCES_CF])
+        .expect("Failed creating database");
+    let divergences_map =
+        reopen!(&rocksdb, STATE_DIVERGENCES_CF;<(Epoch, SequenceNumber), StateDivergence>);
+    Store::new(divergences_map)
+}
+
 #[must_use]
 pub fn worker_listener(
     num_of_expected_responses: i32,
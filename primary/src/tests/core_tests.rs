@@ -2,12 +2,13 @@
 // Copyright (c) 2022, Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 use super::*;
-use crate::common::{create_db_stores, create_test_vote_store};
+use crate::common::{create_db_stores, create_test_equivocation_store, create_test_vote_store};
 use anemo::{types::PeerInfo, PeerId};
 use fastcrypto::traits::KeyPair;
 use prometheus::Registry;
+use std::time::Duration;
 use test_utils::{fixture_batch_with_transactions, CommitteeFixture, PrimaryToPrimaryMockServer};
-use types::{CertificateDigest, Header, Vote};
+use types::{CertificateDigest, Equivocation, Header, Vote};
 
 #[tokio::test]
 async fn process_header() {
@@ -85,10 +86,12 @@ async fn process_header() {
         header_store.clone(),
         certificates_store.clone(),
         create_test_vote_store(),
+        create_test_equivocation_store(),
         synchronizer,
         signature_service,
         rx_consensus_round_updates,
         /* gc_depth */ 50,
+        HeaderLimitsParameters::default(),
         rx_reconfigure,
         /* rx_primaries */ rx_primary_messages,
         /* rx_header_waiter */ rx_headers_loopback,
@@ -144,6 +147,125 @@ async fn process_header() {
     );
 }
 
+#[tokio::test]
+async fn record_equivocating_headers() {
+    let fixture = CommitteeFixture::builder().randomize_ports(true).build();
+    let committee = fixture.committee();
+    let worker_cache = fixture.shared_worker_cache();
+    let author = fixture.authorities().next().unwrap();
+    let primary = fixture.authorities().nth(1).unwrap();
+
+    // Three different headers of the same author for the same round.
+    let header = author.header(&committee);
+    let conflicting = author
+        .header_builder(&committee)
+        .with_payload_batch(fixture_batch_with_transactions(10), 0)
+        .build(author.keypair())
+        .unwrap();
+    let another = author
+        .header_builder(&committee)
+        .with_payload_batch(fixture_batch_with_transactions(20), 0)
+        .build(author.keypair())
+        .unwrap();
+
+    let name = primary.public_key();
+    let signature_service = SignatureService::new(primary.keypair().copy());
+
+    let (_tx_reconfigure, rx_reconfigure) =
+        watch::channel(ReconfigureNotification::NewEpoch(committee.clone()));
+    let (tx_sync_headers, _rx_sync_headers) = test_utils::test_channel!(1);
+    let (tx_sync_certificates, _rx_sync_certificates) = test_utils::test_channel!(1);
+    let (tx_primary_messages, rx_primary_messages) = test_utils::test_channel!(1);
+    let (_tx_headers_loopback, rx_headers_loopback) = test_utils::test_channel!(1);
+    let (_tx_certificates_loopback, rx_certificates_loopback) = test_utils::test_channel!(1);
+    let (_tx_headers, rx_headers) = test_utils::test_channel!(1);
+    let (tx_consensus, _rx_consensus) = test_utils::test_channel!(1);
+    let (tx_parents, _rx_parents) = test_utils::test_channel!(1);
+    let (_tx_consensus_round_updates, rx_consensus_round_updates) = watch::channel(0u64);
+
+    // Create test stores.
+    let (header_store, certificates_store, payload_store) = create_db_stores();
+    let equivocation_store = create_test_equivocation_store();
+
+    // Make a synchronizer for the core.
+    let synchronizer = Synchronizer::new(
+        name.clone(),
+        &committee,
+        certificates_store.clone(),
+        payload_store,
+        /* tx_header_waiter */ tx_sync_headers,
+        /* tx_certificate_waiter */ tx_sync_certificates,
+        None,
+    );
+
+    let metrics = Arc::new(PrimaryMetrics::new(&Registry::new()));
+    let network = test_utils::test_network(primary.network_keypair(), primary.address());
+
+    // Spawn the core.
+    let _core_handle = Core::spawn(
+        name.clone(),
+        committee.clone(),
+        worker_cache,
+        header_store.clone(),
+        certificates_store.clone(),
+        create_test_vote_store(),
+        equivocation_store.clone(),
+        synchronizer,
+        signature_service,
+        rx_consensus_round_updates,
+        /* gc_depth */ 50,
+        HeaderLimitsParameters::default(),
+        rx_reconfigure,
+        /* rx_primaries */ rx_primary_messages,
+        /* rx_header_waiter */ rx_headers_loopback,
+        /* rx_certificate_waiter */ rx_certificates_loopback,
+        /* rx_proposer */ rx_headers,
+        tx_consensus,
+        /* tx_proposer */ tx_parents,
+        metrics.clone(),
+        P2pNetwork::new(network),
+    );
+
+    // Send the headers to the core, the second one twice.
+    for x in [&header, &conflicting, &conflicting, &another] {
+        tx_primary_messages
+            .send(PrimaryMessage::Header(x.clone()))
+            .await
+            .unwrap();
+    }
+
+    // Ensure the proof of equivocation is stored.
+    let expected = Equivocation::Headers(header, conflicting.clone());
+    let stored = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            if let Some(x) = equivocation_store.read(expected.digest()).await.unwrap() {
+                break x;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("The equivocation was not recorded");
+    assert_eq!(stored.digest(), expected.digest());
+    assert_eq!(stored.offender(), &author.public_key());
+    assert!(stored.verify().is_ok());
+
+    // Ensure a single proof is recorded for the round.
+    let stored = equivocation_store.iter(None).await;
+    assert_eq!(stored.len(), 1);
+    assert!(matches!(
+        stored.get(&expected.digest()),
+        Some(Equivocation::Headers(_, x)) if x.id == conflicting.id
+    ));
+    assert_eq!(
+        metrics
+            .equivocations_detected
+            .with_label_values(&["0", "headers"])
+            .get(),
+        1
+    );
+}
+
 #[tokio::test]
 async fn process_header_missing_parent() {
     let fixture = CommitteeFixture::builder().randomize_ports(true).build();
@@ -196,10 +318,12 @@ async fn process_header_missing_parent() {
         header_store.clone(),
         certificates_store.clone(),
         create_test_vote_store(),
+        create_test_equivocation_store(),
         synchronizer,
         signature_service,
         rx_consensus_round_updates,
         /* gc_depth */ 50,
+        HeaderLimitsParameters::default(),
         rx_reconfigure,
         /* rx_primaries */ rx_primary_messages,
         /* rx_header_waiter */ rx_headers_loopback,
@@ -284,10 +408,12 @@ async fn process_header_missing_payload() {
         header_store.clone(),
         certificates_store.clone(),
         create_test_vote_store(),
+        create_test_equivocation_store(),
         synchronizer,
         signature_service,
         rx_consensus_round_updates,
         /* gc_depth */ 50,
+        HeaderLimitsParameters::default(),
         rx_reconfigure,
         /* rx_primaries */ rx_primary_messages,
         /* rx_header_waiter */ rx_headers_loopback,
@@ -320,6 +446,111 @@ async fn process_header_missing_payload() {
     assert!(header_store.read(id).await.unwrap().is_none());
 }
 
+#[tokio::test]
+async fn process_header_exceeding_limits() {
+    let fixture = CommitteeFixture::builder().randomize_ports(true).build();
+    let committee = fixture.committee();
+    let worker_cache = fixture.shared_worker_cache();
+    let primary = fixture.authorities().next().unwrap();
+    let network_key = primary.network_keypair().copy().private().0.to_bytes();
+    let name = primary.public_key();
+    let signature_service = SignatureService::new(primary.keypair().copy());
+
+    let (_, rx_reconfigure) = watch::channel(ReconfigureNotification::NewEpoch(committee.clone()));
+    let (tx_sync_headers, _rx_sync_headers) = test_utils::test_channel!(1);
+    let (tx_sync_certificates, _rx_sync_certificates) = test_utils::test_channel!(1);
+    let (tx_primary_messages, rx_primary_messages) = test_utils::test_channel!(1);
+    let (_tx_headers_loopback, rx_headers_loopback) = test_utils::test_channel!(1);
+    let (_tx_certificates_loopback, rx_certificates_loopback) = test_utils::test_channel!(1);
+    let (_tx_headers, rx_headers) = test_utils::test_channel!(1);
+    let (tx_consensus, _rx_consensus) = test_utils::test_channel!(1);
+    let (tx_parents, _rx_parents) = test_utils::test_channel!(1);
+    let (_tx_consensus_round_updates, rx_consensus_round_updates) = watch::channel(0u64);
+
+    // Create test stores.
+    let (header_store, certificates_store, payload_store) = create_db_stores();
+
+    // Make a synchronizer for the core.
+    let synchronizer = Synchronizer::new(
+        name.clone(),
+        &committee,
+        certificates_store.clone(),
+        payload_store.clone(),
+        /* tx_header_waiter */ tx_sync_headers,
+        /* tx_certificate_waiter */ tx_sync_certificates,
+        None,
+    );
+
+    let metrics = Arc::new(PrimaryMetrics::new(&Registry::new()));
+
+    let own_address = network::multiaddr_to_address(&committee.primary(&name).unwrap()).unwrap();
+    let network = anemo::Network::bind(own_address)
+        .server_name("narwhal")
+        .private_key(network_key)
+        .start(anemo::Router::new())
+        .unwrap();
+
+    // Spawn the core, accepting a single batch per header.
+    let _core_handle = Core::spawn(
+        name.clone(),
+        committee.clone(),
+        worker_cache,
+        header_store.clone(),
+        certificates_store.clone(),
+        create_test_vote_store(),
+        create_test_equivocation_store(),
+        synchronizer,
+        signature_service,
+        rx_consensus_round_updates,
+        /* gc_depth */ 50,
+        HeaderLimitsParameters {
+            max_payload_entries: 1,
+            ..HeaderLimitsParameters::default()
+        },
+        rx_reconfigure,
+        /* rx_primaries */ rx_primary_messages,
+        /* rx_header_waiter */ rx_headers_loopback,
+        /* rx_certificate_waiter */ rx_certificates_loopback,
+        /* rx_proposer */ rx_headers,
+        tx_consensus,
+        /* tx_proposer */ tx_parents,
+        metrics.clone(),
+        P2pNetwork::new(network),
+    );
+
+    // Send a header with two batches to the core.
+    let author = fixture.authorities().nth(1).unwrap();
+    let header = author
+        .header_builder(&committee)
+        .with_payload_batch(fixture_batch_with_transactions(10), 0)
+        .with_payload_batch(fixture_batch_with_transactions(20), 0)
+        .build(author.keypair())
+        .unwrap();
+
+    let id = header.id;
+    tx_primary_messages
+        .send(PrimaryMessage::Header(header))
+        .await
+        .unwrap();
+
+    // Ensure the header is rejected by the payload limit.
+    let mut m = HashMap::new();
+    m.insert("epoch", "0");
+    m.insert("limit", "payload_entries");
+    while metrics
+        .headers_exceeding_limits
+        .get_metric_with(&m)
+        .unwrap()
+        .get()
+        == 0
+    {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    // Ensure the header is not stored.
+    assert!(header_store.read(id).await.unwrap().is_none());
+}
+
 #[tokio::test]
 async fn process_votes() {
     let fixture = CommitteeFixture::builder().randomize_ports(true).build();
@@ -383,10 +614,12 @@ async fn process_votes() {
         header_store.clone(),
         certificates_store.clone(),
         create_test_vote_store(),
+        create_test_equivocation_store(),
         synchronizer,
         signature_service,
         rx_consensus_round_updates,
         /* gc_depth */ 50,
+        HeaderLimitsParameters::default(),
         rx_reconfigure,
         /* rx_primaries */ rx_primary_messages,
         /* rx_header_waiter */ rx_headers_loopback,
@@ -492,10 +725,12 @@ async fn process_certificates() {
         header_store.clone(),
         certificates_store.clone(),
         create_test_vote_store(),
+        create_test_equivocation_store(),
         synchronizer,
         signature_service,
         rx_consensus_round_updates,
         /* gc_depth */ 50,
+        HeaderLimitsParameters::default(),
         rx_reconfigure,
         /* rx_primaries */ rx_primary_messages,
         /* rx_header_waiter */ rx_headers_loopback,
@@ -609,10 +844,12 @@ async fn shutdown_core() {
         header_store,
         certificates_store,
         create_test_vote_store(),
+        create_test_equivocation_store(),
         synchronizer,
         signature_service,
         rx_consensus_round_updates,
         /* gc_depth */ 50,
+        HeaderLimitsParameters::default(),
         rx_reconfigure,
         /* rx_primaries */ rx_primary_messages,
         /* rx_header_waiter */ rx_headers_loopback,
@@ -704,10 +941,12 @@ async fn reconfigure_core() {
         header_store.clone(),
         certificates_store.clone(),
         create_test_vote_store(),
+        create_test_equivocation_store(),
         synchronizer,
         signature_service,
         rx_consensus_round_updates,
         /* gc_depth */ 50,
+        HeaderLimitsParameters::default(),
         rx_reconfigure,
         /* rx_primaries */ rx_primary_messages,
         /* rx_header_waiter */ rx_headers_loopback,
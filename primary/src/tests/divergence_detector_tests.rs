@@ -0,0 +1,199 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use super::*;
+use crate::common::create_test_divergence_store;
+use anemo::{types::PeerInfo, PeerId};
+use arc_swap::ArcSwap;
+use fastcrypto::traits::KeyPair;
+use prometheus::Registry;
+use std::time::Duration;
+use test_utils::{CommitteeFixture, PrimaryToPrimaryMockServer};
+use tokio::time::{sleep, timeout};
+
+#[tokio::test]
+async fn detect_divergence() {
+    let fixture = CommitteeFixture::builder().randomize_ports(true).build();
+    let committee = fixture.committee();
+    let epoch = committee.epoch();
+    let author = fixture.authorities().next().unwrap();
+    let name = author.public_key();
+    let peer = fixture.authorities().nth(1).unwrap();
+
+    // Spawn a mock of another primary.
+    let (mut rx_primary, _primary_network) =
+        PrimaryToPrimaryMockServer::spawn(peer.network_keypair(), peer.address().clone());
+    let network = test_utils::test_network(author.network_keypair(), author.address());
+    network.known_peers().insert(PeerInfo {
+        peer_id: PeerId(peer.network_public_key().0.to_bytes()),
+        affinity: anemo::types::PeerAffinity::High,
+        address: vec![network::multiaddr_to_address(peer.address()).unwrap()],
+    });
+
+    let divergence_store = create_test_divergence_store();
+    let metrics = Arc::new(PrimaryMetrics::new(&Registry::new()));
+    let (_tx_reconfigure, rx_reconfigure) =
+        watch::channel(ReconfigureNotification::NewEpoch(committee.clone()));
+    let (tx_state_digests, rx_state_digests) = test_utils::test_channel!(1);
+    let (tx_commitments, rx_commitments) = test_utils::test_channel!(1);
+    let _handle = DivergenceDetector::spawn(
+        name.clone(),
+        Arc::new(ArcSwap::from_pointee(committee.clone())),
+        SignatureService::new(author.keypair().copy()),
+        rx_reconfigure,
+        rx_state_digests,
+        rx_commitments,
+        P2pNetwork::new(network),
+        divergence_store.clone(),
+        /* interval */ 10,
+        /* max_pending */ 10,
+        metrics.clone(),
+    );
+
+    let ours = StateDigest::new([1; 32]);
+    let theirs = StateDigest::new([2; 32]);
+
+    // We commit to our state at two consensus indices, and the other primaries receive our
+    // signed commitments.
+    for consensus_index in [9, 19] {
+        tx_state_digests
+            .send((epoch, consensus_index, ours))
+            .await
+            .unwrap();
+        match timeout(Duration::from_secs(5), rx_primary.recv()).await {
+            Ok(Some(PrimaryMessage::StateCommitment(commitment))) => {
+                assert_eq!(commitment.author, name);
+                assert_eq!(commitment.consensus_index, consensus_index);
+                assert_eq!(commitment.state, ours);
+                assert!(commitment.verify(&committee).is_ok());
+            }
+            _ => panic!("Expected a state commitment"),
+        }
+    }
+
+    // At index 9, a single authority disagrees with us. At index 19, they all do.
+    for (i, authority) in fixture.authorities().skip(1).enumerate() {
+        let mut signature_service = SignatureService::new(authority.keypair().copy());
+        let state_at_9 = if i == 0 { theirs } else { ours };
+        for (consensus_index, state) in [(9, state_at_9), (19, theirs)] {
+            let commitment = StateCommitment::new(
+                authority.public_key(),
+                epoch,
+                consensus_index,
+                state,
+                &mut signature_service,
+            )
+            .await;
+            tx_commitments.send(commitment).await.unwrap();
+        }
+    }
+
+    // Only the divergence from the quorum is reported.
+    let divergence = timeout(Duration::from_secs(5), async {
+        loop {
+            if let Some(divergence) = divergence_store.read((epoch, 19)).await.unwrap() {
+                break divergence;
+            }
+            sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .expect("Expected a state divergence");
+    assert_eq!(divergence.ours, ours);
+    assert_eq!(divergence.quorum.len(), 3);
+    for commitment in &divergence.quorum {
+        assert_eq!(commitment.state, theirs);
+        assert!(commitment.verify(&committee).is_ok());
+    }
+    assert!(divergence_store.read((epoch, 9)).await.unwrap().is_none());
+    assert_eq!(
+        metrics
+            .state_divergences
+            .with_label_values(&[&epoch.to_string()])
+            .get(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn bound_pending_commitments() {
+    let fixture = CommitteeFixture::builder().randomize_ports(true).build();
+    let committee = fixture.committee();
+    let epoch = committee.epoch();
+    let author = fixture.authorities().next().unwrap();
+    let network = test_utils::test_network(author.network_keypair(), author.address());
+    let (_tx_reconfigure, rx_reconfigure) =
+        watch::channel(ReconfigureNotification::NewEpoch(committee.clone()));
+    let mut detector = DivergenceDetector {
+        name: author.public_key(),
+        committee: Arc::new(ArcSwap::from_pointee(committee.clone())),
+        signature_service: SignatureService::new(author.keypair().copy()),
+        rx_reconfigure,
+        rx_state_digests: test_utils::test_channel!(1).1,
+        rx_commitments: test_utils::test_channel!(1).1,
+        network: P2pNetwork::new(network),
+        divergence_store: create_test_divergence_store(),
+        interval: 10,
+        max_pending: 2,
+        executed: 0,
+        checkpoints: BTreeMap::new(),
+        metrics: Arc::new(PrimaryMetrics::new(&Registry::new())),
+    };
+
+    let state = StateDigest::new([1; 32]);
+    let commit = |authority: usize, consensus_index| {
+        let authority = fixture.authorities().nth(authority).unwrap();
+        let mut signature_service = SignatureService::new(authority.keypair().copy());
+        async move {
+            StateCommitment::new(
+                authority.public_key(),
+                epoch,
+                consensus_index,
+                state,
+                &mut signature_service,
+            )
+            .await
+        }
+    };
+
+    // The commitments further than 2 intervals ahead of our execution are ignored.
+    for consensus_index in [9, 19, 29] {
+        let commitment = commit(1, consensus_index).await;
+        detector.handle_commitment(commitment).await.unwrap();
+    }
+    let indices: Vec<_> = detector.checkpoints.keys().copied().collect();
+    assert_eq!(indices, vec![9, 19]);
+
+    // And an authority cannot be more than 2 commitments ahead of us.
+    detector
+        .handle_commitment(commit(1, 5).await)
+        .await
+        .unwrap();
+    detector
+        .handle_commitment(commit(2, 5).await)
+        .await
+        .unwrap();
+    let checkpoint = detector.checkpoints.get(&5).unwrap();
+    assert_eq!(checkpoint.commitments.len(), 1);
+    assert!(checkpoint
+        .commitments
+        .contains_key(&fixture.authorities().nth(2).unwrap().public_key()));
+
+    // The window moves along with our execution, and the commitments behind it are dropped.
+    detector.handle_state_digest(epoch, 59, state).await;
+    let indices: Vec<_> = detector.checkpoints.keys().copied().collect();
+    assert_eq!(indices, vec![59]);
+    detector
+        .handle_commitment(commit(1, 29).await)
+        .await
+        .unwrap();
+    detector
+        .handle_commitment(commit(1, 79).await)
+        .await
+        .unwrap();
+    detector
+        .handle_commitment(commit(1, 89).await)
+        .await
+        .unwrap();
+    let indices: Vec<_> = detector.checkpoints.keys().copied().collect();
+    assert_eq!(indices, vec![59, 79]);
+}
@@ -1,7 +1,7 @@
 // Copyright (c) 2022, Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 use crate::{
-    common::{create_db_stores, worker_listener},
+    common::{create_db_stores, create_test_peer_reputation, worker_listener},
     header_waiter::{HeaderWaiter, WaiterMessage},
     metrics::PrimaryMetrics,
     PrimaryWorkerMessage,
@@ -51,6 +51,7 @@ async fn successfully_synchronize_batches() {
         gc_depth,
         /* sync_retry_delay */ Duration::from_secs(5),
         /* sync_retry_nodes */ 3,
+        create_test_peer_reputation(),
         rx_reconfigure,
         rx_synchronizer,
         tx_core,
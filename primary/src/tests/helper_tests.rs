@@ -260,6 +260,106 @@ async fn test_process_certificates_batch_mode() {
     );
 }
 
+#[tokio::test]
+async fn test_process_certificates_range() {
+    // GIVEN
+    let (_, certificate_store, payload_store) = create_db_stores();
+    let fixture = CommitteeFixture::builder().randomize_ports(true).build();
+    let committee = fixture.committee();
+    let author = fixture.authorities().next().unwrap();
+    let name = author.public_key();
+    let requestor = fixture.authorities().nth(1).unwrap();
+    let requestor_name = requestor.public_key();
+    let (_tx_reconfigure, rx_reconfigure) =
+        watch::channel(ReconfigureNotification::NewEpoch(committee.clone()));
+    let (tx_primaries, rx_primaries) = test_utils::test_channel!(10);
+
+    let own_address = network::multiaddr_to_address(&committee.primary(&name).unwrap()).unwrap();
+    let network = anemo::Network::bind(own_address)
+        .server_name("narwhal")
+        .private_key(author.network_keypair().copy().private().0.to_bytes())
+        .start(anemo::Router::new())
+        .unwrap();
+
+    let address = committee.primary(&requestor_name).unwrap();
+    let address = network::multiaddr_to_address(&address).unwrap();
+    let peer_info = PeerInfo {
+        peer_id: PeerId(requestor.network_public_key().0.to_bytes()),
+        affinity: anemo::types::PeerAffinity::High,
+        address: vec![address],
+    };
+    network.known_peers().insert(peer_info);
+
+    // AND a helper
+    let _helper_handle = Helper::spawn(
+        name.clone(),
+        committee.clone(),
+        certificate_store.clone(),
+        payload_store.clone(),
+        rx_reconfigure,
+        rx_primaries,
+        P2pNetwork::new(network.clone()),
+    );
+
+    // AND the certificates of rounds 1 ~ 10
+    let mut certificates = Vec::new();
+    for round in 1..=10 {
+        let mut header = author.header(&committee);
+        header.round = round;
+        certificates.push(fixture.certificate(&header));
+    }
+    certificate_store.write_all(certificates.clone()).unwrap();
+
+    // AND spin up a mock node
+    let address = committee.primary(&requestor_name).unwrap();
+    let requestor_key = requestor.network_keypair().copy();
+    let (mut handler, _network) = PrimaryToPrimaryMockServer::spawn(requestor_key, address);
+
+    // Wait for connectivity
+    let (mut events, mut peers) = network.subscribe();
+    while peers.len() != 1 {
+        let event = events.recv().await.unwrap();
+        match event {
+            anemo::types::PeerEvent::NewPeer(peer_id) => peers.push(peer_id),
+            anemo::types::PeerEvent::LostPeer(_, _) => {
+                panic!("we shouldn't see any lost peer events")
+            }
+        }
+    }
+
+    // WHEN requesting the digests of 4 rounds from round 3
+    tx_primaries
+        .send(PrimaryMessage::CertificatesRangeRequest {
+            range_start: 3,
+            max_rounds: 4,
+            requestor: requestor_name,
+        })
+        .await
+        .expect("Couldn't send message");
+
+    // THEN we get back the digests of rounds 3 ~ 6
+    let message = timeout(Duration::from_millis(4_000), handler.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    match message {
+        PrimaryMessage::CertificatesRangeResponse {
+            certificate_ids,
+            from,
+        } => {
+            assert_eq!(from, name);
+            let expected: Vec<_> = certificates[2..6]
+                .iter()
+                .map(|c| (c.round(), vec![c.digest()]))
+                .collect();
+            assert_eq!(certificate_ids.into_iter().collect::<Vec<_>>(), expected);
+        }
+        msg => {
+            panic!("Didn't expect message {:?}", msg);
+        }
+    }
+}
+
 #[tokio::test]
 async fn test_process_payload_availability_success() {
     // GIVEN
@@ -20,6 +20,7 @@ async fn propose_empty() {
     let (_tx_parents, rx_parents) = test_utils::test_channel!(1);
     let (_tx_our_digests, rx_our_digests) = test_utils::test_channel!(1);
     let (tx_headers, mut rx_headers) = test_utils::test_channel!(1);
+    let (_tx_committed_own_headers, rx_committed_own_headers) = test_utils::test_channel!(1);
 
     let metrics = Arc::new(PrimaryMetrics::new(&Registry::new()));
 
@@ -27,14 +28,18 @@ async fn propose_empty() {
     let _proposer_handle = Proposer::spawn(
         name,
         committee.clone(),
-        signature_service,
+        signature_service.clone(),
+        /* coin_signature_service */ signature_service,
         /* header_size */ 1_000,
+        /* max_payload_entries */ 10_000,
         /* max_header_delay */ Duration::from_millis(20),
+        /* gc_depth */ 50,
         NetworkModel::PartiallySynchronous,
         rx_reconfigure,
         /* rx_core */ rx_parents,
         /* rx_workers */ rx_our_digests,
         /* tx_core */ tx_headers,
+        rx_committed_own_headers,
         metrics,
     );
 
@@ -59,6 +64,7 @@ async fn propose_payload() {
     let (_tx_parents, rx_parents) = test_utils::test_channel!(1);
     let (tx_our_digests, rx_our_digests) = test_utils::test_channel!(1);
     let (tx_headers, mut rx_headers) = test_utils::test_channel!(1);
+    let (_tx_committed_own_headers, rx_committed_own_headers) = test_utils::test_channel!(1);
 
     let metrics = Arc::new(PrimaryMetrics::new(&Registry::new()));
 
@@ -66,15 +72,19 @@ async fn propose_payload() {
     let _proposer_handle = Proposer::spawn(
         name.clone(),
         committee.clone(),
-        signature_service,
+        signature_service.clone(),
+        /* coin_signature_service */ signature_service,
         /* header_size */ 32,
+        /* max_payload_entries */ 10_000,
         /* max_header_delay */
         Duration::from_millis(1_000_000), // Ensure it is not triggered.
+        /* gc_depth */ 50,
         NetworkModel::PartiallySynchronous,
         rx_reconfigure,
         /* rx_core */ rx_parents,
         /* rx_workers */ rx_our_digests,
         /* tx_core */ tx_headers,
+        rx_committed_own_headers,
         metrics,
     );
 
@@ -92,3 +102,87 @@ async fn propose_payload() {
     assert_eq!(header.payload.get(&digest), Some(&worker_id));
     assert!(header.verify(&committee, shared_worker_cache).is_ok());
 }
+
+#[tokio::test]
+async fn repropose_garbage_collected_payload() {
+    let fixture = CommitteeFixture::builder().build();
+    let committee = fixture.committee();
+    let primary = fixture.authorities().next().unwrap();
+    let name = primary.public_key();
+    let signature_service = SignatureService::new(primary.keypair().copy());
+
+    let (_tx_reconfigure, rx_reconfigure) =
+        watch::channel(ReconfigureNotification::NewEpoch(committee.clone()));
+    let (tx_parents, rx_parents) = test_utils::test_channel!(1);
+    let (tx_our_digests, rx_our_digests) = test_utils::test_channel!(1);
+    let (tx_headers, mut rx_headers) = test_utils::test_channel!(1);
+    let (tx_committed_own_headers, rx_committed_own_headers) = test_utils::test_channel!(1);
+
+    let metrics = Arc::new(PrimaryMetrics::new(&Registry::new()));
+
+    // Spawn the proposer.
+    let gc_depth = 50;
+    let _proposer_handle = Proposer::spawn(
+        name.clone(),
+        committee.clone(),
+        signature_service.clone(),
+        /* coin_signature_service */ signature_service,
+        /* header_size */ 32,
+        /* max_payload_entries */ 10_000,
+        /* max_header_delay */
+        Duration::from_millis(1_000_000), // Ensure it is not triggered.
+        gc_depth,
+        NetworkModel::Asynchronous,
+        rx_reconfigure,
+        /* rx_core */ rx_parents,
+        /* rx_workers */ rx_our_digests,
+        /* tx_core */ tx_headers,
+        rx_committed_own_headers,
+        metrics.clone(),
+    );
+
+    // Propose a header with a payload.
+    let digest = BatchDigest([1u8; 32]);
+    let worker_id = 0;
+    tx_our_digests.send((digest, worker_id)).await.unwrap();
+    let header = rx_headers.recv().await.unwrap();
+    assert_eq!(header.round, 1);
+    assert_eq!(header.payload.get(&digest), Some(&worker_id));
+
+    // Consensus commits far enough ahead to garbage collect the header without committing it.
+    tx_committed_own_headers
+        .send((gc_depth + 2, Vec::new()))
+        .await
+        .unwrap();
+
+    // Ensure the next header carries the payload again.
+    tx_parents
+        .send((Certificate::genesis(&committee), 1, committee.epoch()))
+        .await
+        .unwrap();
+    let header = rx_headers.recv().await.unwrap();
+    assert_eq!(header.round, 2);
+    assert_eq!(header.payload.get(&digest), Some(&worker_id));
+    assert_eq!(
+        metrics
+            .reproposed_batches
+            .with_label_values(&[&committee.epoch().to_string()])
+            .get(),
+        1
+    );
+
+    // Once the second header is committed, its payload is not proposed again.
+    tx_committed_own_headers
+        .send((2 * gc_depth, vec![2]))
+        .await
+        .unwrap();
+    tx_parents
+        .send((Certificate::genesis(&committee), 2, committee.epoch()))
+        .await
+        .unwrap();
+    assert!(
+        tokio::time::timeout(Duration::from_millis(100), rx_headers.recv())
+            .await
+            .is_err()
+    );
+}
@@ -0,0 +1,108 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use super::*;
+use crate::common::create_db_stores;
+use consensus::{consensus::ConsensusState, metrics::ConsensusMetrics};
+use fastcrypto::traits::KeyPair;
+use prometheus::Registry;
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Arc,
+};
+use test_utils::{make_consensus_store, temp_dir, CommitteeFixture};
+
+#[tokio::test]
+async fn export_and_import() {
+    let fixture = CommitteeFixture::builder().build();
+    let committee = fixture.committee();
+    let keys: Vec<_> = fixture.authorities().map(|a| a.keypair().copy()).collect();
+    let genesis: BTreeSet<_> = Certificate::genesis(&committee)
+        .iter()
+        .map(|x| x.digest())
+        .collect();
+    let (certificates, _) =
+        test_utils::make_optimal_signed_certificates(1..=6, &genesis, &committee, &keys);
+
+    // GIVEN a node that committed the first 4 rounds, each round by its own leader.
+    let (_, certificate_store, _) = create_db_stores();
+    let consensus_store = make_consensus_store(&temp_dir());
+    certificate_store
+        .write_all(certificates.iter().cloned())
+        .unwrap();
+    let mut last_committed = HashMap::new();
+    let committed: Vec<_> = certificates.iter().filter(|x| x.round() <= 4).collect();
+    for (consensus_index, certificate) in committed.iter().enumerate() {
+        last_committed.insert(certificate.origin(), certificate.round());
+        consensus_store
+            .write_consensus_state(
+                &last_committed,
+                &(consensus_index as SequenceNumber + 1),
+                &certificate.digest(),
+                &certificate.round(),
+            )
+            .unwrap();
+    }
+
+    // WHEN we export a snapshot
+    let gc_depth = 2;
+    let snapshot = Snapshot::export(&consensus_store, &certificate_store, gc_depth).unwrap();
+
+    // THEN it holds the consensus state and the certificates from the gc round onwards
+    let last = committed.last().unwrap();
+    assert_eq!(
+        snapshot.last_sequenced,
+        Some((
+            committed.len() as SequenceNumber - 1,
+            last.digest(),
+            last.round()
+        ))
+    );
+    assert_eq!(
+        snapshot.last_committed,
+        last_committed.clone().into_iter().collect()
+    );
+    assert!(snapshot.certificates.iter().all(|x| x.round() >= 2));
+    assert_eq!(
+        snapshot.certificates.len(),
+        certificates.iter().filter(|x| x.round() >= 2).count()
+    );
+    snapshot
+        .verify(&committee, fixture.shared_worker_cache())
+        .unwrap();
+
+    // WHEN we import it into a fresh node, through a file
+    let bytes = bincode::serialize(&snapshot).unwrap();
+    let snapshot: Snapshot = bincode::deserialize(&bytes).unwrap();
+    let (_, fresh_certificate_store, _) = create_db_stores();
+    let fresh_consensus_store = make_consensus_store(&temp_dir());
+    snapshot
+        .import(&fresh_consensus_store, &fresh_certificate_store)
+        .unwrap();
+
+    // THEN consensus resumes from the same state
+    assert_eq!(
+        fresh_consensus_store.read_last_consensus_index().unwrap(),
+        committed.len() as SequenceNumber
+    );
+    assert_eq!(fresh_consensus_store.read_last_committed(), last_committed);
+    let metrics = Arc::new(ConsensusMetrics::new(&Registry::new()));
+    let state = ConsensusState::new_from_store(
+        Certificate::genesis(&committee),
+        metrics,
+        fresh_consensus_store.read_last_committed(),
+        fresh_certificate_store.clone(),
+        gc_depth,
+    )
+    .await;
+    assert_eq!(state.last_committed_round, 4);
+    assert_eq!(
+        state.dag.get(&4).map(|x| x.len()),
+        Some(committee.authorities.len())
+    );
+
+    // AND the snapshot cannot be imported twice
+    assert!(matches!(
+        snapshot.import(&fresh_consensus_store, &fresh_certificate_store),
+        Err(DagError::NonEmptyStore)
+    ));
+}
@@ -0,0 +1,249 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use super::*;
+use anemo::{types::PeerInfo, PeerId};
+use arc_swap::ArcSwap;
+use crypto::NetworkKeyPair;
+use fastcrypto::traits::KeyPair;
+use prometheus::Registry;
+use rand::rngs::OsRng;
+use std::{num::NonZeroUsize, time::Duration};
+use test_utils::{CommitteeFixture, PrimaryToPrimaryMockServer, PrimaryToWorkerMockServer};
+use tokio::time::timeout;
+
+fn new_worker_info() -> WorkerInfo {
+    WorkerInfo {
+        name: NetworkKeyPair::generate(&mut OsRng).public().clone(),
+        transactions: "/ip4/127.0.0.1/udp/0".parse().unwrap(),
+        worker_address: "/ip4/127.0.0.1/udp/0".parse().unwrap(),
+    }
+}
+
+#[tokio::test]
+async fn add_worker() {
+    let fixture = CommitteeFixture::builder()
+        .number_of_workers(NonZeroUsize::new(1).unwrap())
+        .randomize_ports(true)
+        .build();
+    let committee = fixture.committee();
+    let worker_cache = fixture.shared_worker_cache();
+    let author = fixture.authorities().next().unwrap();
+    let name = author.public_key();
+    let peer = fixture.authorities().nth(1).unwrap();
+
+    // Spawn a mock of our worker and of another primary.
+    let our_worker = author.worker(0);
+    let (mut rx_worker, _worker_network) = PrimaryToWorkerMockServer::spawn(
+        our_worker.keypair(),
+        our_worker.info().worker_address.clone(),
+    );
+    let (mut rx_primary, _primary_network) =
+        PrimaryToPrimaryMockServer::spawn(peer.network_keypair(), peer.address().clone());
+
+    let network = test_utils::test_network(author.network_keypair(), author.address());
+    for (public_key, address) in [
+        (peer.network_public_key(), peer.address().clone()),
+        (
+            our_worker.info().name.clone(),
+            our_worker.info().worker_address.clone(),
+        ),
+    ] {
+        network.known_peers().insert(PeerInfo {
+            peer_id: PeerId(public_key.0.to_bytes()),
+            affinity: anemo::types::PeerAffinity::High,
+            address: vec![network::multiaddr_to_address(&address).unwrap()],
+        });
+    }
+
+    let (_tx_reconfigure, rx_reconfigure) =
+        watch::channel(ReconfigureNotification::NewEpoch(committee.clone()));
+    let (tx_commands, rx_commands) = test_utils::test_channel!(1);
+    let (_tx_updates, rx_updates) = test_utils::test_channel!(1);
+    let _handle = WorkerMembership::spawn(
+        name.clone(),
+        Arc::new(ArcSwap::from_pointee(committee.clone())),
+        worker_cache.clone(),
+        SignatureService::new(author.keypair().copy()),
+        rx_reconfigure,
+        rx_commands,
+        rx_updates,
+        P2pNetwork::new(network),
+        Arc::new(PrimaryMetrics::new(&Registry::new())),
+    );
+
+    // Register a new worker.
+    let info = new_worker_info();
+    let (sender, receiver) = oneshot::channel();
+    tx_commands
+        .send(WorkerMembershipCommand::AddWorker {
+            id: 1,
+            info: info.clone(),
+            sender,
+        })
+        .await
+        .unwrap();
+    receiver.await.unwrap().unwrap();
+
+    // The worker cache holds the new worker.
+    assert_eq!(worker_cache.load().worker(&name, &1).unwrap(), info);
+
+    // Our worker is told about it.
+    match timeout(Duration::from_secs(5), rx_worker.recv()).await {
+        Ok(Some(PrimaryWorkerMessage::UpdateWorkerIndex(authority, workers))) => {
+            assert_eq!(authority, name);
+            assert_eq!(workers.0.len(), 2);
+        }
+        _ => panic!("Expected a worker index update"),
+    }
+
+    // The other primaries receive the signed update.
+    match timeout(Duration::from_secs(5), rx_primary.recv()).await {
+        Ok(Some(PrimaryMessage::WorkerIndexUpdate(update))) => {
+            assert_eq!(update.author, name);
+            assert_eq!(update.workers.0.get(&1), Some(&info));
+            assert!(update.verify(&committee).is_ok());
+        }
+        _ => panic!("Expected a worker index update"),
+    }
+
+    // Removing an unknown worker fails.
+    let (sender, receiver) = oneshot::channel();
+    tx_commands
+        .send(WorkerMembershipCommand::RemoveWorker { id: 7, sender })
+        .await
+        .unwrap();
+    assert!(matches!(
+        receiver.await.unwrap(),
+        Err(DagError::UnknownWorker(7))
+    ));
+
+    // Adding a worker with an address the network does not support fails.
+    let info = WorkerInfo {
+        worker_address: "/ip4/127.0.0.1".parse().unwrap(),
+        ..new_worker_info()
+    };
+    let (sender, receiver) = oneshot::channel();
+    tx_commands
+        .send(WorkerMembershipCommand::AddWorker {
+            id: 2,
+            info,
+            sender,
+        })
+        .await
+        .unwrap();
+    assert!(matches!(
+        receiver.await.unwrap(),
+        Err(DagError::InvalidWorkerAddress(_))
+    ));
+    assert!(worker_cache.load().worker(&name, &2).is_err());
+}
+
+#[tokio::test]
+async fn apply_peer_updates() {
+    let fixture = CommitteeFixture::builder()
+        .number_of_workers(NonZeroUsize::new(1).unwrap())
+        .randomize_ports(true)
+        .build();
+    let committee = fixture.committee();
+    let worker_cache = fixture.shared_worker_cache();
+    let author = fixture.authorities().next().unwrap();
+    let name = author.public_key();
+    let peer = fixture.authorities().nth(1).unwrap();
+    let peer_name = peer.public_key();
+
+    let network = test_utils::test_network(author.network_keypair(), author.address());
+    let metrics = Arc::new(PrimaryMetrics::new(&Registry::new()));
+    let (_tx_reconfigure, rx_reconfigure) =
+        watch::channel(ReconfigureNotification::NewEpoch(committee.clone()));
+    let (_tx_commands, rx_commands) = test_utils::test_channel!(1);
+    let (tx_updates, rx_updates) = test_utils::test_channel!(1);
+    let _handle = WorkerMembership::spawn(
+        name,
+        Arc::new(ArcSwap::from_pointee(committee.clone())),
+        worker_cache.clone(),
+        SignatureService::new(author.keypair().copy()),
+        rx_reconfigure,
+        rx_commands,
+        rx_updates,
+        P2pNetwork::new(network),
+        metrics.clone(),
+    );
+
+    let mut signature_service = SignatureService::new(peer.keypair().copy());
+    let mut updates = Vec::new();
+
+    // The peer adds a worker.
+    let mut workers = peer.worker_index();
+    workers.0.insert(1, new_worker_info());
+    updates.push(
+        WorkerIndexUpdate::new(
+            peer_name.clone(),
+            committee.epoch(),
+            2,
+            workers,
+            &mut signature_service,
+        )
+        .await,
+    );
+
+    // A stale update is ignored.
+    updates.push(
+        WorkerIndexUpdate::new(
+            peer_name.clone(),
+            committee.epoch(),
+            1,
+            peer.worker_index(),
+            &mut signature_service,
+        )
+        .await,
+    );
+
+    // An update signed by another authority is rejected.
+    let forged = WorkerIndexUpdate::new(
+        peer_name.clone(),
+        committee.epoch(),
+        4,
+        peer.worker_index(),
+        &mut SignatureService::new(author.keypair().copy()),
+    )
+    .await;
+    assert!(forged.verify(&committee).is_err());
+    updates.push(forged);
+
+    // The peer adds another worker.
+    let mut workers = peer.worker_index();
+    let info = new_worker_info();
+    workers.0.insert(2, info.clone());
+    updates.push(
+        WorkerIndexUpdate::new(
+            peer_name.clone(),
+            committee.epoch(),
+            3,
+            workers,
+            &mut signature_service,
+        )
+        .await,
+    );
+
+    for update in updates {
+        tx_updates.send(update).await.unwrap();
+    }
+
+    // Only the two valid updates are applied.
+    timeout(Duration::from_secs(5), async {
+        while worker_cache.load().worker(&peer_name, &2).is_err() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("The last update was not applied");
+    assert_eq!(worker_cache.load().worker(&peer_name, &2).unwrap(), info);
+    assert!(worker_cache.load().worker(&peer_name, &1).is_err());
+    assert_eq!(
+        metrics
+            .worker_index_updates
+            .with_label_values(&[&committee.epoch().to_string(), "other"])
+            .get(),
+        2
+    );
+}
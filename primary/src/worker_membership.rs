@@ -0,0 +1,289 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use crate::{metrics::PrimaryMetrics, primary::PrimaryWorkerMessage};
+use config::{SharedCommittee, SharedWorkerCache, WorkerCache, WorkerId, WorkerIndex, WorkerInfo};
+use crypto::{PublicKey, Signature};
+use fastcrypto::{traits::EncodeDecodeBase64, SignatureService};
+use network::{CancelOnDropHandler, P2pNetwork, ReliableNetwork};
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    sync::{oneshot, watch},
+    task::JoinHandle,
+};
+use tracing::{debug, info, warn};
+use types::{
+    error::{DagError, DagResult},
+    metered_channel::Receiver,
+    PrimaryMessage, ReconfigureNotification, WorkerIndexUpdate,
+};
+
+#[cfg(test)]
+#[path = "tests/worker_membership_tests.rs"]
+mod worker_membership_tests;
+
+/// Commands changing the workers of this authority within the current epoch.
+#[derive(Debug)]
+pub enum WorkerMembershipCommand {
+    /// Registers the worker with the given id, or replaces its information if the id is
+    /// already registered.
+    AddWorker {
+        id: WorkerId,
+        info: WorkerInfo,
+        // The channel to send the result to.
+        sender: oneshot::Sender<DagResult<()>>,
+    },
+
+    /// Deregisters the worker with the given id.
+    RemoveWorker {
+        id: WorkerId,
+        // The channel to send the result to.
+        sender: oneshot::Sender<DagResult<()>>,
+    },
+}
+
+/// Keeps the workers of every authority up to date within an epoch. It applies the commands
+/// changing our own workers and signs and broadcasts the resulting `WorkerIndex` to the other
+/// primaries, and applies the `WorkerIndex` they broadcast in turn. Once an authority's
+/// `WorkerIndex` is updated in the worker cache, `Header::verify` accepts the batches of its
+/// new workers.
+///
+/// Changes are not persisted: they last until the end of the epoch (or a restart), after which
+/// the worker cache is loaded again from the configuration. Note also that a worker only
+/// exchanges batches with the workers of the same id at the other authorities.
+pub struct WorkerMembership {
+    /// The public key of this authority.
+    name: PublicKey,
+    /// The committee information.
+    committee: SharedCommittee,
+    /// The worker information cache.
+    worker_cache: SharedWorkerCache,
+    /// Service to sign our worker index updates.
+    signature_service: SignatureService<Signature>,
+    /// Watch channel to reconfigure the committee.
+    rx_reconfigure: watch::Receiver<ReconfigureNotification>,
+    /// Receives the commands changing our workers.
+    rx_commands: Receiver<WorkerMembershipCommand>,
+    /// Receives the worker index updates of the other primaries.
+    rx_updates: Receiver<WorkerIndexUpdate>,
+    /// A network sender to broadcast our updates and notify our workers.
+    network: P2pNetwork,
+    /// The version of our last update. Versions follow the wall clock so that the updates made
+    /// after a restart supersede the ones made before it.
+    version: u64,
+    /// The version of the last update applied for each other authority.
+    versions: HashMap<PublicKey, u64>,
+    /// The pending messages propagating the last update of each authority, superseded by its
+    /// next update.
+    pending: HashMap<PublicKey, Vec<CancelOnDropHandler<anyhow::Result<anemo::Response<()>>>>>,
+    /// The metrics handler
+    metrics: Arc<PrimaryMetrics>,
+}
+
+impl WorkerMembership {
+    #[must_use]
+    pub fn spawn(
+        name: PublicKey,
+        committee: SharedCommittee,
+        worker_cache: SharedWorkerCache,
+        signature_service: SignatureService<Signature>,
+        rx_reconfigure: watch::Receiver<ReconfigureNotification>,
+        rx_commands: Receiver<WorkerMembershipCommand>,
+        rx_updates: Receiver<WorkerIndexUpdate>,
+        network: P2pNetwork,
+        metrics: Arc<PrimaryMetrics>,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            Self {
+                name,
+                committee,
+                worker_cache,
+                signature_service,
+                rx_reconfigure,
+                rx_commands,
+                rx_updates,
+                network,
+                version: 0,
+                versions: HashMap::new(),
+                pending: HashMap::new(),
+                metrics,
+            }
+            .run()
+            .await;
+        })
+    }
+
+    /// Applies a command changing our workers and returns the resulting worker index.
+    fn apply_command(&mut self, command: &WorkerMembershipCommand) -> DagResult<WorkerIndex> {
+        let mut workers = self
+            .worker_cache
+            .load()
+            .workers
+            .get(&self.name)
+            .cloned()
+            .ok_or_else(|| DagError::UnknownAuthority(self.name.encode_base64()))?;
+
+        match command {
+            WorkerMembershipCommand::AddWorker { id, info, .. } => {
+                self.network
+                    .add_known_peer(info.name.clone(), &info.worker_address)
+                    .map_err(|e| DagError::InvalidWorkerAddress(e.to_string()))?;
+                if let Some(old) = workers.0.insert(*id, info.clone()) {
+                    if old.name != info.name {
+                        self.network.remove_known_peer(&old.name);
+                    }
+                }
+            }
+            WorkerMembershipCommand::RemoveWorker { id, .. } => {
+                let old = workers.0.remove(id).ok_or(DagError::UnknownWorker(*id))?;
+                self.network.remove_known_peer(&old.name);
+            }
+        }
+        Ok(workers)
+    }
+
+    /// Replaces the worker index of an authority in the worker cache and notifies our workers.
+    /// The messages propagating the previous update of the authority are superseded.
+    async fn update_worker_cache(&mut self, authority: PublicKey, workers: WorkerIndex) {
+        let mut worker_cache: WorkerCache = (**self.worker_cache.load()).clone();
+        worker_cache
+            .workers
+            .insert(authority.clone(), workers.clone());
+        self.worker_cache.swap(Arc::new(worker_cache));
+
+        // Our workers need the new index to exchange batches with the workers it adds.
+        let addresses = self
+            .worker_cache
+            .load()
+            .our_workers(&self.name)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|x| x.name)
+            .collect();
+        let message = PrimaryWorkerMessage::UpdateWorkerIndex(authority.clone(), workers);
+        let handlers = self.network.broadcast(addresses, &message).await;
+        self.pending.insert(authority, handlers);
+    }
+
+    async fn handle_command(&mut self, command: WorkerMembershipCommand) {
+        let result = self.apply_command(&command);
+        let sender = match command {
+            WorkerMembershipCommand::AddWorker { sender, .. } => sender,
+            WorkerMembershipCommand::RemoveWorker { sender, .. } => sender,
+        };
+        let workers = match result {
+            Ok(workers) => workers,
+            Err(e) => {
+                let _ = sender.send(Err(e));
+                return;
+            }
+        };
+
+        self.update_worker_cache(self.name.clone(), workers.clone())
+            .await;
+
+        // Sign and broadcast our new worker index to the other primaries.
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Failed to measure time")
+            .as_millis() as u64;
+        self.version = now.max(self.version + 1);
+        let epoch = self.committee.load().epoch();
+        let update = WorkerIndexUpdate::new(
+            self.name.clone(),
+            epoch,
+            self.version,
+            workers,
+            &mut self.signature_service,
+        )
+        .await;
+        debug!("Broadcasting {update:?}");
+
+        let addresses = self
+            .committee
+            .load()
+            .others_primaries(&self.name)
+            .into_iter()
+            .map(|(_, _, network_key)| network_key)
+            .collect();
+        let message = PrimaryMessage::WorkerIndexUpdate(update);
+        let handlers = self.network.broadcast(addresses, &message).await;
+        self.pending
+            .entry(self.name.clone())
+            .or_default()
+            .extend(handlers);
+
+        self.metrics
+            .worker_index_updates
+            .with_label_values(&[&epoch.to_string(), "own"])
+            .inc();
+        let _ = sender.send(Ok(()));
+    }
+
+    async fn handle_update(&mut self, update: WorkerIndexUpdate) -> DagResult<()> {
+        update.verify(&self.committee.load())?;
+        for info in update.workers.0.values() {
+            network::multiaddr_to_address(&info.worker_address)
+                .map_err(|e| DagError::InvalidWorkerAddress(e.to_string()))?;
+        }
+
+        // Ignore our own updates and the ones superseded by an update we already applied.
+        if update.author == self.name {
+            return Ok(());
+        }
+        if let Some(version) = self.versions.get(&update.author) {
+            if *version >= update.version {
+                debug!("Ignoring stale {update:?}");
+                return Ok(());
+            }
+        }
+        self.versions.insert(update.author.clone(), update.version);
+
+        info!("Applying {update:?}");
+        self.update_worker_cache(update.author, update.workers)
+            .await;
+
+        self.metrics
+            .worker_index_updates
+            .with_label_values(&[&update.epoch.to_string(), "other"])
+            .inc();
+        Ok(())
+    }
+
+    async fn run(&mut self) {
+        info!(
+            "WorkerMembership on node {} has started successfully.",
+            self.name
+        );
+        loop {
+            tokio::select! {
+                Some(command) = self.rx_commands.recv() => {
+                    self.handle_command(command).await;
+                },
+
+                Some(update) = self.rx_updates.recv() => {
+                    if let Err(e) = self.handle_update(update).await {
+                        warn!("Invalid worker index update: {e}");
+                    }
+                },
+
+                result = self.rx_reconfigure.changed() => {
+                    result.expect("Committee channel dropped");
+                    let message = self.rx_reconfigure.borrow().clone();
+                    match message {
+                        ReconfigureNotification::NewEpoch(_) => {
+                            // The updates of the previous epoch are dropped with its worker cache.
+                            self.pending.clear();
+                            self.versions.clear();
+                        },
+                        ReconfigureNotification::UpdateCommittee(_) => (),
+                        ReconfigureNotification::Shutdown => return
+                    }
+                }
+            }
+        }
+    }
+}
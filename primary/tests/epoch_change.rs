@@ -48,9 +48,15 @@ async fn test_simple_epoch_change() {
 
         let store = NodeStorage::reopen(temp_dir());
 
+        let (_tx_new_epochs, rx_new_epochs) = test_utils::test_new_epochs_channel!(1);
+
+        let (_tx_state_digests, rx_state_digests) = test_utils::test_state_digests_channel!(1);
+        let (tx_block_removal_commands, rx_block_removal_commands) =
+            test_utils::test_block_removal_commands!(1);
         Primary::spawn(
             name,
             signer.copy(),
+            /* coin_signer */ None,
             authority.network_keypair().copy(),
             Arc::new(ArcSwap::from_pointee(committee_0.clone())),
             worker_cache_0.clone(),
@@ -59,14 +65,20 @@ async fn test_simple_epoch_change() {
             store.certificate_store.clone(),
             store.payload_store.clone(),
             store.vote_digest_store.clone(),
+            store.equivocation_store.clone(),
+            store.divergence_store.clone(),
             /* tx_consensus */ tx_new_certificates,
             /* rx_consensus */ rx_feedback,
             tx_get_block_commands,
             rx_get_block_commands,
+            tx_block_removal_commands,
+            rx_block_removal_commands,
             /* dag */ None,
             NetworkModel::Asynchronous,
             tx_reconfigure,
             /* tx_committed_certificates */ tx_feedback,
+            rx_new_epochs,
+            rx_state_digests,
             &Registry::new(),
         );
     }
@@ -175,9 +187,15 @@ async fn test_partial_committee_change() {
 
         let store = NodeStorage::reopen(temp_dir());
 
+        let (_tx_new_epochs, rx_new_epochs) = test_utils::test_new_epochs_channel!(1);
+
+        let (_tx_state_digests, rx_state_digests) = test_utils::test_state_digests_channel!(1);
+        let (tx_block_removal_commands, rx_block_removal_commands) =
+            test_utils::test_block_removal_commands!(1);
         Primary::spawn(
             name,
             signer.copy(),
+            /* coin_signer */ None,
             authority.network_keypair().copy(),
             Arc::new(ArcSwap::from_pointee(committee_0.clone())),
             worker_cache_0.clone(),
@@ -186,14 +204,20 @@ async fn test_partial_committee_change() {
             store.certificate_store.clone(),
             store.payload_store.clone(),
             store.vote_digest_store.clone(),
+            store.equivocation_store.clone(),
+            store.divergence_store.clone(),
             /* tx_consensus */ tx_new_certificates,
             /* rx_consensus */ rx_feedback,
             tx_get_block_commands,
             rx_get_block_commands,
+            tx_block_removal_commands,
+            rx_block_removal_commands,
             /* dag */ None,
             NetworkModel::Asynchronous,
             tx_reconfigure,
             /* tx_committed_certificates */ tx_feedback,
+            rx_new_epochs,
+            rx_state_digests,
             &Registry::new(),
         );
     }
@@ -270,9 +294,15 @@ async fn test_partial_committee_change() {
 
         let store = NodeStorage::reopen(temp_dir());
 
+        let (_tx_new_epochs, rx_new_epochs) = test_utils::test_new_epochs_channel!(1);
+
+        let (_tx_state_digests, rx_state_digests) = test_utils::test_state_digests_channel!(1);
+        let (tx_block_removal_commands, rx_block_removal_commands) =
+            test_utils::test_block_removal_commands!(1);
         Primary::spawn(
             name,
             signer.copy(),
+            /* coin_signer */ None,
             authority.network_keypair().copy(),
             Arc::new(ArcSwap::from_pointee(committee_1.clone())),
             worker_cache_1.clone(),
@@ -281,14 +311,20 @@ async fn test_partial_committee_change() {
             store.certificate_store.clone(),
             store.payload_store,
             store.vote_digest_store,
+            store.equivocation_store,
+            store.divergence_store,
             /* tx_consensus */ tx_new_certificates,
             /* rx_consensus */ rx_feedback,
             tx_get_block_commands,
             rx_get_block_commands,
+            tx_block_removal_commands,
+            rx_block_removal_commands,
             /* dag */ None,
             NetworkModel::Asynchronous,
             tx_reconfigure,
             /* tx_committed_certificates */ tx_feedback,
+            rx_new_epochs,
+            rx_state_digests,
             &Registry::new(),
         );
     }
@@ -344,9 +380,15 @@ async fn test_restart_with_new_committee_change() {
 
         let store = NodeStorage::reopen(temp_dir());
 
+        let (_tx_new_epochs, rx_new_epochs) = test_utils::test_new_epochs_channel!(1);
+
+        let (_tx_state_digests, rx_state_digests) = test_utils::test_state_digests_channel!(1);
+        let (tx_block_removal_commands, rx_block_removal_commands) =
+            test_utils::test_block_removal_commands!(1);
         let primary_handles = Primary::spawn(
             name,
             signer.copy(),
+            /* coin_signer */ None,
             authority.network_keypair().copy(),
             Arc::new(ArcSwap::new(Arc::new(committee_0.clone()))),
             worker_cache_0.clone(),
@@ -355,14 +397,20 @@ async fn test_restart_with_new_committee_change() {
             store.certificate_store.clone(),
             store.payload_store.clone(),
             store.vote_digest_store.clone(),
+            store.equivocation_store.clone(),
+            store.divergence_store.clone(),
             /* tx_consensus */ tx_new_certificates,
             /* rx_consensus */ rx_feedback,
             tx_get_block_commands,
             rx_get_block_commands,
+            tx_block_removal_commands,
+            rx_block_removal_commands,
             /* dag */ None,
             NetworkModel::Asynchronous,
             tx_reconfigure,
             /* tx_committed_certificates */ tx_feedback,
+            rx_new_epochs,
+            rx_state_digests,
             &Registry::new(),
         );
         handles.extend(primary_handles);
@@ -432,9 +480,15 @@ async fn test_restart_with_new_committee_change() {
 
             let store = NodeStorage::reopen(temp_dir());
 
+            let (_tx_new_epochs, rx_new_epochs) = test_utils::test_new_epochs_channel!(1);
+
+            let (_tx_state_digests, rx_state_digests) = test_utils::test_state_digests_channel!(1);
+            let (tx_block_removal_commands, rx_block_removal_commands) =
+                test_utils::test_block_removal_commands!(1);
             let primary_handles = Primary::spawn(
                 name,
                 signer.copy(),
+                /* coin_signer */ None,
                 authority.network_keypair().copy(),
                 Arc::new(ArcSwap::new(Arc::new(new_committee.clone()))),
                 Arc::new(ArcSwap::new(Arc::new(new_worker_cache.clone()))),
@@ -443,14 +497,20 @@ async fn test_restart_with_new_committee_change() {
                 store.certificate_store.clone(),
                 store.payload_store.clone(),
                 store.vote_digest_store.clone(),
+                store.equivocation_store.clone(),
+                store.divergence_store.clone(),
                 /* tx_consensus */ tx_new_certificates,
                 /* rx_consensus */ rx_feedback,
                 tx_get_block_commands,
                 rx_get_block_commands,
+                tx_block_removal_commands,
+                rx_block_removal_commands,
                 /* dag */ None,
                 NetworkModel::Asynchronous,
                 tx_reconfigure,
                 /* tx_committed_certificates */ tx_feedback,
+                rx_new_epochs,
+                rx_state_digests,
                 &Registry::new(),
             );
             handles.extend(primary_handles);
@@ -526,9 +586,15 @@ async fn test_simple_committee_update() {
 
         let store = NodeStorage::reopen(temp_dir());
 
+        let (_tx_new_epochs, rx_new_epochs) = test_utils::test_new_epochs_channel!(1);
+
+        let (_tx_state_digests, rx_state_digests) = test_utils::test_state_digests_channel!(1);
+        let (tx_block_removal_commands, rx_block_removal_commands) =
+            test_utils::test_block_removal_commands!(1);
         Primary::spawn(
             name,
             signer.copy(),
+            /* coin_signer */ None,
             authority.network_keypair().copy(),
             Arc::new(ArcSwap::from_pointee(committee_0.clone())),
             worker_cache_0.clone(),
@@ -537,14 +603,20 @@ async fn test_simple_committee_update() {
             store.certificate_store.clone(),
             store.payload_store.clone(),
             store.vote_digest_store.clone(),
+            store.equivocation_store.clone(),
+            store.divergence_store.clone(),
             /* tx_consensus */ tx_new_certificates,
             /* rx_consensus */ rx_feedback,
             tx_get_block_commands,
             rx_get_block_commands,
+            tx_block_removal_commands,
+            rx_block_removal_commands,
             /* dag */ None,
             NetworkModel::Asynchronous,
             tx_reconfigure,
             /* tx_committed_certificates */ tx_feedback,
+            rx_new_epochs,
+            rx_state_digests,
             &Registry::new(),
         );
     }
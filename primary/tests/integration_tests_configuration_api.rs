@@ -1,9 +1,14 @@
 // Copyright (c) 2022, Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
+use bytes::Bytes;
+use crypto::NetworkKeyPair;
+use fastcrypto::traits::KeyPair;
+use rand::rngs::OsRng;
 use std::time::Duration;
 use test_utils::cluster::Cluster;
 use types::{
-    Empty, MultiAddrProto, NewEpochRequest, NewNetworkInfoRequest, PublicKeyProto, ValidatorData,
+    AddWorkerRequest, Empty, MultiAddrProto, NewEpochRequest, NewNetworkInfoRequest,
+    PublicKeyProto, RemoveWorkerRequest, ValidatorData,
 };
 
 #[tokio::test]
@@ -129,3 +134,77 @@ async fn test_get_primary_address() {
             .to_string()
     )
 }
+
+#[tokio::test]
+async fn test_add_and_remove_worker() {
+    let mut cluster = Cluster::new(None, false);
+
+    // start the cluster will all the possible nodes
+    cluster.start(Some(2), Some(1), None).await;
+
+    // give some time for nodes to bootstrap
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    let worker_cache = cluster.worker_cache_shared.clone();
+    let authority = cluster.authority(0);
+    let name = authority.name.clone();
+
+    // Test gRPC server with client call
+    let mut client = authority.new_configuration_client().await;
+
+    let network_key = NetworkKeyPair::generate(&mut OsRng).public().clone();
+    let request = tonic::Request::new(AddWorkerRequest {
+        worker_id: 5,
+        network_key: Bytes::from(network_key.as_ref().to_vec()),
+        transactions: Some(MultiAddrProto {
+            address: "/ip4/127.0.0.1/tcp/0/http".to_string(),
+        }),
+        worker_address: None,
+    });
+
+    let status = client.add_worker(request).await.unwrap_err();
+    assert!(status.message().contains("Missing worker address"));
+
+    let request = tonic::Request::new(AddWorkerRequest {
+        worker_id: 5,
+        network_key: Bytes::from(network_key.as_ref().to_vec()),
+        transactions: Some(MultiAddrProto {
+            address: "/ip4/127.0.0.1/tcp/0/http".to_string(),
+        }),
+        worker_address: Some(MultiAddrProto {
+            address: "/ip4/127.0.0.1".to_string(),
+        }),
+    });
+
+    let status = client.add_worker(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    assert!(status.message().contains("Invalid worker address"));
+
+    let request = tonic::Request::new(AddWorkerRequest {
+        worker_id: 5,
+        network_key: Bytes::from(network_key.as_ref().to_vec()),
+        transactions: Some(MultiAddrProto {
+            address: "/ip4/127.0.0.1/tcp/0/http".to_string(),
+        }),
+        worker_address: Some(MultiAddrProto {
+            address: "/ip4/127.0.0.1/udp/0".to_string(),
+        }),
+    });
+
+    let response = client.add_worker(request).await.unwrap();
+    assert_eq!(Empty {}, response.into_inner());
+    assert_eq!(
+        worker_cache.load().worker(&name, &5).unwrap().name,
+        network_key
+    );
+
+    let request = tonic::Request::new(RemoveWorkerRequest { worker_id: 5 });
+    let response = client.remove_worker(request).await.unwrap();
+    assert_eq!(Empty {}, response.into_inner());
+    assert!(worker_cache.load().worker(&name, &5).is_err());
+
+    // The worker is no longer registered.
+    let request = tonic::Request::new(RemoveWorkerRequest { worker_id: 5 });
+    let status = client.remove_worker(request).await.unwrap_err();
+    assert!(status.message().contains("Worker 5 is not registered"));
+}
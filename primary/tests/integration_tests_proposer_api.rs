@@ -91,13 +91,20 @@ async fn test_rounds_errors() {
             .iter()
             .filter_map(|(pk, a)| (*pk != name).then_some((pk.clone(), a.clone())))
             .collect::<BTreeMap<_, _>>(),
+        coin: None,
     };
 
     let consensus_metrics = Arc::new(ConsensusMetrics::new(&Registry::new()));
 
+    let (_tx_new_epochs, rx_new_epochs) = test_utils::test_new_epochs_channel!(1);
+
+    let (_tx_state_digests, rx_state_digests) = test_utils::test_state_digests_channel!(1);
+    let (tx_block_removal_commands, rx_block_removal_commands) =
+        test_utils::test_block_removal_commands!(1);
     Primary::spawn(
         name.clone(),
         keypair.copy(),
+        /* coin_signer */ None,
         network_keypair,
         Arc::new(ArcSwap::from_pointee(committee.clone())),
         worker_cache,
@@ -106,17 +113,23 @@ async fn test_rounds_errors() {
         store_primary.certificate_store,
         store_primary.payload_store,
         store_primary.vote_digest_store,
+        store_primary.equivocation_store.clone(),
+        store_primary.divergence_store.clone(),
         /* tx_consensus */ tx_new_certificates,
         /* rx_consensus */ rx_feedback,
         /* external_consensus */
         tx_get_block_commands,
         rx_get_block_commands,
+        tx_block_removal_commands,
+        rx_block_removal_commands,
         Some(Arc::new(
             Dag::new(&no_name_committee, rx_new_certificates, consensus_metrics).1,
         )),
         NetworkModel::Asynchronous,
         tx_reconfigure,
         tx_feedback,
+        rx_new_epochs,
+        rx_state_digests,
         &Registry::new(),
     );
 
@@ -181,9 +194,15 @@ async fn test_rounds_return_successful_response() {
     let consensus_metrics = Arc::new(ConsensusMetrics::new(&Registry::new()));
     let dag = Arc::new(Dag::new(&committee, rx_new_certificates, consensus_metrics).1);
 
+    let (_tx_new_epochs, rx_new_epochs) = test_utils::test_new_epochs_channel!(1);
+
+    let (_tx_state_digests, rx_state_digests) = test_utils::test_state_digests_channel!(1);
+    let (tx_block_removal_commands, rx_block_removal_commands) =
+        test_utils::test_block_removal_commands!(1);
     Primary::spawn(
         name.clone(),
         keypair.copy(),
+        /* coin_signer */ None,
         author.network_keypair().copy(),
         Arc::new(ArcSwap::from_pointee(committee.clone())),
         worker_cache,
@@ -192,14 +211,20 @@ async fn test_rounds_return_successful_response() {
         store_primary.certificate_store,
         store_primary.payload_store,
         store_primary.vote_digest_store,
+        store_primary.equivocation_store.clone(),
+        store_primary.divergence_store.clone(),
         /* tx_consensus */ tx_new_certificates,
         /* rx_consensus */ rx_feedback,
         tx_get_block_commands,
         rx_get_block_commands,
+        tx_block_removal_commands,
+        rx_block_removal_commands,
         /* external_consensus */ Some(dag.clone()),
         NetworkModel::Asynchronous,
         tx_reconfigure,
         tx_feedback,
+        rx_new_epochs,
+        rx_state_digests,
         &Registry::new(),
     );
 
@@ -335,9 +360,14 @@ async fn test_node_read_causal_signed_certificates() {
         test_utils::test_get_block_commands!(1);
 
     // Spawn Primary 1 that we will be interacting with.
+    let (_tx_new_epochs, rx_new_epochs) = test_utils::test_new_epochs_channel!(1);
+    let (_tx_state_digests, rx_state_digests) = test_utils::test_state_digests_channel!(1);
+    let (tx_block_removal_commands, rx_block_removal_commands) =
+        test_utils::test_block_removal_commands!(1);
     Primary::spawn(
         name_1.clone(),
         keypair_1.copy(),
+        /* coin_signer */ None,
         authority_1.network_keypair().copy(),
         Arc::new(ArcSwap::from_pointee(committee.clone())),
         worker_cache.clone(),
@@ -346,14 +376,20 @@ async fn test_node_read_causal_signed_certificates() {
         primary_store_1.certificate_store.clone(),
         primary_store_1.payload_store.clone(),
         primary_store_1.vote_digest_store.clone(),
+        primary_store_1.equivocation_store.clone(),
+        primary_store_1.divergence_store.clone(),
         /* tx_consensus */ tx_new_certificates,
         /* rx_consensus */ rx_feedback,
         tx_get_block_commands_1,
         rx_get_block_commands_1,
+        tx_block_removal_commands,
+        rx_block_removal_commands,
         /* dag */ Some(dag.clone()),
         NetworkModel::Asynchronous,
         tx_reconfigure,
         tx_feedback,
+        rx_new_epochs,
+        rx_state_digests,
         &Registry::new(),
     );
 
@@ -376,9 +412,14 @@ async fn test_node_read_causal_signed_certificates() {
         test_utils::test_get_block_commands!(1);
 
     // Spawn Primary 2
+    let (_tx_new_epochs, rx_new_epochs) = test_utils::test_new_epochs_channel!(1);
+    let (_tx_state_digests, rx_state_digests) = test_utils::test_state_digests_channel!(1);
+    let (tx_block_removal_commands, rx_block_removal_commands) =
+        test_utils::test_block_removal_commands!(1);
     Primary::spawn(
         name_2.clone(),
         keypair_2.copy(),
+        /* coin_signer */ None,
         authority_2.network_keypair().copy(),
         Arc::new(ArcSwap::from_pointee(committee.clone())),
         worker_cache.clone(),
@@ -387,17 +428,23 @@ async fn test_node_read_causal_signed_certificates() {
         primary_store_2.certificate_store,
         primary_store_2.payload_store,
         primary_store_2.vote_digest_store,
+        primary_store_2.equivocation_store.clone(),
+        primary_store_2.divergence_store.clone(),
         /* tx_consensus */ tx_new_certificates_2,
         /* rx_consensus */ rx_feedback_2,
         /* external_consensus */
         tx_get_block_commands_2,
         rx_get_block_commands_2,
+        tx_block_removal_commands,
+        rx_block_removal_commands,
         Some(Arc::new(
             Dag::new(&committee, rx_new_certificates_2, consensus_metrics_2).1,
         )),
         NetworkModel::Asynchronous,
         tx_reconfigure,
         tx_feedback_2,
+        rx_new_epochs,
+        rx_state_digests,
         &Registry::new(),
     );
 
@@ -0,0 +1,279 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use config::utils::get_available_port;
+use fastcrypto::Hash;
+use multiaddr::Multiaddr;
+use node::NodeStorage;
+use primary::{SequencerGrpc, Snapshot};
+use std::{
+    collections::{BTreeSet, HashMap},
+    time::Duration,
+};
+use test_utils::{fixture_batch_with_transactions, temp_dir, CommitteeFixture};
+use tokio::time::timeout;
+use tonic::Code;
+use types::{
+    Certificate, CertificateDigest, CertificateDigestProto, CommittedCertificatesRequest,
+    ConsensusStore, Empty, Equivocation, EvidenceClient, GetEquivocationsRequest, PublicKeyProto,
+    SequencedCertificate, SequencerClient, SubscribeConsensusOutputRequest,
+};
+
+#[tokio::test]
+async fn test_subscribe_consensus_output() {
+    // GIVEN a sequence of certificates
+    let fixture = CommitteeFixture::builder().build();
+    let committee = fixture.committee();
+    let keys: Vec<_> = fixture.authorities().map(|a| a.public_key()).collect();
+    let genesis: BTreeSet<_> = Certificate::genesis(&committee)
+        .iter()
+        .map(|x| x.digest())
+        .collect();
+    let (certificates, _) =
+        test_utils::make_optimal_certificates(&committee, 1..=2, &genesis, &keys);
+    let certificates: Vec<_> = certificates.into_iter().collect();
+
+    // AND the first half of it is already sequenced, with the batches of the first certificate
+    // available to the executor
+    let store = NodeStorage::reopen(temp_dir());
+    for certificate in &certificates {
+        store.certificate_store.write(certificate.clone()).unwrap();
+    }
+    let (sequenced, pending) = certificates.split_at(certificates.len() / 2);
+    for (consensus_index, certificate) in sequenced.iter().enumerate() {
+        sequence(&store.consensus_store, consensus_index as u64, certificate);
+    }
+    let batch = fixture_batch_with_transactions(3);
+    for digest in sequenced[0].header.payload.keys() {
+        store
+            .temp_batch_store
+            .write((sequenced[0].digest(), *digest), batch.clone())
+            .await;
+    }
+
+    // AND the sequencer gRPC server
+    let address: Multiaddr = format!(
+        "/ip4/127.0.0.1/tcp/{}/http",
+        get_available_port("127.0.0.1")
+    )
+    .parse()
+    .unwrap();
+    let (tx_get_block_commands, _rx_get_block_commands) = test_utils::test_get_block_commands!(1);
+    let _handle = SequencerGrpc::spawn(
+        address.clone(),
+        store.consensus_store.clone(),
+        store.certificate_store.clone(),
+        store.temp_batch_store.clone(),
+        tx_get_block_commands,
+        Duration::from_secs(1),
+        50,
+        store.equivocation_store.clone(),
+        store.divergence_store.clone(),
+    );
+
+    // AND Wait for tasks to start
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let config = mysten_network::config::Config::new();
+    let mut client = SequencerClient::new(config.connect_lazy(&address).unwrap());
+
+    // WHEN we subscribe to the output, including the batches
+    let mut stream = client
+        .subscribe_consensus_output(SubscribeConsensusOutputRequest {
+            start_index: 0,
+            include_batches: true,
+        })
+        .await
+        .unwrap()
+        .into_inner();
+
+    // THEN the first certificate comes with its batches
+    let output = stream.message().await.unwrap().unwrap();
+    assert_eq!(output.consensus_index, 0);
+    assert_eq!(
+        output.certificate_id,
+        Some(CertificateDigestProto::from(sequenced[0].digest()))
+    );
+    let certificate: Certificate = bincode::deserialize(&output.certificate).unwrap();
+    assert_eq!(certificate, sequenced[0]);
+    assert_eq!(output.batches.len(), sequenced[0].header.payload.len());
+    for batch_proto in output.batches {
+        assert_eq!(batch_proto.transactions.len(), batch.0.len());
+    }
+
+    // WHEN we resume from the middle of the sequence, without the batches
+    let start_index = 2;
+    let mut stream = client
+        .subscribe_consensus_output(SubscribeConsensusOutputRequest {
+            start_index,
+            include_batches: false,
+        })
+        .await
+        .unwrap()
+        .into_inner();
+
+    // THEN we first receive the certificates already sequenced
+    for (consensus_index, certificate) in sequenced.iter().enumerate().skip(start_index as usize) {
+        let output = stream.message().await.unwrap().unwrap();
+        assert_eq!(output.consensus_index, consensus_index as u64);
+        assert_eq!(
+            output.certificate_id,
+            Some(CertificateDigestProto::from(certificate.digest()))
+        );
+        assert!(output.batches.is_empty());
+    }
+
+    // AND then the certificates as they get sequenced
+    for (i, certificate) in pending.iter().enumerate() {
+        let consensus_index = (sequenced.len() + i) as u64;
+        sequence(&store.consensus_store, consensus_index, certificate);
+
+        let output = timeout(Duration::from_secs(5), stream.message())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(output.consensus_index, consensus_index);
+        assert_eq!(
+            output.certificate_id,
+            Some(CertificateDigestProto::from(certificate.digest()))
+        );
+    }
+
+    // WHEN we look up the position of a certificate in the sequence
+    let response = client
+        .get_consensus_index(CertificateDigestProto::from(pending[1].digest()))
+        .await
+        .unwrap();
+
+    // THEN we get the index at which it was sequenced
+    assert_eq!(
+        response.into_inner().consensus_index,
+        (sequenced.len() + 1) as u64
+    );
+
+    // AND a certificate that was never sequenced is not found
+    let status = client
+        .get_consensus_index(CertificateDigestProto::from(CertificateDigest::default()))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+
+    // WHEN we look up the certificates committed by the leader of round 1
+    let response = client
+        .get_committed_certificates(CommittedCertificatesRequest { leader_round: 1 })
+        .await
+        .unwrap();
+
+    // THEN we get all the certificates of round 1, in order
+    let expected: Vec<_> = sequenced
+        .iter()
+        .enumerate()
+        .map(|(consensus_index, certificate)| SequencedCertificate {
+            certificate_id: Some(certificate.digest().into()),
+            consensus_index: consensus_index as u64,
+        })
+        .collect();
+    assert_eq!(response.into_inner().certificates, expected);
+
+    // WHEN we export a snapshot of the consensus state
+    let response = client.export_snapshot(Empty {}).await.unwrap();
+
+    // THEN it holds the last sequenced certificate and all the certificates of the store
+    let snapshot: Snapshot = bincode::deserialize(&response.into_inner().snapshot).unwrap();
+    let last = certificates.last().unwrap();
+    assert_eq!(
+        snapshot.last_sequenced,
+        Some((certificates.len() as u64 - 1, last.digest(), last.round()))
+    );
+    assert_eq!(snapshot.certificates.len(), certificates.len());
+}
+
+#[tokio::test]
+async fn test_get_equivocations() {
+    // GIVEN an authority that signed two different headers for the same round
+    let fixture = CommitteeFixture::builder().build();
+    let committee = fixture.committee();
+    let author = fixture.authorities().next().unwrap();
+    let header = author.header(&committee);
+    let conflicting = author
+        .header_builder(&committee)
+        .with_payload_batch(fixture_batch_with_transactions(10), 0)
+        .build(author.keypair())
+        .unwrap();
+    let equivocation = Equivocation::Headers(header, conflicting);
+
+    // AND the proof is in the store
+    let store = NodeStorage::reopen(temp_dir());
+    store
+        .equivocation_store
+        .write(equivocation.digest(), equivocation.clone())
+        .await;
+
+    // AND the sequencer gRPC server
+    let address: Multiaddr = format!(
+        "/ip4/127.0.0.1/tcp/{}/http",
+        get_available_port("127.0.0.1")
+    )
+    .parse()
+    .unwrap();
+    let (tx_get_block_commands, _rx_get_block_commands) = test_utils::test_get_block_commands!(1);
+    let _handle = SequencerGrpc::spawn(
+        address.clone(),
+        store.consensus_store.clone(),
+        store.certificate_store.clone(),
+        store.temp_batch_store.clone(),
+        tx_get_block_commands,
+        Duration::from_secs(1),
+        50,
+        store.equivocation_store.clone(),
+        store.divergence_store.clone(),
+    );
+
+    // AND Wait for tasks to start
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let config = mysten_network::config::Config::new();
+    let mut client = EvidenceClient::new(config.connect_lazy(&address).unwrap());
+
+    // WHEN we export the equivocations of the current epoch
+    let response = client
+        .get_equivocations(GetEquivocationsRequest {
+            from_epoch: committee.epoch(),
+        })
+        .await
+        .unwrap();
+
+    // THEN we get a verifiable proof against the author
+    let equivocations = response.into_inner().equivocations;
+    assert_eq!(equivocations.len(), 1);
+    assert_eq!(
+        equivocations[0].offender,
+        Some(PublicKeyProto::from(author.public_key()))
+    );
+    assert_eq!(equivocations[0].round, equivocation.round());
+    let proof: Equivocation = bincode::deserialize(&equivocations[0].proof).unwrap();
+    assert_eq!(proof.digest(), equivocation.digest());
+    assert!(proof.verify().is_ok());
+
+    // AND nothing is returned for the later epochs
+    let response = client
+        .get_equivocations(GetEquivocationsRequest {
+            from_epoch: committee.epoch() + 1,
+        })
+        .await
+        .unwrap();
+    assert!(response.into_inner().equivocations.is_empty());
+}
+
+/// Persist a certificate in the sequence, as consensus would, as if every round had a leader
+/// committing its own certificates.
+fn sequence(store: &ConsensusStore, consensus_index: u64, certificate: &Certificate) {
+    store
+        .write_consensus_state(
+            &HashMap::new(),
+            &(consensus_index + 1),
+            &certificate.digest(),
+            &certificate.round(),
+        )
+        .unwrap();
+}
@@ -6,7 +6,7 @@ use consensus::{dag::Dag, metrics::ConsensusMetrics};
 use crypto::PublicKey;
 use fastcrypto::{traits::KeyPair as _, Hash};
 use indexmap::IndexMap;
-use network::metrics::WorkerNetworkMetrics;
+use network::metrics::{ReputationMetrics, WorkerNetworkMetrics};
 use node::NodeStorage;
 use primary::{NetworkModel, PayloadToken, Primary, CHANNEL_CAPACITY};
 use prometheus::Registry;
@@ -31,7 +31,7 @@ use types::{
 };
 use worker::{
     metrics::{Metrics, WorkerChannelMetrics, WorkerEndpointMetrics, WorkerMetrics},
-    Worker,
+    TrivialTransactionValidator, Worker,
 };
 
 #[tokio::test]
@@ -108,9 +108,15 @@ async fn test_get_collections() {
     let (tx_reconfigure, _rx_reconfigure) = watch::channel(initial_committee);
     let consensus_metrics = Arc::new(ConsensusMetrics::new(&Registry::new()));
 
+    let (_tx_new_epochs, rx_new_epochs) = test_utils::test_new_epochs_channel!(1);
+
+    let (_tx_state_digests, rx_state_digests) = test_utils::test_state_digests_channel!(1);
+    let (tx_block_removal_commands, rx_block_removal_commands) =
+        test_utils::test_block_removal_commands!(1);
     Primary::spawn(
         name.clone(),
         signer.copy(),
+        /* coin_signer */ None,
         author.network_keypair().copy(),
         Arc::new(ArcSwap::from_pointee(committee.clone())),
         worker_cache.clone(),
@@ -119,17 +125,23 @@ async fn test_get_collections() {
         store.certificate_store.clone(),
         store.payload_store.clone(),
         store.vote_digest_store,
+        store.equivocation_store.clone(),
+        store.divergence_store.clone(),
         /* tx_consensus */ tx_new_certificates,
         /* rx_consensus */ rx_feedback,
         /* dag */
         tx_get_block_commands,
         rx_get_block_commands,
+        tx_block_removal_commands,
+        rx_block_removal_commands,
         Some(Arc::new(
             Dag::new(&committee, rx_new_certificates, consensus_metrics).1,
         )),
         NetworkModel::Asynchronous,
         tx_reconfigure,
         tx_feedback,
+        rx_new_epochs,
+        rx_state_digests,
         &Registry::new(),
     );
 
@@ -139,6 +151,7 @@ async fn test_get_collections() {
         channel_metrics: Some(WorkerChannelMetrics::new(&registry)),
         endpoint_metrics: Some(WorkerEndpointMetrics::new(&registry)),
         network_metrics: Some(WorkerNetworkMetrics::new(&registry)),
+        reputation_metrics: Some(ReputationMetrics::new(&registry, "worker")),
     };
 
     // Spawn a `Worker` instance.
@@ -149,7 +162,9 @@ async fn test_get_collections() {
         Arc::new(ArcSwap::from_pointee(committee.clone())),
         worker_cache.clone(),
         parameters.clone(),
+        TrivialTransactionValidator::default(),
         store.batch_store.clone(),
+        store.chunk_store.clone(),
         metrics,
     );
 
@@ -304,9 +319,15 @@ async fn test_remove_collections() {
     let initial_committee = ReconfigureNotification::NewEpoch(committee.clone());
     let (tx_reconfigure, _rx_reconfigure) = watch::channel(initial_committee);
 
+    let (_tx_new_epochs, rx_new_epochs) = test_utils::test_new_epochs_channel!(1);
+
+    let (_tx_state_digests, rx_state_digests) = test_utils::test_state_digests_channel!(1);
+    let (tx_block_removal_commands, rx_block_removal_commands) =
+        test_utils::test_block_removal_commands!(1);
     Primary::spawn(
         name.clone(),
         signer.copy(),
+        /* coin_signer */ None,
         author.network_keypair().copy(),
         Arc::new(ArcSwap::from_pointee(committee.clone())),
         worker_cache.clone(),
@@ -315,14 +336,20 @@ async fn test_remove_collections() {
         store.certificate_store.clone(),
         store.payload_store.clone(),
         store.vote_digest_store.clone(),
+        store.equivocation_store.clone(),
+        store.divergence_store.clone(),
         /* tx_consensus */ tx_new_certificates,
         /* rx_consensus */ rx_feedback,
         tx_get_block_commands,
         rx_get_block_commands,
+        tx_block_removal_commands,
+        rx_block_removal_commands,
         /* dag */ Some(dag.clone()),
         NetworkModel::Asynchronous,
         tx_reconfigure,
         tx_feedback,
+        rx_new_epochs,
+        rx_state_digests,
         &Registry::new(),
     );
 
@@ -359,6 +386,7 @@ async fn test_remove_collections() {
         channel_metrics: Some(WorkerChannelMetrics::new(&registry)),
         endpoint_metrics: Some(WorkerEndpointMetrics::new(&registry)),
         network_metrics: Some(WorkerNetworkMetrics::new(&registry)),
+        reputation_metrics: Some(ReputationMetrics::new(&registry, "worker")),
     };
 
     // Spawn a `Worker` instance.
@@ -369,7 +397,9 @@ async fn test_remove_collections() {
         Arc::new(ArcSwap::from_pointee(committee.clone())),
         worker_cache.clone(),
         parameters.clone(),
+        TrivialTransactionValidator::default(),
         store.batch_store.clone(),
+        store.chunk_store.clone(),
         metrics,
     );
 
@@ -523,9 +553,14 @@ async fn test_read_causal_signed_certificates() {
         test_utils::test_get_block_commands!(1);
 
     // Spawn Primary 1 that we will be interacting with.
+    let (_tx_new_epochs, rx_new_epochs) = test_utils::test_new_epochs_channel!(1);
+    let (_tx_state_digests, rx_state_digests) = test_utils::test_state_digests_channel!(1);
+    let (tx_block_removal_commands, rx_block_removal_commands) =
+        test_utils::test_block_removal_commands!(1);
     Primary::spawn(
         name_1.clone(),
         keypair_1.copy(),
+        /* coin_signer */ None,
         authority_1.network_keypair().copy(),
         Arc::new(ArcSwap::from_pointee(committee.clone())),
         worker_cache.clone(),
@@ -534,14 +569,20 @@ async fn test_read_causal_signed_certificates() {
         primary_store_1.certificate_store.clone(),
         primary_store_1.payload_store.clone(),
         primary_store_1.vote_digest_store.clone(),
+        primary_store_1.equivocation_store.clone(),
+        primary_store_1.divergence_store.clone(),
         /* tx_consensus */ tx_new_certificates,
         /* rx_consensus */ rx_feedback,
         tx_get_block_commands_1,
         rx_get_block_commands_1,
+        tx_block_removal_commands,
+        rx_block_removal_commands,
         /* dag */ Some(dag.clone()),
         NetworkModel::Asynchronous,
         tx_reconfigure,
         tx_feedback,
+        rx_new_epochs,
+        rx_state_digests,
         &Registry::new(),
     );
 
@@ -564,9 +605,14 @@ async fn test_read_causal_signed_certificates() {
     let consensus_metrics_2 = Arc::new(ConsensusMetrics::new(&Registry::new()));
 
     // Spawn Primary 2
+    let (_tx_new_epochs, rx_new_epochs) = test_utils::test_new_epochs_channel!(1);
+    let (_tx_state_digests, rx_state_digests) = test_utils::test_state_digests_channel!(1);
+    let (tx_block_removal_commands, rx_block_removal_commands) =
+        test_utils::test_block_removal_commands!(1);
     Primary::spawn(
         name_2.clone(),
         keypair_2.copy(),
+        /* coin_signer */ None,
         authority_2.network_keypair().copy(),
         Arc::new(ArcSwap::from_pointee(committee.clone())),
         worker_cache.clone(),
@@ -575,10 +621,14 @@ async fn test_read_causal_signed_certificates() {
         primary_store_2.certificate_store,
         primary_store_2.payload_store,
         primary_store_2.vote_digest_store,
+        primary_store_2.equivocation_store.clone(),
+        primary_store_2.divergence_store.clone(),
         /* tx_consensus */ tx_new_certificates_2,
         /* rx_consensus */ rx_feedback_2,
         tx_get_block_commands_2,
         rx_get_block_commands_2,
+        tx_block_removal_commands,
+        rx_block_removal_commands,
         /* external_consensus */
         Some(Arc::new(
             Dag::new(&committee, rx_new_certificates_2, consensus_metrics_2).1,
@@ -586,6 +636,8 @@ async fn test_read_causal_signed_certificates() {
         NetworkModel::Asynchronous,
         tx_reconfigure,
         tx_feedback_2,
+        rx_new_epochs,
+        rx_state_digests,
         &Registry::new(),
     );
 
@@ -736,9 +788,14 @@ async fn test_read_causal_unsigned_certificates() {
     let (tx_reconfigure, _rx_reconfigure) = watch::channel(initial_committee);
 
     // Spawn Primary 1 that we will be interacting with.
+    let (_tx_new_epochs, rx_new_epochs) = test_utils::test_new_epochs_channel!(1);
+    let (_tx_state_digests, rx_state_digests) = test_utils::test_state_digests_channel!(1);
+    let (tx_block_removal_commands, rx_block_removal_commands) =
+        test_utils::test_block_removal_commands!(1);
     Primary::spawn(
         name_1.clone(),
         keypair_1.copy(),
+        /* coin_signer */ None,
         authority_1.network_keypair().copy(),
         Arc::new(ArcSwap::from_pointee(committee.clone())),
         worker_cache.clone(),
@@ -747,14 +804,20 @@ async fn test_read_causal_unsigned_certificates() {
         primary_store_1.certificate_store.clone(),
         primary_store_1.payload_store.clone(),
         primary_store_1.vote_digest_store.clone(),
+        primary_store_1.equivocation_store.clone(),
+        primary_store_1.divergence_store.clone(),
         /* tx_consensus */ tx_new_certificates,
         /* rx_consensus */ rx_feedback,
         tx_get_block_commands_1,
         rx_get_block_commands_1,
+        tx_block_removal_commands,
+        rx_block_removal_commands,
         /* dag */ Some(dag.clone()),
         NetworkModel::Asynchronous,
         tx_reconfigure,
         tx_feedback,
+        rx_new_epochs,
+        rx_state_digests,
         &Registry::new(),
     );
 
@@ -770,9 +833,14 @@ async fn test_read_causal_unsigned_certificates() {
     let consensus_metrics_2 = Arc::new(ConsensusMetrics::new(&Registry::new()));
 
     // Spawn Primary 2
+    let (_tx_new_epochs, rx_new_epochs) = test_utils::test_new_epochs_channel!(1);
+    let (_tx_state_digests, rx_state_digests) = test_utils::test_state_digests_channel!(1);
+    let (tx_block_removal_commands, rx_block_removal_commands) =
+        test_utils::test_block_removal_commands!(1);
     Primary::spawn(
         name_2.clone(),
         keypair_2.copy(),
+        /* coin_signer */ None,
         network_keypair_2,
         Arc::new(ArcSwap::from_pointee(committee.clone())),
         worker_cache.clone(),
@@ -781,10 +849,14 @@ async fn test_read_causal_unsigned_certificates() {
         primary_store_2.certificate_store,
         primary_store_2.payload_store,
         primary_store_2.vote_digest_store,
+        primary_store_2.equivocation_store.clone(),
+        primary_store_2.divergence_store.clone(),
         /* tx_consensus */ tx_new_certificates_2,
         /* rx_consensus */ rx_feedback_2,
         tx_get_block_commands_2,
         rx_get_block_commands_2,
+        tx_block_removal_commands,
+        rx_block_removal_commands,
         /* external_consensus */
         Some(Arc::new(
             Dag::new(&committee, rx_new_certificates_2, consensus_metrics_2).1,
@@ -792,6 +864,8 @@ async fn test_read_causal_unsigned_certificates() {
         NetworkModel::Asynchronous,
         tx_reconfigure,
         tx_feedback_2,
+        rx_new_epochs,
+        rx_state_digests,
         &Registry::new(),
     );
 
@@ -933,9 +1007,15 @@ async fn test_get_collections_with_missing_certificates() {
     let (tx_reconfigure, _rx_reconfigure) = watch::channel(initial_committee);
     let consensus_metrics = Arc::new(ConsensusMetrics::new(&Registry::new()));
 
+    let (_tx_new_epochs, rx_new_epochs) = test_utils::test_new_epochs_channel!(1);
+
+    let (_tx_state_digests, rx_state_digests) = test_utils::test_state_digests_channel!(1);
+    let (tx_block_removal_commands, rx_block_removal_commands) =
+        test_utils::test_block_removal_commands!(1);
     Primary::spawn(
         name_1.clone(),
         authority_1.keypair().copy(),
+        /* coin_signer */ None,
         authority_1.network_keypair().copy(),
         Arc::new(ArcSwap::from_pointee(committee.clone())),
         worker_cache.clone(),
@@ -944,17 +1024,23 @@ async fn test_get_collections_with_missing_certificates() {
         store_primary_1.certificate_store,
         store_primary_1.payload_store,
         store_primary_1.vote_digest_store,
+        store_primary_1.equivocation_store.clone(),
+        store_primary_1.divergence_store.clone(),
         /* tx_consensus */ tx_new_certificates_1,
         /* rx_consensus */ rx_feedback_1,
         /* external_consensus */
         tx_get_block_commands_1,
         rx_get_block_commands_1,
+        tx_block_removal_commands,
+        rx_block_removal_commands,
         Some(Arc::new(
             Dag::new(&committee, rx_new_certificates_1, consensus_metrics).1,
         )),
         NetworkModel::Asynchronous,
         tx_reconfigure,
         tx_feedback_1,
+        rx_new_epochs,
+        rx_state_digests,
         &Registry::new(),
     );
 
@@ -964,6 +1050,7 @@ async fn test_get_collections_with_missing_certificates() {
         channel_metrics: Some(WorkerChannelMetrics::new(&registry_1)),
         endpoint_metrics: Some(WorkerEndpointMetrics::new(&registry_1)),
         network_metrics: Some(WorkerNetworkMetrics::new(&registry_1)),
+        reputation_metrics: Some(ReputationMetrics::new(&registry_1, "worker")),
     };
 
     // Spawn a `Worker` instance for primary 1.
@@ -974,7 +1061,9 @@ async fn test_get_collections_with_missing_certificates() {
         Arc::new(ArcSwap::from_pointee(committee.clone())),
         worker_cache.clone(),
         parameters.clone(),
+        TrivialTransactionValidator::default(),
         store_primary_1.batch_store,
+        store_primary_1.chunk_store,
         metrics_1,
     );
 
@@ -988,9 +1077,15 @@ async fn test_get_collections_with_missing_certificates() {
     let initial_committee = ReconfigureNotification::NewEpoch(committee.clone());
     let (tx_reconfigure, _rx_reconfigure) = watch::channel(initial_committee);
 
+    let (_tx_new_epochs, rx_new_epochs) = test_utils::test_new_epochs_channel!(1);
+
+    let (_tx_state_digests, rx_state_digests) = test_utils::test_state_digests_channel!(1);
+    let (tx_block_removal_commands, rx_block_removal_commands) =
+        test_utils::test_block_removal_commands!(1);
     Primary::spawn(
         name_2.clone(),
         authority_2.keypair().copy(),
+        /* coin_signer */ None,
         authority_2.network_keypair().copy(),
         Arc::new(ArcSwap::from_pointee(committee.clone())),
         worker_cache.clone(),
@@ -999,15 +1094,21 @@ async fn test_get_collections_with_missing_certificates() {
         store_primary_2.certificate_store,
         store_primary_2.payload_store,
         store_primary_2.vote_digest_store,
+        store_primary_2.equivocation_store.clone(),
+        store_primary_2.divergence_store.clone(),
         /* tx_consensus */ tx_new_certificates_2,
         /* rx_consensus */ rx_feedback_2,
         tx_get_block_commands_2,
         rx_get_block_commands_2,
+        tx_block_removal_commands,
+        rx_block_removal_commands,
         /* external_consensus */
         None,
         NetworkModel::Asynchronous,
         tx_reconfigure,
         tx_feedback_2,
+        rx_new_epochs,
+        rx_state_digests,
         &Registry::new(),
     );
 
@@ -1017,6 +1118,7 @@ async fn test_get_collections_with_missing_certificates() {
         channel_metrics: Some(WorkerChannelMetrics::new(&registry_2)),
         endpoint_metrics: Some(WorkerEndpointMetrics::new(&registry_2)),
         network_metrics: Some(WorkerNetworkMetrics::new(&registry_2)),
+        reputation_metrics: Some(ReputationMetrics::new(&registry_2, "worker")),
     };
 
     // Spawn a `Worker` instance for primary 2.
@@ -1027,7 +1129,9 @@ async fn test_get_collections_with_missing_certificates() {
         Arc::new(ArcSwap::from_pointee(committee.clone())),
         worker_cache.clone(),
         parameters.clone(),
+        TrivialTransactionValidator::default(),
         store_primary_2.batch_store,
+        store_primary_2.chunk_store,
         metrics_2,
     );
 
@@ -2,7 +2,11 @@
 // SPDX-License-Identifier: Apache-2.0
 use dashmap::DashMap;
 use fastcrypto::Hash;
-use std::{collections::VecDeque, iter, sync::Arc};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    iter,
+    sync::Arc,
+};
 use store::{
     rocks::{DBMap, TypedStoreError::RocksDBError},
     Map,
@@ -240,6 +244,26 @@ impl CertificateStore {
             .collect()
     }
 
+    /// Retrieves the digests of the certificates with `from` <= round < `from + max_rounds`,
+    /// grouped by round. Only the secondary index is read.
+    pub fn digests_by_round(
+        &self,
+        from: Round,
+        max_rounds: u64,
+    ) -> StoreResult<BTreeMap<Round, Vec<CertificateDigest>>> {
+        let key = (from, CertificateDigest::default());
+        let to = from.saturating_add(max_rounds);
+
+        let mut digests = BTreeMap::<Round, Vec<CertificateDigest>>::new();
+        for (round, digest) in self.certificate_ids_by_round.keys().skip_to(&key)? {
+            if round >= to {
+                break;
+            }
+            digests.entry(round).or_default().push(digest);
+        }
+        Ok(digests)
+    }
+
     /// Retrieves the certificates of the last round
     pub fn last_round(&self) -> StoreResult<Vec<Certificate>> {
         // starting from the last element - hence the last round - move backwards until
@@ -415,6 +439,33 @@ mod test {
         }
     }
 
+    #[tokio::test]
+    async fn test_digests_by_round() {
+        // GIVEN
+        let store = new_store(temp_dir());
+
+        // create certificates for 10 rounds
+        let certs = certificates(10);
+        store.write_all(certs.clone()).unwrap();
+
+        // WHEN
+        let result = store.digests_by_round(3, 4).unwrap();
+
+        // THEN
+        assert_eq!(result.keys().copied().collect::<Vec<_>>(), vec![3, 4, 5, 6]);
+        for (round, digests) in result {
+            let expected: HashSet<_> = certs
+                .iter()
+                .filter(|c| c.round() == round)
+                .map(|c| c.digest())
+                .collect();
+            assert_eq!(digests.into_iter().collect::<HashSet<_>>(), expected);
+        }
+
+        // AND nothing is returned past the last round
+        assert!(store.digests_by_round(11, 4).unwrap().is_empty());
+    }
+
     #[tokio::test]
     async fn test_last_round() {
         // GIVEN
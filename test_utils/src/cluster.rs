@@ -4,7 +4,7 @@ use crate::{temp_dir, CommitteeFixture};
 use arc_swap::ArcSwap;
 use config::{Parameters, SharedCommittee, SharedWorkerCache, WorkerId};
 use crypto::{KeyPair, NetworkKeyPair, PublicKey};
-use executor::{SerializedTransaction, SubscriberResult};
+use executor::{BincodeCodec, SerializedTransaction, SubscriberResult};
 use fastcrypto::traits::KeyPair as _;
 use itertools::Itertools;
 use multiaddr::Multiaddr;
@@ -23,6 +23,7 @@ use tokio::{
 use tonic::transport::Channel;
 use tracing::info;
 use types::{ConfigurationClient, ProposerClient, TransactionsClient};
+use worker::TrivialTransactionValidator;
 
 #[cfg(test)]
 #[path = "tests/cluster_tests.rs"]
@@ -347,6 +348,7 @@ impl PrimaryNodeDetails {
         let primary_store: NodeStorage = NodeStorage::reopen(store_path.clone());
         let mut primary_handlers = Node::spawn_primary(
             self.key_pair.copy(),
+            /* coin_keypair */ None,
             self.network_key_pair.copy(),
             self.committee.clone(),
             self.worker_cache.clone(),
@@ -354,6 +356,7 @@ impl PrimaryNodeDetails {
             self.parameters.clone(),
             /* consensus */ self.internal_consensus_enabled,
             /* execution_state */ Arc::new(SimpleExecutionState::default()),
+            BincodeCodec::default(),
             tx_transaction_confirmation,
             &registry,
         )
@@ -463,6 +466,7 @@ impl WorkerNodeDetails {
             self.worker_cache.clone(),
             &worker_store,
             self.parameters.clone(),
+            TrivialTransactionValidator::default(),
             &registry,
         );
 
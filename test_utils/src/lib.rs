@@ -25,8 +25,8 @@ use store::{reopen, rocks, rocks::DBMap, Store};
 use tokio::sync::mpsc::{channel, Receiver, Sender};
 use tracing::info;
 use types::{
-    Batch, BatchDigest, Certificate, CertificateDigest, ConsensusStore, Header, HeaderBuilder,
-    PrimaryMessage, PrimaryToPrimary, PrimaryToPrimaryServer, PrimaryToWorker,
+    Batch, BatchChunk, BatchDigest, Certificate, CertificateDigest, ConsensusStore, Header,
+    HeaderBuilder, PrimaryMessage, PrimaryToPrimary, PrimaryToPrimaryServer, PrimaryToWorker,
     PrimaryToWorkerServer, PrimaryWorkerMessage, Round, SequenceNumber, Transaction, Vote,
     WorkerBatchRequest, WorkerBatchResponse, WorkerInfoResponse, WorkerMessage,
     WorkerPrimaryMessage, WorkerToPrimary, WorkerToPrimaryServer, WorkerToWorker,
@@ -40,6 +40,8 @@ pub const HEADERS_CF: &str = "headers";
 pub const CERTIFICATES_CF: &str = "certificates";
 pub const CERTIFICATE_ID_BY_ROUND_CF: &str = "certificate_id_by_round";
 pub const PAYLOAD_CF: &str = "payload";
+pub const EQUIVOCATIONS_CF: &str = "equivocations";
+pub const STATE_DIVERGENCES_CF: &str = "state_divergences";
 
 pub fn temp_dir() -> std::path::PathBuf {
     tempfile::tempdir()
@@ -117,6 +119,48 @@ macro_rules! test_get_block_commands {
     };
 }
 
+#[macro_export]
+macro_rules! test_block_removal_commands {
+    ($e:expr) => {
+        types::metered_channel::channel(
+            $e,
+            &prometheus::IntGauge::new(
+                primary::PrimaryChannelMetrics::NAME_BLOCK_REMOVAL_COMMANDS,
+                primary::PrimaryChannelMetrics::DESC_BLOCK_REMOVAL_COMMANDS,
+            )
+            .unwrap(),
+        );
+    };
+}
+
+#[macro_export]
+macro_rules! test_state_digests_channel {
+    ($e:expr) => {
+        types::metered_channel::channel(
+            $e,
+            &prometheus::IntGauge::new(
+                primary::PrimaryChannelMetrics::NAME_STATE_DIGESTS,
+                primary::PrimaryChannelMetrics::DESC_STATE_DIGESTS,
+            )
+            .unwrap(),
+        );
+    };
+}
+
+#[macro_export]
+macro_rules! test_new_epochs_channel {
+    ($e:expr) => {
+        types::metered_channel::channel(
+            $e,
+            &prometheus::IntGauge::new(
+                primary::PrimaryChannelMetrics::NAME_NEW_EPOCHS,
+                primary::PrimaryChannelMetrics::DESC_NEW_EPOCHS,
+            )
+            .unwrap(),
+        );
+    };
+}
+
 ////////////////////////////////////////////////////////////////
 /// Keys, Committee
 ////////////////////////////////////////////////////////////////
@@ -132,16 +176,39 @@ pub fn random_key() -> KeyPair {
 pub fn make_consensus_store(store_path: &std::path::Path) -> Arc<ConsensusStore> {
     const LAST_COMMITTED_CF: &str = "last_committed";
     const SEQUENCE_CF: &str = "sequence";
-
-    let rocksdb = rocks::open_cf(store_path, None, &[LAST_COMMITTED_CF, SEQUENCE_CF])
-        .expect("Failed creating database");
-
-    let (last_committed_map, sequence_map) = reopen!(&rocksdb,
+    const SEQUENCE_BY_CERTIFICATE_CF: &str = "sequence_by_certificate";
+    const SEQUENCE_BY_LEADER_ROUND_CF: &str = "sequence_by_leader_round";
+
+    let rocksdb = rocks::open_cf(
+        store_path,
+        None,
+        &[
+            LAST_COMMITTED_CF,
+            SEQUENCE_CF,
+            SEQUENCE_BY_CERTIFICATE_CF,
+            SEQUENCE_BY_LEADER_ROUND_CF,
+        ],
+    )
+    .expect("Failed creating database");
+
+    let (
+        last_committed_map,
+        sequence_map,
+        sequence_by_certificate_map,
+        sequence_by_leader_round_map,
+    ) = reopen!(&rocksdb,
         LAST_COMMITTED_CF;<PublicKey, Round>,
-        SEQUENCE_CF;<SequenceNumber, CertificateDigest>
+        SEQUENCE_CF;<SequenceNumber, CertificateDigest>,
+        SEQUENCE_BY_CERTIFICATE_CF;<CertificateDigest, SequenceNumber>,
+        SEQUENCE_BY_LEADER_ROUND_CF;<(Round, SequenceNumber), CertificateDigest>
     );
 
-    Arc::new(ConsensusStore::new(last_committed_map, sequence_map))
+    Arc::new(ConsensusStore::new(
+        last_committed_map,
+        sequence_map,
+        sequence_by_certificate_map,
+        sequence_by_leader_round_map,
+    ))
 }
 
 pub fn fixture_payload(number_of_batches: u8) -> IndexMap<BatchDigest, WorkerId> {
@@ -346,9 +413,7 @@ impl WorkerToWorker for WorkerToWorkerMockServer {
         self.batch_request_sender.send(message).await.unwrap();
 
         // For testing stub, just always reply with no batches.
-        Ok(anemo::Response::new(WorkerBatchResponse {
-            batches: vec![],
-        }))
+        Ok(anemo::Response::new(WorkerBatchResponse::default()))
     }
 }
 
@@ -390,6 +455,13 @@ pub fn open_batch_store() -> Store<BatchDigest, Batch> {
     Store::new(db)
 }
 
+const CHUNKS_CF: &str = "chunks";
+
+pub fn open_chunk_store() -> Store<BatchDigest, BatchChunk> {
+    let db = DBMap::<BatchDigest, BatchChunk>::open(temp_dir(), None, Some(CHUNKS_CF)).unwrap();
+    Store::new(db)
+}
+
 // Creates one certificate per authority starting and finishing at the specified rounds (inclusive).
 // Outputs a VecDeque of certificates (the certificate with higher round is on the front) and a set
 // of digests to be used as parents for the certificates of the next round.
@@ -696,6 +768,7 @@ impl CommitteeFixture {
                     (pubkey, authority)
                 })
                 .collect(),
+            coin: None,
         }
     }
 
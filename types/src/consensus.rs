@@ -9,7 +9,7 @@ use store::{
     rocks::{DBMap, TypedStoreError},
     traits::Map,
 };
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, watch};
 
 /// A global sequence number assigned to every certificate.
 pub type SequenceNumber = u64;
@@ -26,6 +26,12 @@ pub struct ConsensusStore {
     last_committed: DBMap<PublicKey, Round>,
     /// The global consensus sequence.
     sequence: DBMap<SequenceNumber, CertificateDigest>,
+    /// The position of each certificate in the global consensus sequence.
+    sequence_by_certificate: DBMap<CertificateDigest, SequenceNumber>,
+    /// The certificates committed by each leader, keyed by leader round and sequence number.
+    sequence_by_leader_round: DBMap<(Round, SequenceNumber), CertificateDigest>,
+    /// Notifies the readers following the sequence of the latest persisted consensus index.
+    tx_sequence_updates: watch::Sender<SequenceNumber>,
 }
 
 impl ConsensusStore {
@@ -33,10 +39,16 @@ impl ConsensusStore {
     pub fn new(
         last_committed: DBMap<PublicKey, Round>,
         sequence: DBMap<SequenceNumber, CertificateDigest>,
+        sequence_by_certificate: DBMap<CertificateDigest, SequenceNumber>,
+        sequence_by_leader_round: DBMap<(Round, SequenceNumber), CertificateDigest>,
     ) -> Self {
+        let (tx_sequence_updates, _) = watch::channel(SequenceNumber::default());
         Self {
             last_committed,
             sequence,
+            sequence_by_certificate,
+            sequence_by_leader_round,
+            tx_sequence_updates,
         }
     }
 
@@ -44,15 +56,19 @@ impl ConsensusStore {
     pub fn clear(&self) -> StoreResult<()> {
         self.last_committed.clear()?;
         self.sequence.clear()?;
+        self.sequence_by_certificate.clear()?;
+        self.sequence_by_leader_round.clear()?;
         Ok(())
     }
 
-    /// Persist the consensus state.
+    /// Persist the consensus state, along with the leader round whose sub-dag the certificate
+    /// was committed with.
     pub fn write_consensus_state(
         &self,
         last_committed: &HashMap<PublicKey, Round>,
         consensus_index: &SequenceNumber,
         certificate_id: &CertificateDigest,
+        leader_round: &Round,
     ) -> Result<(), TypedStoreError> {
         let mut write_batch = self.last_committed.batch();
         write_batch = write_batch.insert_batch(&self.last_committed, last_committed.iter())?;
@@ -60,7 +76,25 @@ impl ConsensusStore {
             &self.sequence,
             std::iter::once((consensus_index, certificate_id)),
         )?;
-        write_batch.write()
+        write_batch = write_batch.insert_batch(
+            &self.sequence_by_certificate,
+            std::iter::once((certificate_id, consensus_index)),
+        )?;
+        write_batch = write_batch.insert_batch(
+            &self.sequence_by_leader_round,
+            std::iter::once(((*leader_round, *consensus_index), certificate_id)),
+        )?;
+        write_batch.write()?;
+
+        // Record the index even when nobody follows the sequence yet.
+        self.tx_sequence_updates.send_replace(*consensus_index);
+        Ok(())
+    }
+
+    /// Subscribe to the updates of the sequence. The receiver is notified every time a new
+    /// certificate is sequenced, with the last consensus index that was persisted.
+    pub fn subscribe(&self) -> watch::Receiver<SequenceNumber> {
+        self.tx_sequence_updates.subscribe()
     }
 
     /// Load the last committed round of each validator.
@@ -68,20 +102,93 @@ impl ConsensusStore {
         self.last_committed.iter().collect()
     }
 
-    /// Load the certificate digests sequenced at a specific indices.
+    /// Load the certificate digests sequenced at a specific indices. The indices that were
+    /// pruned are returned as `None`.
     pub fn read_sequenced_certificates(
         &self,
         missing: &RangeInclusive<SequenceNumber>,
     ) -> StoreResult<Vec<Option<CertificateDigest>>> {
+        self.sequence.multi_get(missing.clone())
+    }
+
+    /// Load the consensus index assigned to a certificate, if it was sequenced.
+    pub fn read_consensus_index(
+        &self,
+        certificate_id: &CertificateDigest,
+    ) -> StoreResult<Option<SequenceNumber>> {
+        // The certificate sequenced at index `i` is persisted under the key `i + 1`.
         Ok(self
-            .sequence
+            .sequence_by_certificate
+            .get(certificate_id)?
+            .map(|index| index - 1))
+    }
+
+    /// Load the certificates committed by the leader of the specified round, along with their
+    /// consensus index, in the order they were sequenced.
+    pub fn read_committed_by_leader_round(
+        &self,
+        leader_round: Round,
+    ) -> StoreResult<Vec<(SequenceNumber, CertificateDigest)>> {
+        Ok(self
+            .sequence_by_leader_round
             .iter()
-            .skip_to(missing.start())?
-            .take_while(|(index, _)| index <= missing.end())
-            .map(|(_, digest)| Some(digest))
+            .skip_to(&(leader_round, SequenceNumber::MIN))?
+            .take_while(|((round, _), _)| *round == leader_round)
+            .map(|((_, index), digest)| (index - 1, digest))
             .collect())
     }
 
+    /// Load the last sequenced certificate, along with its consensus index and the round of the
+    /// leader that committed it.
+    pub fn read_last_sequenced(
+        &self,
+    ) -> StoreResult<Option<(SequenceNumber, CertificateDigest, Round)>> {
+        // The certificate sequenced at index `i` is persisted under the key `i + 1`.
+        Ok(self
+            .sequence_by_leader_round
+            .iter()
+            .skip_to_last()
+            .next()
+            .map(|((leader_round, key), digest)| (key - 1, digest, leader_round)))
+    }
+
+    /// Load the first (ie. the lowest) consensus index that was not pruned, if any.
+    pub fn read_first_consensus_index(&self) -> StoreResult<Option<SequenceNumber>> {
+        // The certificate sequenced at index `i` is persisted under the key `i + 1`.
+        Ok(self.sequence.keys().next().map(|key| key - 1))
+    }
+
+    /// Remove from the sequence all the certificates with a consensus index lower than the
+    /// specified one. The last sequenced certificate is always kept, as it determines the next
+    /// consensus index.
+    pub fn prune(&self, consensus_index: SequenceNumber) -> StoreResult<()> {
+        let last = self.read_last_consensus_index()?;
+        let keys: Vec<_> = self
+            .sequence
+            .iter()
+            .take_while(|(key, _)| *key <= consensus_index && *key < last)
+            .collect();
+        if keys.is_empty() {
+            return Ok(());
+        }
+        let pruned = keys.last().map(|(key, _)| *key).unwrap_or_default();
+        let by_leader_round: Vec<_> = self
+            .sequence_by_leader_round
+            .keys()
+            .take_while(|(_, key)| *key <= pruned)
+            .collect();
+
+        let mut write_batch = self.sequence.batch();
+        write_batch = write_batch.delete_batch(
+            &self.sequence_by_certificate,
+            keys.iter().map(|(_, digest)| *digest),
+        )?;
+        write_batch = write_batch.delete_batch(&self.sequence_by_leader_round, by_leader_round)?;
+        write_batch =
+            write_batch.delete_batch(&self.sequence, keys.into_iter().map(|(key, _)| key))?;
+        write_batch.write()
+    }
+
     /// Load the last (ie. the highest) consensus index associated to a certificate.
     pub fn read_last_consensus_index(&self) -> StoreResult<SequenceNumber> {
         Ok(self
@@ -1,8 +1,8 @@
 // Copyright (c) 2021, Facebook, Inc. and its affiliates
 // Copyright (c) 2022, Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
-use crate::{HeaderDigest, Round};
-use config::Epoch;
+use crate::{HeaderDigest, HeaderLimit, Round};
+use config::{Epoch, WorkerId};
 use fastcrypto::Digest;
 use store::StoreError;
 use thiserror::Error;
@@ -92,4 +92,19 @@ pub enum DagError {
 
     #[error("System shutting down")]
     ShuttingDown,
+
+    #[error("Cannot import a snapshot into a store that is not empty")]
+    NonEmptyStore,
+
+    #[error("Worker {0} is not registered")]
+    UnknownWorker(WorkerId),
+
+    #[error("Invalid worker address: {0}")]
+    InvalidWorkerAddress(String),
+
+    #[error("The messages do not conflict")]
+    NotAnEquivocation,
+
+    #[error("Header {0} exceeds the maximum {1}")]
+    HeaderLimitExceeded(HeaderDigest, HeaderLimit),
 }
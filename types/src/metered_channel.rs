@@ -75,6 +75,11 @@ impl<T> Receiver<T> {
             s => s,
         }
     }
+
+    /// Returns a reference to the underlying gauge.
+    pub fn gauge(&self) -> &IntGauge {
+        &self.gauge
+    }
 }
 
 impl<T> Unpin for Receiver<T> {}
@@ -4,11 +4,13 @@
 use crate::{
     error::{DagError, DagResult},
     serde::NarwhalBitmap,
-    CertificateDigestProto,
+    CertificateDigestProto, SequenceNumber,
 };
 use blake2::{digest::Update, VarBlake2b};
 use bytes::Bytes;
-use config::{Committee, Epoch, SharedWorkerCache, WorkerId, WorkerInfo};
+use config::{
+    Committee, Epoch, HeaderLimitsParameters, SharedWorkerCache, WorkerId, WorkerIndex, WorkerInfo,
+};
 use crypto::{AggregateSignature, PublicKey, Signature};
 use dag::node_dag::Affiliated;
 use derive_builder::Builder;
@@ -62,6 +64,33 @@ impl BatchDigest {
     }
 }
 
+#[derive(Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TransactionDigest(pub [u8; DIGEST_LEN]);
+
+impl fmt::Debug for TransactionDigest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{}", base64::encode(self.0))
+    }
+}
+
+impl fmt::Display for TransactionDigest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{}", base64::encode(self.0).get(0..16).unwrap())
+    }
+}
+
+impl TransactionDigest {
+    pub fn new(val: [u8; DIGEST_LEN]) -> TransactionDigest {
+        TransactionDigest(val)
+    }
+}
+
+/// Hashes a single transaction. Clients use this digest to follow their transactions through
+/// the system.
+pub fn transaction_digest(transaction: &[u8]) -> TransactionDigest {
+    TransactionDigest::new(fastcrypto::blake2b_256(|hasher| hasher.update(transaction)))
+}
+
 impl Hash for Batch {
     type TypedDigest = BatchDigest;
 
@@ -72,6 +101,35 @@ impl Hash for Batch {
     }
 }
 
+/// The limits on the headers received from the other primaries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeaderLimit {
+    PayloadEntries,
+    Parents,
+    Size,
+}
+
+impl HeaderLimit {
+    /// The label of the limit in the metrics.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HeaderLimit::PayloadEntries => "payload_entries",
+            HeaderLimit::Parents => "parents",
+            HeaderLimit::Size => "size",
+        }
+    }
+}
+
+impl fmt::Display for HeaderLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeaderLimit::PayloadEntries => write!(f, "number of payload entries"),
+            HeaderLimit::Parents => write!(f, "number of parents"),
+            HeaderLimit::Size => write!(f, "serialized size"),
+        }
+    }
+}
+
 #[derive(Builder, Clone, Default, Deserialize, MallocSizeOf, Serialize)]
 #[builder(pattern = "owned", build_fn(skip))]
 pub struct Header {
@@ -83,6 +141,9 @@ pub struct Header {
     pub parents: BTreeSet<CertificateDigest>,
     pub id: HeaderDigest,
     pub signature: Signature,
+    /// The author's share of the common coin of this round. It is not part of the header's digest
+    /// as it is uniquely determined by the author, the epoch and the round.
+    pub randomness: Signature,
 }
 
 impl HeaderBuilder {
@@ -98,11 +159,19 @@ impl HeaderBuilder {
             parents: self.parents.unwrap(),
             id: HeaderDigest::default(),
             signature: Signature::default(),
+            randomness: Signature::default(),
         };
 
+        // The randomness share defaults to the signature of the author, as when no coin keys
+        // were dealt to the committee.
+        let randomness = match self.randomness {
+            Some(randomness) => randomness,
+            None => signer.try_sign(coin_message(h.epoch, h.round).as_ref())?,
+        };
         Ok(Header {
             id: h.digest(),
             signature: signer.try_sign(Digest::from(h.digest()).as_ref())?,
+            randomness,
             ..h
         })
     }
@@ -128,6 +197,7 @@ impl Header {
         payload: IndexMap<BatchDigest, WorkerId>,
         parents: BTreeSet<CertificateDigest>,
         signature_service: &mut SignatureService<Signature>,
+        coin_signature_service: &mut SignatureService<Signature>,
     ) -> Self {
         let header = Self {
             author,
@@ -137,16 +207,36 @@ impl Header {
             parents,
             id: HeaderDigest::default(),
             signature: Signature::default(),
+            randomness: Signature::default(),
         };
         let id = header.digest();
         let signature = signature_service.request_signature(id.into()).await;
+        let randomness = coin_signature_service
+            .request_signature(coin_message(epoch, round))
+            .await;
         Self {
             id,
             signature,
+            randomness,
             ..header
         }
     }
 
+    /// Returns the first of the limits that the header exceeds, if any.
+    pub fn check_limits(&self, limits: &HeaderLimitsParameters) -> Result<(), HeaderLimit> {
+        if self.payload.len() > limits.max_payload_entries {
+            return Err(HeaderLimit::PayloadEntries);
+        }
+        if self.parents.len() > limits.max_parents {
+            return Err(HeaderLimit::Parents);
+        }
+        let size = bincode::serialized_size(self).unwrap_or(u64::MAX);
+        if size > limits.max_header_size as u64 {
+            return Err(HeaderLimit::Size);
+        }
+        Ok(())
+    }
+
     pub fn verify(&self, committee: &Committee, worker_cache: SharedWorkerCache) -> DagResult<()> {
         // Ensure the header is from the correct epoch.
         ensure!(
@@ -179,10 +269,38 @@ impl Header {
         let id_digest: Digest = Digest::from(self.id);
         self.author
             .verify(id_digest.as_ref(), &self.signature)
+            .map_err(DagError::from)?;
+
+        // Check the randomness share, against the author's share of the coin if coin keys were
+        // dealt to the committee.
+        let coin_key = match &committee.coin {
+            Some(coin) => {
+                &coin
+                    .share(&self.author)
+                    .ok_or_else(|| DagError::UnknownAuthority(self.author.encode_base64()))?
+                    .public_key
+            }
+            None => &self.author,
+        };
+        coin_key
+            .verify(
+                coin_message(self.epoch, self.round).as_ref(),
+                &self.randomness,
+            )
             .map_err(DagError::from)
     }
 }
 
+/// The message an authority signs to produce its share of the common coin of a round. BLS
+/// signatures are unique, so the share cannot be ground by its author.
+pub fn coin_message(epoch: Epoch, round: Round) -> Digest {
+    Digest::new(fastcrypto::blake2b_256(|hasher| {
+        hasher.update(b"narwhal-common-coin");
+        hasher.update(epoch.to_le_bytes());
+        hasher.update(round.to_le_bytes());
+    }))
+}
+
 #[derive(
     Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq, Hash, PartialOrd, Ord, MallocSizeOf,
 )]
@@ -383,6 +501,396 @@ impl PartialEq for Vote {
     }
 }
 
+/// The workers of an authority, signed by the authority. Authorities broadcast it to add or
+/// remove workers within an epoch.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WorkerIndexUpdate {
+    pub author: PublicKey,
+    pub epoch: Epoch,
+    /// Orders the updates of the author: an update only replaces the updates of lower versions.
+    pub version: u64,
+    pub workers: WorkerIndex,
+    pub signature: <PublicKey as VerifyingKey>::Sig,
+}
+
+impl WorkerIndexUpdate {
+    pub async fn new(
+        author: PublicKey,
+        epoch: Epoch,
+        version: u64,
+        workers: WorkerIndex,
+        signature_service: &mut SignatureService<Signature>,
+    ) -> Self {
+        let update = Self {
+            author,
+            epoch,
+            version,
+            workers,
+            signature: Signature::default(),
+        };
+        let signature = signature_service
+            .request_signature(update.digest().into())
+            .await;
+        Self {
+            signature,
+            ..update
+        }
+    }
+
+    pub fn verify(&self, committee: &Committee) -> DagResult<()> {
+        // Ensure the update is from the correct epoch.
+        ensure!(
+            self.epoch == committee.epoch(),
+            DagError::InvalidEpoch {
+                expected: committee.epoch(),
+                received: self.epoch
+            }
+        );
+
+        // Ensure the authority is part of the committee.
+        ensure!(
+            committee.stake(&self.author) > 0,
+            DagError::UnknownAuthority(self.author.encode_base64())
+        );
+
+        // Check the signature.
+        let digest: Digest = self.digest().into();
+        self.author
+            .verify(digest.as_ref(), &self.signature)
+            .map_err(DagError::from)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Copy)]
+pub struct WorkerIndexUpdateDigest([u8; DIGEST_LEN]);
+
+impl From<WorkerIndexUpdateDigest> for Digest {
+    fn from(digest: WorkerIndexUpdateDigest) -> Self {
+        Digest::new(digest.0)
+    }
+}
+
+impl fmt::Debug for WorkerIndexUpdateDigest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{}", base64::encode(self.0))
+    }
+}
+
+impl fmt::Display for WorkerIndexUpdateDigest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{}", base64::encode(self.0).get(0..16).unwrap())
+    }
+}
+
+impl Hash for WorkerIndexUpdate {
+    type TypedDigest = WorkerIndexUpdateDigest;
+
+    fn digest(&self) -> WorkerIndexUpdateDigest {
+        let hasher_update = |hasher: &mut VarBlake2b| {
+            hasher.update(&self.author);
+            hasher.update(self.epoch.to_le_bytes());
+            hasher.update(self.version.to_le_bytes());
+            hasher.update(
+                bincode::serialize(&self.workers).expect("Failed to serialize worker index"),
+            );
+        };
+
+        WorkerIndexUpdateDigest(fastcrypto::blake2b_256(hasher_update))
+    }
+}
+
+impl fmt::Debug for WorkerIndexUpdate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(
+            f,
+            "{}: W{}({}, {} workers, E{})",
+            self.digest(),
+            self.version,
+            self.author.encode_base64(),
+            self.workers.0.len(),
+            self.epoch
+        )
+    }
+}
+
+/// The digest of the execution state, as computed by the application after executing the
+/// certificates up to some consensus index.
+#[derive(Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StateDigest(pub [u8; DIGEST_LEN]);
+
+impl fmt::Debug for StateDigest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{}", base64::encode(self.0))
+    }
+}
+
+impl fmt::Display for StateDigest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{}", base64::encode(self.0).get(0..16).unwrap())
+    }
+}
+
+impl StateDigest {
+    pub fn new(val: [u8; DIGEST_LEN]) -> StateDigest {
+        StateDigest(val)
+    }
+}
+
+/// The digest of the execution state of an authority once it executed the certificate of the
+/// given consensus index, signed by the authority. Authorities broadcast it so that a
+/// nondeterministic execution is detected.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StateCommitment {
+    pub author: PublicKey,
+    pub epoch: Epoch,
+    pub consensus_index: SequenceNumber,
+    pub state: StateDigest,
+    pub signature: <PublicKey as VerifyingKey>::Sig,
+}
+
+impl StateCommitment {
+    pub async fn new(
+        author: PublicKey,
+        epoch: Epoch,
+        consensus_index: SequenceNumber,
+        state: StateDigest,
+        signature_service: &mut SignatureService<Signature>,
+    ) -> Self {
+        let commitment = Self {
+            author,
+            epoch,
+            consensus_index,
+            state,
+            signature: Signature::default(),
+        };
+        let signature = signature_service
+            .request_signature(commitment.digest().into())
+            .await;
+        Self {
+            signature,
+            ..commitment
+        }
+    }
+
+    pub fn verify(&self, committee: &Committee) -> DagResult<()> {
+        // Ensure the commitment is from the correct epoch.
+        ensure!(
+            self.epoch == committee.epoch(),
+            DagError::InvalidEpoch {
+                expected: committee.epoch(),
+                received: self.epoch
+            }
+        );
+
+        // Ensure the authority is part of the committee.
+        ensure!(
+            committee.stake(&self.author) > 0,
+            DagError::UnknownAuthority(self.author.encode_base64())
+        );
+
+        // Check the signature.
+        let digest: Digest = self.digest().into();
+        self.author
+            .verify(digest.as_ref(), &self.signature)
+            .map_err(DagError::from)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Copy)]
+pub struct StateCommitmentDigest([u8; DIGEST_LEN]);
+
+impl From<StateCommitmentDigest> for Digest {
+    fn from(digest: StateCommitmentDigest) -> Self {
+        Digest::new(digest.0)
+    }
+}
+
+impl fmt::Debug for StateCommitmentDigest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{}", base64::encode(self.0))
+    }
+}
+
+impl fmt::Display for StateCommitmentDigest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{}", base64::encode(self.0).get(0..16).unwrap())
+    }
+}
+
+impl Hash for StateCommitment {
+    type TypedDigest = StateCommitmentDigest;
+
+    fn digest(&self) -> StateCommitmentDigest {
+        let hasher_update = |hasher: &mut VarBlake2b| {
+            hasher.update(&self.author);
+            hasher.update(self.epoch.to_le_bytes());
+            hasher.update(self.consensus_index.to_le_bytes());
+            hasher.update(self.state.0);
+        };
+
+        StateCommitmentDigest(fastcrypto::blake2b_256(hasher_update))
+    }
+}
+
+impl fmt::Debug for StateCommitment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(
+            f,
+            "{}: S{}({}, {}, E{})",
+            self.digest(),
+            self.consensus_index,
+            self.author.encode_base64(),
+            self.state,
+            self.epoch
+        )
+    }
+}
+
+/// Our execution state disagreeing with the one a quorum of the committee committed to at the
+/// same consensus index. The signed commitments of the quorum make the report self-contained.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct StateDivergence {
+    pub epoch: Epoch,
+    pub consensus_index: SequenceNumber,
+    /// The digest of our execution state.
+    pub ours: StateDigest,
+    /// The commitments of the quorum, all to the same digest.
+    pub quorum: Vec<StateCommitment>,
+}
+
+/// Proof that an authority signed two conflicting messages for the same round. It is
+/// self-contained, so it can be checked with the public key of the offender alone.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Equivocation {
+    /// Two different headers of the same author for the same round.
+    Headers(Header, Header),
+    /// Two votes of the same authority for different headers of the same origin and round.
+    Votes(Vote, Vote),
+}
+
+impl Equivocation {
+    /// The authority that equivocated.
+    pub fn offender(&self) -> &PublicKey {
+        match self {
+            Self::Headers(header, _) => &header.author,
+            Self::Votes(vote, _) => &vote.author,
+        }
+    }
+
+    pub fn epoch(&self) -> Epoch {
+        match self {
+            Self::Headers(header, _) => header.epoch,
+            Self::Votes(vote, _) => vote.epoch,
+        }
+    }
+
+    pub fn round(&self) -> Round {
+        match self {
+            Self::Headers(header, _) => header.round,
+            Self::Votes(vote, _) => vote.round,
+        }
+    }
+
+    pub fn verify(&self) -> DagResult<()> {
+        match self {
+            Self::Headers(first, second) => {
+                // Ensure the headers conflict.
+                ensure!(
+                    first.author == second.author
+                        && first.epoch == second.epoch
+                        && first.round == second.round
+                        && first.id != second.id,
+                    DagError::NotAnEquivocation
+                );
+
+                // Check the headers' ids and signatures.
+                for header in [first, second] {
+                    ensure!(header.digest() == header.id, DagError::InvalidHeaderId);
+                    let id_digest: Digest = Digest::from(header.id);
+                    header
+                        .author
+                        .verify(id_digest.as_ref(), &header.signature)?;
+                }
+                Ok(())
+            }
+            Self::Votes(first, second) => {
+                // Ensure the votes conflict.
+                ensure!(
+                    first.author == second.author
+                        && first.origin == second.origin
+                        && first.epoch == second.epoch
+                        && first.round == second.round
+                        && first.id != second.id,
+                    DagError::NotAnEquivocation
+                );
+
+                // Check the votes' signatures.
+                for vote in [first, second] {
+                    let vote_digest: Digest = vote.digest().into();
+                    vote.author.verify(vote_digest.as_ref(), &vote.signature)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Copy)]
+pub struct EquivocationDigest([u8; DIGEST_LEN]);
+
+impl From<EquivocationDigest> for Digest {
+    fn from(digest: EquivocationDigest) -> Self {
+        Digest::new(digest.0)
+    }
+}
+
+impl fmt::Debug for EquivocationDigest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{}", base64::encode(self.0))
+    }
+}
+
+impl fmt::Display for EquivocationDigest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{}", base64::encode(self.0).get(0..16).unwrap())
+    }
+}
+
+/// The digest identifies the offence rather than the conflicting messages: all the proofs that an
+/// authority equivocated at a round (for the same origin, for votes) share it, so that a single
+/// one is kept however many conflicting messages the authority sends.
+impl Hash for Equivocation {
+    type TypedDigest = EquivocationDigest;
+
+    fn digest(&self) -> EquivocationDigest {
+        let hasher_update = |hasher: &mut VarBlake2b| match self {
+            Self::Headers(first, _) => {
+                hasher.update([0u8]);
+                hasher.update(&first.author);
+                hasher.update(first.epoch.to_le_bytes());
+                hasher.update(first.round.to_le_bytes());
+            }
+            Self::Votes(first, _) => {
+                hasher.update([1u8]);
+                hasher.update(&first.author);
+                hasher.update(&first.origin);
+                hasher.update(first.epoch.to_le_bytes());
+                hasher.update(first.round.to_le_bytes());
+            }
+        };
+        EquivocationDigest(fastcrypto::blake2b_256(hasher_update))
+    }
+}
+
+impl fmt::Debug for Equivocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            Self::Headers(first, second) => write!(f, "Equivocation({first:?}, {second:?})"),
+            Self::Votes(first, second) => write!(f, "Equivocation({first:?}, {second:?})"),
+        }
+    }
+}
+
 #[serde_as]
 #[derive(Clone, MallocSizeOf, Serialize, Deserialize, Default)]
 pub struct Certificate {
@@ -685,6 +1193,10 @@ pub enum PrimaryMessage {
         payload_availability: Vec<(CertificateDigest, bool)>,
         from: PublicKey,
     },
+
+    WorkerIndexUpdate(WorkerIndexUpdate),
+
+    StateCommitment(StateCommitment),
 }
 
 /// Message to reconfigure worker tasks. This message must be sent by a trusted source.
@@ -712,6 +1224,16 @@ pub enum PrimaryWorkerMessage {
     RequestBatch(BatchDigest),
     /// Delete the batches, dictated from the provided vector of digest, from the worker node
     DeleteBatches(Vec<BatchDigest>),
+    /// The primary indicates that the batches are part of one of its certificates.
+    Certified(Vec<BatchDigest>, CertificateDigest),
+    /// The primary indicates that the certificate holding the batches has been sequenced.
+    Sequenced(
+        Vec<BatchDigest>,
+        CertificateDigest,
+        /* consensus_index */ SequenceNumber,
+    ),
+    /// The workers of an authority changed within the epoch.
+    UpdateWorkerIndex(PublicKey, WorkerIndex),
 }
 
 #[derive(Clone, Default, Debug, Eq, PartialEq)]
@@ -12,7 +12,10 @@ mod narwhal {
 
 use std::{array::TryFromSliceError, ops::Deref};
 
-use crate::{BlockError, BlockErrorKind, CertificateDigest, Transaction};
+use crate::{
+    BatchDigest, BatchMessage, BlockError, BlockErrorKind, CertificateDigest, Transaction,
+    TransactionDigest, TransactionStatus,
+};
 use bytes::Bytes;
 use crypto::PublicKey;
 
@@ -21,12 +24,17 @@ pub use narwhal::{
     collection_retrieval_result::RetrievalResult,
     configuration_client::ConfigurationClient,
     configuration_server::{Configuration, ConfigurationServer},
+    evidence_client::EvidenceClient,
+    evidence_server::{Evidence, EvidenceServer},
     primary_to_primary_client::PrimaryToPrimaryClient,
     primary_to_primary_server::{PrimaryToPrimary, PrimaryToPrimaryServer},
     primary_to_worker_client::PrimaryToWorkerClient,
     primary_to_worker_server::{PrimaryToWorker, PrimaryToWorkerServer},
     proposer_client::ProposerClient,
     proposer_server::{Proposer, ProposerServer},
+    sequencer_client::SequencerClient,
+    sequencer_server::{Sequencer, SequencerServer},
+    transaction_status::Status as TransactionStatusKind,
     transactions_client::TransactionsClient,
     transactions_server::{Transactions, TransactionsServer},
     validator_client::ValidatorClient,
@@ -35,12 +43,19 @@ pub use narwhal::{
     worker_to_primary_server::{WorkerToPrimary, WorkerToPrimaryServer},
     worker_to_worker_client::WorkerToWorkerClient,
     worker_to_worker_server::{WorkerToWorker, WorkerToWorkerServer},
+    AddWorkerRequest, Batch as BatchProto, BatchDigest as BatchDigestProto,
     CertificateDigest as CertificateDigestProto, Collection, CollectionError,
-    CollectionRetrievalResult, Empty, GetCollectionsRequest, GetCollectionsResponse,
-    GetPrimaryAddressResponse, MultiAddr as MultiAddrProto, NewEpochRequest, NewNetworkInfoRequest,
-    NodeReadCausalRequest, NodeReadCausalResponse, PublicKey as PublicKeyProto, ReadCausalRequest,
-    ReadCausalResponse, RemoveCollectionsRequest, RoundsRequest, RoundsResponse,
-    Transaction as TransactionProto, ValidatorData,
+    CollectionRetrievalResult, CommittedCertificatesRequest, CommittedCertificatesResponse,
+    ConsensusIndex, ConsensusOutput as ConsensusOutputProto, Empty, EquivocationProof,
+    GetCollectionsRequest, GetCollectionsResponse, GetEquivocationsRequest,
+    GetEquivocationsResponse, GetPrimaryAddressResponse, GetStateDivergencesRequest,
+    GetStateDivergencesResponse, MultiAddr as MultiAddrProto, NewEpochRequest,
+    NewNetworkInfoRequest, NodeReadCausalRequest, NodeReadCausalResponse,
+    PublicKey as PublicKeyProto, ReadCausalRequest, ReadCausalResponse, RemoveCollectionsRequest,
+    RemoveWorkerRequest, RoundsRequest, RoundsResponse, SequencedCertificate, SequencedTransaction,
+    SnapshotResponse, StateDivergenceReport, SubscribeConsensusOutputRequest,
+    Transaction as TransactionProto, TransactionDigest as TransactionDigestProto,
+    TransactionStatus as TransactionStatusProto, ValidatorData,
 };
 
 impl From<PublicKey> for PublicKeyProto {
@@ -91,3 +106,54 @@ impl TryFrom<CertificateDigestProto> for CertificateDigest {
         Ok(CertificateDigest::new(digest.digest.deref().try_into()?))
     }
 }
+
+impl From<BatchDigest> for BatchDigestProto {
+    fn from(digest: BatchDigest) -> Self {
+        BatchDigestProto {
+            digest: Bytes::from(digest.0.to_vec()),
+        }
+    }
+}
+
+impl From<BatchMessage> for BatchProto {
+    fn from(batch: BatchMessage) -> Self {
+        BatchProto {
+            id: Some(batch.id.into()),
+            transactions: batch.transactions.0.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<TransactionDigest> for TransactionDigestProto {
+    fn from(digest: TransactionDigest) -> Self {
+        TransactionDigestProto {
+            digest: Bytes::from(digest.0.to_vec()),
+        }
+    }
+}
+
+impl TryFrom<TransactionDigestProto> for TransactionDigest {
+    type Error = TryFromSliceError;
+
+    fn try_from(digest: TransactionDigestProto) -> Result<Self, Self::Error> {
+        Ok(TransactionDigest::new(digest.digest.deref().try_into()?))
+    }
+}
+
+impl From<TransactionStatus> for TransactionStatusProto {
+    fn from(status: TransactionStatus) -> Self {
+        let status = match status {
+            TransactionStatus::Batched(digest) => TransactionStatusKind::Batched(digest.into()),
+            TransactionStatus::Certified(digest) => TransactionStatusKind::Certified(digest.into()),
+            TransactionStatus::Sequenced(digest, consensus_index) => {
+                TransactionStatusKind::Sequenced(SequencedTransaction {
+                    certificate_id: Some(digest.into()),
+                    consensus_index,
+                })
+            }
+        };
+        TransactionStatusProto {
+            status: Some(status),
+        }
+    }
+}
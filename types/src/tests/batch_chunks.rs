@@ -0,0 +1,132 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{Batch, BatchChunk, ErasureError};
+use blake2::digest::Update;
+use fastcrypto::Hash;
+use proptest::arbitrary::Arbitrary;
+use test_utils::CommitteeFixture;
+
+#[test]
+fn test_data_shards() {
+    assert_eq!(BatchChunk::data_shards(2), 1);
+    assert_eq!(BatchChunk::data_shards(4), 2);
+    assert_eq!(BatchChunk::data_shards(7), 3);
+    assert_eq!(BatchChunk::data_shards(10), 4);
+}
+
+#[test]
+fn test_reconstruct_from_any_chunks() {
+    let batch = Batch((0..10).map(|i| vec![i; 100]).collect());
+    let chunks = BatchChunk::encode(&batch, 7).unwrap();
+    assert_eq!(chunks.len(), 7);
+    assert!(chunks.iter().all(|chunk| chunk.verify().is_ok()));
+
+    // Any 3 chunks are enough.
+    for skip in 0..5 {
+        let subset: Vec<_> = chunks.iter().skip(skip).take(3).cloned().collect();
+        assert_eq!(BatchChunk::reconstruct(&subset).unwrap(), batch);
+    }
+
+    // But not 2.
+    assert!(matches!(
+        BatchChunk::reconstruct(&chunks[..2]),
+        Err(ErasureError::NotEnoughChunks(2))
+    ));
+}
+
+#[test]
+fn test_reject_tampered_chunks() {
+    let batch = Batch((0..10).map(|i| vec![i; 100]).collect());
+    let mut chunks = BatchChunk::encode(&batch, 4).unwrap();
+
+    // A chunk whose shard does not match the commitment.
+    chunks[0].shard[0] ^= 1;
+    assert!(matches!(
+        chunks[0].verify(),
+        Err(ErasureError::InvalidChunk(_, 0))
+    ));
+    assert!(BatchChunk::reconstruct(&chunks[..2]).is_err());
+
+    // A sender committing to shards that are not a valid encoding of the batch: whatever chunks
+    // they are reconstructed from, they hold an empty batch.
+    let mut chunks = BatchChunk::encode(&batch, 4).unwrap();
+    chunks[3].shard[0] ^= 1;
+    let shard_digests: Vec<_> = chunks
+        .iter()
+        .map(|chunk| fastcrypto::blake2b_256(|hasher| hasher.update(&chunk.shard)))
+        .collect();
+    for chunk in chunks.iter_mut() {
+        chunk.digest = BatchChunk::commitment(&shard_digests);
+        chunk.shard_digests = shard_digests.clone();
+        assert!(chunk.verify().is_ok());
+    }
+    for subset in [&chunks[..2], &chunks[2..]] {
+        assert_eq!(BatchChunk::reconstruct(subset).unwrap(), Batch(vec![]));
+    }
+
+    // Chunks that do not carry the commitment to their encoding.
+    let mut chunks = BatchChunk::encode(&batch, 4).unwrap();
+    chunks[1].digest = batch.digest();
+    assert!(matches!(
+        chunks[1].verify(),
+        Err(ErasureError::InvalidChunk(_, 1))
+    ));
+    assert!(matches!(
+        BatchChunk::reconstruct(&chunks[..2]),
+        Err(ErasureError::Inconsistent(_))
+    ));
+}
+
+#[test]
+fn test_identify_batch_by_commitment() {
+    let batch = Batch((0..10).map(|i| vec![i; 100]).collect());
+    let chunks = BatchChunk::encode(&batch, 4).unwrap();
+    assert_ne!(chunks[0].digest, batch.digest());
+    assert_eq!(
+        chunks[0].digest,
+        BatchChunk::commitment(&chunks[0].shard_digests)
+    );
+    assert!(chunks.iter().all(|chunk| chunk.digest == chunks[0].digest));
+
+    // The commitment depends on the number of authorities the batch is encoded for.
+    let chunks_7 = BatchChunk::encode(&batch, 7).unwrap();
+    assert_ne!(chunks_7[0].digest, chunks[0].digest);
+}
+
+#[test]
+fn test_reject_weighted_committee() {
+    let fixture = CommitteeFixture::builder().build();
+    let mut committee = fixture.committee();
+    assert_eq!(
+        BatchChunk::authorities(&committee).unwrap(),
+        committee.size()
+    );
+
+    committee.authorities.values_mut().next().unwrap().stake = 2;
+    assert!(matches!(
+        BatchChunk::authorities(&committee),
+        Err(ErasureError::WeightedCommittee)
+    ));
+}
+
+#[test]
+fn test_committee_too_small() {
+    let batch = Batch(vec![vec![1; 10]]);
+    assert!(matches!(
+        BatchChunk::encode(&batch, 1),
+        Err(ErasureError::CommitteeTooSmall(1))
+    ));
+}
+
+proptest::proptest! {
+
+    #[test]
+    fn test_encode_and_reconstruct(
+        batch in Batch::arbitrary()
+    ) {
+        let chunks = BatchChunk::encode(&batch, 4).expect("Failed to encode our own batch");
+        let reconstructed = BatchChunk::reconstruct(&chunks[2..]).expect("Failed to reconstruct our own batch");
+        assert_eq!(reconstructed, batch);
+    }
+}
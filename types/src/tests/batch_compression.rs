@@ -0,0 +1,61 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{Batch, CompressedBatch, CompressionError};
+use config::BatchCodec;
+use fastcrypto::Hash;
+use proptest::arbitrary::Arbitrary;
+
+const CODECS: [BatchCodec; 3] = [BatchCodec::None, BatchCodec::Lz4, BatchCodec::Zstd];
+
+#[test]
+fn test_compress_repetitive_batch() {
+    let batch = Batch((0..100).map(|_| vec![1; 100]).collect());
+    let serialized = bincode::serialize(&batch).unwrap();
+
+    for codec in [BatchCodec::Lz4, BatchCodec::Zstd] {
+        let compressed = CompressedBatch::compress(&batch, codec).unwrap();
+        assert!(compressed.bytes.len() < serialized.len() / 10);
+        assert_eq!(compressed.decompress(serialized.len()).unwrap(), batch);
+    }
+}
+
+#[test]
+fn test_decompress_too_large() {
+    let batch = Batch((0..100).map(|_| vec![1; 100]).collect());
+    let serialized = bincode::serialize(&batch).unwrap();
+
+    for codec in CODECS {
+        let compressed = CompressedBatch::compress(&batch, codec).unwrap();
+        assert!(matches!(
+            compressed.decompress(serialized.len() - 1),
+            Err(CompressionError::TooLarge(_))
+        ));
+    }
+}
+
+#[test]
+fn test_decompress_corrupted() {
+    for codec in [BatchCodec::Lz4, BatchCodec::Zstd] {
+        let compressed = CompressedBatch {
+            codec,
+            bytes: vec![0xff; 16],
+        };
+        assert!(compressed.decompress(1024).is_err());
+    }
+}
+
+proptest::proptest! {
+
+    #[test]
+    fn test_compressed_batch_digest(
+        batch in Batch::arbitrary()
+    ) {
+        let digest = batch.digest();
+        for codec in CODECS {
+            let compressed = CompressedBatch::compress(&batch, codec).expect("Failed to compress our own batch");
+            let decompressed = compressed.decompress(usize::MAX).expect("Failed to decompress our own batch");
+            assert_eq!(digest, decompressed.digest());
+        }
+    }
+}
@@ -1,34 +1,329 @@
 // Copyright (c) 2022, Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::{Batch, BatchDigest};
+use crate::{Batch, BatchDigest, CertificateDigest, SequenceNumber};
 use blake2::digest::Update;
+use config::{BatchCodec, Committee};
+use fastcrypto::Hash;
+use reed_solomon_erasure::galois_8::ReedSolomon;
 
 use serde::{Deserialize, Serialize};
+use std::io::Read;
 use thiserror::Error;
 
 #[cfg(test)]
 #[path = "tests/batch_serde.rs"]
 mod batch_serde;
 
+#[cfg(test)]
+#[path = "tests/batch_compression.rs"]
+mod batch_compression;
+
+#[cfg(test)]
+#[path = "tests/batch_chunks.rs"]
+mod batch_chunks;
+
 /// Unsolicited messages exchanged between workers.
 #[allow(clippy::large_enum_variant)]
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub enum WorkerMessage {
     /// Used by workers to send a new batch.
     Batch(Batch),
+    /// Used by workers to send a new batch, compressed.
+    CompressedBatch(CompressedBatch),
+    /// Used by workers to send their chunk of a new erasure-coded batch.
+    Chunk(BatchChunk),
 }
 
 /// Used by workers to request batches from other workers.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct WorkerBatchRequest {
     pub digests: Vec<BatchDigest>,
+    /// The codecs the requester can decompress the batches with.
+    pub accepted_codecs: Vec<BatchCodec>,
 }
 
 /// Used by workers to provide batches to other workers.
 #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub struct WorkerBatchResponse {
     pub batches: Vec<Batch>,
+    /// The batches compressed with one of the codecs accepted by the requester.
+    pub compressed_batches: Vec<CompressedBatch>,
+    /// The chunks held by the responder of the erasure-coded batches, sent instead of the batches.
+    pub chunks: Vec<BatchChunk>,
+}
+
+/// The zstd compression level of the batches: the default level of the library.
+const ZSTD_LEVEL: i32 = 0;
+
+/// A serialized batch, compressed with a codec. The digest of the batch is that of the
+/// decompressed batch, so compression does not change how batches are referenced.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CompressedBatch {
+    pub codec: BatchCodec,
+    pub bytes: Vec<u8>,
+}
+
+impl CompressedBatch {
+    /// Serializes and compresses a batch with the specified codec.
+    pub fn compress(batch: &Batch, codec: BatchCodec) -> Result<Self, CompressionError> {
+        let serialized = bincode::serialize(batch)?;
+        Self::compress_serialized(&serialized, codec)
+    }
+
+    /// Compresses a serialized batch with the specified codec.
+    pub fn compress_serialized(
+        serialized: &[u8],
+        codec: BatchCodec,
+    ) -> Result<Self, CompressionError> {
+        let bytes = match codec {
+            BatchCodec::None => serialized.to_vec(),
+            BatchCodec::Lz4 => lz4_flex::compress_prepend_size(serialized),
+            BatchCodec::Zstd => zstd::bulk::compress(serialized, ZSTD_LEVEL)
+                .map_err(|e| CompressionError::Codec(codec, e.to_string()))?,
+        };
+        Ok(Self { codec, bytes })
+    }
+
+    /// Decompresses and deserializes the batch. The batches decompressing to more than
+    /// `max_size` bytes are rejected before being inflated.
+    pub fn decompress(&self, max_size: usize) -> Result<Batch, CompressionError> {
+        let codec = self.codec;
+        let serialized = match codec {
+            BatchCodec::None => {
+                if self.bytes.len() > max_size {
+                    return Err(CompressionError::TooLarge(max_size));
+                }
+                return Ok(bincode::deserialize(&self.bytes)?);
+            }
+            BatchCodec::Lz4 => {
+                let size = self
+                    .bytes
+                    .get(..4)
+                    .map(|prefix| u32::from_le_bytes(prefix.try_into().unwrap()) as usize)
+                    .ok_or_else(|| CompressionError::Codec(codec, "missing size".to_string()))?;
+                if size > max_size {
+                    return Err(CompressionError::TooLarge(max_size));
+                }
+                lz4_flex::decompress_size_prepended(&self.bytes)
+                    .map_err(|e| CompressionError::Codec(codec, e.to_string()))?
+            }
+            BatchCodec::Zstd => {
+                // Stop inflating as soon as the batch exceeds the limit.
+                let mut serialized = Vec::new();
+                zstd::stream::read::Decoder::new(&self.bytes[..])
+                    .and_then(|decoder| {
+                        decoder
+                            .take((max_size as u64).saturating_add(1))
+                            .read_to_end(&mut serialized)
+                    })
+                    .map_err(|e| CompressionError::Codec(codec, e.to_string()))?;
+                if serialized.len() > max_size {
+                    return Err(CompressionError::TooLarge(max_size));
+                }
+                serialized
+            }
+        };
+        Ok(bincode::deserialize(&serialized)?)
+    }
+}
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum CompressionError {
+    #[error("Batch decompresses to more than {0} bytes")]
+    TooLarge(usize),
+
+    #[error("Invalid {0:?} batch: {1}")]
+    Codec(BatchCodec, String),
+
+    #[error("Codec {0:?} is not accepted")]
+    NotAccepted(BatchCodec),
+
+    #[error("Failed to serialize or deserialize batch: {0}")]
+    Serialization(#[from] bincode::Error),
+}
+
+/// A chunk of an erasure-coded batch. A batch is encoded into one chunk per authority, any
+/// `data_shards` of which are enough to reconstruct it. Every chunk carries the digests of all
+/// the chunks of the batch, committing the sender to its encoding: the batch is identified by
+/// that commitment rather than by its own digest.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BatchChunk {
+    /// The commitment to the encoding of the batch, see [`BatchChunk::commitment`].
+    pub digest: BatchDigest,
+    /// The index of the chunk, ie. of the authority it is sent to in the committee.
+    pub index: u32,
+    /// The number of chunks needed to reconstruct the batch.
+    pub data_shards: u32,
+    /// The digests of the shards of all the chunks of the batch.
+    pub shard_digests: Vec<[u8; 32]>,
+    /// The shard of the encoded batch held by this chunk.
+    pub shard: Vec<u8>,
+}
+
+impl BatchChunk {
+    /// The number of chunks needed to reconstruct a batch: the chunks held by the f+1 honest
+    /// authorities among any 2f+1.
+    pub fn data_shards(authorities: usize) -> usize {
+        (authorities.max(1) - 1) / 3 + 1
+    }
+
+    /// The number of chunks a batch is encoded into for a committee, one per authority. The
+    /// chunks are counted rather than weighted by stake, so only committees of authorities of
+    /// equal stake can erasure-code their batches.
+    pub fn authorities(committee: &Committee) -> Result<usize, ErasureError> {
+        let mut stakes = committee.authorities.values().map(|x| x.stake);
+        match stakes.next() {
+            Some(stake) if stakes.all(|x| x == stake) => Ok(committee.size()),
+            _ => Err(ErasureError::WeightedCommittee),
+        }
+    }
+
+    /// The commitment to an encoding, that identifies the erasure-coded batch.
+    pub fn commitment(shard_digests: &[[u8; 32]]) -> BatchDigest {
+        BatchDigest::new(fastcrypto::blake2b_256(|hasher| {
+            for digest in shard_digests {
+                hasher.update(digest);
+            }
+        }))
+    }
+
+    /// Erasure-codes a batch into one chunk per authority, in the order of the committee.
+    pub fn encode(batch: &Batch, authorities: usize) -> Result<Vec<Self>, ErasureError> {
+        let data_shards = Self::data_shards(authorities);
+        let parity_shards = authorities - data_shards;
+        if parity_shards == 0 {
+            return Err(ErasureError::CommitteeTooSmall(authorities));
+        }
+        let codec = ReedSolomon::new(data_shards, parity_shards)
+            .map_err(|e| ErasureError::Codec(e.to_string()))?;
+
+        // Prefix the serialized batch with its length, to strip the padding of the last shard.
+        let serialized = bincode::serialize(batch)?;
+        let mut data = (serialized.len() as u64).to_le_bytes().to_vec();
+        data.extend_from_slice(&serialized);
+        let shard_size = (data.len() + data_shards - 1) / data_shards;
+        data.resize(shard_size * authorities, 0);
+
+        let mut shards: Vec<_> = data.chunks(shard_size).map(<[u8]>::to_vec).collect();
+        codec
+            .encode(&mut shards)
+            .map_err(|e| ErasureError::Codec(e.to_string()))?;
+
+        let shard_digests: Vec<_> = shards.iter().map(|shard| shard_digest(shard)).collect();
+        let digest = Self::commitment(&shard_digests);
+        Ok(shards
+            .into_iter()
+            .enumerate()
+            .map(|(index, shard)| Self {
+                digest,
+                index: index as u32,
+                data_shards: data_shards as u32,
+                shard_digests: shard_digests.clone(),
+                shard,
+            })
+            .collect())
+    }
+
+    /// Checks that the chunk is well formed and that its shard matches the commitment.
+    pub fn verify(&self) -> Result<(), ErasureError> {
+        let authorities = self.shard_digests.len();
+        if self.data_shards as usize != Self::data_shards(authorities)
+            || self.digest != Self::commitment(&self.shard_digests)
+        {
+            return Err(ErasureError::InvalidChunk(self.digest, self.index));
+        }
+        match self.shard_digests.get(self.index as usize) {
+            Some(expected) if *expected == shard_digest(&self.shard) => Ok(()),
+            _ => Err(ErasureError::InvalidChunk(self.digest, self.index)),
+        }
+    }
+
+    /// Reconstructs a batch from chunks of the same commitment. The batch is encoded again to
+    /// check that the sender committed to a valid encoding: the batch of an invalid encoding is
+    /// empty. Either way, all the authorities reconstruct the same batch from any set of chunks.
+    pub fn reconstruct(chunks: &[Self]) -> Result<Batch, ErasureError> {
+        let first = chunks.first().ok_or(ErasureError::NotEnoughChunks(0))?;
+        let authorities = first.shard_digests.len();
+        let data_shards = first.data_shards as usize;
+
+        let mut shards: Vec<Option<Vec<u8>>> = vec![None; authorities];
+        for chunk in chunks {
+            if chunk.digest != first.digest {
+                return Err(ErasureError::Inconsistent(first.digest));
+            }
+            chunk.verify()?;
+            shards[chunk.index as usize] = Some(chunk.shard.clone());
+        }
+        let available = shards.iter().filter(|shard| shard.is_some()).count();
+        if available < data_shards {
+            return Err(ErasureError::NotEnoughChunks(available));
+        }
+
+        Ok(Self::decode(shards, data_shards)
+            .filter(|batch| {
+                Self::encode(batch, authorities)
+                    .map(|encoded| encoded[0].digest == first.digest)
+                    .unwrap_or(false)
+            })
+            .unwrap_or_default())
+    }
+
+    /// Decodes the batch held by enough shards, if they are shards of any batch at all.
+    fn decode(mut shards: Vec<Option<Vec<u8>>>, data_shards: usize) -> Option<Batch> {
+        let codec = ReedSolomon::new(data_shards, shards.len() - data_shards).ok()?;
+        codec.reconstruct_data(&mut shards).ok()?;
+
+        let data: Vec<u8> = shards
+            .into_iter()
+            .take(data_shards)
+            .flat_map(|shard| shard.unwrap_or_default())
+            .collect();
+        let length = u64::from_le_bytes(data.get(..8)?.try_into().unwrap());
+        let serialized = data.get(8..8usize.checked_add(usize::try_from(length).ok()?)?)?;
+        bincode::deserialize(serialized).ok()
+    }
+}
+
+fn shard_digest(shard: &[u8]) -> [u8; 32] {
+    fastcrypto::blake2b_256(|hasher| hasher.update(shard))
+}
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum ErasureError {
+    #[error("Cannot erasure-code batches for a committee of {0} authorities")]
+    CommitteeTooSmall(usize),
+
+    #[error("Cannot erasure-code batches for a committee of authorities of unequal stake")]
+    WeightedCommittee,
+
+    #[error("Invalid chunk {1} of batch {0}")]
+    InvalidChunk(BatchDigest, u32),
+
+    #[error("Not enough chunks to reconstruct batch: got {0}")]
+    NotEnoughChunks(usize),
+
+    #[error("Chunks of batch {0} commit to different encodings")]
+    Inconsistent(BatchDigest),
+
+    #[error("Erasure code failure: {0}")]
+    Codec(String),
+
+    #[error("Failed to serialize or deserialize batch: {0}")]
+    Serialization(#[from] bincode::Error),
+}
+
+/// The status transitions of a client transaction, as reported by the worker that received it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TransactionStatus {
+    /// The transaction has been sealed into a batch.
+    Batched(BatchDigest),
+    /// The batch holding the transaction is part of a certificate.
+    Certified(CertificateDigest),
+    /// The certificate holding the transaction has been sequenced by consensus.
+    Sequenced(CertificateDigest, /* consensus_index */ SequenceNumber),
 }
 
 /// Hashes a serialized batch message without deserializing it into a batch.
@@ -1,7 +1,10 @@
 // Copyright (c) 2021, Facebook, Inc. and its affiliates
 // Copyright (c) 2022, Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
-use crate::metrics::WorkerMetrics;
+use crate::{
+    batching_policy::{BatchingPolicy, SealReason},
+    metrics::WorkerMetrics,
+};
 #[cfg(feature = "benchmark")]
 use byteorder::{BigEndian, ReadBytesExt};
 use config::Committee;
@@ -11,7 +14,7 @@ use std::sync::Arc;
 use tokio::{
     sync::watch,
     task::JoinHandle,
-    time::{sleep, Duration, Instant},
+    time::{sleep, Instant},
 };
 use types::{
     error::DagError,
@@ -24,13 +27,11 @@ use types::{
 pub mod batch_maker_tests;
 
 /// Assemble clients transactions into batches.
-pub struct BatchMaker {
+pub struct BatchMaker<P> {
     /// The committee information.
     committee: Committee,
-    /// The preferred batch size (in bytes).
-    batch_size: usize,
-    /// The maximum delay after which to seal the batch.
-    max_batch_delay: Duration,
+    /// Decides which transactions to accept and when to seal the batch.
+    policy: P,
     /// Receive reconfiguration updates.
     rx_reconfigure: watch::Receiver<ReconfigureNotification>,
     /// Channel to receive transactions from the network.
@@ -45,12 +46,14 @@ pub struct BatchMaker {
     node_metrics: Arc<WorkerMetrics>,
 }
 
-impl BatchMaker {
+impl<P: BatchingPolicy> BatchMaker<P> {
+    /// The number of transactions for which the current batch is pre-allocated.
+    const INITIAL_BATCH_CAPACITY: usize = 1_000;
+
     #[must_use]
     pub fn spawn(
         committee: Committee,
-        batch_size: usize,
-        max_batch_delay: Duration,
+        policy: P,
         rx_reconfigure: watch::Receiver<ReconfigureNotification>,
         rx_transaction: Receiver<Transaction>,
         tx_message: Sender<Batch>,
@@ -59,12 +62,11 @@ impl BatchMaker {
         tokio::spawn(async move {
             Self {
                 committee,
-                batch_size,
-                max_batch_delay,
+                policy,
                 rx_reconfigure,
                 rx_transaction,
                 tx_message,
-                current_batch: Batch(Vec::with_capacity(batch_size * 2)),
+                current_batch: Batch(Vec::with_capacity(Self::INITIAL_BATCH_CAPACITY)),
                 current_batch_size: 0,
                 node_metrics,
             }
@@ -75,27 +77,42 @@ impl BatchMaker {
 
     /// Main loop receiving incoming transactions and creating batches.
     async fn run(&mut self) {
-        let timer = sleep(self.max_batch_delay);
+        let now = Instant::now();
+        let timer = sleep(self.policy.next_delay(now));
         tokio::pin!(timer);
 
         loop {
             tokio::select! {
-                // Assemble client transactions into batches of preset size.
+                // Assemble client transactions into batches as directed by the policy.
                 Some(transaction) = self.rx_transaction.recv() => {
+                    if let Err(e) = self.policy.admit(&transaction) {
+                        tracing::debug!("Dropping transaction: {e}");
+                        self.node_metrics
+                            .dropped_transactions
+                            .with_label_values(&["batch_maker"])
+                            .inc();
+                        continue;
+                    }
+                    self.policy.observe(&transaction);
                     self.current_batch_size += transaction.len();
                     self.current_batch.0.push(transaction);
-                    if self.current_batch_size >= self.batch_size {
-                        self.seal(false).await;
-                        timer.as_mut().reset(Instant::now() + self.max_batch_delay);
+                    if let Some(reason) = self
+                        .policy
+                        .should_seal(self.current_batch_size, self.current_batch.0.len())
+                    {
+                        self.seal(reason).await;
+                        let now = Instant::now();
+                        timer.as_mut().reset(now + self.policy.next_delay(now));
                     }
                 },
 
                 // If the timer triggers, seal the batch even if it contains few transactions.
                 () = &mut timer => {
                     if !self.current_batch.0.is_empty() {
-                        self.seal(true).await;
+                        self.seal(SealReason::Timeout).await;
                     }
-                    timer.as_mut().reset(Instant::now() + self.max_batch_delay);
+                    let now = Instant::now();
+                    timer.as_mut().reset(now + self.policy.next_delay(now));
                 }
 
                 // Trigger reconfigure.
@@ -122,7 +139,7 @@ impl BatchMaker {
     }
 
     /// Seal and broadcast the current batch.
-    async fn seal(&mut self, timeout: bool) {
+    async fn seal(&mut self, reason: SealReason) {
         let size = self.current_batch_size;
 
         // Serialize the batch.
@@ -179,11 +196,9 @@ impl BatchMaker {
             tracing::info!("Batch {:?} contains {} B", digest, size);
         }
 
-        let reason = if timeout { "timeout" } else { "size_reached" };
-
         self.node_metrics
             .created_batch_size
-            .with_label_values(&[self.committee.epoch.to_string().as_str(), reason])
+            .with_label_values(&[self.committee.epoch.to_string().as_str(), reason.as_str()])
             .observe(size as f64);
 
         // Send the batch through the deliver channel for further processing.
@@ -0,0 +1,168 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use config::BatchingParameters;
+use std::fmt::{self, Display};
+use tokio::time::{Duration, Instant};
+
+#[cfg(test)]
+#[path = "tests/batching_policy_tests.rs"]
+pub mod batching_policy_tests;
+
+/// Why a batch was sealed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SealReason {
+    /// The batch reached the preferred size.
+    SizeReached,
+    /// The batch reached the maximum number of transactions.
+    CountReached,
+    /// The batch delay expired.
+    Timeout,
+}
+
+impl SealReason {
+    /// The label of the reason in the metrics.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SealReason::SizeReached => "size_reached",
+            SealReason::CountReached => "count_reached",
+            SealReason::Timeout => "timeout",
+        }
+    }
+}
+
+/// Defines how the `BatchMaker` assembles client transactions into batches: which transactions
+/// it accepts, when it seals a batch, and how long it waits for a batch to fill up.
+pub trait BatchingPolicy: Send + 'static {
+    /// The error returned when a transaction is rejected.
+    type Error: Display + Send;
+
+    /// Determines if a transaction may be added to a batch. Rejected transactions are dropped
+    /// after the client was acknowledged, so the transaction receiver enforces the limits the
+    /// clients must observe before acknowledging them.
+    fn admit(&self, transaction: &[u8]) -> Result<(), Self::Error>;
+
+    /// Records a transaction added to the current batch.
+    fn observe(&mut self, transaction: &[u8]);
+
+    /// Determines if the current batch, holding `size` bytes in `count` transactions, must be
+    /// sealed right away.
+    fn should_seal(&self, size: usize, count: usize) -> Option<SealReason>;
+
+    /// Returns the delay after which to seal the next batch, even if it is not full. It is
+    /// called every time the batch timer is reset.
+    fn next_delay(&mut self, now: Instant) -> Duration;
+}
+
+/// A transaction exceeding the maximum transaction size.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransactionTooLarge {
+    pub size: usize,
+    pub max_size: usize,
+}
+
+impl Display for TransactionTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Transaction of {} B exceeds the maximum size of {} B",
+            self.size, self.max_size
+        )
+    }
+}
+
+/// The batching policy configured by the node parameters. With the default `BatchingParameters`,
+/// batches are sealed when they reach `batch_size` bytes or after `max_batch_delay`.
+pub struct DefaultBatchingPolicy {
+    /// The preferred batch size (in bytes).
+    batch_size: usize,
+    /// The maximum delay after which to seal the batch.
+    max_batch_delay: Duration,
+    /// The limits on transactions and the adaptive delay settings.
+    parameters: BatchingParameters,
+    /// The start of the current observation window of the ingress rate.
+    window_start: Instant,
+    /// The number of bytes received during the current observation window.
+    window_bytes: usize,
+    /// The smoothed ingress rate (in bytes per second).
+    ingress_rate: f64,
+}
+
+impl DefaultBatchingPolicy {
+    /// The weight of the last observation window in the smoothed ingress rate.
+    const SMOOTHING_FACTOR: f64 = 0.5;
+
+    pub fn new(
+        batch_size: usize,
+        max_batch_delay: Duration,
+        parameters: BatchingParameters,
+    ) -> Self {
+        Self {
+            batch_size,
+            max_batch_delay,
+            parameters,
+            window_start: Instant::now(),
+            window_bytes: 0,
+            ingress_rate: 0.0,
+        }
+    }
+}
+
+impl BatchingPolicy for DefaultBatchingPolicy {
+    type Error = TransactionTooLarge;
+
+    fn admit(&self, transaction: &[u8]) -> Result<(), Self::Error> {
+        let max_size = self.parameters.max_transaction_size;
+        if max_size > 0 && transaction.len() > max_size {
+            return Err(TransactionTooLarge {
+                size: transaction.len(),
+                max_size,
+            });
+        }
+        Ok(())
+    }
+
+    fn observe(&mut self, transaction: &[u8]) {
+        self.window_bytes += transaction.len();
+    }
+
+    fn should_seal(&self, size: usize, count: usize) -> Option<SealReason> {
+        if size >= self.batch_size {
+            Some(SealReason::SizeReached)
+        } else if self.parameters.max_batch_transactions > 0
+            && count >= self.parameters.max_batch_transactions
+        {
+            Some(SealReason::CountReached)
+        } else {
+            None
+        }
+    }
+
+    fn next_delay(&mut self, now: Instant) -> Duration {
+        if !self.parameters.adaptive_delay {
+            return self.max_batch_delay;
+        }
+
+        // Update the smoothed ingress rate with the last observation window.
+        let elapsed = now
+            .saturating_duration_since(self.window_start)
+            .as_secs_f64();
+        if elapsed > 0.0 {
+            let rate = self.window_bytes as f64 / elapsed;
+            self.ingress_rate =
+                Self::SMOOTHING_FACTOR * rate + (1.0 - Self::SMOOTHING_FACTOR) * self.ingress_rate;
+        }
+        self.window_start = now;
+        self.window_bytes = 0;
+
+        // Wait longer as the ingress rate gets closer to the rate filling a batch within the
+        // maximum delay: below it, waiting only adds latency without making batches much fuller.
+        let min_delay = self.parameters.min_batch_delay.min(self.max_batch_delay);
+        let filling_rate = self.batch_size as f64 / self.max_batch_delay.as_secs_f64();
+        let load = if filling_rate > 0.0 {
+            (self.ingress_rate / filling_rate).min(1.0)
+        } else {
+            1.0
+        };
+        min_delay + (self.max_batch_delay - min_delay).mul_f64(load)
+    }
+}
@@ -0,0 +1,143 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use anemo::PeerId;
+use config::{BatchCodec, CompressionParameters};
+use crypto::NetworkPublicKey;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tracing::warn;
+use types::{Batch, CompressedBatch, CompressionError, WorkerBatchResponse, WorkerMessage};
+
+#[cfg(test)]
+#[path = "tests/compression_tests.rs"]
+pub mod compression_tests;
+
+/// Compresses the batches this worker sends to the other workers, and decompresses the batches
+/// it receives from them.
+#[derive(Clone, Debug)]
+pub struct BatchCompressor {
+    /// The compression configuration of this worker.
+    parameters: CompressionParameters,
+    /// The codecs the other workers advertised in their last batch request, shared by the
+    /// clones of the compressor.
+    peer_codecs: Arc<Mutex<HashMap<PeerId, Vec<BatchCodec>>>>,
+}
+
+impl BatchCompressor {
+    pub fn new(parameters: CompressionParameters) -> Self {
+        Self {
+            parameters,
+            peer_codecs: Arc::default(),
+        }
+    }
+
+    /// The codecs to advertise in our batch requests.
+    pub fn accepted_codecs(&self) -> Vec<BatchCodec> {
+        self.parameters.accepted_codecs.clone()
+    }
+
+    /// Remembers the codecs a worker advertised in its batch request, to compress the batches
+    /// we broadcast to it.
+    pub fn record_accepted_codecs(&self, peer: PeerId, accepted_codecs: &[BatchCodec]) {
+        self.peer_codecs
+            .lock()
+            .unwrap()
+            .insert(peer, accepted_codecs.to_vec());
+    }
+
+    /// Whether a worker advertised our codec.
+    fn peer_accepts_codec(&self, worker: &NetworkPublicKey) -> bool {
+        self.peer_codecs
+            .lock()
+            .unwrap()
+            .get(&PeerId(worker.0.to_bytes()))
+            .map_or(false, |codecs| codecs.contains(&self.parameters.codec))
+    }
+
+    /// Compresses a batch with our codec if the receiver accepts it and the batch is large
+    /// enough. Returns `None` if the batch should be sent as is.
+    fn compress(&self, batch: &Batch, accepted_codecs: &[BatchCodec]) -> Option<CompressedBatch> {
+        let codec = self.parameters.codec;
+        if codec == BatchCodec::None || !accepted_codecs.contains(&codec) {
+            return None;
+        }
+        let serialized = bincode::serialize(batch).expect("Failed to serialize our own batch");
+        if serialized.len() < self.parameters.min_batch_size {
+            return None;
+        }
+        match CompressedBatch::compress_serialized(&serialized, codec) {
+            // Do not bother the receiver with batches that do not compress.
+            Ok(compressed) if compressed.bytes.len() < serialized.len() => Some(compressed),
+            Ok(_) => None,
+            Err(e) => {
+                warn!("Failed to compress batch: {e}");
+                None
+            }
+        }
+    }
+
+    /// The messages broadcasting a new batch to the other workers, along with the workers to
+    /// send each of them to. The batch is only compressed for the workers which advertised our
+    /// codec in their batch requests, and sent as is to the others.
+    pub fn messages<T>(
+        &self,
+        batch: Batch,
+        workers: Vec<(T, NetworkPublicKey)>,
+    ) -> Vec<(WorkerMessage, Vec<(T, NetworkPublicKey)>)> {
+        let (accepting, mut others): (Vec<_>, Vec<_>) = workers
+            .into_iter()
+            .partition(|(_, worker)| self.peer_accepts_codec(worker));
+
+        let mut messages = Vec::new();
+        if !accepting.is_empty() {
+            match self.compress(&batch, &[self.parameters.codec]) {
+                Some(compressed) => {
+                    messages.push((WorkerMessage::CompressedBatch(compressed), accepting))
+                }
+                None => others.extend(accepting),
+            }
+        }
+        if !others.is_empty() {
+            messages.push((WorkerMessage::Batch(batch), others));
+        }
+        messages
+    }
+
+    /// The response to a worker requesting batches, compressed with a codec it accepts.
+    pub fn response(
+        &self,
+        batches: Vec<Batch>,
+        accepted_codecs: &[BatchCodec],
+    ) -> WorkerBatchResponse {
+        let mut response = WorkerBatchResponse::default();
+        for batch in batches {
+            match self.compress(&batch, accepted_codecs) {
+                Some(compressed) => response.compressed_batches.push(compressed),
+                None => response.batches.push(batch),
+            }
+        }
+        response
+    }
+
+    /// Decompresses a batch received from another worker, provided we accept its codec.
+    pub fn decompress(&self, batch: &CompressedBatch) -> Result<Batch, CompressionError> {
+        if !self.parameters.accepted_codecs.contains(&batch.codec) {
+            return Err(CompressionError::NotAccepted(batch.codec));
+        }
+        batch.decompress(self.parameters.max_decompressed_size)
+    }
+
+    /// The batches of a response to our request, dropping those that fail to decompress.
+    pub fn batches(&self, response: WorkerBatchResponse) -> Vec<Batch> {
+        let mut batches = response.batches;
+        for compressed in &response.compressed_batches {
+            match self.decompress(compressed) {
+                Ok(batch) => batches.push(batch),
+                Err(e) => warn!("Dropping compressed batch: {e}"),
+            }
+        }
+        batches
+    }
+}
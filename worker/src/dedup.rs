@@ -0,0 +1,89 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+use tokio::time::{Duration, Instant};
+use types::{transaction_digest, TransactionDigest};
+
+#[cfg(test)]
+#[path = "tests/dedup_tests.rs"]
+pub mod dedup_tests;
+
+/// Remembers the digests of the transactions recently received by the worker, to drop the
+/// identical transactions submitted again by retrying clients or to several workers.
+pub struct TransactionDedup {
+    /// The maximum number of digests remembered. Disabled if 0.
+    capacity: usize,
+    /// How long a digest is remembered.
+    window: Duration,
+    inner: Mutex<Window>,
+}
+
+#[derive(Default)]
+struct Window {
+    /// The time at which each remembered transaction was last received.
+    received: HashMap<TransactionDigest, Instant>,
+    /// The remembered transactions, by time of reception.
+    queue: VecDeque<(Instant, TransactionDigest)>,
+}
+
+impl TransactionDedup {
+    pub fn new(capacity: usize, window: Duration) -> Self {
+        Self {
+            capacity,
+            window,
+            inner: Mutex::new(Window::default()),
+        }
+    }
+
+    /// Records a transaction received at the specified time. Returns false if the same
+    /// transaction was already received within the window.
+    pub fn insert(&self, transaction: &[u8], now: Instant) -> bool {
+        if self.capacity == 0 {
+            return true;
+        }
+        let digest = transaction_digest(transaction);
+
+        let mut inner = self.inner.lock().unwrap();
+        let Window { received, queue } = &mut *inner;
+
+        if let Some(time) = received.get(&digest) {
+            if now.saturating_duration_since(*time) < self.window {
+                return false;
+            }
+        }
+
+        // Forget the transactions received before the window, and the oldest ones beyond the
+        // capacity.
+        while let Some((time, oldest)) = queue.front() {
+            let expired = now.saturating_duration_since(*time) >= self.window;
+            if !expired && received.len() < self.capacity {
+                break;
+            }
+            if received.get(oldest) == Some(time) {
+                received.remove(oldest);
+            }
+            queue.pop_front();
+        }
+
+        received.insert(digest, now);
+        queue.push_back((now, digest));
+        true
+    }
+
+    /// Forgets a transaction recorded at the specified time, so that it is accepted again. Used
+    /// when the transaction is not admitted after all.
+    pub fn remove(&self, transaction: &[u8], time: Instant) {
+        if self.capacity == 0 {
+            return;
+        }
+        let digest = transaction_digest(transaction);
+
+        let mut inner = self.inner.lock().unwrap();
+        if inner.received.get(&digest) == Some(&time) {
+            inner.received.remove(&digest);
+        }
+    }
+}
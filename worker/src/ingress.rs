@@ -0,0 +1,333 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use crate::metrics::WorkerMetrics;
+use config::IngressParameters;
+use std::{
+    collections::{hash_map::Entry, HashMap, VecDeque},
+    fmt::{self, Display},
+    sync::{Arc, Mutex},
+};
+use tokio::{
+    sync::{
+        mpsc::{self, error::TryRecvError},
+        Notify,
+    },
+    task::JoinHandle,
+    time::Instant,
+};
+use tonic::Request;
+use types::{
+    metered_channel::{self, Permit, Receiver, Sender},
+    Transaction,
+};
+
+#[cfg(test)]
+#[path = "tests/ingress_tests.rs"]
+pub mod ingress_tests;
+
+/// Identifies a client submitting transactions to the worker: its IP address.
+pub type ClientId = String;
+
+/// Returns the identity of the client that sent a request.
+pub fn client_id<T>(request: &Request<T>) -> ClientId {
+    request
+        .remote_addr()
+        .map(|address| address.ip().to_string())
+        .unwrap_or_default()
+}
+
+/// Why a transaction was not admitted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IngressError {
+    /// The client exceeded its rate limit.
+    RateLimited,
+    /// The worker is shutting down.
+    ShuttingDown,
+}
+
+impl Display for IngressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IngressError::RateLimited => write!(f, "Client exceeded its rate limit"),
+            IngressError::ShuttingDown => write!(f, "System shutting down"),
+        }
+    }
+}
+
+/// Limits the rate of a client: the bucket is refilled with `rate` tokens per second, up to
+/// `capacity` tokens, and every transaction takes a token.
+#[derive(Debug)]
+pub struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// Creates a full bucket.
+    pub fn new(rate: u64, burst: u64, now: Instant) -> Self {
+        let capacity = burst.max(1) as f64;
+        Self {
+            rate: rate as f64,
+            capacity,
+            tokens: capacity,
+            last_refill: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.rate).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// Takes a token if one is available at the specified time.
+    pub fn try_acquire(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        if self.tokens < 1.0 {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+
+    /// Whether the bucket refilled completely, ie. the client has been idle long enough to
+    /// be forgotten.
+    pub fn is_full(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.tokens >= self.capacity
+    }
+}
+
+/// The admission state of a client.
+struct Client {
+    /// Limits the rate of the client, if configured.
+    bucket: Option<TokenBucket>,
+    /// Queues the transactions of the client for the `FairQueue`.
+    sender: mpsc::Sender<Transaction>,
+}
+
+/// The queue of a client, drained by the `FairQueue`.
+struct ClientQueue {
+    /// The number of transactions forwarded from this queue in every round.
+    weight: u64,
+    /// The number of transactions this queue may still forward in the current round.
+    deficit: u64,
+    receiver: mpsc::Receiver<Transaction>,
+    /// Whether the queue is drained and the client forgotten.
+    closed: bool,
+}
+
+/// Admits the transactions of the clients of the worker: it enforces the rate limit of every
+/// client, then queues its transactions separately from those of the other clients. The
+/// `FairQueue` forwards the queued transactions to the `BatchMaker`, so that a client flooding
+/// the worker only fills its own queue.
+pub struct Ingress {
+    /// The configuration of the admission control.
+    parameters: IngressParameters,
+    /// The clients that recently submitted transactions.
+    clients: Mutex<HashMap<ClientId, Client>>,
+    /// Hands the queues of the new clients to the `FairQueue`.
+    tx_new_clients: Sender<ClientQueue>,
+    /// Wakes up the `FairQueue` when a transaction is queued.
+    notify: Arc<Notify>,
+}
+
+impl Ingress {
+    /// Spawns the `FairQueue` forwarding the admitted transactions to the `BatchMaker`. It
+    /// stops once the returned `Ingress` is dropped and its queues are drained.
+    #[must_use]
+    pub fn spawn(
+        parameters: IngressParameters,
+        tx_batch_maker: Sender<Transaction>,
+        metrics: Arc<WorkerMetrics>,
+    ) -> (Arc<Self>, JoinHandle<()>) {
+        let (tx_new_clients, rx_new_clients) = metered_channel::channel(
+            parameters.client_queue_size.max(1),
+            &metrics.ingress_new_clients,
+        );
+        let notify = Arc::new(Notify::new());
+        let ingress = Self {
+            parameters,
+            clients: Mutex::new(HashMap::new()),
+            tx_new_clients,
+            notify: notify.clone(),
+        };
+        let handle = tokio::spawn(async move {
+            FairQueue {
+                rx_new_clients,
+                notify,
+                tx_batch_maker,
+                queues: VecDeque::new(),
+                metrics,
+            }
+            .run()
+            .await;
+        });
+        (Arc::new(ingress), handle)
+    }
+
+    /// Queues a transaction of a client, waiting if the queue of the client is full.
+    pub async fn submit(
+        &self,
+        client: ClientId,
+        transaction: Transaction,
+    ) -> Result<(), IngressError> {
+        // The queue of a new client is handed to the `FairQueue` through a bounded channel:
+        // a slot is reserved before registering the client, outside of the lock.
+        let mut permit: Option<Permit<'_, ClientQueue>> = None;
+        let sender = loop {
+            {
+                let now = Instant::now();
+                let mut clients = self.clients.lock().unwrap();
+                if !clients.contains_key(&client) {
+                    self.forget_idle_clients(&mut clients, now);
+                }
+                let entry = match clients.entry(client.clone()) {
+                    Entry::Occupied(entry) => Some(entry.into_mut()),
+                    Entry::Vacant(entry) => match permit.take() {
+                        Some(permit) => {
+                            let (sender, receiver) = mpsc::channel(self.queue_size());
+                            let weight = self
+                                .parameters
+                                .client_weights
+                                .get(entry.key())
+                                .copied()
+                                .unwrap_or(1)
+                                .max(1);
+                            permit.send(ClientQueue {
+                                weight,
+                                deficit: 0,
+                                receiver,
+                                closed: false,
+                            });
+                            let bucket = (self.parameters.client_rate > 0).then(|| {
+                                TokenBucket::new(
+                                    self.parameters.client_rate,
+                                    self.parameters.client_burst,
+                                    now,
+                                )
+                            });
+                            Some(entry.insert(Client { bucket, sender }))
+                        }
+                        None => None,
+                    },
+                };
+                if let Some(entry) = entry {
+                    if let Some(bucket) = &mut entry.bucket {
+                        if !bucket.try_acquire(now) {
+                            return Err(IngressError::RateLimited);
+                        }
+                    }
+                    break entry.sender.clone();
+                }
+            }
+            permit = Some(
+                self.tx_new_clients
+                    .reserve()
+                    .await
+                    .map_err(|_| IngressError::ShuttingDown)?,
+            );
+        };
+
+        sender
+            .send(transaction)
+            .await
+            .map_err(|_| IngressError::ShuttingDown)?;
+        self.notify.notify_one();
+        Ok(())
+    }
+
+    fn queue_size(&self) -> usize {
+        self.parameters.client_queue_size.max(1)
+    }
+
+    /// Forgets the clients without queued transactions whose rate limit would not apply anymore.
+    /// Their queues are closed and dropped by the `FairQueue`.
+    fn forget_idle_clients(&self, clients: &mut HashMap<ClientId, Client>, now: Instant) {
+        let queue_size = self.queue_size();
+        clients.retain(|_, client| {
+            let idle = client.sender.capacity() == queue_size
+                && client
+                    .bucket
+                    .as_mut()
+                    .map_or(true, |bucket| bucket.is_full(now));
+            !idle
+        });
+    }
+}
+
+/// Forwards the queued transactions of the clients to the `BatchMaker` by deficit round robin:
+/// in every round, each client with queued transactions forwards as many of them as its weight.
+struct FairQueue {
+    /// Receives the queues of the new clients.
+    rx_new_clients: Receiver<ClientQueue>,
+    /// Notified when a transaction is queued.
+    notify: Arc<Notify>,
+    /// Channel to deliver the transactions to the `BatchMaker`.
+    tx_batch_maker: Sender<Transaction>,
+    /// The queues of the clients, in the order they are served.
+    queues: VecDeque<ClientQueue>,
+    /// Metrics handler
+    metrics: Arc<WorkerMetrics>,
+}
+
+impl FairQueue {
+    /// Runs a round over the queues of the clients. Returns the number of transactions
+    /// forwarded, or `None` if the `BatchMaker` is gone.
+    async fn round(&mut self) -> Option<usize> {
+        let mut forwarded = 0;
+        for queue in self.queues.iter_mut() {
+            queue.deficit += queue.weight;
+            while queue.deficit > 0 {
+                match queue.receiver.try_recv() {
+                    Ok(transaction) => {
+                        self.tx_batch_maker.send(transaction).await.ok()?;
+                        queue.deficit -= 1;
+                        forwarded += 1;
+                    }
+                    // Idle clients do not accumulate a deficit.
+                    Err(TryRecvError::Empty) => queue.deficit = 0,
+                    Err(TryRecvError::Disconnected) => {
+                        queue.deficit = 0;
+                        queue.closed = true;
+                    }
+                }
+            }
+        }
+        Some(forwarded)
+    }
+
+    async fn run(&mut self) {
+        let mut closed = false;
+        loop {
+            while let Ok(queue) = self.rx_new_clients.try_recv() {
+                self.queues.push_back(queue);
+            }
+
+            let forwarded = match self.round().await {
+                Some(forwarded) => forwarded,
+                None => return,
+            };
+
+            // Drop the queues of the forgotten clients once they are drained.
+            self.queues.retain(|queue| !queue.closed);
+            self.metrics.ingress_clients.set(self.queues.len() as i64);
+
+            if forwarded > 0 {
+                continue;
+            }
+            if closed && self.queues.is_empty() {
+                return;
+            }
+            tokio::select! {
+                () = self.notify.notified() => (),
+                queue = self.rx_new_clients.recv(), if !closed => match queue {
+                    Some(queue) => self.queues.push_back(queue),
+                    None => closed = true,
+                },
+            }
+        }
+    }
+}
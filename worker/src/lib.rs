@@ -9,11 +9,21 @@
 )]
 
 mod batch_maker;
+mod batching_policy;
+mod compression;
+mod dedup;
+mod ingress;
 pub mod metrics;
 mod primary_connector;
 mod processor;
 mod quorum_waiter;
+mod receipts;
 mod synchronizer;
+mod tx_validator;
 mod worker;
 
-pub use crate::worker::{Worker, WorkerMessage};
+pub use crate::{
+    batching_policy::{BatchingPolicy, DefaultBatchingPolicy, SealReason, TransactionTooLarge},
+    tx_validator::{TransactionValidator, TrivialTransactionValidator},
+    worker::{Worker, WorkerMessage},
+};
@@ -1,7 +1,7 @@
 // Copyright (c) 2022, Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 use mysten_network::metrics::MetricsCallbackProvider;
-use network::metrics::WorkerNetworkMetrics;
+use network::metrics::{ReputationMetrics, WorkerNetworkMetrics};
 use prometheus::{
     default_registry, register_histogram_vec_with_registry, register_int_counter_vec_with_registry,
     register_int_gauge_vec_with_registry, register_int_gauge_with_registry, HistogramVec,
@@ -16,6 +16,7 @@ pub struct Metrics {
     pub channel_metrics: Option<WorkerChannelMetrics>,
     pub endpoint_metrics: Option<WorkerEndpointMetrics>,
     pub network_metrics: Option<WorkerNetworkMetrics>,
+    pub reputation_metrics: Option<ReputationMetrics>,
 }
 
 /// Initialises the metrics
@@ -32,11 +33,15 @@ pub fn initialise_metrics(metrics_registry: &Registry) -> Metrics {
     // The network metrics
     let network_metrics = WorkerNetworkMetrics::new(metrics_registry);
 
+    // The reputation of the other workers
+    let reputation_metrics = ReputationMetrics::new(metrics_registry, "worker");
+
     Metrics {
         worker_metrics: Some(node_metrics),
         channel_metrics: Some(channel_metrics),
         endpoint_metrics: Some(endpoint_metrics),
         network_metrics: Some(network_metrics),
+        reputation_metrics: Some(reputation_metrics),
     }
 }
 
@@ -46,6 +51,20 @@ pub struct WorkerMetrics {
     pub pending_elements_worker_synchronizer: IntGaugeVec,
     /// Number of created batches from the batch_maker
     pub created_batch_size: HistogramVec,
+    /// Number of client transactions rejected by the transaction validator
+    pub rejected_transactions: IntCounterVec,
+    /// Number of client transactions rejected for exceeding the maximum transaction size
+    pub oversized_transactions: IntCounterVec,
+    /// Number of acknowledged client transactions the batching policy dropped
+    pub dropped_transactions: IntCounterVec,
+    /// Number of client transactions dropped as duplicates of recently received ones
+    pub duplicate_transactions: IntCounterVec,
+    /// Number of client transactions rejected for exceeding the rate limit of their client
+    pub rate_limited_transactions: IntCounterVec,
+    /// Number of clients with a queue of transactions for the batch maker
+    pub ingress_clients: IntGauge,
+    /// Number of queues of new clients waiting to be served by the fair queue
+    pub ingress_new_clients: IntGauge,
 }
 
 impl WorkerMetrics {
@@ -65,6 +84,53 @@ impl WorkerMetrics {
                 registry
             )
             .unwrap(),
+            rejected_transactions: register_int_counter_vec_with_registry!(
+                "rejected_transactions",
+                "Number of client transactions rejected by the transaction validator",
+                &["route"],
+                registry
+            )
+            .unwrap(),
+            oversized_transactions: register_int_counter_vec_with_registry!(
+                "oversized_transactions",
+                "Number of client transactions rejected for exceeding the maximum transaction size",
+                &["route"],
+                registry
+            )
+            .unwrap(),
+            dropped_transactions: register_int_counter_vec_with_registry!(
+                "dropped_transactions",
+                "Number of acknowledged client transactions the batching policy dropped",
+                &["route"],
+                registry
+            )
+            .unwrap(),
+            duplicate_transactions: register_int_counter_vec_with_registry!(
+                "duplicate_transactions",
+                "Number of client transactions dropped as duplicates of recently received ones",
+                &["route"],
+                registry
+            )
+            .unwrap(),
+            rate_limited_transactions: register_int_counter_vec_with_registry!(
+                "rate_limited_transactions",
+                "Number of client transactions rejected for exceeding the rate limit of their client",
+                &["route"],
+                registry
+            )
+            .unwrap(),
+            ingress_clients: register_int_gauge_with_registry!(
+                "ingress_clients",
+                "Number of clients with a queue of transactions for the batch maker",
+                registry
+            )
+            .unwrap(),
+            ingress_new_clients: register_int_gauge_with_registry!(
+                "ingress_new_clients",
+                "Number of queues of new clients waiting to be served by the fair queue",
+                registry
+            )
+            .unwrap(),
         }
     }
 }
@@ -3,7 +3,6 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use config::WorkerId;
-use fastcrypto::Hash;
 use store::Store;
 use tokio::{sync::watch, task::JoinHandle};
 use types::{
@@ -16,7 +15,8 @@ use types::{
 #[path = "tests/processor_tests.rs"]
 pub mod processor_tests;
 
-/// Hashes and stores batches, it then outputs the batch's digest.
+/// Stores batches under their digest, it then outputs the batch's digest. The digest of a batch
+/// is the hash of the batch, or the commitment to its encoding if it is erasure-coded.
 pub struct Processor;
 
 impl Processor {
@@ -28,8 +28,8 @@ impl Processor {
         store: Store<BatchDigest, Batch>,
         // Receive reconfiguration signals.
         mut rx_reconfigure: watch::Receiver<ReconfigureNotification>,
-        // Input channel to receive batches.
-        mut rx_batch: Receiver<Batch>,
+        // Input channel to receive batches along with their digest.
+        mut rx_batch: Receiver<(BatchDigest, Batch)>,
         // Output channel to send out batches' digests.
         tx_digest: Sender<WorkerPrimaryMessage>,
         // Whether we are processing our own batches or the batches of other nodes.
@@ -38,10 +38,7 @@ impl Processor {
         tokio::spawn(async move {
             loop {
                 tokio::select! {
-                    Some(batch) = rx_batch.recv() => {
-                        // Hash the batch.
-                        let digest = batch.digest();
-
+                    Some((digest, batch)) = rx_batch.recv() => {
                         // Store the batch.
                         store.write(digest, batch).await;
 
@@ -1,15 +1,18 @@
 // Copyright (c) 2021, Facebook, Inc. and its affiliates
 // Copyright (c) 2022, Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
-use config::{Committee, SharedWorkerCache, Stake, WorkerId};
+use crate::{compression::BatchCompressor, receipts::TransactionReceipts};
+use config::{Committee, Dissemination, SharedWorkerCache, Stake, WorkerId};
 use crypto::PublicKey;
+use fastcrypto::Hash;
 use futures::stream::{futures_unordered::FuturesUnordered, StreamExt as _};
 use network::{CancelOnDropHandler, P2pNetwork, ReliableNetwork};
+use std::sync::Arc;
 use tokio::{sync::watch, task::JoinHandle};
 use types::{
     error::DagError,
     metered_channel::{Receiver, Sender},
-    Batch, ReconfigureNotification, WorkerMessage,
+    Batch, BatchChunk, BatchDigest, ReconfigureNotification, WorkerMessage,
 };
 
 #[cfg(test)]
@@ -30,10 +33,16 @@ pub struct QuorumWaiter {
     rx_reconfigure: watch::Receiver<ReconfigureNotification>,
     /// Input Channel to receive commands.
     rx_message: Receiver<Batch>,
-    /// Channel to deliver batches for which we have enough acknowledgments.
-    tx_batch: Sender<Batch>,
+    /// Channel to deliver batches for which we have enough acknowledgments, with their digest.
+    tx_batch: Sender<(BatchDigest, Batch)>,
     /// A network sender to broadcast the batches to the other workers.
     network: P2pNetwork,
+    /// Whether the batches are replicated to the other workers or erasure-coded.
+    dissemination: Dissemination,
+    /// Compresses the batches broadcast to the other workers.
+    compressor: BatchCompressor,
+    /// The clients following the status of their transactions.
+    receipts: Arc<TransactionReceipts>,
 }
 
 impl QuorumWaiter {
@@ -46,8 +55,11 @@ impl QuorumWaiter {
         worker_cache: SharedWorkerCache,
         rx_reconfigure: watch::Receiver<ReconfigureNotification>,
         rx_message: Receiver<Batch>,
-        tx_batch: Sender<Batch>,
+        tx_batch: Sender<(BatchDigest, Batch)>,
         network: P2pNetwork,
+        dissemination: Dissemination,
+        compressor: BatchCompressor,
+        receipts: Arc<TransactionReceipts>,
     ) -> JoinHandle<()> {
         tokio::spawn(async move {
             Self {
@@ -59,6 +71,9 @@ impl QuorumWaiter {
                 rx_message,
                 tx_batch,
                 network,
+                dissemination,
+                compressor,
+                receipts,
             }
             .run()
             .await;
@@ -74,27 +89,110 @@ impl QuorumWaiter {
         deliver
     }
 
+    /// Sends the whole batch to the workers of all the other authorities.
+    async fn replicate(
+        &mut self,
+        batch: &Batch,
+    ) -> Vec<(
+        PublicKey,
+        CancelOnDropHandler<anemo::Result<anemo::Response<()>>>,
+    )> {
+        let workers: Vec<_> = self
+            .worker_cache
+            .load()
+            .others_workers(&self.name, &self.id)
+            .into_iter()
+            .map(|(name, info)| (name, info.name))
+            .collect();
+        let mut handlers = Vec::new();
+        for (message, workers) in self.compressor.messages(batch.clone(), workers) {
+            let (primary_names, worker_names): (Vec<_>, _) = workers.into_iter().unzip();
+            let sent = self.network.broadcast(worker_names, &message).await;
+            handlers.extend(primary_names.into_iter().zip(sent.into_iter()));
+        }
+        handlers
+    }
+
+    /// Sends to the worker of every other authority its chunk of the erasure-coded batch, and
+    /// returns the digest identifying the batch: the commitment to its encoding. The batch is
+    /// replicated if the committee cannot erasure-code it.
+    async fn disperse(
+        &mut self,
+        batch: &Batch,
+    ) -> (
+        BatchDigest,
+        Vec<(
+            PublicKey,
+            CancelOnDropHandler<anemo::Result<anemo::Response<()>>>,
+        )>,
+    ) {
+        let encoded = BatchChunk::authorities(&self.committee)
+            .and_then(|authorities| BatchChunk::encode(batch, authorities));
+        let chunks = match encoded {
+            Ok(chunks) => chunks,
+            Err(e) => {
+                tracing::debug!("Replicating batch: {e}");
+                return (batch.digest(), self.replicate(batch).await);
+            }
+        };
+        let digest = chunks[0].digest;
+
+        #[cfg(feature = "benchmark")]
+        // NOTE: This log entry is used to compute performance.
+        tracing::info!(
+            "Batch {:?} is erasure-coded as {:?}",
+            batch.digest(),
+            digest
+        );
+
+        // The chunks are assigned to the authorities in the order of the committee.
+        let workers: Vec<_> = {
+            let worker_cache = self.worker_cache.load();
+            self.committee
+                .authorities
+                .keys()
+                .zip(chunks)
+                .filter(|(name, _)| *name != &self.name)
+                .filter_map(|(name, chunk)| match worker_cache.worker(name, &self.id) {
+                    Ok(info) => Some((name.clone(), info.name, chunk)),
+                    Err(e) => {
+                        tracing::warn!("Cannot send chunk of batch: {e}");
+                        None
+                    }
+                })
+                .collect()
+        };
+
+        let mut handlers = Vec::new();
+        for (name, worker_name, chunk) in workers {
+            let message = WorkerMessage::Chunk(chunk);
+            let handler = self.network.send(worker_name, &message).await;
+            handlers.push((name, handler));
+        }
+        (digest, handlers)
+    }
+
     /// Main loop.
     async fn run(&mut self) {
         loop {
             tokio::select! {
                 Some(batch) = self.rx_message.recv() => {
-                    // Broadcast the batch to the other workers.
-                    let workers: Vec<_> = self
-                        .worker_cache
-                        .load()
-                        .others_workers(&self.name, &self.id)
-                        .into_iter()
-                        .map(|(name, info)| (name, info.name))
-                        .collect();
-                    let (primary_names, worker_names): (Vec<_>, _) = workers.into_iter().unzip();
-                    let message = WorkerMessage::Batch(batch.clone());
-                    let handlers = self.network.broadcast(worker_names, &message).await;
+                    // Send the batch to the other workers.
+                    let (digest, handlers) = match self.dissemination {
+                        Dissemination::Replication => {
+                            (batch.digest(), self.replicate(&batch).await)
+                        },
+                        Dissemination::ErasureCoding => self.disperse(&batch).await,
+                    };
+
+                    // Let the clients following any of these transactions know they are batched.
+                    if !self.receipts.is_empty() {
+                        self.receipts.batched(digest, &batch.0);
+                    }
 
                     // Collect all the handlers to receive acknowledgements.
-                    let mut wait_for_quorum: FuturesUnordered<_> = primary_names
+                    let mut wait_for_quorum: FuturesUnordered<_> = handlers
                         .into_iter()
-                        .zip(handlers.into_iter())
                         .map(|(name, handler)| {
                             let stake = self.committee.stake(&name);
                             Self::waiter(handler, stake)
@@ -111,7 +209,7 @@ impl QuorumWaiter {
                             Some(stake) = wait_for_quorum.next() => {
                                 total_stake += stake;
                                 if total_stake >= threshold {
-                                    if self.tx_batch.send(batch).await.is_err() {
+                                    if self.tx_batch.send((digest, batch)).await.is_err() {
                                         tracing::debug!("{}", DagError::ShuttingDown);
                                     }
                                     break;
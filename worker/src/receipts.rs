@@ -0,0 +1,213 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use crate::ingress::ClientId;
+use std::{collections::HashMap, sync::Mutex};
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+use types::{
+    transaction_digest, BatchDigest, CertificateDigest, Round, SequenceNumber, Transaction,
+    TransactionDigest, TransactionStatus,
+};
+
+#[cfg(test)]
+#[path = "tests/receipts_tests.rs"]
+pub mod receipts_tests;
+
+/// The number of status transitions of a transaction. Subscribers' channels are sized accordingly
+/// so that notifying them never blocks.
+const STATUS_TRANSITIONS: usize = 3;
+
+/// Keeps track of the clients waiting for the status transitions of their transactions. Only the
+/// transactions with at least one subscriber are tracked.
+#[derive(Default)]
+pub struct TransactionReceipts {
+    /// The number of subscriptions each client may hold at once, unlimited if 0.
+    max_subscriptions: usize,
+    /// The number of rounds after which a subscription expires, never if 0.
+    expiry: Round,
+    inner: Mutex<Subscriptions>,
+}
+
+#[derive(Default)]
+struct Subscriptions {
+    /// The clients subscribed to each transaction.
+    subscribers: HashMap<TransactionDigest, Vec<Subscriber>>,
+    /// The subscribed transactions included in each of our batches.
+    batches: HashMap<BatchDigest, Vec<TransactionDigest>>,
+    /// The number of subscriptions held by each client.
+    clients: HashMap<ClientId, usize>,
+    /// The last round of the primary, 0 if not known yet.
+    round: Round,
+}
+
+/// A client following the status transitions of a transaction.
+struct Subscriber {
+    client: ClientId,
+    /// The round of the primary when the client subscribed, 0 if not known yet.
+    round: Round,
+    sender: Sender<TransactionStatus>,
+}
+
+impl TransactionReceipts {
+    pub fn new(max_subscriptions: usize, expiry: Round) -> Self {
+        Self {
+            max_subscriptions,
+            expiry,
+            inner: Mutex::default(),
+        }
+    }
+
+    /// Subscribe a client to the status transitions of a transaction. The channel closes once
+    /// the transaction is sequenced, or the subscription expires. Returns `None` if the client
+    /// already holds as many subscriptions as allowed.
+    pub fn subscribe(
+        &self,
+        client: ClientId,
+        transaction: TransactionDigest,
+    ) -> Option<Receiver<TransactionStatus>> {
+        let mut inner = self.inner.lock().unwrap();
+        let subscriptions = inner.clients.entry(client.clone()).or_default();
+        if self.max_subscriptions != 0 && *subscriptions >= self.max_subscriptions {
+            return None;
+        }
+        *subscriptions += 1;
+
+        let (tx, rx) = channel(STATUS_TRANSITIONS);
+        let subscriber = Subscriber {
+            client,
+            round: inner.round,
+            sender: tx,
+        };
+        inner
+            .subscribers
+            .entry(transaction)
+            .or_insert_with(Vec::new)
+            .push(subscriber);
+        Some(rx)
+    }
+
+    /// Whether no client is waiting for any transaction. Allows callers to skip hashing.
+    pub fn is_empty(&self) -> bool {
+        self.inner.lock().unwrap().subscribers.is_empty()
+    }
+
+    /// Notify the subscribers of the transactions sealed into a batch.
+    pub fn batched(&self, batch: BatchDigest, transactions: &[Transaction]) {
+        let mut inner = self.inner.lock().unwrap();
+        let Subscriptions {
+            subscribers,
+            batches,
+            clients,
+            ..
+        } = &mut *inner;
+
+        for digest in transactions.iter().map(|tx| transaction_digest(tx)) {
+            if let Some(senders) = subscribers.get_mut(&digest) {
+                notify(senders, clients, TransactionStatus::Batched(batch));
+                batches.entry(batch).or_insert_with(Vec::new).push(digest);
+            }
+        }
+    }
+
+    /// Notify the subscribers of the transactions held by batches included in our certificate.
+    pub fn certified(&self, digests: &[BatchDigest], certificate: CertificateDigest) {
+        let mut inner = self.inner.lock().unwrap();
+        let Subscriptions {
+            subscribers,
+            batches,
+            clients,
+            ..
+        } = &mut *inner;
+
+        for transaction in digests.iter().filter_map(|x| batches.get(x)).flatten() {
+            if let Some(senders) = subscribers.get_mut(transaction) {
+                notify(senders, clients, TransactionStatus::Certified(certificate));
+            }
+        }
+    }
+
+    /// Notify the subscribers of the transactions held by batches of a sequenced certificate. This
+    /// is the last status transition, so the subscriptions are dropped.
+    pub fn sequenced(
+        &self,
+        digests: &[BatchDigest],
+        certificate: CertificateDigest,
+        consensus_index: SequenceNumber,
+    ) {
+        let mut inner = self.inner.lock().unwrap();
+        let Subscriptions {
+            subscribers,
+            batches,
+            clients,
+            ..
+        } = &mut *inner;
+
+        for transaction in digests.iter().filter_map(|x| batches.remove(x)).flatten() {
+            if let Some(mut senders) = subscribers.remove(&transaction) {
+                notify(
+                    &mut senders,
+                    clients,
+                    TransactionStatus::Sequenced(certificate, consensus_index),
+                );
+                senders.iter().for_each(|x| forget(clients, x));
+            }
+        }
+    }
+
+    /// Record the new round of the primary, and forget the subscriptions of clients that went
+    /// away or that expired.
+    pub fn cleanup(&self, round: Round) {
+        let mut inner = self.inner.lock().unwrap();
+        let Subscriptions {
+            subscribers,
+            batches,
+            clients,
+            round: last_round,
+        } = &mut *inner;
+        *last_round = round;
+
+        subscribers.retain(|_, senders| {
+            senders.retain_mut(|x| {
+                // The subscriptions made before we learned the round of the primary start now.
+                if x.round == 0 {
+                    x.round = round;
+                }
+                let expired = self.expiry != 0 && x.round + self.expiry < round;
+                let keep = !x.sender.is_closed() && !expired;
+                if !keep {
+                    forget(clients, x);
+                }
+                keep
+            });
+            !senders.is_empty()
+        });
+        batches.retain(|_, transactions| {
+            transactions.retain(|x| subscribers.contains_key(x));
+            !transactions.is_empty()
+        });
+    }
+}
+
+/// Send a status to all subscribers, dropping the ones that can no longer receive it.
+fn notify(
+    senders: &mut Vec<Subscriber>,
+    clients: &mut HashMap<ClientId, usize>,
+    status: TransactionStatus,
+) {
+    senders.retain(|x| {
+        let keep = x.sender.try_send(status.clone()).is_ok();
+        if !keep {
+            forget(clients, x);
+        }
+        keep
+    });
+}
+
+/// Release the subscription of a client.
+fn forget(clients: &mut HashMap<ClientId, usize>, subscriber: &Subscriber) {
+    if let Some(subscriptions) = clients.get_mut(&subscriber.client) {
+        *subscriptions -= 1;
+        if *subscriptions == 0 {
+            clients.remove(&subscriber.client);
+        }
+    }
+}
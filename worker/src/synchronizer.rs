@@ -1,12 +1,17 @@
 // Copyright (c) 2021, Facebook, Inc. and its affiliates
 // Copyright (c) 2022, Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
-use crate::metrics::WorkerMetrics;
-use config::{SharedCommittee, SharedWorkerCache, WorkerCache, WorkerId, WorkerIndex};
-use crypto::PublicKey;
+use crate::{compression::BatchCompressor, metrics::WorkerMetrics, receipts::TransactionReceipts};
+use config::{
+    Dissemination, SharedCommittee, SharedWorkerCache, WorkerCache, WorkerId, WorkerIndex,
+};
+use crypto::{NetworkPublicKey, PublicKey};
 use fastcrypto::Hash;
-use futures::stream::{futures_unordered::FuturesUnordered, StreamExt as _};
-use network::{LuckyNetwork, P2pNetwork, UnreliableNetwork};
+use futures::{
+    future::{BoxFuture, FutureExt as _},
+    stream::{futures_unordered::FuturesUnordered, StreamExt as _},
+};
+use network::{P2pNetwork, PeerReputation, UnreliableNetwork};
 use primary::PrimaryWorkerMessage;
 use std::{
     collections::{BTreeMap, HashMap, HashSet},
@@ -17,15 +22,15 @@ use store::Store;
 use tap::{TapFallible, TapOptional};
 use tokio::{
     sync::watch,
-    task::JoinHandle,
+    task::{JoinError, JoinHandle},
     time::{sleep, Duration, Instant},
 };
 use tracing::{debug, error, info, trace, warn};
 use types::{
     error::DagError,
     metered_channel::{Receiver, Sender},
-    Batch, BatchDigest, ReconfigureNotification, Round, WorkerBatchRequest, WorkerPrimaryError,
-    WorkerPrimaryMessage,
+    Batch, BatchChunk, BatchDigest, ReconfigureNotification, Round, WorkerBatchRequest,
+    WorkerBatchResponse, WorkerPrimaryError, WorkerPrimaryMessage,
 };
 
 #[cfg(test)]
@@ -35,6 +40,17 @@ pub mod synchronizer_tests;
 /// Resolution of the timer managing retrials of sync requests (in ms).
 const TIMER_RESOLUTION: u64 = 1_000;
 
+/// A batch request sent to another worker. It resolves to the worker, the time it took to
+/// respond, and its response.
+type BatchRequest = BoxFuture<
+    'static,
+    (
+        NetworkPublicKey,
+        Duration,
+        Result<anyhow::Result<anemo::Response<WorkerBatchResponse>>, JoinError>,
+    ),
+>;
+
 // The `Synchronizer` is responsible to keep the worker in sync with the others.
 pub struct Synchronizer {
     /// The public key of this authority.
@@ -47,6 +63,8 @@ pub struct Synchronizer {
     worker_cache: SharedWorkerCache,
     // The persistent storage.
     store: Store<BatchDigest, Batch>,
+    /// Our chunks of the erasure-coded batches of the other workers.
+    chunk_store: Store<BatchDigest, BatchChunk>,
     /// The depth of the garbage collection.
     gc_depth: Round,
     /// The delay to wait before re-trying to send sync requests.
@@ -54,6 +72,12 @@ pub struct Synchronizer {
     /// Determine with how many nodes to sync when re-trying to send sync-requests. These nodes
     /// are picked at random from the committee.
     sync_retry_nodes: usize,
+    /// Scores the other workers by their responses, to pick the ones to sync with.
+    reputation: PeerReputation<NetworkPublicKey>,
+    /// Whether the batches are replicated to all the workers or erasure-coded.
+    dissemination: Dissemination,
+    /// Negotiates the compression of the batches we request.
+    compressor: BatchCompressor,
     /// Input channel to receive the commands from the primary.
     rx_message: Receiver<PrimaryWorkerMessage>,
     /// A network sender to send requests to the other workers.
@@ -64,12 +88,18 @@ pub struct Synchronizer {
     /// processing will resume when we get the missing batches in the store or we no longer need them.
     /// It also keeps the round number and a time stamp (`u128`) of each request we sent.
     pending: HashMap<BatchDigest, (Round, u128)>,
+    /// The chunks received so far of the pending erasure-coded batches.
+    chunks: HashMap<BatchDigest, Vec<BatchChunk>>,
+    /// The pending batches requested by the primary, to send it once reconstructed.
+    requested: HashSet<BatchDigest>,
     /// Send reconfiguration update to other tasks.
     tx_reconfigure: watch::Sender<ReconfigureNotification>,
     /// Output channel to send out the batch requests.
     tx_primary: Sender<WorkerPrimaryMessage>,
-    /// Output channel to process received batches.
-    tx_batch_processor: Sender<Batch>,
+    /// Output channel to process received batches, with the digest they are pending under.
+    tx_batch_processor: Sender<(BatchDigest, Batch)>,
+    /// Notifies the clients waiting for their transactions to be certified or sequenced.
+    receipts: Arc<TransactionReceipts>,
     /// Metrics handler
     metrics: Arc<WorkerMetrics>,
 }
@@ -82,13 +112,18 @@ impl Synchronizer {
         committee: SharedCommittee,
         worker_cache: SharedWorkerCache,
         store: Store<BatchDigest, Batch>,
+        chunk_store: Store<BatchDigest, BatchChunk>,
         gc_depth: Round,
         sync_retry_delay: Duration,
         sync_retry_nodes: usize,
+        reputation: PeerReputation<NetworkPublicKey>,
+        dissemination: Dissemination,
+        compressor: BatchCompressor,
         rx_message: Receiver<PrimaryWorkerMessage>,
         tx_reconfigure: watch::Sender<ReconfigureNotification>,
         tx_primary: Sender<WorkerPrimaryMessage>,
-        tx_batch_processor: Sender<Batch>,
+        tx_batch_processor: Sender<(BatchDigest, Batch)>,
+        receipts: Arc<TransactionReceipts>,
         metrics: Arc<WorkerMetrics>,
         network: P2pNetwork,
     ) -> JoinHandle<()> {
@@ -99,16 +134,23 @@ impl Synchronizer {
                 committee,
                 worker_cache,
                 store,
+                chunk_store,
                 gc_depth,
                 sync_retry_delay,
                 sync_retry_nodes,
+                reputation,
+                dissemination,
+                compressor,
                 rx_message,
                 network,
                 round: Round::default(),
                 pending: HashMap::new(),
+                chunks: HashMap::new(),
+                requested: HashSet::new(),
                 tx_reconfigure,
                 tx_primary,
                 tx_batch_processor,
+                receipts,
                 metrics,
             }
             .run()
@@ -180,6 +222,14 @@ impl Synchronizer {
                                 self.pending.insert(*digest, (self.round, now));
                             }
 
+                            // The erasure-coded batches are only available in full at the worker
+                            // of their author, so we rather collect their chunks from everyone.
+                            if self.dissemination == Dissemination::ErasureCoding {
+                                let digests = missing.into_iter().collect();
+                                waiting.extend(self.request_chunks(digests).await);
+                                continue;
+                            }
+
                             // Send sync request to a single node. If this fails, we will send it
                             // to other nodes when a timer times out.
                             let worker_name = match self.worker_cache.load().worker(&target, &self.id) {
@@ -194,8 +244,11 @@ impl Synchronizer {
 
                             // TODO: restore ability to cancel these requests when primary->worker RPCs are
                             // made synchronous and this one becomes a child of those.
-                            let message = WorkerBatchRequest{digests: missing.into_iter().collect::<Vec<_>>() };
-                            waiting.push(self.network.unreliable_send(worker_name, &message).await);
+                            let message = WorkerBatchRequest{
+                                digests: missing.into_iter().collect::<Vec<_>>(),
+                                accepted_codecs: self.compressor.accepted_codecs(),
+                            };
+                            waiting.extend(self.send_request(vec![worker_name], &message).await);
                         } else {
                             debug!("All batches are already available {:?} nothing to request from peers", digests);
                         }
@@ -204,6 +257,9 @@ impl Synchronizer {
                         // Keep track of the primary's round number.
                         self.round = round;
 
+                        // Forget the clients that stopped following their transactions.
+                        self.receipts.cleanup(round);
+
                         // Cleanup internal state.
                         if self.round < self.gc_depth {
                             continue;
@@ -211,6 +267,8 @@ impl Synchronizer {
 
                         let mut gc_round = self.round - self.gc_depth;
                         self.pending.retain(|_, (r, _)| r > &mut gc_round);
+                        self.chunks.retain(|digest, _| self.pending.contains_key(digest));
+                        self.requested.retain(|digest| self.pending.contains_key(digest));
                     },
                     PrimaryWorkerMessage::Reconfigure(message) => {
                         // Reconfigure this task and update the shared committee.
@@ -237,6 +295,8 @@ impl Synchronizer {
                                 }));
 
                                 self.pending.clear();
+                                self.chunks.clear();
+                                self.requested.clear();
                                 self.round = 0;
                                 waiting.clear();
 
@@ -280,31 +340,89 @@ impl Synchronizer {
                             return;
                         }
                     }
+                    PrimaryWorkerMessage::UpdateWorkerIndex(authority, workers) => {
+                        // Connect to the worker of the authority with our id, in case it changed.
+                        if authority != self.name {
+                            let old = self.worker_cache.load().worker(&authority, &self.id).ok();
+                            let new = workers.0.get(&self.id);
+                            if old.as_ref() != new {
+                                if let Some(old) = old {
+                                    self.network.remove_known_peer(&old.name);
+                                }
+                                if let Some(new) = new {
+                                    if let Err(e) = self.network.add_known_peer(new.name.clone(), &new.worker_address) {
+                                        warn!("Cannot connect to worker {}: {e}", new.worker_address);
+                                    }
+                                }
+                            }
+                        }
+
+                        // Update the worker cache.
+                        let mut worker_cache = (**self.worker_cache.load()).clone();
+                        worker_cache.workers.insert(authority, workers);
+                        self.worker_cache.swap(Arc::new(worker_cache));
+                    },
                     PrimaryWorkerMessage::RequestBatch(digest) => {
-                        self.handle_request_batch(digest).await;
+                        waiting.extend(self.handle_request_batch(digest).await);
                     },
                     PrimaryWorkerMessage::DeleteBatches(digests) => {
                         self.handle_delete_batches(digests).await;
+                    },
+                    PrimaryWorkerMessage::Certified(digests, certificate) => {
+                        self.receipts.certified(&digests, certificate);
+                    },
+                    PrimaryWorkerMessage::Sequenced(digests, certificate, consensus_index) => {
+                        self.receipts.sequenced(&digests, certificate, consensus_index);
                     }
                 },
 
                 // Stream out the futures of the `FuturesUnordered` that completed.
-                Some(result) = waiting.next() => match result {
+                Some((peer, latency, result)) = waiting.next() => match result {
                     Ok(Ok(response)) => {
-                        for batch in response.into_body().batches {
-                            // TODO: remove duplicate hashing of batch after primary-to-worker
-                            // communication is refactored to be synchronous.
-                            if self.pending.remove(&batch.digest()).is_some() {
+                        let mut response = response.into_body();
+                        let chunks = std::mem::take(&mut response.chunks);
+                        let received = response.batches.len() + response.compressed_batches.len();
+                        let batches = self.compressor.batches(response);
+
+                        // Batches that fail to decompress are the only invalid data the peer can
+                        // send us: the other batches are identified by their digest.
+                        if batches.len() < received {
+                            self.reputation.record_invalid(&peer);
+                        } else if received > 0 || !chunks.is_empty() {
+                            self.reputation.record_success(&peer, latency);
+                        }
+                        // TODO: remove duplicate hashing of batch after primary-to-worker
+                        // communication is refactored to be synchronous.
+                        let mut batches: Vec<_> = batches
+                            .into_iter()
+                            .filter_map(|batch| Some((self.pending_digest(&batch)?, batch)))
+                            .collect();
+                        batches.extend(self.reconstruct(&peer, chunks));
+
+                        for (digest, batch) in batches {
+                            if self.pending.remove(&digest).is_some() {
+                                self.chunks.remove(&digest);
+                                if self.requested.remove(&digest) {
+                                    let message = WorkerPrimaryMessage::RequestedBatch(digest, batch.clone());
+                                    let _ = self.tx_primary.send(message).await.tap_err(|err|{
+                                        debug!("{err:?} {}", DagError::ShuttingDown);
+                                    });
+                                }
+
                                 // Only send batch to processor if we haven't received it already
                                 // from another source.
-                                if self.tx_batch_processor.send(batch).await.is_err() {
+                                if self.tx_batch_processor.send((digest, batch)).await.is_err() {
                                     // Assume error sending to processor means we're shutting down.
                                     break
                                 }
                             }
                         }
                     },
-                    Ok(Err(e)) => info!("{e}"),  // occasional RPC errors are expected
+                    Ok(Err(e)) => {
+                        // occasional RPC errors are expected
+                        info!("{e}");
+                        self.reputation.record_timeout(&peer);
+                    },
                     Err(e) => error!("{e}"),
                 },
 
@@ -333,12 +451,16 @@ impl Synchronizer {
                             .into_iter()
                             .map(|(_, info)| info.name)
                             .collect();
-                        let message = WorkerBatchRequest{digests: retry};
-                        for fut in self.network
-                            .lucky_broadcast(names, &message, self.sync_retry_nodes)
-                            .await {
-                            waiting.push(fut);
-                        }
+                        let message = WorkerBatchRequest{
+                            digests: retry,
+                            accepted_codecs: self.compressor.accepted_codecs(),
+                        };
+                        let nodes = match self.dissemination {
+                            Dissemination::Replication => self.sync_retry_nodes,
+                            Dissemination::ErasureCoding => usize::MAX,
+                        };
+                        let names = self.reputation.select(names, nodes);
+                        waiting.extend(self.send_request(names, &message).await);
                     }
 
                     // Reschedule the timer.
@@ -353,9 +475,23 @@ impl Synchronizer {
         }
     }
 
-    async fn handle_request_batch(&mut self, digest: BatchDigest) {
+    /// Sends a batch to the primary. The erasure-coded batches we only hold a chunk of are
+    /// reconstructed first: this returns the requests for their chunks.
+    async fn handle_request_batch(&mut self, digest: BatchDigest) -> Vec<BatchRequest> {
         let message = match self.store.read(digest).await {
             Ok(Some(batch)) => WorkerPrimaryMessage::RequestedBatch(digest, batch),
+            Ok(None) if self.dissemination == Dissemination::ErasureCoding => {
+                self.requested.insert(digest);
+                if self.pending.contains_key(&digest) {
+                    return Vec::new();
+                }
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .expect("Failed to measure time")
+                    .as_millis();
+                self.pending.insert(digest, (self.round, now));
+                return self.request_chunks(vec![digest]).await;
+            }
             _ => WorkerPrimaryMessage::Error(WorkerPrimaryError::RequestedBatchNotFound(digest)),
         };
 
@@ -363,9 +499,124 @@ impl Synchronizer {
             .send(message)
             .await
             .expect("Failed to send message to primary channel");
+        Vec::new()
+    }
+
+    /// Requests erasure-coded batches from the workers of all the other authorities. Our own
+    /// chunks of the batches count towards their reconstruction.
+    async fn request_chunks(&mut self, digests: Vec<BatchDigest>) -> Vec<BatchRequest> {
+        for digest in &digests {
+            if let Ok(Some(chunk)) = self.chunk_store.read(*digest).await {
+                self.chunks.entry(*digest).or_default().push(chunk);
+            }
+        }
+
+        let names = self
+            .worker_cache
+            .load()
+            .others_workers(&self.name, &self.id)
+            .into_iter()
+            .map(|(_, info)| info.name)
+            .collect();
+        let message = WorkerBatchRequest {
+            digests,
+            accepted_codecs: self.compressor.accepted_codecs(),
+        };
+        let names = self.reputation.rank(names);
+        self.send_request(names, &message).await
+    }
+
+    /// Sends a batch request to the specified workers.
+    async fn send_request(
+        &mut self,
+        names: Vec<NetworkPublicKey>,
+        message: &WorkerBatchRequest,
+    ) -> Vec<BatchRequest> {
+        let start = Instant::now();
+        let handles = self
+            .network
+            .unreliable_broadcast(names.clone(), message)
+            .await;
+        names
+            .into_iter()
+            .zip(handles)
+            .map(|(name, handle)| {
+                async move {
+                    let result = handle.await;
+                    (name, start.elapsed(), result)
+                }
+                .boxed()
+            })
+            .collect()
+    }
+
+    /// The digest a batch received in full is pending under, if any: the hash of the batch, or
+    /// the commitment to its encoding if its author erasure-coded it.
+    fn pending_digest(&self, batch: &Batch) -> Option<BatchDigest> {
+        let digest = batch.digest();
+        if self.pending.contains_key(&digest) {
+            return Some(digest);
+        }
+        let committee = self.committee.load();
+        let chunks = BatchChunk::authorities(&committee)
+            .and_then(|authorities| BatchChunk::encode(batch, authorities))
+            .ok()?;
+        Some(chunks[0].digest).filter(|digest| self.pending.contains_key(digest))
+    }
+
+    /// Collects the chunks of the pending batches received from a peer, and returns the batches
+    /// reconstructed from enough chunks of the same commitment, with their commitment. The
+    /// batch of an invalid encoding is empty.
+    fn reconstruct(
+        &mut self,
+        peer: &NetworkPublicKey,
+        chunks: Vec<BatchChunk>,
+    ) -> Vec<(BatchDigest, Batch)> {
+        let mut batches = Vec::new();
+        for chunk in chunks {
+            let digest = chunk.digest;
+            if !self.pending.contains_key(&digest) {
+                continue;
+            }
+            if let Err(e) = chunk.verify() {
+                warn!("{e}");
+                self.reputation.record_invalid(peer);
+                continue;
+            }
+
+            let collected = self.chunks.entry(digest).or_default();
+            if collected
+                .iter()
+                .any(|x| x.index == chunk.index && x.shard_digests == chunk.shard_digests)
+            {
+                continue;
+            }
+            let consistent: Vec<_> = collected
+                .iter()
+                .filter(|x| x.shard_digests == chunk.shard_digests)
+                .cloned()
+                .chain(std::iter::once(chunk.clone()))
+                .collect();
+            collected.push(chunk);
+            if consistent.len() < consistent[0].data_shards as usize {
+                continue;
+            }
+
+            match BatchChunk::reconstruct(&consistent) {
+                Ok(batch) => {
+                    self.chunks.remove(&digest);
+                    batches.push((digest, batch));
+                }
+                Err(e) => warn!("Failed to reconstruct batch {digest}: {e}"),
+            }
+        }
+        batches
     }
 
     async fn handle_delete_batches(&mut self, digests: Vec<BatchDigest>) {
+        if let Err(err) = self.chunk_store.remove_all(digests.clone()).await {
+            error!("{err}");
+        }
         let message = match self.store.remove_all(digests.clone()).await {
             Ok(_) => WorkerPrimaryMessage::DeletedBatches(digests),
             Err(err) => {
@@ -2,8 +2,11 @@
 // Copyright (c) 2022, Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 use super::*;
+use crate::batching_policy::DefaultBatchingPolicy;
+use config::BatchingParameters;
 use prometheus::Registry;
 use test_utils::{transaction, CommitteeFixture};
+use tokio::time::Duration;
 
 #[tokio::test]
 async fn make_batch() {
@@ -18,9 +21,12 @@ async fn make_batch() {
     // Spawn a `BatchMaker` instance.
     let _batch_maker_handle = BatchMaker::spawn(
         committee,
-        /* max_batch_size */ 200,
-        /* max_batch_delay */
-        Duration::from_millis(1_000_000), // Ensure the timer is not triggered.
+        DefaultBatchingPolicy::new(
+            /* max_batch_size */ 200,
+            /* max_batch_delay */
+            Duration::from_millis(1_000_000), // Ensure the timer is not triggered.
+            BatchingParameters::default(),
+        ),
         rx_reconfiguration,
         rx_transaction,
         tx_message,
@@ -51,9 +57,12 @@ async fn batch_timeout() {
     // Spawn a `BatchMaker` instance.
     let _batch_maker_handle = BatchMaker::spawn(
         committee,
-        /* max_batch_size */ 200,
-        /* max_batch_delay */
-        Duration::from_millis(50), // Ensure the timer is triggered.
+        DefaultBatchingPolicy::new(
+            /* max_batch_size */ 200,
+            /* max_batch_delay */
+            Duration::from_millis(50), // Ensure the timer is triggered.
+            BatchingParameters::default(),
+        ),
         rx_reconfiguration,
         rx_transaction,
         tx_message,
@@ -69,3 +78,47 @@ async fn batch_timeout() {
     let batch = rx_message.recv().await.unwrap();
     assert_eq!(batch, expected_batch);
 }
+
+#[tokio::test]
+async fn batch_count_reached() {
+    let fixture = CommitteeFixture::builder().build();
+    let committee = fixture.committee();
+    let (_tx_reconfiguration, rx_reconfiguration) =
+        watch::channel(ReconfigureNotification::NewEpoch(committee.clone()));
+    let (tx_transaction, rx_transaction) = test_utils::test_channel!(1);
+    let (tx_message, mut rx_message) = test_utils::test_channel!(1);
+    let node_metrics = WorkerMetrics::new(&Registry::new());
+
+    // Spawn a `BatchMaker` instance limiting both the transaction count and size.
+    let tx = transaction();
+    let parameters = BatchingParameters {
+        max_batch_transactions: 2,
+        max_transaction_size: tx.len(),
+        ..BatchingParameters::default()
+    };
+    let _batch_maker_handle = BatchMaker::spawn(
+        committee,
+        DefaultBatchingPolicy::new(
+            /* max_batch_size */ 1_000_000,
+            /* max_batch_delay */
+            Duration::from_millis(1_000_000), // Ensure the timer is not triggered.
+            parameters,
+        ),
+        rx_reconfiguration,
+        rx_transaction,
+        tx_message,
+        Arc::new(node_metrics),
+    );
+
+    // Send an oversized transaction, then enough transactions to seal a batch.
+    let mut oversized = tx.clone();
+    oversized.push(0);
+    tx_transaction.send(oversized).await.unwrap();
+    tx_transaction.send(tx.clone()).await.unwrap();
+    tx_transaction.send(tx.clone()).await.unwrap();
+
+    // Ensure the batch is sealed on the transaction count, without the oversized transaction.
+    let expected_batch = Batch(vec![tx.clone(), tx.clone()]);
+    let batch = rx_message.recv().await.unwrap();
+    assert_eq!(batch, expected_batch);
+}
@@ -0,0 +1,79 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use super::*;
+
+#[test]
+fn default_parameters_seal_on_size_only() {
+    let mut policy = DefaultBatchingPolicy::new(
+        100,
+        Duration::from_millis(200),
+        BatchingParameters::default(),
+    );
+
+    assert!(policy.admit(&[0u8; 10_000]).is_ok());
+    assert_eq!(policy.should_seal(99, 1_000_000), None);
+    assert_eq!(policy.should_seal(100, 1), Some(SealReason::SizeReached));
+    assert_eq!(
+        policy.next_delay(Instant::now()),
+        Duration::from_millis(200)
+    );
+}
+
+#[test]
+fn transaction_limits() {
+    let parameters = BatchingParameters {
+        max_batch_transactions: 3,
+        max_transaction_size: 10,
+        ..BatchingParameters::default()
+    };
+    let policy = DefaultBatchingPolicy::new(100, Duration::from_millis(200), parameters);
+
+    assert!(policy.admit(&[0u8; 10]).is_ok());
+    assert_eq!(
+        policy.admit(&[0u8; 11]),
+        Err(TransactionTooLarge {
+            size: 11,
+            max_size: 10
+        })
+    );
+    assert_eq!(policy.should_seal(30, 2), None);
+    assert_eq!(policy.should_seal(30, 3), Some(SealReason::CountReached));
+    assert_eq!(policy.should_seal(100, 3), Some(SealReason::SizeReached));
+}
+
+#[test]
+fn adaptive_delay_follows_ingress_rate() {
+    let parameters = BatchingParameters {
+        adaptive_delay: true,
+        min_batch_delay: Duration::from_millis(10),
+        ..BatchingParameters::default()
+    };
+    // A batch fills up within the maximum delay at 1000 B/s.
+    let mut policy = DefaultBatchingPolicy::new(100, Duration::from_millis(100), parameters);
+    let start = Instant::now();
+
+    // Without any transaction, batches are sealed after the minimum delay.
+    assert_eq!(
+        policy.next_delay(start + Duration::from_secs(1)),
+        Duration::from_millis(10)
+    );
+
+    // At a high ingress rate, the delay grows towards the maximum.
+    let mut now = start + Duration::from_secs(1);
+    let mut delay = Duration::ZERO;
+    for _ in 0..10 {
+        policy.observe(&[0u8; 1_000]);
+        now += Duration::from_millis(100);
+        let next = policy.next_delay(now);
+        assert!(next >= delay);
+        delay = next;
+    }
+    assert_eq!(delay, Duration::from_millis(100));
+
+    // When the ingress stops, it shrinks back.
+    for _ in 0..4 {
+        now += Duration::from_secs(1);
+        delay = policy.next_delay(now);
+    }
+    assert!(delay < Duration::from_millis(100));
+}
@@ -0,0 +1,125 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use super::*;
+use crypto::traits::KeyPair as _;
+use fastcrypto::Hash;
+use test_utils::CommitteeFixture;
+
+fn repetitive_batch() -> Batch {
+    Batch((0..100).map(|_| vec![1; 100]).collect())
+}
+
+#[test]
+fn compress_broadcast_batches() {
+    let batch = repetitive_batch();
+    let fixture = CommitteeFixture::builder().build();
+    let workers: Vec<_> = fixture
+        .authorities()
+        .map(|authority| authority.worker(0).keypair().public().clone())
+        .collect();
+    let peer = |worker: &NetworkPublicKey| PeerId(worker.0.to_bytes());
+
+    // Batches are sent as is by default.
+    let compressor = BatchCompressor::new(CompressionParameters::default());
+    compressor.record_accepted_codecs(peer(&workers[0]), &[BatchCodec::Zstd]);
+    let messages = compressor.messages(batch.clone(), vec![((), workers[0].clone())]);
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].0, WorkerMessage::Batch(batch.clone()));
+
+    // Or compressed with our codec for the workers which advertised it.
+    let compressor = BatchCompressor::new(CompressionParameters {
+        codec: BatchCodec::Zstd,
+        ..CompressionParameters::default()
+    });
+    compressor.record_accepted_codecs(peer(&workers[0]), &[BatchCodec::Zstd]);
+    compressor.record_accepted_codecs(peer(&workers[1]), &[BatchCodec::Lz4]);
+    let recipients: Vec<_> = workers.iter().cloned().enumerate().collect();
+    let messages = compressor.messages(batch.clone(), recipients);
+    assert_eq!(messages.len(), 2);
+    match &messages[0] {
+        (WorkerMessage::CompressedBatch(compressed), recipients) => {
+            assert_eq!(recipients, &vec![(0, workers[0].clone())]);
+            assert_eq!(compressed.codec, BatchCodec::Zstd);
+            let decompressed = compressor.decompress(compressed).unwrap();
+            assert_eq!(decompressed.digest(), batch.digest());
+        }
+        message => panic!("Unexpected message {message:?}"),
+    }
+
+    // The others get the batch as is.
+    let (message, recipients) = &messages[1];
+    assert_eq!(message, &WorkerMessage::Batch(batch));
+    let recipients: Vec<_> = recipients.iter().map(|(i, _)| *i).collect();
+    assert_eq!(recipients, (1..workers.len()).collect::<Vec<_>>());
+
+    // Unless the batch is too small to be worth it.
+    let small = Batch(vec![vec![1; 10]]);
+    let messages = compressor.messages(small.clone(), vec![((), workers[0].clone())]);
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].0, WorkerMessage::Batch(small));
+}
+
+#[test]
+fn negotiate_response_codec() {
+    let batches = vec![repetitive_batch(), Batch(vec![vec![1; 10]])];
+    let compressor = BatchCompressor::new(CompressionParameters {
+        codec: BatchCodec::Lz4,
+        ..CompressionParameters::default()
+    });
+
+    // The requester does not accept our codec.
+    let response = compressor.response(batches.clone(), &[BatchCodec::Zstd]);
+    assert_eq!(response.batches, batches);
+    assert!(response.compressed_batches.is_empty());
+
+    // The requester accepts our codec: the large batch is compressed.
+    let response = compressor.response(batches.clone(), &[BatchCodec::Lz4, BatchCodec::Zstd]);
+    assert_eq!(response.batches, vec![batches[1].clone()]);
+    assert_eq!(response.compressed_batches.len(), 1);
+    assert_eq!(response.compressed_batches[0].codec, BatchCodec::Lz4);
+
+    // And the requester gets all the batches back.
+    let mut received = compressor.batches(response);
+    received.sort_by_key(|batch| batch.0.len());
+    let mut expected = batches;
+    expected.sort_by_key(|batch| batch.0.len());
+    assert_eq!(received, expected);
+}
+
+#[test]
+fn reject_decompression_bombs() {
+    let batch = repetitive_batch();
+    let sender = BatchCompressor::new(CompressionParameters {
+        codec: BatchCodec::Zstd,
+        ..CompressionParameters::default()
+    });
+    let receiver = BatchCompressor::new(CompressionParameters {
+        max_decompressed_size: 1_024,
+        ..CompressionParameters::default()
+    });
+
+    let response = sender.response(vec![batch], &[BatchCodec::Zstd]);
+    assert_eq!(response.compressed_batches.len(), 1);
+    assert!(receiver.batches(response).is_empty());
+}
+
+#[test]
+fn reject_unaccepted_codecs() {
+    let batch = repetitive_batch();
+    let sender = BatchCompressor::new(CompressionParameters {
+        codec: BatchCodec::Lz4,
+        ..CompressionParameters::default()
+    });
+    let receiver = BatchCompressor::new(CompressionParameters {
+        accepted_codecs: vec![BatchCodec::Zstd],
+        ..CompressionParameters::default()
+    });
+
+    let response = sender.response(vec![batch], &[BatchCodec::Lz4]);
+    assert_eq!(response.compressed_batches.len(), 1);
+    assert!(matches!(
+        receiver.decompress(&response.compressed_batches[0]),
+        Err(CompressionError::NotAccepted(BatchCodec::Lz4))
+    ));
+    assert!(receiver.batches(response).is_empty());
+}
@@ -0,0 +1,55 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use super::*;
+
+#[test]
+fn drop_duplicates_within_window() {
+    let dedup = TransactionDedup::new(10, Duration::from_secs(1));
+    let now = Instant::now();
+
+    assert!(dedup.insert(b"transaction", now));
+    assert!(!dedup.insert(b"transaction", now + Duration::from_millis(500)));
+    assert!(dedup.insert(b"other transaction", now + Duration::from_millis(500)));
+
+    // The transaction is accepted again once the window elapsed.
+    assert!(dedup.insert(b"transaction", now + Duration::from_secs(1)));
+    assert!(!dedup.insert(b"transaction", now + Duration::from_secs(1)));
+}
+
+#[test]
+fn forget_oldest_beyond_capacity() {
+    let dedup = TransactionDedup::new(2, Duration::from_secs(60));
+    let now = Instant::now();
+
+    assert!(dedup.insert(b"first", now));
+    assert!(dedup.insert(b"second", now));
+    assert!(dedup.insert(b"third", now));
+
+    // The first transaction was evicted to make room for the third one.
+    assert!(!dedup.insert(b"third", now));
+    assert!(!dedup.insert(b"second", now));
+    assert!(dedup.insert(b"first", now));
+}
+
+#[test]
+fn accept_removed_transactions() {
+    let dedup = TransactionDedup::new(10, Duration::from_secs(60));
+    let now = Instant::now();
+
+    assert!(dedup.insert(b"transaction", now));
+    dedup.remove(b"transaction", now);
+    assert!(dedup.insert(b"transaction", now + Duration::from_secs(1)));
+
+    // Removing an older reception does not forget the latest one.
+    dedup.remove(b"transaction", now);
+    assert!(!dedup.insert(b"transaction", now + Duration::from_secs(1)));
+}
+
+#[test]
+fn disabled() {
+    let dedup = TransactionDedup::new(0, Duration::from_secs(60));
+    let now = Instant::now();
+
+    assert!(dedup.insert(b"transaction", now));
+    assert!(dedup.insert(b"transaction", now));
+}
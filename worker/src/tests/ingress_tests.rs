@@ -0,0 +1,137 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use super::*;
+use prometheus::Registry;
+use std::collections::BTreeMap;
+use test_utils::test_channel;
+use tokio::time::{sleep, timeout, Duration};
+
+#[test]
+fn token_bucket() {
+    let now = Instant::now();
+    let mut bucket = TokenBucket::new(2, 3, now);
+
+    // The bucket starts full.
+    assert!(bucket.is_full(now));
+    for _ in 0..3 {
+        assert!(bucket.try_acquire(now));
+    }
+    assert!(!bucket.try_acquire(now));
+
+    // It refills at the rate.
+    assert!(bucket.try_acquire(now + Duration::from_millis(500)));
+    assert!(!bucket.try_acquire(now + Duration::from_millis(500)));
+
+    // Up to the burst.
+    assert!(bucket.is_full(now + Duration::from_secs(10)));
+    for _ in 0..3 {
+        assert!(bucket.try_acquire(now + Duration::from_secs(10)));
+    }
+    assert!(!bucket.try_acquire(now + Duration::from_secs(10)));
+}
+
+#[tokio::test]
+async fn rate_limit_per_client() {
+    let (tx_batch_maker, mut rx_batch_maker) = test_channel!(10);
+    let parameters = IngressParameters {
+        client_rate: 1,
+        client_burst: 2,
+        ..IngressParameters::default()
+    };
+    let metrics = Arc::new(WorkerMetrics::new(&Registry::new()));
+    let (ingress, _handle) = Ingress::spawn(parameters, tx_batch_maker, metrics);
+
+    // The client is limited to its burst.
+    let client = ClientId::from("127.0.0.1");
+    for i in 0..2 {
+        ingress.submit(client.clone(), vec![i]).await.unwrap();
+    }
+    assert_eq!(
+        ingress.submit(client, vec![2]).await,
+        Err(IngressError::RateLimited)
+    );
+
+    // Other clients are not affected.
+    ingress
+        .submit(ClientId::from("127.0.0.2"), vec![3])
+        .await
+        .unwrap();
+
+    for i in [0, 1, 3] {
+        assert_eq!(rx_batch_maker.recv().await.unwrap(), vec![i]);
+    }
+}
+
+#[tokio::test]
+async fn weighted_fair_queueing() {
+    let (tx_batch_maker, mut rx_batch_maker) = test_channel!(1);
+    let heavy = ClientId::from("127.0.0.1");
+    let light = ClientId::from("127.0.0.2");
+    let parameters = IngressParameters {
+        client_weights: BTreeMap::from([(heavy.clone(), 3)]),
+        ..IngressParameters::default()
+    };
+    let metrics = Arc::new(WorkerMetrics::new(&Registry::new()));
+    let (ingress, _handle) = Ingress::spawn(parameters, tx_batch_maker, metrics.clone());
+
+    // Register both clients.
+    ingress.submit(heavy.clone(), vec![0]).await.unwrap();
+    ingress.submit(light.clone(), vec![100]).await.unwrap();
+    assert_eq!(rx_batch_maker.recv().await.unwrap(), vec![0]);
+    assert_eq!(rx_batch_maker.recv().await.unwrap(), vec![100]);
+    assert_eq!(metrics.ingress_clients.get(), 2);
+
+    // Hold back the fair queue: the batch maker is busy.
+    ingress.submit(heavy.clone(), vec![u8::MAX]).await.unwrap();
+    sleep(Duration::from_millis(100)).await;
+
+    // Both clients flood the worker.
+    for i in 1..=6 {
+        ingress.submit(heavy.clone(), vec![i]).await.unwrap();
+        ingress.submit(light.clone(), vec![100 + i]).await.unwrap();
+    }
+
+    // The heavy client gets three times the share of the light one, until it is idle.
+    assert_eq!(rx_batch_maker.recv().await.unwrap(), vec![u8::MAX]);
+    let expected = [1, 2, 3, 101, 4, 5, 6, 102, 103, 104, 105, 106];
+    for x in expected {
+        assert_eq!(rx_batch_maker.recv().await.unwrap(), vec![x]);
+    }
+}
+
+#[tokio::test]
+async fn bound_new_clients() {
+    let (tx_batch_maker, mut rx_batch_maker) = test_channel!(1);
+    let parameters = IngressParameters {
+        client_queue_size: 1,
+        ..IngressParameters::default()
+    };
+    let metrics = Arc::new(WorkerMetrics::new(&Registry::new()));
+    let (ingress, _handle) = Ingress::spawn(parameters, tx_batch_maker, metrics.clone());
+    let clients: Vec<_> = (1..=3)
+        .map(|i| ClientId::from(format!("127.0.0.{i}")))
+        .collect();
+
+    // Hold back the fair queue: the batch maker is busy.
+    ingress.submit(clients[0].clone(), vec![0]).await.unwrap();
+    ingress.submit(clients[0].clone(), vec![1]).await.unwrap();
+    sleep(Duration::from_millis(100)).await;
+
+    // The queue of a single new client waits for the fair queue.
+    ingress.submit(clients[1].clone(), vec![2]).await.unwrap();
+    assert_eq!(metrics.ingress_new_clients.get(), 1);
+    assert!(timeout(
+        Duration::from_millis(100),
+        ingress.submit(clients[2].clone(), vec![3])
+    )
+    .await
+    .is_err());
+
+    // The new clients are served once the fair queue resumes.
+    for i in 0..=2 {
+        assert_eq!(rx_batch_maker.recv().await.unwrap(), vec![i]);
+    }
+    ingress.submit(clients[2].clone(), vec![3]).await.unwrap();
+    assert_eq!(rx_batch_maker.recv().await.unwrap(), vec![3]);
+    assert_eq!(metrics.ingress_new_clients.get(), 0);
+}
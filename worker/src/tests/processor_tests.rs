@@ -38,11 +38,11 @@ async fn hash_and_store_our_batch() {
     // the same batch it should be stored and output the message to the tx_digest channel
     for _ in 0..3 {
         // WHEN
-        tx_batch.send(batch.clone()).await.unwrap();
+        let digest = batch.digest();
+        tx_batch.send((digest, batch.clone())).await.unwrap();
 
         // THEN
         // Ensure the `Processor` outputs the batch's digest.
-        let digest = batch.digest();
         match rx_digest.recv().await.unwrap() {
             WorkerPrimaryMessage::OurBatch(x, y) => {
                 assert_eq!(x, digest);
@@ -88,11 +88,11 @@ async fn hash_and_store_others_batch() {
 
     for _ in 0..3 {
         // WHEN
-        tx_batch.send(batch.clone()).await.unwrap();
+        let digest = batch.digest();
+        tx_batch.send((digest, batch.clone())).await.unwrap();
 
         // THEN
         // Ensure the `Processor` outputs the batch's digest.
-        let digest = batch.digest();
         match rx_digest.recv().await.unwrap() {
             WorkerPrimaryMessage::OthersBatch(x, y) => {
                 assert_eq!(x, digest);
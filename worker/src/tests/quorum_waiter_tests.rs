@@ -3,6 +3,7 @@
 // SPDX-License-Identifier: Apache-2.0
 use super::*;
 use crate::worker::WorkerMessage;
+use config::{CompressionParameters, Dissemination};
 use test_utils::{batch, test_network, CommitteeFixture, WorkerToWorkerMockServer};
 
 #[tokio::test]
@@ -30,6 +31,9 @@ async fn wait_for_quorum() {
         rx_message,
         tx_batch,
         P2pNetwork::new(network.clone()),
+        Dissemination::Replication,
+        BatchCompressor::new(CompressionParameters::default()),
+        Arc::new(TransactionReceipts::default()),
     );
 
     // Make a batch.
@@ -55,10 +59,141 @@ async fn wait_for_quorum() {
 
     // Wait for the `QuorumWaiter` to gather enough acknowledgements and output the batch.
     let output = rx_batch.recv().await.unwrap();
-    assert_eq!(output, batch);
+    assert_eq!(output, (batch.digest(), batch));
 
     // Ensure the other listeners correctly received the batch.
     for (mut handle, _, _network) in listener_handles {
         assert_eq!(handle.recv().await.unwrap(), message);
     }
 }
+
+#[tokio::test]
+async fn disperse_chunks() {
+    let (tx_message, rx_message) = test_utils::test_channel!(1);
+    let (tx_batch, mut rx_batch) = test_utils::test_channel!(1);
+    let fixture = CommitteeFixture::builder().randomize_ports(true).build();
+    let committee = fixture.committee();
+    let worker_cache = fixture.shared_worker_cache();
+    let my_primary = fixture.authorities().next().unwrap().public_key();
+    let myself = fixture.authorities().next().unwrap().worker(0);
+
+    let (_tx_reconfiguration, rx_reconfiguration) =
+        watch::channel(ReconfigureNotification::NewEpoch(committee.clone()));
+
+    // setup network
+    let network = test_network(myself.keypair(), &myself.info().worker_address);
+    // Spawn a `QuorumWaiter` instance erasure-coding the batches.
+    let _quorum_waiter_handler = QuorumWaiter::spawn(
+        my_primary.clone(),
+        /* worker_id */ 0,
+        committee.clone(),
+        worker_cache.clone(),
+        rx_reconfiguration,
+        rx_message,
+        tx_batch,
+        P2pNetwork::new(network.clone()),
+        Dissemination::ErasureCoding,
+        BatchCompressor::new(CompressionParameters::default()),
+        Arc::new(TransactionReceipts::default()),
+    );
+
+    // Spawn enough listeners to acknowledge our chunks.
+    let mut listener_handles = Vec::new();
+    for authority in fixture.authorities().skip(1) {
+        let worker = authority.worker(0);
+        let handle =
+            WorkerToWorkerMockServer::spawn(worker.keypair(), worker.info().worker_address.clone());
+        listener_handles.push((authority.public_key(), handle));
+
+        // ensure that the networks are connected
+        network
+            .connect(network::multiaddr_to_address(&worker.info().worker_address).unwrap())
+            .await
+            .unwrap();
+    }
+
+    // Forward the batch to the `QuorumWaiter`.
+    let batch = batch();
+    tx_message.send(batch.clone()).await.unwrap();
+
+    // Wait for the `QuorumWaiter` to gather enough acknowledgements and output the batch.
+    let (digest, output) = rx_batch.recv().await.unwrap();
+    assert_eq!(output, batch);
+
+    // Every listener received the chunk of its authority.
+    let authorities: Vec<_> = committee.authorities.keys().cloned().collect();
+    let mut chunks = Vec::new();
+    for (name, (mut handle, _, _network)) in listener_handles {
+        match handle.recv().await.unwrap() {
+            WorkerMessage::Chunk(chunk) => {
+                let index = authorities.iter().position(|x| x == &name).unwrap();
+                assert_eq!(chunk.index as usize, index);
+                assert_eq!(chunk.digest, digest);
+                chunks.push(chunk);
+            }
+            message => panic!("Unexpected message {message:?}"),
+        }
+    }
+
+    // And any f+1 chunks reconstruct the batch, identified by the commitment to its encoding.
+    assert_ne!(digest, batch.digest());
+    assert_eq!(types::BatchChunk::reconstruct(&chunks[1..]).unwrap(), batch);
+}
+
+#[tokio::test]
+async fn replicate_for_weighted_committee() {
+    let (tx_message, rx_message) = test_utils::test_channel!(1);
+    let (tx_batch, mut rx_batch) = test_utils::test_channel!(1);
+    let fixture = CommitteeFixture::builder().randomize_ports(true).build();
+    let mut committee = fixture.committee();
+    committee.authorities.values_mut().next().unwrap().stake = 2;
+    let worker_cache = fixture.shared_worker_cache();
+    let my_primary = fixture.authorities().next().unwrap().public_key();
+    let myself = fixture.authorities().next().unwrap().worker(0);
+
+    let (_tx_reconfiguration, rx_reconfiguration) =
+        watch::channel(ReconfigureNotification::NewEpoch(committee.clone()));
+
+    // setup network
+    let network = test_network(myself.keypair(), &myself.info().worker_address);
+    // Spawn a `QuorumWaiter` instance erasure-coding the batches.
+    let _quorum_waiter_handler = QuorumWaiter::spawn(
+        my_primary.clone(),
+        /* worker_id */ 0,
+        committee.clone(),
+        worker_cache.clone(),
+        rx_reconfiguration,
+        rx_message,
+        tx_batch,
+        P2pNetwork::new(network.clone()),
+        Dissemination::ErasureCoding,
+        BatchCompressor::new(CompressionParameters::default()),
+        Arc::new(TransactionReceipts::default()),
+    );
+
+    // Spawn the listeners of the other authorities.
+    let mut listener_handles = Vec::new();
+    for worker in fixture.authorities().skip(1).map(|a| a.worker(0)) {
+        let handle =
+            WorkerToWorkerMockServer::spawn(worker.keypair(), worker.info().worker_address.clone());
+        listener_handles.push(handle);
+
+        // ensure that the networks are connected
+        network
+            .connect(network::multiaddr_to_address(&worker.info().worker_address).unwrap())
+            .await
+            .unwrap();
+    }
+
+    // Forward the batch to the `QuorumWaiter`.
+    let batch = batch();
+    tx_message.send(batch.clone()).await.unwrap();
+
+    // The committee cannot erasure-code the batch: it is replicated instead.
+    let output = rx_batch.recv().await.unwrap();
+    assert_eq!(output, (batch.digest(), batch.clone()));
+    let message = WorkerMessage::Batch(batch);
+    for (mut handle, _, _network) in listener_handles {
+        assert_eq!(handle.recv().await.unwrap(), message);
+    }
+}
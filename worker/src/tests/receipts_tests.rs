@@ -0,0 +1,118 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use super::*;
+use test_utils::transaction;
+
+#[tokio::test]
+async fn status_transitions() {
+    let receipts = TransactionReceipts::default();
+    assert!(receipts.is_empty());
+
+    // Subscribe to a transaction.
+    let tx = transaction();
+    let mut rx_status = receipts
+        .subscribe(client(), transaction_digest(&tx))
+        .unwrap();
+    assert!(!receipts.is_empty());
+
+    // Seal the transaction into a batch.
+    let batch = BatchDigest::new([1; 32]);
+    receipts.batched(batch, &[transaction(), tx]);
+    assert_eq!(
+        rx_status.recv().await.unwrap(),
+        TransactionStatus::Batched(batch)
+    );
+
+    // Certify a batch not holding the transaction.
+    let certificate = CertificateDigest::new([2; 32]);
+    receipts.certified(&[BatchDigest::new([3; 32])], certificate);
+    assert!(rx_status.try_recv().is_err());
+
+    // Certify the batch holding the transaction.
+    receipts.certified(&[batch], certificate);
+    assert_eq!(
+        rx_status.recv().await.unwrap(),
+        TransactionStatus::Certified(certificate)
+    );
+
+    // Sequence the certificate: this is the last status, the subscription ends.
+    receipts.sequenced(&[batch], certificate, 10);
+    assert_eq!(
+        rx_status.recv().await.unwrap(),
+        TransactionStatus::Sequenced(certificate, 10)
+    );
+    assert!(rx_status.recv().await.is_none());
+    assert!(receipts.is_empty());
+}
+
+#[tokio::test]
+async fn cleanup_dropped_subscribers() {
+    let receipts = TransactionReceipts::default();
+
+    // Subscribe to a transaction and have it batched.
+    let tx = transaction();
+    let rx_status = receipts
+        .subscribe(client(), transaction_digest(&tx))
+        .unwrap();
+    receipts.batched(BatchDigest::new([1; 32]), &[tx]);
+
+    // The client goes away.
+    drop(rx_status);
+    receipts.cleanup(1);
+    assert!(receipts.is_empty());
+}
+
+#[tokio::test]
+async fn expire_subscriptions() {
+    let receipts = TransactionReceipts::new(0, 10);
+    receipts.cleanup(5);
+
+    // Subscribe to a transaction which is never sequenced.
+    let tx = transaction();
+    let mut rx_status = receipts
+        .subscribe(client(), transaction_digest(&tx))
+        .unwrap();
+
+    // The subscription lasts for the specified number of rounds.
+    receipts.cleanup(15);
+    assert!(!receipts.is_empty());
+    receipts.cleanup(16);
+    assert!(receipts.is_empty());
+    assert!(rx_status.recv().await.is_none());
+}
+
+#[tokio::test]
+async fn limit_subscriptions_per_client() {
+    let receipts = TransactionReceipts::new(2, 0);
+
+    // A client may only hold 2 subscriptions at once.
+    let batch = BatchDigest::new([1; 32]);
+    let transactions = vec![transaction(), transaction()];
+    let _rx_status: Vec<_> = transactions
+        .iter()
+        .map(|tx| {
+            receipts
+                .subscribe(client(), transaction_digest(tx))
+                .unwrap()
+        })
+        .collect();
+    assert!(receipts
+        .subscribe(client(), transaction_digest(&transaction()))
+        .is_none());
+
+    // The other clients are not affected.
+    assert!(receipts
+        .subscribe("127.0.0.2".to_string(), transaction_digest(&transaction()))
+        .is_some());
+
+    // Once its transactions are sequenced, the client may subscribe again.
+    receipts.batched(batch, &transactions);
+    receipts.sequenced(&[batch], CertificateDigest::new([2; 32]), 10);
+    assert!(receipts
+        .subscribe(client(), transaction_digest(&transaction()))
+        .is_some());
+}
+
+fn client() -> ClientId {
+    "127.0.0.1".to_string()
+}
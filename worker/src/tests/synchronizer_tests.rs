@@ -3,13 +3,23 @@
 // SPDX-License-Identifier: Apache-2.0
 use super::*;
 use arc_swap::ArcSwap;
+use config::{BatchCodec, CompressionParameters, Dissemination, PeerReputationParameters};
 use fastcrypto::Hash;
+use network::metrics::ReputationMetrics;
 use prometheus::Registry;
 use test_utils::{
-    batch, batches, open_batch_store, test_network, CommitteeFixture, WorkerToWorkerMockServer,
+    batch, batches, open_batch_store, open_chunk_store, test_network, CommitteeFixture,
+    WorkerToWorkerMockServer,
 };
 use tokio::time::timeout;
 
+fn peer_reputation() -> PeerReputation<NetworkPublicKey> {
+    PeerReputation::new(
+        PeerReputationParameters::default(),
+        ReputationMetrics::new(&Registry::new(), "worker"),
+    )
+}
+
 #[tokio::test]
 async fn synchronize() {
     let (tx_message, rx_message) = test_utils::test_channel!(1);
@@ -39,14 +49,19 @@ async fn synchronize() {
         Arc::new(ArcSwap::from_pointee(committee.clone())),
         worker_cache.clone(),
         store.clone(),
+        open_chunk_store(),
         /* gc_depth */ 50, // Not used in this test.
         /* sync_retry_delay */
         Duration::from_millis(1_000_000), // Ensure it is not triggered.
         /* sync_retry_nodes */ 3, // Not used in this test.
+        peer_reputation(),
+        Dissemination::Replication,
+        BatchCompressor::new(CompressionParameters::default()),
         rx_message,
         tx_reconfiguration,
         tx_primary,
         tx_batch_processor,
+        Arc::new(TransactionReceipts::default()),
         metrics,
         P2pNetwork::new(network.clone()),
     );
@@ -58,6 +73,7 @@ async fn synchronize() {
     let missing = vec![batch().digest()];
     let expected = WorkerBatchRequest {
         digests: missing.clone(),
+        accepted_codecs: vec![BatchCodec::Lz4, BatchCodec::Zstd],
     };
     let (_, mut rx_worker_batch_request, _network) = WorkerToWorkerMockServer::spawn(
         target_worker.keypair(),
@@ -107,14 +123,19 @@ async fn synchronize_when_batch_exists() {
         Arc::new(ArcSwap::from_pointee(committee.clone())),
         worker_cache.clone(),
         store.clone(),
+        open_chunk_store(),
         /* gc_depth */ 50, // Not used in this test.
         /* sync_retry_delay */
         Duration::from_millis(1_000_000), // Ensure it is not triggered.
         /* sync_retry_nodes */ 3, // Not used in this test.
+        peer_reputation(),
+        Dissemination::Replication,
+        BatchCompressor::new(CompressionParameters::default()),
         rx_message,
         tx_reconfiguration,
         tx_primary,
         tx_batch_processor,
+        Arc::new(TransactionReceipts::default()),
         metrics,
         P2pNetwork::new(network.clone()),
     );
@@ -190,14 +211,19 @@ async fn test_successful_request_batch() {
         Arc::new(ArcSwap::from_pointee(committee.clone())),
         worker_cache,
         store.clone(),
+        open_chunk_store(),
         /* gc_depth */ 50, // Not used in this test.
         /* sync_retry_delay */
         Duration::from_millis(1_000_000), // Ensure it is not triggered.
         /* sync_retry_nodes */ 3, // Not used in this test.
+        peer_reputation(),
+        Dissemination::Replication,
+        BatchCompressor::new(CompressionParameters::default()),
         rx_message,
         tx_reconfiguration,
         tx_primary,
         tx_batch_processor,
+        Arc::new(TransactionReceipts::default()),
         metrics,
         P2pNetwork::new(network),
     );
@@ -258,14 +284,19 @@ async fn test_request_batch_not_found() {
         Arc::new(ArcSwap::from_pointee(committee.clone())),
         worker_cache,
         store.clone(),
+        open_chunk_store(),
         /* gc_depth */ 50, // Not used in this test.
         /* sync_retry_delay */
         Duration::from_millis(1_000_000), // Ensure it is not triggered.
         /* sync_retry_nodes */ 3, // Not used in this test.
+        peer_reputation(),
+        Dissemination::Replication,
+        BatchCompressor::new(CompressionParameters::default()),
         rx_message,
         tx_reconfiguration,
         tx_primary,
         tx_batch_processor,
+        Arc::new(TransactionReceipts::default()),
         metrics,
         P2pNetwork::new(network),
     );
@@ -326,14 +357,19 @@ async fn test_successful_batch_delete() {
         Arc::new(ArcSwap::from_pointee(committee.clone())),
         worker_cache,
         store.clone(),
+        open_chunk_store(),
         /* gc_depth */ 50, // Not used in this test.
         /* sync_retry_delay */
         Duration::from_millis(1_000_000), // Ensure it is not triggered.
         /* sync_retry_nodes */ 3, // Not used in this test.
+        peer_reputation(),
+        Dissemination::Replication,
+        BatchCompressor::new(CompressionParameters::default()),
         rx_message,
         tx_reconfiguration,
         tx_primary,
         tx_batch_processor,
+        Arc::new(TransactionReceipts::default()),
         metrics,
         P2pNetwork::new(network),
     );
@@ -2,14 +2,18 @@
 // Copyright (c) 2022, Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 use super::*;
+use crate::TrivialTransactionValidator;
 use arc_swap::ArcSwap;
 use bytes::Bytes;
+use config::{CompressionParameters, IngressParameters};
 use fastcrypto::Hash;
-use network::metrics::WorkerNetworkMetrics;
+use network::metrics::{ReputationMetrics, WorkerNetworkMetrics};
 use prometheus::Registry;
+use std::time::Duration;
 use store::rocks;
 use test_utils::{
-    batch, temp_dir, CommitteeFixture, WorkerToPrimaryMockServer, WorkerToWorkerMockServer,
+    batch, open_chunk_store, temp_dir, test_channel, transaction, CommitteeFixture,
+    WorkerToPrimaryMockServer, WorkerToWorkerMockServer,
 };
 use types::{TransactionsClient, WorkerPrimaryMessage};
 
@@ -39,6 +43,7 @@ async fn handle_clients_transactions() {
         channel_metrics: Some(WorkerChannelMetrics::new(&registry)),
         endpoint_metrics: Some(WorkerEndpointMetrics::new(&registry)),
         network_metrics: Some(WorkerNetworkMetrics::new(&registry)),
+        reputation_metrics: Some(ReputationMetrics::new(&registry, "worker")),
     };
 
     // Spawn a `Worker` instance.
@@ -49,7 +54,9 @@ async fn handle_clients_transactions() {
         Arc::new(ArcSwap::from_pointee(committee.clone())),
         worker_cache.clone(),
         parameters,
+        TrivialTransactionValidator::default(),
         store,
+        open_chunk_store(),
         metrics,
     );
 
@@ -91,3 +98,271 @@ async fn handle_clients_transactions() {
     // Ensure the primary received the batch's digest (ie. it did not panic).
     assert_eq!(handle.recv().await.unwrap(), expected);
 }
+
+/// Test validator rejecting every transaction.
+#[derive(Clone)]
+struct NilTxValidator;
+
+impl TransactionValidator for NilTxValidator {
+    type Error = String;
+
+    fn validate(&self, _transaction: &[u8]) -> Result<(), Self::Error> {
+        Err("Nothing is valid".to_string())
+    }
+}
+
+#[tokio::test]
+async fn reject_invalid_clients_transactions() {
+    let (tx_batch_maker, mut rx_batch_maker) = test_channel!(1);
+    let node_metrics = Arc::new(WorkerMetrics::new(&Registry::new()));
+    let (ingress, _ingress_handle) = Ingress::spawn(
+        IngressParameters::default(),
+        tx_batch_maker,
+        node_metrics.clone(),
+    );
+    let handler = TxReceiverHandler {
+        ingress,
+        validator: NilTxValidator,
+        max_transaction_size: 0,
+        dedup: Arc::new(TransactionDedup::new(0, Duration::from_secs(60))),
+        receipts: Arc::new(TransactionReceipts::default()),
+        node_metrics: node_metrics.clone(),
+    };
+
+    // Submit a transaction the validator rejects.
+    let request = Request::new(TransactionProto {
+        transaction: Bytes::from(transaction()),
+    });
+    let status = handler.submit_transaction(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+    // Ensure the transaction never reached the batch maker.
+    assert!(rx_batch_maker.try_recv().is_err());
+    assert_eq!(
+        node_metrics
+            .rejected_transactions
+            .with_label_values(&["submit_transaction"])
+            .get(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn reject_oversized_clients_transactions() {
+    let (tx_batch_maker, mut rx_batch_maker) = test_channel!(1);
+    let node_metrics = Arc::new(WorkerMetrics::new(&Registry::new()));
+    let (ingress, _ingress_handle) = Ingress::spawn(
+        IngressParameters::default(),
+        tx_batch_maker,
+        node_metrics.clone(),
+    );
+    let tx = transaction();
+    let handler = TxReceiverHandler {
+        ingress,
+        validator: TrivialTransactionValidator::default(),
+        max_transaction_size: tx.len() - 1,
+        dedup: Arc::new(TransactionDedup::new(10, Duration::from_secs(60))),
+        receipts: Arc::new(TransactionReceipts::default()),
+        node_metrics: node_metrics.clone(),
+    };
+
+    // Submit a transaction exceeding the maximum size: it is rejected rather than acknowledged.
+    let request = Request::new(TransactionProto {
+        transaction: Bytes::from(tx),
+    });
+    let status = handler.submit_transaction(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+    // Ensure the transaction never reached the batch maker.
+    assert!(rx_batch_maker.try_recv().is_err());
+    assert_eq!(
+        node_metrics
+            .oversized_transactions
+            .with_label_values(&["submit_transaction"])
+            .get(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn drop_duplicate_clients_transactions() {
+    let (tx_batch_maker, mut rx_batch_maker) = test_channel!(2);
+    let node_metrics = Arc::new(WorkerMetrics::new(&Registry::new()));
+    let (ingress, _ingress_handle) = Ingress::spawn(
+        IngressParameters::default(),
+        tx_batch_maker,
+        node_metrics.clone(),
+    );
+    let handler = TxReceiverHandler {
+        ingress,
+        validator: TrivialTransactionValidator::default(),
+        max_transaction_size: 0,
+        dedup: Arc::new(TransactionDedup::new(10, Duration::from_secs(60))),
+        receipts: Arc::new(TransactionReceipts::default()),
+        node_metrics: node_metrics.clone(),
+    };
+
+    // Submit the same transaction twice, as a retrying client would.
+    let tx = transaction();
+    for _ in 0..2 {
+        let request = Request::new(TransactionProto {
+            transaction: Bytes::from(tx.clone()),
+        });
+        handler.submit_transaction(request).await.unwrap();
+    }
+
+    // Ensure only the first one reached the batch maker.
+    assert_eq!(rx_batch_maker.recv().await.unwrap(), tx);
+    assert!(rx_batch_maker.try_recv().is_err());
+    assert_eq!(
+        node_metrics
+            .duplicate_transactions
+            .with_label_values(&["submit_transaction"])
+            .get(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn accept_again_rate_limited_transactions() {
+    let (tx_batch_maker, mut rx_batch_maker) = test_channel!(2);
+    let node_metrics = Arc::new(WorkerMetrics::new(&Registry::new()));
+    let parameters = IngressParameters {
+        client_rate: 1,
+        client_burst: 1,
+        ..IngressParameters::default()
+    };
+    let (ingress, _ingress_handle) =
+        Ingress::spawn(parameters, tx_batch_maker, node_metrics.clone());
+    let handler = TxReceiverHandler {
+        ingress,
+        validator: TrivialTransactionValidator::default(),
+        max_transaction_size: 0,
+        dedup: Arc::new(TransactionDedup::new(10, Duration::from_secs(60))),
+        receipts: Arc::new(TransactionReceipts::default()),
+        node_metrics: node_metrics.clone(),
+    };
+
+    // Exhaust the rate of the client, then submit the same rejected transaction twice.
+    let tx = transaction();
+    let request = Request::new(TransactionProto {
+        transaction: Bytes::from(tx.clone()),
+    });
+    handler.submit_transaction(request).await.unwrap();
+    let rejected = transaction();
+    for _ in 0..2 {
+        let request = Request::new(TransactionProto {
+            transaction: Bytes::from(rejected.clone()),
+        });
+        let status = handler.submit_transaction(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+    }
+
+    // Ensure the rejected transaction was not acknowledged as a duplicate.
+    assert_eq!(rx_batch_maker.recv().await.unwrap(), tx);
+    assert!(rx_batch_maker.try_recv().is_err());
+    assert_eq!(
+        node_metrics
+            .duplicate_transactions
+            .with_label_values(&["submit_transaction"])
+            .get(),
+        0
+    );
+}
+
+#[tokio::test]
+async fn rate_limit_clients_transactions() {
+    let (tx_batch_maker, mut rx_batch_maker) = test_channel!(2);
+    let node_metrics = Arc::new(WorkerMetrics::new(&Registry::new()));
+    let parameters = IngressParameters {
+        client_rate: 1,
+        client_burst: 1,
+        ..IngressParameters::default()
+    };
+    let (ingress, _ingress_handle) =
+        Ingress::spawn(parameters, tx_batch_maker, node_metrics.clone());
+    let handler = TxReceiverHandler {
+        ingress,
+        validator: TrivialTransactionValidator::default(),
+        max_transaction_size: 0,
+        dedup: Arc::new(TransactionDedup::new(0, Duration::from_secs(60))),
+        receipts: Arc::new(TransactionReceipts::default()),
+        node_metrics: node_metrics.clone(),
+    };
+
+    // Submit two transactions in a row, beyond the rate of the client.
+    let tx = transaction();
+    let request = Request::new(TransactionProto {
+        transaction: Bytes::from(tx.clone()),
+    });
+    handler.submit_transaction(request).await.unwrap();
+    let request = Request::new(TransactionProto {
+        transaction: Bytes::from(tx.clone()),
+    });
+    let status = handler.submit_transaction(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+
+    // Ensure only the first one reached the batch maker.
+    assert_eq!(rx_batch_maker.recv().await.unwrap(), tx);
+    assert!(rx_batch_maker.try_recv().is_err());
+    assert_eq!(
+        node_metrics
+            .rate_limited_transactions
+            .with_label_values(&["submit_transaction"])
+            .get(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn reject_misdirected_chunks() {
+    let fixture = CommitteeFixture::builder().build();
+    let committee = fixture.committee();
+    let name = fixture.authorities().nth(1).unwrap().public_key();
+    let index = committee
+        .authorities
+        .keys()
+        .position(|x| x == &name)
+        .unwrap();
+
+    let (tx_processor, _rx_processor) = test_channel!(1);
+    let (tx_primary, mut rx_primary) = test_channel!(1);
+    let db = rocks::DBMap::<BatchDigest, Batch>::open(temp_dir(), None, Some("batches")).unwrap();
+    let handler = WorkerReceiverHandler {
+        name,
+        committee: Arc::new(ArcSwap::from_pointee(committee.clone())),
+        tx_processor,
+        store: Store::new(db),
+        chunk_store: open_chunk_store(),
+        compressor: BatchCompressor::new(CompressionParameters::default()),
+        tx_primary,
+        id: 0,
+    };
+    let chunks = BatchChunk::encode(&batch(), committee.size()).unwrap();
+
+    // The chunk of another authority.
+    let other = (index + 1) % chunks.len();
+    assert!(handler.handle_chunk(chunks[other].clone()).await.is_err());
+
+    // Our chunk of an encoding for another number of authorities.
+    let encoded = BatchChunk::encode(&batch(), committee.size() + 3).unwrap();
+    assert!(handler.handle_chunk(encoded[index].clone()).await.is_err());
+
+    // Our chunk.
+    let chunk = chunks[index].clone();
+    handler.handle_chunk(chunk.clone()).await.unwrap();
+    assert_eq!(
+        rx_primary.recv().await.unwrap(),
+        WorkerPrimaryMessage::OthersBatch(chunk.digest, 0)
+    );
+    assert_eq!(
+        handler.chunk_store.read(chunk.digest).await.unwrap(),
+        Some(chunk.clone())
+    );
+
+    // But not if the committee cannot erasure-code batches.
+    let mut weighted = committee;
+    weighted.authorities.values_mut().next().unwrap().stake = 2;
+    handler.committee.store(Arc::new(weighted));
+    assert!(handler.handle_chunk(chunk).await.is_err());
+}
@@ -0,0 +1,26 @@
+// Copyright (c) 2022, Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use std::{convert::Infallible, fmt::Display};
+
+/// Defines the validation procedure applied to the transactions submitted by clients. The worker
+/// calls it before handing a transaction over to the `BatchMaker`, so invalid transactions are
+/// rejected at ingress and never take up batch space or bandwidth.
+pub trait TransactionValidator: Clone + Send + Sync + 'static {
+    /// The error returned when a transaction is rejected.
+    type Error: Display + Send;
+
+    /// Determines if a transaction is valid for the worker to consider putting it in a batch.
+    fn validate(&self, transaction: &[u8]) -> Result<(), Self::Error>;
+}
+
+/// Simple validator that accepts all transactions.
+#[derive(Debug, Clone, Default)]
+pub struct TrivialTransactionValidator;
+
+impl TransactionValidator for TrivialTransactionValidator {
+    type Error = Infallible;
+
+    fn validate(&self, _transaction: &[u8]) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
@@ -2,28 +2,41 @@
 // Copyright (c) 2022, Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 use crate::{
-    batch_maker::BatchMaker, metrics::WorkerChannelMetrics, primary_connector::PrimaryConnector,
-    processor::Processor, quorum_waiter::QuorumWaiter, synchronizer::Synchronizer,
+    batch_maker::BatchMaker,
+    batching_policy::{DefaultBatchingPolicy, TransactionTooLarge},
+    compression::BatchCompressor,
+    dedup::TransactionDedup,
+    ingress::{client_id, Ingress, IngressError},
+    metrics::WorkerChannelMetrics,
+    primary_connector::PrimaryConnector,
+    processor::Processor,
+    quorum_waiter::QuorumWaiter,
+    receipts::TransactionReceipts,
+    synchronizer::Synchronizer,
+    TransactionValidator,
 };
 use anemo::{types::PeerInfo, PeerId};
 use async_trait::async_trait;
 use config::{Parameters, SharedCommittee, SharedWorkerCache, WorkerId};
-use crypto::{traits::KeyPair as _, NetworkKeyPair, PublicKey};
-use futures::StreamExt;
+use crypto::{traits::KeyPair as _, NetworkKeyPair, NetworkPublicKey, PublicKey};
+use fastcrypto::Hash;
+use futures::{Stream, StreamExt};
 use multiaddr::{Multiaddr, Protocol};
-use network::P2pNetwork;
+use network::{P2pNetwork, PeerReputation};
 use primary::PrimaryWorkerMessage;
-use std::{net::Ipv4Addr, sync::Arc};
+use std::{net::Ipv4Addr, pin::Pin, sync::Arc};
 use store::Store;
-use tokio::{sync::watch, task::JoinHandle};
+use tokio::{sync::watch, task::JoinHandle, time::Instant};
+use tokio_stream::wrappers::ReceiverStream;
 use tonic::{Request, Response, Status};
 use tracing::info;
 use types::{
     error::DagError,
     metered_channel::{channel, Receiver, Sender},
-    Batch, BatchDigest, Empty, PrimaryToWorker, PrimaryToWorkerServer, ReconfigureNotification,
-    Transaction, TransactionProto, Transactions, TransactionsServer, WorkerPrimaryMessage,
-    WorkerToWorker, WorkerToWorkerServer,
+    Batch, BatchChunk, BatchDigest, Empty, ErasureError, PrimaryToWorker, PrimaryToWorkerServer,
+    ReconfigureNotification, TransactionDigest, TransactionDigestProto, TransactionProto,
+    TransactionStatusProto, Transactions, TransactionsServer, WorkerPrimaryMessage, WorkerToWorker,
+    WorkerToWorkerServer,
 };
 
 #[cfg(test)]
@@ -51,6 +64,8 @@ pub struct Worker {
     parameters: Parameters,
     /// The persistent storage.
     store: Store<BatchDigest, Batch>,
+    /// The chunks of the erasure-coded batches of the other workers.
+    chunk_store: Store<BatchDigest, BatchChunk>,
 }
 
 impl Worker {
@@ -61,7 +76,9 @@ impl Worker {
         committee: SharedCommittee,
         worker_cache: SharedWorkerCache,
         parameters: Parameters,
+        validator: impl TransactionValidator,
         store: Store<BatchDigest, Batch>,
+        chunk_store: Store<BatchDigest, BatchChunk>,
         metrics: Metrics,
     ) -> Vec<JoinHandle<()>> {
         // Define a worker instance.
@@ -73,11 +90,16 @@ impl Worker {
             worker_cache,
             parameters,
             store,
+            chunk_store,
         };
 
         let node_metrics = Arc::new(metrics.worker_metrics.unwrap());
         let endpoint_metrics = metrics.endpoint_metrics.unwrap();
         let channel_metrics: Arc<WorkerChannelMetrics> = Arc::new(metrics.channel_metrics.unwrap());
+        let peer_reputation = PeerReputation::new(
+            worker.parameters.peer_reputation.clone(),
+            metrics.reputation_metrics.unwrap(),
+        );
 
         // Spawn all worker tasks.
         let (tx_primary, rx_primary) = channel(CHANNEL_CAPACITY, &channel_metrics.tx_primary);
@@ -91,9 +113,27 @@ impl Worker {
         let (tx_synchronizer, rx_synchronizer) =
             channel(CHANNEL_CAPACITY, &channel_metrics.tx_synchronizer);
 
+        // Keeps track of the clients following the status of their transactions. A batch is only
+        // proposed again once its header is garbage collected, so its transactions may take up to
+        // twice the garbage collection depth to be sequenced.
+        let receipts = Arc::new(TransactionReceipts::new(
+            worker.parameters.ingress.client_subscriptions,
+            2 * worker.parameters.gc_depth,
+        ));
+
+        // Learns the codecs of the other workers from their batch requests, to compress the
+        // batches we broadcast to them.
+        let compressor = BatchCompressor::new(worker.parameters.compression.clone());
+
         let worker_service = WorkerToWorkerServer::new(WorkerReceiverHandler {
+            name: primary_name.clone(),
+            committee: committee.clone(),
             tx_processor: tx_worker_processor.clone(),
             store: worker.store.clone(),
+            chunk_store: worker.chunk_store.clone(),
+            compressor: compressor.clone(),
+            tx_primary: tx_primary.clone(),
+            id,
         });
         let primary_service =
             PrimaryToWorkerServer::new(PrimaryReceiverHandler { tx_synchronizer });
@@ -171,7 +211,10 @@ impl Worker {
 
         let client_flow_handles = worker.handle_clients_transactions(
             &tx_reconfigure,
+            validator,
             tx_primary.clone(),
+            receipts.clone(),
+            compressor.clone(),
             node_metrics.clone(),
             channel_metrics,
             endpoint_metrics,
@@ -187,8 +230,11 @@ impl Worker {
             tx_reconfigure,
             tx_primary,
             tx_worker_processor,
+            receipts,
+            compressor,
             node_metrics,
             network,
+            peer_reputation,
         );
 
         // NOTE: This log entry is used to compute performance.
@@ -216,9 +262,12 @@ impl Worker {
         rx_synchronizer: Receiver<PrimaryWorkerMessage>,
         tx_reconfigure: watch::Sender<ReconfigureNotification>,
         tx_primary: Sender<WorkerPrimaryMessage>,
-        tx_batch_processor: Sender<Batch>,
+        tx_batch_processor: Sender<(BatchDigest, Batch)>,
+        receipts: Arc<TransactionReceipts>,
+        compressor: BatchCompressor,
         node_metrics: Arc<WorkerMetrics>,
         network: anemo::Network,
+        peer_reputation: PeerReputation<NetworkPublicKey>,
     ) -> Vec<JoinHandle<()>> {
         // The `Synchronizer` is responsible to keep the worker in sync with the others. It handles the commands
         // it receives from the primary (which are mainly notifications that we are out of sync).
@@ -228,13 +277,18 @@ impl Worker {
             self.committee.clone(),
             self.worker_cache.clone(),
             self.store.clone(),
+            self.chunk_store.clone(),
             self.parameters.gc_depth,
             self.parameters.sync_retry_delay,
             self.parameters.sync_retry_nodes,
+            peer_reputation,
+            self.parameters.dissemination,
+            compressor,
             /* rx_message */ rx_synchronizer,
             tx_reconfigure,
             tx_primary,
             tx_batch_processor,
+            receipts,
             node_metrics,
             P2pNetwork::new(network),
         );
@@ -246,7 +300,10 @@ impl Worker {
     fn handle_clients_transactions(
         &self,
         tx_reconfigure: &watch::Sender<ReconfigureNotification>,
+        validator: impl TransactionValidator,
         tx_primary: Sender<WorkerPrimaryMessage>,
+        receipts: Arc<TransactionReceipts>,
+        compressor: BatchCompressor,
         node_metrics: Arc<WorkerMetrics>,
         channel_metrics: Arc<WorkerChannelMetrics>,
         endpoint_metrics: WorkerEndpointMetrics,
@@ -269,7 +326,25 @@ impl Worker {
         let address = address
             .replace(0, |_protocol| Some(Protocol::Ip4(Ipv4Addr::UNSPECIFIED)))
             .unwrap();
-        let tx_receiver_handle = TxReceiverHandler { tx_batch_maker }.spawn(
+        // The transactions of every client are rate limited and queued separately, then
+        // forwarded fairly to the `BatchMaker`.
+        let (ingress, ingress_handle) = Ingress::spawn(
+            self.parameters.ingress.clone(),
+            tx_batch_maker,
+            node_metrics.clone(),
+        );
+        let tx_receiver_handle = TxReceiverHandler {
+            ingress,
+            validator,
+            max_transaction_size: self.parameters.batching.max_transaction_size,
+            dedup: Arc::new(TransactionDedup::new(
+                self.parameters.deduplication.worker_cache_size,
+                self.parameters.deduplication.worker_window,
+            )),
+            receipts: receipts.clone(),
+            node_metrics: node_metrics.clone(),
+        }
+        .spawn(
             address.clone(),
             tx_reconfigure.subscribe(),
             endpoint_metrics,
@@ -280,8 +355,11 @@ impl Worker {
         // gathers the 'cancel handlers' of the messages and send them to the `QuorumWaiter`.
         let batch_maker_handle = BatchMaker::spawn(
             (*(*(*self.committee).load()).clone()).clone(),
-            self.parameters.batch_size,
-            self.parameters.max_batch_delay,
+            DefaultBatchingPolicy::new(
+                self.parameters.batch_size,
+                self.parameters.max_batch_delay,
+                self.parameters.batching.clone(),
+            ),
             tx_reconfigure.subscribe(),
             /* rx_transaction */ rx_batch_maker,
             /* tx_message */ tx_quorum_waiter,
@@ -299,6 +377,9 @@ impl Worker {
             /* rx_message */ rx_quorum_waiter,
             /* tx_batch */ tx_client_processor,
             P2pNetwork::new(network),
+            self.parameters.dissemination,
+            compressor,
+            receipts,
         );
 
         // The `Processor` hashes and stores the batch. It then forwards the batch's digest to the `PrimaryConnector`
@@ -318,6 +399,7 @@ impl Worker {
         );
 
         vec![
+            ingress_handle,
             batch_maker_handle,
             quorum_waiter_handle,
             processor_handle,
@@ -330,7 +412,7 @@ impl Worker {
         &self,
         tx_reconfigure: &watch::Sender<ReconfigureNotification>,
         tx_primary: Sender<WorkerPrimaryMessage>,
-        rx_worker_processor: types::metered_channel::Receiver<Batch>,
+        rx_worker_processor: types::metered_channel::Receiver<(BatchDigest, Batch)>,
     ) -> Vec<JoinHandle<()>> {
         // This `Processor` hashes and stores the batches we receive from the other workers. It then forwards the
         // batch's digest to the `PrimaryConnector` that will send it to our primary.
@@ -349,11 +431,17 @@ impl Worker {
 
 /// Defines how the network receiver handles incoming transactions.
 #[derive(Clone)]
-struct TxReceiverHandler {
-    tx_batch_maker: Sender<Transaction>,
+struct TxReceiverHandler<V> {
+    ingress: Arc<Ingress>,
+    validator: V,
+    /// The maximum size of a transaction (in bytes). No limit if set to 0.
+    max_transaction_size: usize,
+    dedup: Arc<TransactionDedup>,
+    receipts: Arc<TransactionReceipts>,
+    node_metrics: Arc<WorkerMetrics>,
 }
 
-impl TxReceiverHandler {
+impl<V: TransactionValidator> TxReceiverHandler<V> {
     async fn wait_for_shutdown(mut rx_reconfigure: watch::Receiver<ReconfigureNotification>) {
         loop {
             let result = rx_reconfigure.changed().await;
@@ -365,6 +453,22 @@ impl TxReceiverHandler {
         }
     }
 
+    /// Rejects a transaction exceeding the maximum transaction size, before it is acknowledged.
+    fn check_size(&self, transaction: &[u8], route: &str) -> Result<(), Status> {
+        if self.max_transaction_size > 0 && transaction.len() > self.max_transaction_size {
+            self.node_metrics
+                .oversized_transactions
+                .with_label_values(&[route])
+                .inc();
+            let error = TransactionTooLarge {
+                size: transaction.len(),
+                max_size: self.max_transaction_size,
+            };
+            return Err(Status::invalid_argument(error.to_string()));
+        }
+        Ok(())
+    }
+
     #[must_use]
     fn spawn(
         self,
@@ -389,18 +493,59 @@ impl TxReceiverHandler {
 }
 
 #[async_trait]
-impl Transactions for TxReceiverHandler {
+impl<V: TransactionValidator> Transactions for TxReceiverHandler<V> {
+    type SubscribeTransactionStatusStream =
+        Pin<Box<dyn Stream<Item = Result<TransactionStatusProto, Status>> + Send>>;
+
     async fn submit_transaction(
         &self,
         request: Request<TransactionProto>,
     ) -> Result<Response<Empty>, Status> {
+        let client = client_id(&request);
         let message = request.into_inner().transaction;
-        // Send the transaction to the batch maker.
-        self.tx_batch_maker
-            .send(message.to_vec())
-            .await
-            .map_err(|_| DagError::ShuttingDown)
-            .map_err(|e| Status::not_found(e.to_string()))?;
+        // Reject invalid transactions before they take up any batch space.
+        if let Err(e) = self.validator.validate(message.as_ref()) {
+            self.node_metrics
+                .rejected_transactions
+                .with_label_values(&["submit_transaction"])
+                .inc();
+            return Err(Status::invalid_argument(format!(
+                "Invalid transaction: {e}"
+            )));
+        }
+        self.check_size(message.as_ref(), "submit_transaction")?;
+
+        // Acknowledge the transactions we recently received without batching them again.
+        let received = Instant::now();
+        if !self.dedup.insert(message.as_ref(), received) {
+            self.node_metrics
+                .duplicate_transactions
+                .with_label_values(&["submit_transaction"])
+                .inc();
+            return Ok(Response::new(Empty {}));
+        }
+
+        // Send the transaction to the batch maker. A transaction that is not admitted is forgotten,
+        // so that the client can submit it again.
+        let result = self.ingress.submit(client, message.to_vec()).await;
+        if result.is_err() {
+            self.dedup.remove(message.as_ref(), received);
+        }
+        match result {
+            Ok(()) => (),
+            Err(IngressError::RateLimited) => {
+                self.node_metrics
+                    .rate_limited_transactions
+                    .with_label_values(&["submit_transaction"])
+                    .inc();
+                return Err(Status::resource_exhausted(
+                    IngressError::RateLimited.to_string(),
+                ));
+            }
+            Err(IngressError::ShuttingDown) => {
+                return Err(Status::unavailable(DagError::ShuttingDown.to_string()))
+            }
+        }
 
         Ok(Response::new(Empty {}))
     }
@@ -409,24 +554,92 @@ impl Transactions for TxReceiverHandler {
         &self,
         request: tonic::Request<tonic::Streaming<types::TransactionProto>>,
     ) -> Result<tonic::Response<types::Empty>, tonic::Status> {
+        let client = client_id(&request);
         let mut transactions = request.into_inner();
 
         while let Some(Ok(txn)) = transactions.next().await {
-            // Send the transaction to the batch maker.
-            self.tx_batch_maker
-                .send(txn.transaction.to_vec())
-                .await
-                .expect("Failed to send transaction");
+            // If the stream carries an invalid transaction, better to drop the client.
+            if let Err(e) = self.validator.validate(txn.transaction.as_ref()) {
+                self.node_metrics
+                    .rejected_transactions
+                    .with_label_values(&["submit_transaction_stream"])
+                    .inc();
+                return Err(Status::invalid_argument(format!(
+                    "Stream contains an invalid transaction: {e}"
+                )));
+            }
+            self.check_size(txn.transaction.as_ref(), "submit_transaction_stream")?;
+
+            // Skip the transactions we recently received.
+            let received = Instant::now();
+            if !self.dedup.insert(txn.transaction.as_ref(), received) {
+                self.node_metrics
+                    .duplicate_transactions
+                    .with_label_values(&["submit_transaction_stream"])
+                    .inc();
+                continue;
+            }
+
+            // Send the transaction to the batch maker. A client exceeding its rate limit is
+            // dropped, like one sending invalid transactions. A transaction that is not admitted
+            // is forgotten, so that the client can submit it again.
+            let result = self
+                .ingress
+                .submit(client.clone(), txn.transaction.to_vec())
+                .await;
+            if result.is_err() {
+                self.dedup.remove(txn.transaction.as_ref(), received);
+            }
+            match result {
+                Ok(()) => (),
+                Err(IngressError::RateLimited) => {
+                    self.node_metrics
+                        .rate_limited_transactions
+                        .with_label_values(&["submit_transaction_stream"])
+                        .inc();
+                    return Err(Status::resource_exhausted(
+                        IngressError::RateLimited.to_string(),
+                    ));
+                }
+                Err(IngressError::ShuttingDown) => {
+                    return Err(Status::unavailable(DagError::ShuttingDown.to_string()))
+                }
+            }
         }
         Ok(Response::new(Empty {}))
     }
+
+    async fn subscribe_transaction_status(
+        &self,
+        request: Request<TransactionDigestProto>,
+    ) -> Result<Response<Self::SubscribeTransactionStatusStream>, Status> {
+        let client = client_id(&request);
+        let digest = TransactionDigest::try_from(request.into_inner())
+            .map_err(|e| Status::invalid_argument(format!("Invalid transaction digest: {e}")))?;
+
+        let rx_status = self
+            .receipts
+            .subscribe(client, digest)
+            .ok_or_else(|| Status::resource_exhausted("Too many transaction subscriptions"))?;
+        let stream = ReceiverStream::new(rx_status)
+            .map(|status| Ok::<_, Status>(TransactionStatusProto::from(status)));
+        Ok(Response::new(Box::pin(stream)))
+    }
 }
 
 /// Defines how the network receiver handles incoming workers messages.
 #[derive(Clone)]
 struct WorkerReceiverHandler {
-    tx_processor: Sender<Batch>,
+    /// The public key of our primary.
+    name: PublicKey,
+    /// The committee information.
+    committee: SharedCommittee,
+    tx_processor: Sender<(BatchDigest, Batch)>,
     store: Store<BatchDigest, Batch>,
+    chunk_store: Store<BatchDigest, BatchChunk>,
+    compressor: BatchCompressor,
+    tx_primary: Sender<WorkerPrimaryMessage>,
+    id: WorkerId,
 }
 
 #[async_trait]
@@ -436,32 +649,94 @@ impl WorkerToWorker for WorkerReceiverHandler {
         request: anemo::Request<types::WorkerMessage>,
     ) -> Result<anemo::Response<()>, anemo::rpc::Status> {
         let message = request.into_body();
-        match message {
-            WorkerMessage::Batch(batch) => self
-                .tx_processor
-                .send(batch)
-                .await
-                .map_err(|_| DagError::ShuttingDown),
-        }
-        .map(|_| anemo::Response::new(()))
-        .map_err(|e| anemo::rpc::Status::internal(e.to_string()))
+        let batch = match message {
+            WorkerMessage::Batch(batch) => batch,
+            WorkerMessage::CompressedBatch(batch) => self
+                .compressor
+                .decompress(&batch)
+                .map_err(|e| anemo::rpc::Status::internal(e.to_string()))?,
+            WorkerMessage::Chunk(chunk) => return self.handle_chunk(chunk).await,
+        };
+        self.tx_processor
+            .send((batch.digest(), batch))
+            .await
+            .map_err(|_| DagError::ShuttingDown)
+            .map(|_| anemo::Response::new(()))
+            .map_err(|e| anemo::rpc::Status::internal(e.to_string()))
     }
     async fn request_batches(
         &self,
         request: anemo::Request<types::WorkerBatchRequest>,
     ) -> Result<anemo::Response<types::WorkerBatchResponse>, anemo::rpc::Status> {
+        if let Some(peer) = request.peer_id() {
+            self.compressor
+                .record_accepted_codecs(*peer, &request.body().accepted_codecs);
+        }
         let message = request.into_body();
         // TODO [issue #7]: Do some accounting to prevent bad actors from monopolizing our resources
         // TODO: Add a limit on number of requested batches
-        let batches: Vec<Batch> = self
+        let stored_chunks = self
+            .chunk_store
+            .read_all(message.digests.clone())
+            .await
+            .map_err(|e| anemo::rpc::Status::from_error(Box::new(e)))?;
+
+        // Send our chunks of the erasure-coded batches rather than the batches: the requester
+        // can only check a batch reconstructed from an invalid encoding against the chunks.
+        let mut chunks = Vec::new();
+        let mut others = Vec::new();
+        for (digest, chunk) in message.digests.into_iter().zip(stored_chunks) {
+            match chunk {
+                Some(chunk) => chunks.push(chunk),
+                None => others.push(digest),
+            }
+        }
+        let batches = self
             .store
-            .read_all(message.digests)
+            .read_all(others)
             .await
             .map_err(|e| anemo::rpc::Status::from_error(Box::new(e)))?
             .into_iter()
             .flatten()
             .collect();
-        Ok(anemo::Response::new(types::WorkerBatchResponse { batches }))
+
+        let mut response = self.compressor.response(batches, &message.accepted_codecs);
+        response.chunks = chunks;
+        Ok(anemo::Response::new(response))
+    }
+}
+
+impl WorkerReceiverHandler {
+    /// Stores our chunk of an erasure-coded batch. Holding the chunk is enough for our primary
+    /// to vote for the batch: the batch can be reconstructed from the chunks of the other
+    /// authorities when needed. The chunk must be the one assigned to us by an encoding for
+    /// the whole committee, otherwise the chunks held by the authorities may not suffice.
+    async fn handle_chunk(
+        &self,
+        chunk: BatchChunk,
+    ) -> Result<anemo::Response<()>, anemo::rpc::Status> {
+        chunk
+            .verify()
+            .map_err(|e| anemo::rpc::Status::internal(e.to_string()))?;
+        let committee = self.committee.load();
+        let authorities = BatchChunk::authorities(&committee)
+            .map_err(|e| anemo::rpc::Status::internal(e.to_string()))?;
+        let index = committee
+            .authorities
+            .keys()
+            .position(|name| name == &self.name);
+        if chunk.shard_digests.len() != authorities || index != Some(chunk.index as usize) {
+            let e = ErasureError::InvalidChunk(chunk.digest, chunk.index);
+            return Err(anemo::rpc::Status::internal(e.to_string()));
+        }
+        let digest = chunk.digest;
+        self.chunk_store.write(digest, chunk).await;
+        self.tx_primary
+            .send(WorkerPrimaryMessage::OthersBatch(digest, self.id))
+            .await
+            .map_err(|_| DagError::ShuttingDown)
+            .map(|_| anemo::Response::new(()))
+            .map_err(|e| anemo::rpc::Status::internal(e.to_string()))
     }
 }
 